@@ -0,0 +1,40 @@
+use std::fmt;
+
+use crate::{http::errors::HttpError, storage::errors::StorageError};
+
+#[derive(Debug)]
+pub enum ArticleError {
+    HttpError(HttpError),
+    DbError(StorageError),
+    NoContent,
+}
+
+/// The `impl fmt::Display for ArticleError` block formats the reason the full text of an entry could
+/// not be extracted.
+impl fmt::Display for ArticleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            ArticleError::HttpError(e) => write!(f, "{}", e),
+            ArticleError::DbError(e) => write!(f, "Saving the full text failed: {}", e),
+            ArticleError::NoContent => write!(f, "No readable content found"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ArticleError {
+    fn from(error: reqwest::Error) -> Self {
+        ArticleError::HttpError(HttpError::from(error))
+    }
+}
+
+impl From<HttpError> for ArticleError {
+    fn from(error: HttpError) -> Self {
+        ArticleError::HttpError(error)
+    }
+}
+
+impl From<StorageError> for ArticleError {
+    fn from(error: StorageError) -> Self {
+        ArticleError::DbError(error)
+    }
+}
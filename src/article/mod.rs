@@ -0,0 +1,511 @@
+pub mod errors;
+
+use ego_tree::{NodeId, NodeRef};
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node};
+use std::collections::HashMap;
+
+use crate::http::HttpClient;
+use errors::ArticleError;
+
+/// Average adult reading speed used for the reading time estimate.
+const WORDS_PER_MINUTE: usize = 200;
+
+lazy_static! {
+    static ref UNLIKELY: Regex = Regex::new(
+        r"(?i)banner|breadcrumbs|combx|comment|community|cookie|disqus|extra|footer|header|legends|menu|modal|nav|popup|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe|tags|tool|widget"
+    )
+    .unwrap();
+    static ref MAYBE_CANDIDATE: Regex =
+        Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap();
+    static ref POSITIVE: Regex = Regex::new(
+        r"(?i)article|body|content|entry|hentry|h-entry|main|page|pagination|post|text|blog|story"
+    )
+    .unwrap();
+    static ref NEGATIVE: Regex = Regex::new(
+        r"(?i)hidden|banner|combx|comment|com-|contact|foot|footer|footnote|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|tool|widget"
+    )
+    .unwrap();
+}
+
+/// Elements that never hold article content and are dropped from the extracted HTML.
+const REMOVED_TAGS: [&str; 14] = [
+    "script", "style", "noscript", "form", "iframe", "object", "embed", "nav", "aside", "footer",
+    "header", "button", "input", "svg",
+];
+
+/// Elements that start a new block when the article is flattened to text.
+const BLOCK_TAGS: [&str; 22] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "pre",
+    "blockquote",
+    "table",
+    "tr",
+    "figure",
+    "figcaption",
+    "dl",
+    "hr",
+];
+
+/// Elements written without a closing tag.
+const VOID_TAGS: [&str; 5] = ["br", "hr", "img", "source", "wbr"];
+
+/// Attributes kept on the extracted HTML in this order, everything else (styles, handlers, classes)
+/// is dropped.
+const KEPT_ATTRIBUTES: [&str; 5] = ["href", "src", "alt", "title", "datetime"];
+
+/// The `Article` struct holds the main content extracted from a web page.
+///
+/// Properties:
+///
+/// * `html`: The cleaned HTML of the main content.
+/// * `text`: The plain text of the main content, paragraphs separated by blank lines.
+/// * `word_count`: The number of words in `text`.
+/// * `reading_minutes`: The estimated reading time in minutes.
+#[derive(Debug, Clone)]
+pub struct Article {
+    pub html: String,
+    pub text: String,
+    pub word_count: usize,
+    pub reading_minutes: usize,
+}
+
+/// The function `fetch` downloads a web page and extracts its main content.
+///
+/// Arguments:
+///
+/// * `client`: The `client` used to download the page.
+/// * `url`: The `url` of the page, usually the `link` of an entry.
+///
+/// Returns:
+///
+/// the extracted `Article`, or an `ArticleError` if the page cannot be downloaded or has no
+/// readable content.
+pub async fn fetch(client: &HttpClient, url: &str) -> Result<Article, ArticleError> {
+    let res = client.get(url).await?.error_for_status()?;
+    let html = client.text(res).await?;
+    extract(&html).ok_or(ArticleError::NoContent)
+}
+
+/// The function `extract` finds the main content of an HTML document with a readability style
+/// algorithm: paragraphs are scored by their length and number of commas, the scores bubble up to
+/// their parent and grandparent, penalized by link density and class names, and the best scoring
+/// element is kept together with its related siblings.
+///
+/// Returns:
+///
+/// the extracted `Article`, or `None` if no element looks like content.
+pub fn extract(html: &str) -> Option<Article> {
+    let document = Html::parse_document(html);
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+    for node in document.tree.root().descendants() {
+        let element = match ElementRef::wrap(node) {
+            Some(element) => element,
+            None => continue,
+        };
+        if !matches!(element.value().name(), "p" | "pre" | "td" | "blockquote") {
+            continue;
+        }
+        if is_unlikely(node) {
+            continue;
+        }
+        let text = collapse_whitespace(&element.text().collect::<String>());
+        if text.chars().count() < 25 {
+            continue;
+        }
+        let score =
+            1.0 + text.matches(',').count() as f64 + (text.chars().count() as f64 / 100.0).min(3.0);
+
+        let parent = node.parent().filter(|parent| parent.value().is_element());
+        let grandparent = parent
+            .and_then(|parent| parent.parent())
+            .filter(|grandparent| grandparent.value().is_element());
+        for (ancestor, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            if let Some(ancestor) = ancestor {
+                let entry = scores
+                    .entry(ancestor.id())
+                    .or_insert_with(|| initial_score(ancestor));
+                *entry += score * share;
+            }
+        }
+    }
+
+    let (top_id, top_score) = scores
+        .iter()
+        .map(|(id, score)| {
+            let node = document.tree.get(*id).unwrap();
+            (*id, score * (1.0 - link_density(node)))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let top = document.tree.get(top_id)?;
+
+    let threshold = (top_score * 0.2).max(10.0);
+    let siblings: Vec<NodeRef<Node>> = match top.parent() {
+        Some(parent) => parent
+            .children()
+            .filter(|sibling| {
+                if sibling.id() == top_id {
+                    return true;
+                }
+                let sibling_score = scores.get(&sibling.id()).copied().unwrap_or(0.0)
+                    * (1.0 - link_density(*sibling));
+                if sibling_score >= threshold {
+                    return true;
+                }
+                let is_paragraph = ElementRef::wrap(*sibling)
+                    .map(|element| element.value().name() == "p")
+                    .unwrap_or(false);
+                is_paragraph && {
+                    let text = node_text(*sibling);
+                    text.chars().count() > 80 && link_density(*sibling) < 0.25
+                }
+            })
+            .collect(),
+        None => vec![top],
+    };
+
+    let mut html = String::new();
+    let mut text = String::new();
+    for node in siblings.iter() {
+        write_html(*node, &mut html);
+        write_text(*node, &mut text);
+    }
+    let text = tidy_text(&text);
+    let word_count = text.split_whitespace().count();
+    if word_count == 0 {
+        return None;
+    }
+    Some(Article {
+        html,
+        text,
+        word_count,
+        reading_minutes: word_count.div_ceil(WORDS_PER_MINUTE),
+    })
+}
+
+/// The function `class_weight` rewards or penalizes an element by its class and id.
+fn class_weight(node: NodeRef<Node>) -> f64 {
+    let element = match ElementRef::wrap(node) {
+        Some(element) => element,
+        None => return 0.0,
+    };
+    let mut weight = 0.0;
+    for value in [
+        element.value().attr("class").unwrap_or(""),
+        element.value().id().unwrap_or(""),
+    ] {
+        if value.is_empty() {
+            continue;
+        }
+        if NEGATIVE.is_match(value) {
+            weight -= 25.0;
+        }
+        if POSITIVE.is_match(value) {
+            weight += 25.0;
+        }
+    }
+    weight
+}
+
+/// The function `initial_score` gives a candidate a head start based on its tag and class.
+fn initial_score(node: NodeRef<Node>) -> f64 {
+    let tag_score = match ElementRef::wrap(node).map(|element| element.value().name()) {
+        Some("div") | Some("article") | Some("main") => 5.0,
+        Some("pre") | Some("td") | Some("blockquote") => 3.0,
+        Some("address") | Some("ol") | Some("ul") | Some("dl") | Some("dd") | Some("dt")
+        | Some("li") | Some("form") => -3.0,
+        Some("h1") | Some("h2") | Some("h3") | Some("h4") | Some("h5") | Some("h6")
+        | Some("th") => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(node)
+}
+
+/// The function `is_unlikely` tells whether a node sits inside an element whose class or id marks
+/// it as page chrome rather than content.
+fn is_unlikely(node: NodeRef<Node>) -> bool {
+    node.ancestors()
+        .chain(std::iter::once(node))
+        .filter_map(ElementRef::wrap)
+        .any(|element| {
+            if matches!(element.value().name(), "body" | "html" | "article" | "main") {
+                return false;
+            }
+            let signature = format!(
+                "{} {}",
+                element.value().attr("class").unwrap_or(""),
+                element.value().id().unwrap_or("")
+            );
+            UNLIKELY.is_match(&signature) && !MAYBE_CANDIDATE.is_match(&signature)
+        })
+}
+
+/// The function `link_density` returns the share of a node's text that sits inside links.
+fn link_density(node: NodeRef<Node>) -> f64 {
+    let total = node_text(node).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = node
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| element.value().name() == "a")
+        .map(|element| element.text().collect::<String>().chars().count())
+        .sum();
+    linked as f64 / total as f64
+}
+
+fn node_text(node: NodeRef<Node>) -> String {
+    node.descendants()
+        .filter_map(|node| match node.value() {
+            Node::Text(text) => Some(&**text),
+            _ => None,
+        })
+        .collect()
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn is_removed(element: &ElementRef) -> bool {
+    if REMOVED_TAGS.contains(&element.value().name()) {
+        return true;
+    }
+    let signature = format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or(""),
+        element.value().id().unwrap_or("")
+    );
+    UNLIKELY.is_match(&signature) && !MAYBE_CANDIDATE.is_match(&signature)
+}
+
+/// The function `write_html` serializes a node without the removed elements and attributes.
+fn write_html(node: NodeRef<Node>, out: &mut String) {
+    match node.value() {
+        Node::Text(text) => out.push_str(&escape(text, false)),
+        Node::Element(_) => {
+            let element = ElementRef::wrap(node).unwrap();
+            if is_removed(&element) {
+                return;
+            }
+            let name = element.value().name();
+            out.push('<');
+            out.push_str(name);
+            // written in a fixed order, the parsed attributes are not ordered
+            for attribute in KEPT_ATTRIBUTES {
+                if let Some(value) = element.value().attr(attribute) {
+                    out.push_str(&format!(" {}=\"{}\"", attribute, escape(value, true)));
+                }
+            }
+            out.push('>');
+            if VOID_TAGS.contains(&name) {
+                return;
+            }
+            for child in node.children() {
+                write_html(child, out);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        _ => {}
+    }
+}
+
+/// The function `write_text` flattens a node to text, starting a new paragraph for block elements.
+fn write_text(node: NodeRef<Node>, out: &mut String) {
+    match node.value() {
+        Node::Text(text) => {
+            let collapsed = collapse_whitespace(text);
+            if collapsed.is_empty() {
+                return;
+            }
+            if text.starts_with(char::is_whitespace) && !out.ends_with(char::is_whitespace) {
+                out.push(' ');
+            }
+            out.push_str(&collapsed);
+            if text.ends_with(char::is_whitespace) {
+                out.push(' ');
+            }
+        }
+        Node::Element(_) => {
+            let element = ElementRef::wrap(node).unwrap();
+            if is_removed(&element) {
+                return;
+            }
+            let name = element.value().name();
+            if name == "br" {
+                out.push('\n');
+                return;
+            }
+            let is_block = BLOCK_TAGS.contains(&name);
+            if is_block {
+                out.push_str("\n\n");
+            }
+            for child in node.children() {
+                write_text(child, out);
+            }
+            if is_block {
+                out.push_str("\n\n");
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The function `tidy_text` trims every line and squeezes runs of blank lines into one.
+fn tidy_text(text: &str) -> String {
+    let mut tidy = String::new();
+    let mut blank = true;
+    for line in text.lines().map(|line| line.trim()) {
+        if line.is_empty() {
+            if !blank {
+                tidy.push('\n');
+            }
+            blank = true;
+            continue;
+        }
+        tidy.push_str(line);
+        tidy.push('\n');
+        blank = false;
+    }
+    tidy.trim_end().to_string()
+}
+
+fn escape(value: &str, attribute: bool) -> String {
+    let escaped = value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    if attribute {
+        escaped.replace('"', "&quot;")
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(topic: &str) -> String {
+        format!(
+            "<p>{} is explained here in some detail, with enough words, commas, and clauses to \
+             look like the body of a real article rather than a caption.</p>",
+            topic
+        )
+    }
+
+    fn page(body: &str) -> String {
+        format!(
+            "<html><head><title>Post</title><script>var tracking = 1;</script></head><body>\
+             <div class=\"nav\"><a href=\"/\">Home</a> <a href=\"/about\">About the author of this blog</a></div>\
+             {}\
+             <div class=\"sidebar\"><p>Subscribe to the newsletter for more posts like this one, every week.</p></div>\
+             <div id=\"comments\"><p>First comment, this post is great and I agree with every word of it.</p></div>\
+             </body></html>",
+            body
+        )
+    }
+
+    #[test]
+    fn the_main_content_is_kept_without_page_chrome() {
+        let html = page(&format!(
+            "<div class=\"post-content\" onclick=\"track()\">{}{}<script>alert(1)</script>\
+             <p>See <a href=\"https://example.com/docs\" class=\"external\">the docs</a>.</p>{}</div>",
+            paragraph("Ownership"),
+            paragraph("Borrowing"),
+            paragraph("Lifetimes")
+        ));
+        let article = extract(&html).unwrap();
+
+        assert!(article.html.starts_with("<div><p>Ownership is explained"));
+        assert!(article
+            .html
+            .contains("<a href=\"https://example.com/docs\">the docs</a>"));
+        for chrome in [
+            "About the author",
+            "Subscribe",
+            "First comment",
+            "script",
+            "alert",
+        ] {
+            assert!(!article.html.contains(chrome), "{} was kept", chrome);
+            assert!(!article.text.contains(chrome), "{} was kept", chrome);
+        }
+        assert!(!article.html.contains("onclick"));
+        assert!(!article.html.contains("class"));
+
+        let paragraphs: Vec<&str> = article.text.split("\n\n").collect();
+        assert_eq!(paragraphs.len(), 4);
+        assert!(paragraphs[1].starts_with("Borrowing is explained"));
+        assert_eq!(paragraphs[2], "See the docs.");
+    }
+
+    #[test]
+    fn the_reading_time_is_rounded_up() {
+        let body: String = (0..30).map(|_| paragraph("Rust")).collect();
+        let article = extract(&page(&format!("<article>{}</article>", body))).unwrap();
+
+        assert_eq!(article.word_count, article.text.split_whitespace().count());
+        assert_eq!(article.word_count, 30 * 26);
+        assert_eq!(article.reading_minutes, 4);
+    }
+
+    #[test]
+    fn a_list_of_links_is_not_taken_for_content() {
+        let links: String = (0..10)
+            .map(|index| {
+                format!(
+                    "<p><a href=\"/{}\">A link to another post with a rather long title, number {}</a></p>",
+                    index, index
+                )
+            })
+            .collect();
+        let html = page(&format!(
+            "<div class=\"links\">{}</div><div class=\"entry\">{}{}</div>",
+            links,
+            paragraph("Traits"),
+            paragraph("Generics")
+        ));
+        let article = extract(&html).unwrap();
+
+        assert!(article.text.starts_with("Traits is explained"));
+        assert!(!article.text.contains("A link to another post"));
+    }
+
+    #[test]
+    fn a_page_without_paragraphs_has_no_article() {
+        assert!(extract("<html><body><div>Short</div><p>Too short.</p></body></html>").is_none());
+        assert!(extract("").is_none());
+    }
+
+    #[test]
+    fn text_and_attributes_are_escaped() {
+        let html = page(&format!(
+            "<div class=\"content\">{}<p>Compare a &lt; b &amp;&amp; c &gt; d, <img src=\"/a.png?x=1&amp;y=&quot;2&quot;\" alt=\"chart\"> then read on, it gets better.</p></div>",
+            paragraph("Escaping")
+        ));
+        let article = extract(&html).unwrap();
+
+        assert!(article.html.contains("a &lt; b &amp;&amp; c &gt; d"));
+        assert!(article
+            .html
+            .contains("<img src=\"/a.png?x=1&amp;y=&quot;2&quot;\" alt=\"chart\">"));
+        assert!(article.text.contains("a < b && c > d"));
+    }
+}
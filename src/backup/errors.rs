@@ -0,0 +1,51 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BackupError {
+    DbError(sqlite::Error),
+    IoError(std::io::Error),
+    JsonError(serde_json::Error),
+    SnapshotError(String),
+    InvalidArchive(String),
+    UnsupportedVersion(u64),
+}
+
+/// The `impl fmt::Display for BackupError` block formats the reason a backup could not be written or
+/// restored.
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            BackupError::DbError(e) => write!(f, "Database error: {}", e),
+            BackupError::IoError(e) => write!(f, "Reading or writing the archive failed: {}", e),
+            BackupError::JsonError(e) => write!(f, "Malformed archive: {}", e),
+            BackupError::SnapshotError(message) => {
+                write!(f, "Taking a snapshot of the database failed: {}", message)
+            }
+            BackupError::InvalidArchive(message) => write!(f, "Not a backup archive: {}", message),
+            BackupError::UnsupportedVersion(version) => write!(
+                f,
+                "The archive has version {}, this build reads up to version {}",
+                version,
+                super::BACKUP_VERSION
+            ),
+        }
+    }
+}
+
+impl From<sqlite::Error> for BackupError {
+    fn from(error: sqlite::Error) -> Self {
+        BackupError::DbError(error)
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(error: std::io::Error) -> Self {
+        BackupError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(error: serde_json::Error) -> Self {
+        BackupError::JsonError(error)
+    }
+}
@@ -0,0 +1,1049 @@
+pub mod errors;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sqlite::{Connection, State, Value};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thread_safe::ThreadSafe;
+
+use self::errors::BackupError;
+
+/// The value of the `format` field identifying a backup archive.
+pub const BACKUP_FORMAT: &str = "rss_rust-backup";
+
+/// The version of the archive layout written by this build. Restoring reads every version up to
+/// this one.
+pub const BACKUP_VERSION: u64 = 1;
+
+/// The number of pages copied per step of the online backup, other connections can write to the
+/// database between two steps.
+const PAGES_PER_STEP: i32 = 256;
+
+/// The columns telling apart the rows of the tables keyed by an `AUTOINCREMENT` id. An id only
+/// means something in the database the archive was taken from, so merging looks a row up by these
+/// columns instead and lets SQLite give the rows it adds a new id.
+const NATURAL_KEYS: &[(&str, &[&str])] = &[
+    ("rss_table", &["rss_url", "profile"]),
+    (
+        "rules",
+        &[
+            "profile", "rss_url", "field", "operator", "value", "negate", "action", "tag",
+        ],
+    ),
+    ("hooks", &["profile", "command"]),
+    ("hook_failures", &["hook_id", "entry_hash", "failed_at"]),
+    ("notification_sinks", &["kind", "url", "profile", "rss_url"]),
+    ("enclosures", &["hash", "url"]),
+    ("saved_searches", &["name"]),
+];
+
+/// The columns holding the id of a row of another table, as `(table, column, referenced table)`.
+/// Merging rewrites them to the id the referenced row has in the database.
+const FOREIGN_KEYS: &[(&str, &str, &str)] = &[("hook_failures", "hook_id", "hooks")];
+
+/// The ids given by the archive to the rows of each table, mapped to their id in the database.
+type IdMap = HashMap<String, HashMap<i64, i64>>;
+
+/// The `BackupLayout` enum is how the rows are laid out in the archive.
+///
+/// Variants:
+///
+/// * `Json`: A single JSON document holding the header and the rows of every table.
+/// * `JsonLines`: The header on the first line, then one line per row, which restores without
+///   holding the whole archive in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupLayout {
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "jsonl")]
+    JsonLines,
+}
+
+impl Display for BackupLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupLayout::Json => write!(f, "json"),
+            BackupLayout::JsonLines => write!(f, "jsonl"),
+        }
+    }
+}
+
+impl FromStr for BackupLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(BackupLayout::Json),
+            "jsonl" => Ok(BackupLayout::JsonLines),
+            _ => Err(format!("Unknown backup format: {}", s)),
+        }
+    }
+}
+
+/// The `RestoreMode` enum is what happens to the rows already in the database on restore.
+///
+/// Variants:
+///
+/// * `Merge`: Keep the existing rows and only add the rows of the archive that are missing, rows
+///   with an id are matched on their `NATURAL_KEYS`.
+/// * `Replace`: Delete every row first, the database ends up holding exactly the archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    Merge,
+    Replace,
+}
+
+/// The `TableSummary` struct describes a table of the archive in its header.
+///
+/// Properties:
+///
+/// * `name`: The name of the table.
+/// * `columns`: The names of the columns, in the order of the values of each row.
+/// * `rows`: The number of rows of the table in the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSummary {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: usize,
+}
+
+/// The `BackupHeader` struct opens every archive.
+///
+/// Properties:
+///
+/// * `format`: Always `BACKUP_FORMAT`.
+/// * `version`: The version of the archive layout, see `BACKUP_VERSION`.
+/// * `created_at`: When the snapshot was taken, in RFC 3339.
+/// * `layout`: How the rows follow the header.
+/// * `tables`: The tables in the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupHeader {
+    pub format: String,
+    pub version: u64,
+    pub created_at: String,
+    pub layout: BackupLayout,
+    pub tables: Vec<TableSummary>,
+}
+
+/// The `JsonArchive` struct is a whole archive in the `Json` layout.
+///
+/// Properties:
+///
+/// * `header`: The header of the archive, flattened into the document.
+/// * `rows`: The rows of each table, keyed by the name of the table.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonArchive {
+    #[serde(flatten)]
+    header: BackupHeader,
+    rows: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The `JsonLine` struct is a row of the archive in the `JsonLines` layout.
+///
+/// Properties:
+///
+/// * `table`: The table the row belongs to.
+/// * `values`: The values of the row, in the order of the columns in the header.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonLine {
+    table: String,
+    values: Vec<serde_json::Value>,
+}
+
+/// The `RestoreReport` struct sums up a restore.
+///
+/// Properties:
+///
+/// * `header`: The header of the restored archive.
+/// * `inserted`: The number of rows written to the database.
+/// * `ignored`: The number of rows of the archive already in the database when merging.
+/// * `skipped_tables`: The tables of the archive this database does not have.
+#[derive(Debug, Clone)]
+pub struct RestoreReport {
+    pub header: BackupHeader,
+    pub inserted: usize,
+    pub ignored: usize,
+    pub skipped_tables: Vec<String>,
+}
+
+impl RestoreReport {
+    fn count(&mut self, added: bool) {
+        if added {
+            self.inserted += 1;
+        } else {
+            self.ignored += 1;
+        }
+    }
+}
+
+/// The function `default_path` returns the file name `backup` writes to when none is given, made
+/// of the current time and the extension of the layout.
+///
+/// Arguments:
+///
+/// * `layout`: The layout the archive is written in.
+///
+/// Returns:
+///
+/// The function `default_path` returns a `PathBuf` like `rss_rust-20240101-120000.jsonl.gz`.
+pub fn default_path(layout: BackupLayout) -> PathBuf {
+    PathBuf::from(format!(
+        "rss_rust-{}.{}.gz",
+        Utc::now().format("%Y%m%d-%H%M%S"),
+        layout
+    ))
+}
+
+/// The function `backup` takes a consistent snapshot of the database with the SQLite online backup
+/// and writes every table of it as a gzip compressed archive.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` to the database to back up. Other processes can keep writing to
+///   it, the archive holds the database as it was when the snapshot completed.
+/// * `layout`: How the rows are laid out in the archive.
+/// * `output`: Where the compressed archive is written.
+///
+/// Returns:
+///
+/// The function `backup` returns the `BackupHeader` of the written archive.
+pub fn backup<W: Write>(
+    connection: &ThreadSafe<&Connection>,
+    layout: BackupLayout,
+    output: W,
+) -> Result<BackupHeader, BackupError> {
+    let snapshot_path = std::env::temp_dir().join(format!(
+        "rss_rust-snapshot-{}-{}.sqlite3",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let result = (|| {
+        let snapshot = sqlite::open(&snapshot_path)?;
+        copy_database(connection.get_ref(), &snapshot)?;
+        write_archive(&snapshot, layout, output)
+    })();
+    let _ = std::fs::remove_file(&snapshot_path);
+    result
+}
+
+/// The function `copy_database` copies `source` page by page into `destination` with the SQLite
+/// online backup API.
+///
+/// Arguments:
+///
+/// * `source`: The database to copy.
+/// * `destination`: The database overwritten with the copy.
+///
+/// Returns:
+///
+/// The function `copy_database` returns a `Result<(), BackupError>`.
+fn copy_database(source: &Connection, destination: &Connection) -> Result<(), BackupError> {
+    let main = CString::new("main").expect("no interior nul");
+    // SAFETY: both handles stay open for the whole copy and the backup object is finished before
+    // returning.
+    unsafe {
+        let backup = sqlite3_sys::sqlite3_backup_init(
+            destination.as_raw(),
+            main.as_ptr(),
+            source.as_raw(),
+            main.as_ptr(),
+        );
+        if backup.is_null() {
+            return Err(BackupError::SnapshotError(error_message(destination)));
+        }
+        loop {
+            match sqlite3_sys::sqlite3_backup_step(backup, PAGES_PER_STEP) {
+                sqlite3_sys::SQLITE_OK => continue,
+                sqlite3_sys::SQLITE_BUSY | sqlite3_sys::SQLITE_LOCKED => {
+                    std::thread::sleep(std::time::Duration::from_millis(50))
+                }
+                _ => break,
+            }
+        }
+        match sqlite3_sys::sqlite3_backup_finish(backup) {
+            sqlite3_sys::SQLITE_OK => Ok(()),
+            _ => Err(BackupError::SnapshotError(error_message(destination))),
+        }
+    }
+}
+
+/// The function `error_message` returns the message of the last error of `connection`.
+fn error_message(connection: &Connection) -> String {
+    // SAFETY: SQLite returns a nul terminated string owned by the connection.
+    unsafe {
+        CStr::from_ptr(sqlite3_sys::sqlite3_errmsg(connection.as_raw()))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// The function `user_tables` returns the names of the tables of the database, leaving out the
+/// internal tables of SQLite.
+fn user_tables(connection: &Connection) -> Result<Vec<String>, sqlite::Error> {
+    let mut statement = connection.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+         ORDER BY name",
+    )?;
+    let mut tables = Vec::new();
+    while let State::Row = statement.next()? {
+        tables.push(statement.read::<String, _>("name")?);
+    }
+    Ok(tables)
+}
+
+/// The function `table_columns` returns the names of the columns of `table`.
+fn table_columns(connection: &Connection, table: &str) -> Result<Vec<String>, sqlite::Error> {
+    let mut statement = connection.prepare(format!("PRAGMA table_info({})", table))?;
+    let mut columns = Vec::new();
+    while let State::Row = statement.next()? {
+        columns.push(statement.read::<String, _>("name")?);
+    }
+    Ok(columns)
+}
+
+/// The function `table_rows` calls `on_row` with the values of every row of `table`, in the order
+/// of `columns`.
+fn table_rows<F>(
+    connection: &Connection,
+    table: &str,
+    columns: &[String],
+    mut on_row: F,
+) -> Result<usize, BackupError>
+where
+    F: FnMut(Vec<serde_json::Value>) -> Result<(), BackupError>,
+{
+    let mut statement = connection.prepare(format!(
+        "SELECT {} FROM {} ORDER BY rowid",
+        columns.join(", "),
+        table
+    ))?;
+    let mut count = 0;
+    while let State::Row = statement.next()? {
+        let mut values = Vec::with_capacity(columns.len());
+        for index in 0..columns.len() {
+            values.push(value_to_json(statement.read::<Value, _>(index)?));
+        }
+        on_row(values)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// The function `write_archive` writes every table of `snapshot` to `output` in `layout`.
+fn write_archive<W: Write>(
+    snapshot: &Connection,
+    layout: BackupLayout,
+    output: W,
+) -> Result<BackupHeader, BackupError> {
+    let mut header = BackupHeader {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        layout,
+        tables: Vec::new(),
+    };
+    for table in user_tables(snapshot)? {
+        let columns = table_columns(snapshot, &table)?;
+        let mut statement = snapshot.prepare(format!("SELECT COUNT(*) FROM {}", table))?;
+        statement.next()?;
+        let rows = statement.read::<i64, _>(0)? as usize;
+        header.tables.push(TableSummary {
+            name: table,
+            columns,
+            rows,
+        });
+    }
+
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    match layout {
+        BackupLayout::Json => {
+            let mut rows = serde_json::Map::new();
+            for table in header.tables.iter() {
+                let mut table_rows_json = Vec::with_capacity(table.rows);
+                table_rows(snapshot, &table.name, &table.columns, |values| {
+                    table_rows_json.push(serde_json::Value::Array(values));
+                    Ok(())
+                })?;
+                rows.insert(
+                    table.name.clone(),
+                    serde_json::Value::Array(table_rows_json),
+                );
+            }
+            let archive = JsonArchive {
+                header: header.clone(),
+                rows,
+            };
+            serde_json::to_writer(&mut encoder, &archive)?;
+            encoder.write_all(b"\n")?;
+        }
+        BackupLayout::JsonLines => {
+            serde_json::to_writer(&mut encoder, &header)?;
+            encoder.write_all(b"\n")?;
+            for table in header.tables.iter() {
+                table_rows(snapshot, &table.name, &table.columns, |values| {
+                    let line = JsonLine {
+                        table: table.name.clone(),
+                        values,
+                    };
+                    serde_json::to_writer(&mut encoder, &line)?;
+                    encoder.write_all(b"\n")?;
+                    Ok(())
+                })?;
+            }
+        }
+    }
+    encoder.finish()?.flush()?;
+    Ok(header)
+}
+
+/// The function `value_to_json` converts a value read from SQLite to JSON. Blobs become an object
+/// holding their base64 encoding so they can be told apart from text on restore.
+fn value_to_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(integer) => integer.into(),
+        Value::Float(float) => serde_json::Number::from_f64(float)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::String(string) => string.into(),
+        Value::Binary(bytes) => serde_json::json!({ "base64": STANDARD.encode(bytes) }),
+    }
+}
+
+/// The function `json_to_value` converts a value of the archive back to a SQLite value.
+fn json_to_value(value: &serde_json::Value) -> Result<Value, BackupError> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(boolean) => Value::Integer(*boolean as i64),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(integer) => Value::Integer(integer),
+            None => Value::Float(number.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(string) => Value::String(string.clone()),
+        serde_json::Value::Object(object) => {
+            let encoded = object
+                .get("base64")
+                .and_then(|encoded| encoded.as_str())
+                .ok_or_else(|| {
+                    BackupError::InvalidArchive(format!("unexpected value {}", value))
+                })?;
+            Value::Binary(
+                STANDARD
+                    .decode(encoded)
+                    .map_err(|e| BackupError::InvalidArchive(e.to_string()))?,
+            )
+        }
+        serde_json::Value::Array(_) => {
+            return Err(BackupError::InvalidArchive(format!(
+                "unexpected value {}",
+                value
+            )))
+        }
+    })
+}
+
+/// The function `open_archive` opens the archive at `path`, `-` being the standard input, and
+/// decompresses it when it is gzip compressed.
+///
+/// Arguments:
+///
+/// * `path`: The path of the archive.
+///
+/// Returns:
+///
+/// The function `open_archive` returns a reader of the decompressed archive.
+pub fn open_archive(path: &Path) -> Result<Box<dyn BufRead>, BackupError> {
+    let input: Box<dyn Read> = if path == Path::new("-") {
+        Box::new(std::io::stdin())
+    } else {
+        Box::new(std::fs::File::open(path)?)
+    };
+    let mut input = BufReader::new(input);
+    let is_gzip = input.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    Ok(if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(input)))
+    } else {
+        Box::new(input)
+    })
+}
+
+/// The function `check_header` rejects archives that are not a backup or were written by a newer
+/// build.
+fn check_header(header: &BackupHeader) -> Result<(), BackupError> {
+    if header.format != BACKUP_FORMAT {
+        return Err(BackupError::InvalidArchive(format!(
+            "unknown format {}",
+            header.format
+        )));
+    }
+    if header.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(header.version));
+    }
+    Ok(())
+}
+
+/// The `TableWriter` struct inserts the rows of one table of the archive into the database.
+///
+/// Properties:
+///
+/// * `name`: The name of the table.
+/// * `statement`: The prepared insert, `None` when the database does not have the table.
+/// * `positions`: For each column inserted, the position of its value in the rows of the archive.
+/// * `lookup`: When merging a table of `NATURAL_KEYS`, the select of the id of a row by its key.
+/// * `key_positions`: The positions of the values of the key in the rows of the archive.
+/// * `id_position`: The position of the id in the rows of the archive, when it is looked up.
+/// * `references`: The positions of the `FOREIGN_KEYS` values with the table they reference.
+/// * `deferred`: The rows waiting for the rows they reference, see `restore`.
+struct TableWriter<'l> {
+    name: String,
+    statement: Option<sqlite::Statement<'l>>,
+    positions: Vec<usize>,
+    lookup: Option<sqlite::Statement<'l>>,
+    key_positions: Vec<usize>,
+    id_position: Option<usize>,
+    references: Vec<(usize, &'static str)>,
+    deferred: Vec<Vec<serde_json::Value>>,
+}
+
+impl<'l> TableWriter<'l> {
+    /// The function `new` prepares the insert of the columns of `table` the database knows, the
+    /// columns added after the archive was written get their default value. When merging a table
+    /// of `NATURAL_KEYS`, the id is left to SQLite.
+    fn new(
+        connection: &'l Connection,
+        table: &TableSummary,
+        existing: &[String],
+        mode: RestoreMode,
+    ) -> Result<Self, BackupError> {
+        let mut writer = TableWriter {
+            name: table.name.clone(),
+            statement: None,
+            positions: Vec::new(),
+            lookup: None,
+            key_positions: Vec::new(),
+            id_position: None,
+            references: Vec::new(),
+            deferred: Vec::new(),
+        };
+        if !existing.contains(&table.name) {
+            return Ok(writer);
+        }
+        let columns = table_columns(connection, &table.name)?;
+        let position = |column: &str| {
+            table
+                .columns
+                .iter()
+                .position(|name| name == column)
+                .filter(|_| columns.iter().any(|name| name == column))
+        };
+        if mode == RestoreMode::Merge {
+            let key = NATURAL_KEYS
+                .iter()
+                .find(|(name, _)| *name == table.name)
+                .map(|(_, key)| *key)
+                .unwrap_or_default();
+            let key_positions: Option<Vec<usize>> =
+                key.iter().map(|column| position(column)).collect();
+            if let (false, Some(key_positions), Some(id_position)) =
+                (key.is_empty(), key_positions, position("id"))
+            {
+                let conditions: Vec<String> = key
+                    .iter()
+                    .map(|column| format!("{} IS ?", column))
+                    .collect();
+                writer.lookup = Some(connection.prepare(format!(
+                    "SELECT id FROM {} WHERE {} LIMIT 1",
+                    table.name,
+                    conditions.join(" AND ")
+                ))?);
+                writer.key_positions = key_positions;
+                writer.id_position = Some(id_position);
+            }
+            for (_, column, referenced) in FOREIGN_KEYS
+                .iter()
+                .filter(|(name, _, _)| *name == table.name)
+            {
+                if let Some(position) = position(column) {
+                    writer.references.push((position, *referenced));
+                }
+            }
+        }
+        let mut names = Vec::new();
+        for (position, column) in table.columns.iter().enumerate() {
+            if Some(position) == writer.id_position || !columns.contains(column) {
+                continue;
+            }
+            names.push(column.as_str());
+            writer.positions.push(position);
+        }
+        let placeholders = vec!["?"; names.len()].join(", ");
+        writer.statement = Some(connection.prepare(format!(
+            "INSERT {} INTO {} ({}) VALUES ({})",
+            match mode {
+                RestoreMode::Merge => "OR IGNORE",
+                RestoreMode::Replace => "OR REPLACE",
+            },
+            table.name,
+            names.join(", "),
+            placeholders
+        ))?);
+        Ok(writer)
+    }
+
+    /// The function `insert` writes a row of the archive and returns whether it was added. The
+    /// ids it references are rewritten with `ids`, and the id it gets is recorded there. A row
+    /// referencing a row missing from the archive is not added.
+    fn insert(
+        &mut self,
+        connection: &Connection,
+        values: &[serde_json::Value],
+        ids: &mut IdMap,
+    ) -> Result<bool, BackupError> {
+        let statement = match self.statement.as_mut() {
+            Some(statement) => statement,
+            None => return Ok(false),
+        };
+        let mut values = values.to_vec();
+        for (position, referenced) in self.references.iter() {
+            let local = values
+                .get(*position)
+                .and_then(|id| id.as_i64())
+                .and_then(|id| ids.get(*referenced)?.get(&id));
+            match local {
+                Some(local) => values[*position] = (*local).into(),
+                None => return Ok(false),
+            }
+        }
+        let archive_id = self
+            .id_position
+            .and_then(|position| values.get(position))
+            .and_then(|id| id.as_i64());
+
+        if let Some(lookup) = self.lookup.as_mut() {
+            let key = row_values(&values, &self.key_positions)?;
+            lookup.reset()?;
+            lookup.bind(&key[..])?;
+            if let State::Row = lookup.next()? {
+                let local = lookup.read::<i64, _>(0)?;
+                if let Some(archive_id) = archive_id {
+                    ids.entry(self.name.clone())
+                        .or_default()
+                        .insert(archive_id, local);
+                }
+                return Ok(false);
+            }
+        }
+
+        let row = row_values(&values, &self.positions)?;
+        statement.reset()?;
+        statement.bind(&row[..])?;
+        statement.next()?;
+        let added = connection.change_count() > 0;
+        if let (true, Some(archive_id)) = (added, archive_id) {
+            let mut last = connection.prepare("SELECT last_insert_rowid()")?;
+            last.next()?;
+            ids.entry(self.name.clone())
+                .or_default()
+                .insert(archive_id, last.read::<i64, _>(0)?);
+        }
+        Ok(added)
+    }
+}
+
+/// The function `row_values` converts the values at `positions` of a row of the archive.
+fn row_values(
+    values: &[serde_json::Value],
+    positions: &[usize],
+) -> Result<Vec<Value>, BackupError> {
+    positions
+        .iter()
+        .map(|position| {
+            values
+                .get(*position)
+                .ok_or_else(|| BackupError::InvalidArchive("row shorter than its columns".into()))
+                .and_then(json_to_value)
+        })
+        .collect()
+}
+
+/// The function `restore` writes the rows of a backup archive into the database, in a single
+/// transaction so a failed restore leaves the database untouched.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` to the database to restore into.
+/// * `archive`: The decompressed archive, see `open_archive`.
+/// * `mode`: Whether the rows already in the database are kept or deleted first.
+///
+/// Returns:
+///
+/// The function `restore` returns a `RestoreReport`.
+pub fn restore<R: BufRead>(
+    connection: &ThreadSafe<&Connection>,
+    mut archive: R,
+    mode: RestoreMode,
+) -> Result<RestoreReport, BackupError> {
+    let connection = connection.get_ref();
+    let mut first_line = String::new();
+    archive.read_line(&mut first_line)?;
+    let first: serde_json::Value = serde_json::from_str(&first_line)?;
+    let header: BackupHeader = serde_json::from_value(first.clone())?;
+    check_header(&header)?;
+
+    let existing = user_tables(connection)?;
+    let mut report = RestoreReport {
+        header: header.clone(),
+        inserted: 0,
+        ignored: 0,
+        skipped_tables: header
+            .tables
+            .iter()
+            .filter(|table| !existing.contains(&table.name))
+            .map(|table| table.name.clone())
+            .collect(),
+    };
+
+    connection.execute("BEGIN")?;
+    let result = (|| {
+        if mode == RestoreMode::Replace {
+            for table in existing.iter() {
+                connection.execute(format!("DELETE FROM {}", table))?;
+            }
+        }
+        let mut writers = Vec::with_capacity(header.tables.len());
+        for table in header.tables.iter() {
+            writers.push(TableWriter::new(connection, table, &existing, mode)?);
+        }
+        let mut ids = IdMap::new();
+        let mut insert = |table: usize, values: &[serde_json::Value]| -> Result<(), BackupError> {
+            let writer = &mut writers[table];
+            if writer.statement.is_none() {
+                return Ok(());
+            }
+            // the tables come in the order of their names, the rows they reference may follow
+            if !writer.references.is_empty() {
+                writer.deferred.push(values.to_vec());
+                return Ok(());
+            }
+            report.count(writer.insert(connection, values, &mut ids)?);
+            Ok(())
+        };
+        match header.layout {
+            BackupLayout::Json => {
+                let archive: JsonArchive = serde_json::from_value(first)?;
+                for (index, table) in header.tables.iter().enumerate() {
+                    let rows = match archive.rows.get(&table.name) {
+                        Some(serde_json::Value::Array(rows)) => rows,
+                        _ => continue,
+                    };
+                    for row in rows {
+                        match row {
+                            serde_json::Value::Array(values) => insert(index, values)?,
+                            _ => {
+                                return Err(BackupError::InvalidArchive(
+                                    "row is not an array".into(),
+                                ))
+                            }
+                        }
+                    }
+                }
+            }
+            BackupLayout::JsonLines => {
+                for line in archive.lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let line: JsonLine = serde_json::from_str(&line)?;
+                    let index = header
+                        .tables
+                        .iter()
+                        .position(|table| table.name == line.table)
+                        .ok_or_else(|| {
+                            BackupError::InvalidArchive(format!(
+                                "row of {} missing from the header",
+                                line.table
+                            ))
+                        })?;
+                    insert(index, &line.values)?;
+                }
+            }
+        }
+        for writer in writers.iter_mut() {
+            for values in std::mem::take(&mut writer.deferred) {
+                report.count(writer.insert(connection, &values, &mut ids)?);
+            }
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => {
+            connection.execute("COMMIT")?;
+            Ok(report)
+        }
+        Err(e) => {
+            let _ = connection.execute("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_database;
+
+    /// The function `database` opens an in-memory database holding a feed, two entries with a tag
+    /// and a category, and an icon, so text, integers, `NULL` and blobs are all backed up.
+    fn database() -> Connection {
+        let connection = open_database(":memory:").unwrap();
+        connection
+            .execute(
+                "INSERT INTO rss_table (rss_url, profile) VALUES ('https://a.test/rss', 'work');
+                INSERT INTO rss_entries (hash, title, link, publish_date, rss_url, is_starred)
+                VALUES ('h1', 'First', 'https://a.test/1', '2024-01-02 03:04:05',
+                'https://a.test/rss', 1);
+                INSERT INTO rss_entries (hash, title, link, publish_date, rss_url, description)
+                VALUES ('h2', 'Second', 'https://a.test/2', '2024-01-03 03:04:05',
+                'https://a.test/rss', NULL);
+                INSERT INTO entry_tags (hash, tag) VALUES ('h1', 'rust');
+                INSERT INTO entry_categories (hash, category) VALUES ('h2', 'Parsing');
+                INSERT INTO feed_icons (rss_url, source_url, mime_type, data)
+                VALUES ('https://a.test/rss', 'https://a.test/favicon.ico', 'image/x-icon',
+                X'00000100FF');",
+            )
+            .unwrap();
+        connection
+    }
+
+    /// The function `dump` returns every row of every table, to compare two databases.
+    fn dump(connection: &Connection) -> Vec<(String, Vec<Vec<serde_json::Value>>)> {
+        user_tables(connection)
+            .unwrap()
+            .into_iter()
+            .map(|table| {
+                let columns = table_columns(connection, &table).unwrap();
+                let mut rows = vec![];
+                table_rows(connection, &table, &columns, |values| {
+                    rows.push(values);
+                    Ok(())
+                })
+                .unwrap();
+                rows.sort_by_key(|values| serde_json::Value::Array(values.clone()).to_string());
+                (table, rows)
+            })
+            .collect()
+    }
+
+    fn archive(connection: &Connection, layout: BackupLayout) -> Vec<u8> {
+        let mut archive = vec![];
+        backup(&ThreadSafe::new(connection), layout, &mut archive).unwrap();
+        archive
+    }
+
+    fn restore_archive(
+        connection: &Connection,
+        archive: &[u8],
+        mode: RestoreMode,
+    ) -> Result<RestoreReport, BackupError> {
+        let archive = BufReader::new(GzDecoder::new(archive));
+        restore(&ThreadSafe::new(connection), archive, mode)
+    }
+
+    #[test]
+    fn backup_restores_into_an_empty_database() {
+        let source = database();
+        for layout in [BackupLayout::Json, BackupLayout::JsonLines] {
+            let archive = archive(&source, layout);
+            assert!(archive.starts_with(&[0x1f, 0x8b]));
+
+            let restored = open_database(":memory:").unwrap();
+            let report = restore_archive(&restored, &archive, RestoreMode::Merge).unwrap();
+            assert_eq!(report.header.layout, layout);
+            assert_eq!(report.inserted, 6);
+            assert_eq!(report.ignored, 0);
+            assert!(report.skipped_tables.is_empty());
+            assert_eq!(dump(&restored), dump(&source));
+
+            let report = restore_archive(&restored, &archive, RestoreMode::Merge).unwrap();
+            assert_eq!((report.inserted, report.ignored), (0, 6));
+            assert_eq!(dump(&restored), dump(&source));
+        }
+    }
+
+    #[test]
+    fn merge_is_idempotent_and_keeps_existing_rows() {
+        let source = database();
+        for layout in [BackupLayout::Json, BackupLayout::JsonLines] {
+            let archive = archive(&source, layout);
+            let restored = database();
+            restored
+                .execute(
+                    "INSERT INTO entry_tags (hash, tag) VALUES ('h2', 'local');
+                    UPDATE rss_entries SET is_read = 1 WHERE hash = 'h2';",
+                )
+                .unwrap();
+            let before = dump(&restored);
+
+            let report = restore_archive(&restored, &archive, RestoreMode::Merge).unwrap();
+            assert_eq!(report.inserted, 0);
+            assert_eq!(report.ignored, 6);
+            let report = restore_archive(&restored, &archive, RestoreMode::Merge).unwrap();
+            assert_eq!(report.inserted, 0);
+            assert_eq!(dump(&restored), before);
+        }
+    }
+
+    /// The function `column` returns the values of `query`, one string per row.
+    fn column(connection: &Connection, query: &str) -> Vec<String> {
+        let mut statement = connection.prepare(query).unwrap();
+        let mut values = vec![];
+        while let State::Row = statement.next().unwrap() {
+            values.push(statement.read::<String, _>(0).unwrap());
+        }
+        values
+    }
+
+    #[test]
+    fn merging_another_database_matches_rows_on_their_natural_key() {
+        // the same feed, hook, rule and enclosure were added in another order on another machine
+        let other = open_database(":memory:").unwrap();
+        other
+            .execute(
+                "INSERT INTO rss_table (rss_url, profile) VALUES
+                    ('https://c.test/rss', 'home'), ('https://a.test/rss', 'work');
+                INSERT INTO hooks (profile, command) VALUES ('work', 'notify'), (NULL, 'log');
+                INSERT INTO hook_failures (hook_id, entry_hash, error, failed_at)
+                VALUES (2, 'h1', 'exit 1', '2024-01-05 00:00:00');
+                INSERT INTO rules (profile, field, operator, value, action) VALUES
+                    ('work', 'title', 'contains', 'ads', 'skip'),
+                    (NULL, 'title', 'contains', 'rust', 'star');
+                INSERT INTO enclosures (hash, url, length) VALUES
+                    ('h2', 'https://a.test/2.mp3', 2), ('h1', 'https://a.test/1.mp3', 1);",
+            )
+            .unwrap();
+        for layout in [BackupLayout::Json, BackupLayout::JsonLines] {
+            let archive = archive(&other, layout);
+            let restored = database();
+            restored
+                .execute(
+                    "INSERT INTO hooks (profile, command) VALUES (NULL, 'log'), (NULL, 'backup');
+                    INSERT INTO rules (profile, field, operator, value, action)
+                    VALUES ('work', 'title', 'contains', 'ads', 'skip');
+                    INSERT INTO enclosures (hash, url, length)
+                    VALUES ('h1', 'https://a.test/1.mp3', 1);",
+                )
+                .unwrap();
+
+            let report = restore_archive(&restored, &archive, RestoreMode::Merge).unwrap();
+            assert_eq!((report.inserted, report.ignored), (5, 4));
+            assert_eq!(
+                column(
+                    &restored,
+                    "SELECT id || ' ' || rss_url || ' ' || profile FROM rss_table ORDER BY id"
+                ),
+                ["1 https://a.test/rss work", "2 https://c.test/rss home"]
+            );
+            assert_eq!(
+                column(
+                    &restored,
+                    "SELECT id || ' ' || command FROM hooks ORDER BY id"
+                ),
+                ["1 log", "2 backup", "3 notify"]
+            );
+            // the failure follows its hook to the id it has here
+            assert_eq!(
+                column(
+                    &restored,
+                    "SELECT hook_id || ' ' || error FROM hook_failures"
+                ),
+                ["1 exit 1"]
+            );
+            assert_eq!(
+                column(&restored, "SELECT value FROM rules ORDER BY id"),
+                ["ads", "rust"]
+            );
+            assert_eq!(
+                column(&restored, "SELECT url FROM enclosures ORDER BY id"),
+                ["https://a.test/1.mp3", "https://a.test/2.mp3"]
+            );
+
+            let before = dump(&restored);
+            let report = restore_archive(&restored, &archive, RestoreMode::Merge).unwrap();
+            assert_eq!((report.inserted, report.ignored), (0, 9));
+            assert_eq!(dump(&restored), before);
+        }
+    }
+
+    #[test]
+    fn replace_leaves_exactly_the_archive() {
+        let source = database();
+        for layout in [BackupLayout::Json, BackupLayout::JsonLines] {
+            let archive = archive(&source, layout);
+            let restored = database();
+            restored
+                .execute(
+                    "INSERT INTO entry_tags (hash, tag) VALUES ('h2', 'local');
+                    INSERT INTO rss_table (rss_url, profile) VALUES ('https://b.test/rss', NULL);
+                    UPDATE rss_entries SET is_read = 1 WHERE hash = 'h2';",
+                )
+                .unwrap();
+
+            let report = restore_archive(&restored, &archive, RestoreMode::Replace).unwrap();
+            assert_eq!(report.inserted, 6);
+            assert_eq!(dump(&restored), dump(&source));
+        }
+    }
+
+    #[test]
+    fn failed_restore_leaves_the_database_untouched() {
+        let source = database();
+        let restored = database();
+        restored
+            .execute("INSERT INTO entry_tags (hash, tag) VALUES ('h2', 'local')")
+            .unwrap();
+        let before = dump(&restored);
+
+        let archive = archive(&source, BackupLayout::JsonLines);
+        let mut text = String::new();
+        GzDecoder::new(&archive[..])
+            .read_to_string(&mut text)
+            .unwrap();
+        let corrupt = format!("{}{{\"table\": \"unknown\", \"values\": []}}\n", text);
+        let result = restore(
+            &ThreadSafe::new(&restored),
+            corrupt.as_bytes(),
+            RestoreMode::Replace,
+        );
+        assert!(matches!(result, Err(BackupError::InvalidArchive(_))));
+        assert_eq!(dump(&restored), before);
+    }
+
+    #[test]
+    fn newer_archives_are_rejected() {
+        let header = BackupHeader {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION + 1,
+            created_at: Utc::now().to_rfc3339(),
+            layout: BackupLayout::JsonLines,
+            tables: vec![],
+        };
+        let archive = serde_json::to_string(&header).unwrap();
+        let connection = open_database(":memory:").unwrap();
+        assert!(matches!(
+            restore(
+                &ThreadSafe::new(&connection),
+                archive.as_bytes(),
+                RestoreMode::Merge
+            ),
+            Err(BackupError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgGroup, SubCommand};
 
 pub fn get_command_line_args() -> App<'static, 'static> {
     App::new("Blog Blunter")
@@ -13,7 +13,7 @@ pub fn get_command_line_args() -> App<'static, 'static> {
                     .short("u")
                     .long("url")
                     .value_name("URL")
-                    .help("URL to insert")
+                    .help("URL to insert, a file:// URL, or - to import the entries of a feed read from stdin once")
                     .takes_value(true),
             )
             .arg(
@@ -23,19 +23,108 @@ pub fn get_command_line_args() -> App<'static, 'static> {
                     .value_name("PROFILE")
                     .help("Profile to insert as [will be useful later]")
                     .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("full-text")
+                    .long("full-text")
+                    .help("Fetch the full article text of new entries of this feed on sync"),
+            )
+            .arg(
+                Arg::with_name("auto-download")
+                    .long("auto-download")
+                    .help("Download the enclosures of new entries of this feed on sync"),
+            )
+            .arg(
+                Arg::with_name("title")
+                    .long("title")
+                    .value_name("TITLE")
+                    .help("Title to list the feed under instead of the channel title")
+                    .takes_value(true),
             ),
     )
     .subcommand(
         SubCommand::with_name("search")
             .about("Search rss entries by regular expression")
+            .args(&filter_args())
             .arg(
-                Arg::with_name("regex")
-                    .short("r")
-                    .long("regex")
-                    .value_name("REGEX")
-                    .help("Regex pattern to search the blogs with [but uses LIKE SQL statement]")
-                    .takes_value(true),
+                Arg::with_name("page")
+                    .short("p")
+                    .long("page")
+                    .value_name("PAGE")
+                    .help("page number of response, to look")
+                    .takes_value(true)
+                    .required(false)
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .help("output format, json exports the enclosures, episode and media of each entry")
+                    .possible_values(&["table", "json"])
+                    .default_value("table")
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("saved")
+            .about("Save searches under a name and run them again")
+            .subcommand(
+                SubCommand::with_name("add")
+                    .about("Save a search, the filters are the ones of the search command")
+                    .arg(
+                        Arg::with_name("name")
+                            .value_name("NAME")
+                            .help("Name to run the search by")
+                            .required(true),
+                    )
+                    .args(&filter_args()),
+            )
+            .subcommand(
+                SubCommand::with_name("edit")
+                    .about("Replace the filters of a saved search")
+                    .arg(
+                        Arg::with_name("name")
+                            .value_name("NAME")
+                            .help("Name of the saved search")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("rename")
+                            .long("rename")
+                            .value_name("NEW_NAME")
+                            .help("New name of the saved search")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("keep")
+                            .long("keep")
+                            .help("Keep the filters that are not given instead of clearing them"),
+                    )
+                    .args(&filter_args()),
+            )
+            .subcommand(
+                SubCommand::with_name("list")
+                    .about("List the saved searches with the number of unread entries they match"),
             )
+            .subcommand(
+                SubCommand::with_name("remove")
+                    .about("Delete a saved search")
+                    .arg(
+                        Arg::with_name("name")
+                            .value_name("NAME")
+                            .help("Name of the saved search")
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("run")
+                    .about("Run a saved search")
+                    .arg(
+                        Arg::with_name("name")
+                            .value_name("NAME")
+                            .help("Name of the saved search")
+                            .required(true),
+                    )
             .arg(
                 Arg::with_name("page")
                     .short("p")
@@ -45,15 +134,1200 @@ pub fn get_command_line_args() -> App<'static, 'static> {
                     .takes_value(true)
                     .required(false)
             )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .help("output format, json exports the enclosures, episode and media of each entry")
+                    .possible_values(&["table", "json"])
+                    .default_value("table")
+            ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("tag")
+            .about("Tag an entry or a feed")
+            .arg(
+                Arg::with_name("entry")
+                    .short("e")
+                    .long("entry")
+                    .value_name("ENTRY")
+                    .help("Id (hash prefix) or part of the title of the entry")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("feed")
+                    .short("u")
+                    .long("feed")
+                    .value_name("RSS_URL")
+                    .help("URL of the feed, its entries inherit its tags")
+                    .takes_value(true),
+            )
+            .group(
+                ArgGroup::with_name("target")
+                    .args(&["entry", "feed"])
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("tags")
+                    .value_name("TAG")
+                    .help("Tags to add, case insensitive")
+                    .multiple(true)
+                    .required(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("untag")
+            .about("Remove tags from an entry or a feed")
+            .arg(
+                Arg::with_name("entry")
+                    .short("e")
+                    .long("entry")
+                    .value_name("ENTRY")
+                    .help("Id (hash prefix) or part of the title of the entry")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("feed")
+                    .short("u")
+                    .long("feed")
+                    .value_name("RSS_URL")
+                    .help("URL of the feed, its entries inherit its tags")
+                    .takes_value(true),
+            )
+            .group(
+                ArgGroup::with_name("target")
+                    .args(&["entry", "feed"])
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("tags")
+                    .value_name("TAG")
+                    .help("Tags to remove")
+                    .multiple(true)
+                    .required(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("tags")
+            .about("List the tags with the number of entries and feeds using them"),
+    )
+    .subcommand(
+        SubCommand::with_name("sync")
+            .about("Fetch every subscribed feed and save the new entries")
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .help("Format of the sync summary")
+                    .possible_values(&["table", "json"])
+                    .default_value("table")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("full-text")
+                    .long("full-text")
+                    .help("Fetch the full article text of new entries of every feed"),
+            )
+            .arg(
+                Arg::with_name("max-items")
+                    .long("max-items")
+                    .value_name("COUNT")
+                    .help("Stop reading each feed after COUNT items")
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("feed")
+            .about("List and configure the subscribed feeds")
+            .subcommand(
+                SubCommand::with_name("list")
+                    .about("List the subscribed feeds")
+                    .arg(
+                        Arg::with_name("verbose")
+                            .short("v")
+                            .long("verbose")
+                            .help("Also print the stored channel metadata of each feed"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("set")
+                    .about("Change the settings of a subscribed feed")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("URL")
+                            .help("URL of the feed to change")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("full-text")
+                            .long("full-text")
+                            .value_name("ON|OFF")
+                            .help("Fetch the full article text of new entries on sync")
+                            .possible_values(&["on", "off"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("auto-download")
+                            .long("auto-download")
+                            .value_name("ON|OFF")
+                            .help("Download the enclosures of new entries on sync")
+                            .possible_values(&["on", "off"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("title")
+                            .long("title")
+                            .value_name("TITLE")
+                            .help("Title to list the feed under instead of the channel title, empty to reset")
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("fulltext")
+            .about("Fetch and store the full article text of entries that do not have it yet")
+            .arg(
+                Arg::with_name("regex")
+                    .short("r")
+                    .long("regex")
+                    .value_name("REGEX")
+                    .help("Only fetch entries whose title matches [but uses LIKE SQL statement]")
+                    .takes_value(true),
+            )
             .arg(
                 Arg::with_name("limit")
                     .short("l")
                     .long("limit")
                     .value_name("LIMIT")
-                    .help("limit number of responses")
-                    .takes_value(true)
-                    .required(false)
+                    .help("Maximum number of entries to fetch")
+                    .default_value("20")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("refetch")
+                    .long("refetch")
+                    .help("Fetch again entries that already have their full text"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("hook")
+            .about("Manage commands that are run for every new entry found by sync")
+            .subcommand(
+                SubCommand::with_name("add")
+                    .about("Add a hook, entry fields are passed as RSS_* env vars and JSON on stdin")
+                    .arg(
+                        Arg::with_name("command")
+                            .short("c")
+                            .long("command")
+                            .value_name("COMMAND")
+                            .help("Shell command to run for each new entry")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("profile")
+                            .short("p")
+                            .long("profile")
+                            .value_name("PROFILE")
+                            .help("Only run the hook for feeds of this profile")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("timeout")
+                            .short("t")
+                            .long("timeout")
+                            .value_name("SECONDS")
+                            .help("Seconds before the command is killed")
+                            .default_value("30")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("list").about("List the configured hooks"))
+            .subcommand(
+                SubCommand::with_name("remove")
+                    .about("Remove a hook")
+                    .arg(
+                        Arg::with_name("id")
+                            .long("id")
+                            .value_name("ID")
+                            .help("Id of the hook to remove")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("failures")
+                    .about("Show the latest failed hook runs")
+                    .arg(
+                        Arg::with_name("limit")
+                            .short("l")
+                            .long("limit")
+                            .value_name("LIMIT")
+                            .help("limit number of responses")
+                            .default_value("20")
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("notify")
+            .about("Manage webhooks that receive the entries found by sync")
+            .subcommand(
+                SubCommand::with_name("add")
+                    .about("Add a webhook notification sink")
+                    .arg(
+                        Arg::with_name("kind")
+                            .short("k")
+                            .long("kind")
+                            .value_name("KIND")
+                            .help("Payload shape of the webhook")
+                            .possible_values(&["slack", "discord", "matrix", "json"])
+                            .default_value("json")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("URL")
+                            .help("Webhook URL to post to")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("profile")
+                            .short("p")
+                            .long("profile")
+                            .value_name("PROFILE")
+                            .help("Only send entries of feeds with this profile")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("feed")
+                            .short("f")
+                            .long("feed")
+                            .value_name("RSS_URL")
+                            .help("Only send entries of this feed")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("retries")
+                            .short("r")
+                            .long("retries")
+                            .value_name("RETRIES")
+                            .help("Number of retries when posting fails")
+                            .default_value("3")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("list").about("List the notification sinks"))
+            .subcommand(
+                SubCommand::with_name("remove")
+                    .about("Remove a notification sink")
+                    .arg(
+                        Arg::with_name("id")
+                            .long("id")
+                            .value_name("ID")
+                            .help("Id of the sink to remove")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("test")
+                    .about("Send a sample message to a sink or to the given URL")
+                    .arg(
+                        Arg::with_name("id")
+                            .long("id")
+                            .value_name("ID")
+                            .help("Id of the sink to test")
+                            .takes_value(true)
+                            .required_unless("url"),
+                    )
+                    .arg(
+                        Arg::with_name("kind")
+                            .short("k")
+                            .long("kind")
+                            .value_name("KIND")
+                            .help("Payload shape used with --url")
+                            .possible_values(&["slack", "discord", "matrix", "json"])
+                            .default_value("json")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("URL")
+                            .help("Webhook URL to send the sample to")
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("rules")
+            .about("Manage the rules evaluated against every item before sync saves it")
+            .subcommand(
+                SubCommand::with_name("add")
+                    .about("Add a rule, exactly one of --contains, --regex, --min-age or --max-age is required")
+                    .arg(
+                        Arg::with_name("field")
+                            .long("field")
+                            .value_name("FIELD")
+                            .help("Part of the item to look at")
+                            .possible_values(&["title", "content", "author", "category"])
+                            .default_value("title")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("contains")
+                            .long("contains")
+                            .value_name("TEXT")
+                            .help("Match items whose field contains TEXT, ignoring case")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("regex")
+                            .long("regex")
+                            .value_name("REGEX")
+                            .help("Match items whose field matches REGEX, ignoring case")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("min-age")
+                            .long("min-age")
+                            .value_name("DAYS")
+                            .help("Match items published at least DAYS ago")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-age")
+                            .long("max-age")
+                            .value_name("DAYS")
+                            .help("Match items published at most DAYS ago")
+                            .takes_value(true),
+                    )
+                    .group(
+                        ArgGroup::with_name("condition")
+                            .args(&["contains", "regex", "min-age", "max-age"])
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("not")
+                            .long("not")
+                            .help("Invert the condition, `--not ... --action skip` only keeps matching items"),
+                    )
+                    .arg(
+                        Arg::with_name("action")
+                            .short("a")
+                            .long("action")
+                            .value_name("ACTION")
+                            .help("What to do with matching items")
+                            .possible_values(&["skip", "read", "star", "tag"])
+                            .default_value("skip")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("tag")
+                            .short("t")
+                            .long("tag")
+                            .value_name("TAG")
+                            .help("Tag attached by the tag action")
+                            .required_if("action", "tag")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("profile")
+                            .short("p")
+                            .long("profile")
+                            .value_name("PROFILE")
+                            .help("Only apply the rule to feeds of this profile")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("feed")
+                            .short("f")
+                            .long("feed")
+                            .value_name("RSS_URL")
+                            .help("Only apply the rule to this feed")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("list").about("List the rules"))
+            .subcommand(
+                SubCommand::with_name("remove")
+                    .about("Remove a rule")
+                    .arg(
+                        Arg::with_name("id")
+                            .long("id")
+                            .value_name("ID")
+                            .help("Id of the rule to remove")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("test")
+                    .about("Fetch a feed and show what the rules would do with each item")
+                    .arg(
+                        Arg::with_name("feed")
+                            .value_name("RSS_URL")
+                            .help("Feed to test the rules against")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("profile")
+                            .short("p")
+                            .long("profile")
+                            .value_name("PROFILE")
+                            .help("Profile to evaluate the rules for, defaults to the feed's profile")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-items")
+                            .long("max-items")
+                            .value_name("COUNT")
+                            .help("Stop reading the feed after COUNT items")
+                            .takes_value(true),
+                    ),
             ),
     )
-    .subcommand(SubCommand::with_name("sync").about("Syncs something"))
+    .subcommand(
+        SubCommand::with_name("show")
+            .about("Show an entry with its HTML content rendered for the terminal")
+            .arg(
+                Arg::with_name("entry")
+                    .value_name("ENTRY")
+                    .help("Id (hash prefix) or part of the title of the entry")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("color")
+                    .long("color")
+                    .value_name("WHEN")
+                    .help("Colorize the output")
+                    .possible_values(&["auto", "always", "never"])
+                    .default_value("auto")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("width")
+                    .short("w")
+                    .long("width")
+                    .value_name("COLUMNS")
+                    .help("Wrap the text at COLUMNS instead of the terminal width")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("no-pager")
+                    .long("no-pager")
+                    .help("Never page long content"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("tui")
+            .about("Browse feeds and entries in an interactive terminal interface"),
+    )
+    .subcommand(
+        SubCommand::with_name("http")
+            .about("Configure the http client globally or for one feed")
+            .subcommand(
+                SubCommand::with_name("set")
+                    .about("Change http settings, unset ones fall back to the global settings")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Feed to configure, the global settings when omitted")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("connect-timeout")
+                            .long("connect-timeout")
+                            .value_name("SECONDS")
+                            .help("Seconds to wait for a connection")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("read-timeout")
+                            .long("read-timeout")
+                            .value_name("SECONDS")
+                            .help("Seconds to wait for the response and each chunk of its body")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("user-agent")
+                            .long("user-agent")
+                            .value_name("USER_AGENT")
+                            .help("User-Agent header sent with every request")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("proxy")
+                            .long("proxy")
+                            .value_name("URL")
+                            .help("Proxy URL, http://, https://, socks5:// or socks5h://")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("ca-cert")
+                            .long("ca-cert")
+                            .value_name("PEM_FILE")
+                            .help("Extra CA certificate to trust, can be repeated")
+                            .multiple(true)
+                            .number_of_values(1)
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("compression")
+                            .long("compression")
+                            .value_name("on|off")
+                            .help("Request gzip, brotli and deflate compressed responses")
+                            .possible_values(&["on", "off"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-body-size")
+                            .long("max-body-size")
+                            .value_name("BYTES")
+                            .help("Reject response bodies larger than BYTES")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("list").about("List the stored http settings"))
+            .subcommand(
+                SubCommand::with_name("reset")
+                    .about("Remove the http settings of a feed, or the global ones")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Feed to reset, the global settings when omitted")
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("retention")
+            .about("Configure which entries prune deletes, globally or for one feed")
+            .subcommand(
+                SubCommand::with_name("set")
+                    .about("Change a retention policy, unset options fall back to the global policy")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Feed to configure, the global policy when omitted")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("keep-newest")
+                            .long("keep-newest")
+                            .value_name("COUNT")
+                            .help("Keep the COUNT newest entries of each feed, 0 for no limit")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-age")
+                            .long("max-age")
+                            .value_name("DAYS")
+                            .help("Delete entries published more than DAYS ago, 0 for no limit")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("keep-starred")
+                            .long("keep-starred")
+                            .value_name("ON|OFF")
+                            .help("Never delete starred entries, on by default")
+                            .possible_values(&["on", "off"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("keep-tagged")
+                            .long("keep-tagged")
+                            .value_name("ON|OFF")
+                            .help("Never delete entries with a tag of their own, on by default")
+                            .possible_values(&["on", "off"])
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("list").about("List the retention policies"))
+            .subcommand(
+                SubCommand::with_name("reset")
+                    .about("Remove the retention policy of a feed, or the global one")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Feed to reset, the global policy when omitted")
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("prune")
+            .about("Delete the entries past the retention policy of their feed")
+            .arg(
+                Arg::with_name("url")
+                    .short("u")
+                    .long("url")
+                    .value_name("RSS_URL")
+                    .help("Only prune the entries of this feed")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("dry-run")
+                    .short("n")
+                    .long("dry-run")
+                    .help("List the entries that would be deleted without deleting them"),
+            )
+            .arg(
+                Arg::with_name("vacuum")
+                    .long("vacuum")
+                    .help("Rebuild the database file afterwards to give the freed space back"),
+            )
+            .arg(
+                Arg::with_name("analyze")
+                    .long("analyze")
+                    .help("Refresh the query planner statistics afterwards"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("digest")
+            .about("Summarize what the feeds published over a day or a week in Markdown or HTML")
+            .after_help(
+                "A template given with --template replaces the whole document. The placeholders \
+                 {title}, {since}, {until}, {generated_at}, {entry_count}, {feed_count} and \
+                 {content} are filled, {content} holding the entries grouped by profile and feed \
+                 in the chosen format.",
+            )
+            .arg(
+                Arg::with_name("profile")
+                    .short("p")
+                    .long("profile")
+                    .value_name("PROFILE")
+                    .help("Only the feeds of this profile")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("feed")
+                    .short("u")
+                    .long("feed")
+                    .value_name("RSS_URL")
+                    .help("Only entries of this feed")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("period")
+                    .long("period")
+                    .value_name("PERIOD")
+                    .help("The time window ending now")
+                    .possible_values(&["day", "week"])
+                    .default_value("week")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("since")
+                    .long("since")
+                    .value_name("DATE")
+                    .help("Start of the window instead of the period: Nd, YYYY-MM-DD or YYYY-MM-DD HH:MM:SS")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("until")
+                    .long("until")
+                    .value_name("DATE")
+                    .help("End of the window: Nd, YYYY-MM-DD or YYYY-MM-DD HH:MM:SS")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .help("The markup of the digest")
+                    .possible_values(&["markdown", "html"])
+                    .default_value("markdown")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .value_name("FILE")
+                    .help("Where to write the digest, the standard output by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("template")
+                    .long("template")
+                    .value_name("FILE")
+                    .help("A document template replacing the built-in one")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("title")
+                    .long("title")
+                    .value_name("TITLE")
+                    .help("The heading of the digest")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("summary-length")
+                    .long("summary-length")
+                    .value_name("CHARS")
+                    .help("Cut the summaries after CHARS characters, 0 to leave them out")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("email")
+                    .long("email")
+                    .help("Mail the digest through the SMTP server set with smtp set instead of writing it")
+                    .requires("to")
+                    .conflicts_with("output"),
+            )
+            .arg(
+                Arg::with_name("to")
+                    .long("to")
+                    .value_name("ADDRESS")
+                    .help("Recipient of the email, each one only gets the entries not mailed to them yet")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .requires("email")
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("smtp")
+            .about("Configure the SMTP server email digests are sent through")
+            .after_help(
+                "The password is encrypted with the passphrase in RSS_RUST_PASSPHRASE. A value of \
+                 the form env:NAME is not stored, it is read from the environment variable NAME \
+                 every time an email is sent.",
+            )
+            .subcommand(
+                SubCommand::with_name("set")
+                    .about("Change the SMTP settings, unset options keep their value")
+                    .arg(
+                        Arg::with_name("host")
+                            .long("host")
+                            .value_name("HOST")
+                            .help("Host name of the server")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("port")
+                            .long("port")
+                            .value_name("PORT")
+                            .help("Port of the server, 0 for the default port of the security mode")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("security")
+                            .long("security")
+                            .value_name("MODE")
+                            .help("Plain text, upgraded with STARTTLS, or TLS from the start")
+                            .possible_values(&["none", "starttls", "tls"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("username")
+                            .long("username")
+                            .value_name("USERNAME")
+                            .help("User name to authenticate with, empty to send without authenticating")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("password")
+                            .long("password")
+                            .value_name("PASSWORD")
+                            .help("Password to authenticate with, or env:NAME")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("from")
+                            .long("from")
+                            .value_name("ADDRESS")
+                            .help("Address the digests are sent from")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("show").about("Show the SMTP settings"))
+            .subcommand(SubCommand::with_name("reset").about("Forget the SMTP settings"))
+            .subcommand(
+                SubCommand::with_name("test")
+                    .about("Send a test email to check the settings")
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .value_name("ADDRESS")
+                            .help("Recipient of the test email")
+                            .required(true)
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("backup")
+            .about("Write a compressed archive of the feeds, entries, read state and settings")
+            .after_help(
+                "The archive is taken from a consistent snapshot of the database, so it can be \
+                 written while a sync is running. Stored credentials stay encrypted with the \
+                 passphrase in RSS_RUST_PASSPHRASE.",
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .value_name("FILE")
+                    .help("Where to write the archive, - for the standard output, a timestamped file in the current directory by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .help("A single JSON document or one JSON line per row")
+                    .possible_values(&["json", "jsonl"])
+                    .default_value("jsonl")
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("restore")
+            .about("Restore an archive written by backup, merging it into the database by default")
+            .arg(
+                Arg::with_name("file")
+                    .value_name("FILE")
+                    .help("The archive to restore, - for the standard input")
+                    .required(true)
+                    .index(1),
+            )
+            .arg(
+                Arg::with_name("replace")
+                    .long("replace")
+                    .help("Delete everything in the database first instead of only adding the missing rows"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("auth")
+            .about("Store the credentials sent when fetching a feed")
+            .after_help(
+                "Credentials are encrypted with the passphrase in RSS_RUST_PASSPHRASE. A value of \
+                 the form env:NAME is not stored, it is read from the environment variable NAME \
+                 every time the feed is fetched.",
+            )
+            .subcommand(
+                SubCommand::with_name("set")
+                    .about("Set credentials of a feed, the ones not given are kept")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Feed the credentials are sent to")
+                            .required(true)
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("username")
+                            .long("username")
+                            .value_name("USERNAME")
+                            .help("User name for basic authentication")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("password")
+                            .long("password")
+                            .value_name("PASSWORD")
+                            .help("Password for basic authentication")
+                            .requires("username")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("token")
+                            .long("token")
+                            .value_name("TOKEN")
+                            .help("Bearer token")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("header")
+                            .short("H")
+                            .long("header")
+                            .value_name("NAME: VALUE")
+                            .help("Extra header, can be repeated, replaces the stored headers")
+                            .multiple(true)
+                            .number_of_values(1)
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("cookie-header")
+                            .long("cookie-header")
+                            .value_name("COOKIES")
+                            .help("Cookie header sent as is, like 'session=abc; theme=dark', cookies set by the server are not kept")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("list").about("List the feeds with credentials, secrets hidden"),
+            )
+            .subcommand(
+                SubCommand::with_name("remove")
+                    .about("Remove the credentials of a feed")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Feed whose credentials are removed")
+                            .required(true)
+                            .takes_value(true),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("download")
+            .about("Download the enclosures of entries, like podcast episodes, for offline use")
+            .subcommand(
+                SubCommand::with_name("run")
+                    .about("Download the enclosures that were not downloaded yet, newest entries first")
+                    .arg(
+                        Arg::with_name("entry")
+                            .value_name("ENTRY")
+                            .help("Id (hash prefix) or part of the title of a single entry"),
+                    )
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Only download enclosures of this feed")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("limit")
+                            .short("l")
+                            .long("limit")
+                            .value_name("LIMIT")
+                            .help("Maximum number of enclosures to download")
+                            .default_value("10")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("set")
+                    .about("Change the download settings, unset options keep their value")
+                    .after_help(
+                        "TEMPLATE placeholders: {profile} {feed} {date} {title} {hash} {filename} {ext}",
+                    )
+                    .arg(
+                        Arg::with_name("dir")
+                            .long("dir")
+                            .value_name("DIRECTORY")
+                            .help("Directory the files are written to")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("template")
+                            .long("template")
+                            .value_name("TEMPLATE")
+                            .help("Path of a file inside the directory")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("concurrency")
+                            .long("concurrency")
+                            .value_name("COUNT")
+                            .help("Number of files downloaded at the same time")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-size")
+                            .long("max-size")
+                            .value_name("BYTES")
+                            .help("Delete the oldest files once all downloads are larger, 0 for no limit")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-files")
+                            .long("max-files")
+                            .value_name("COUNT")
+                            .help("Delete the oldest files once there are more, 0 for no limit")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-file-size")
+                            .long("max-file-size")
+                            .value_name("BYTES")
+                            .help("Reject single files larger than this, 0 for no limit")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("show").about("Show the download settings"))
+            .subcommand(SubCommand::with_name("list").about("List the downloaded files"))
+            .subcommand(
+                SubCommand::with_name("verify")
+                    .about("Check the downloaded files against their checksum, broken files are downloaded again on the next run"),
+            )
+            .subcommand(
+                SubCommand::with_name("prune")
+                    .about("Delete the oldest downloaded files past the size and count budget"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("icon")
+            .about("Cache the channel images and site favicons of the subscribed feeds")
+            .subcommand(
+                SubCommand::with_name("refresh")
+                    .about("Download the missing and stale icons, they are also refreshed on sync")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("Only refresh the icon of this feed")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("force")
+                            .long("force")
+                            .help("Refresh the icons that are not stale too"),
+                    )
+                    .arg(
+                        Arg::with_name("max-size")
+                            .long("max-size")
+                            .value_name("BYTES")
+                            .help("Reject icons larger than this")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(SubCommand::with_name("list").about("List the cached icons"))
+            .subcommand(
+                SubCommand::with_name("export")
+                    .about("Write the cached icon of a feed to a file")
+                    .arg(
+                        Arg::with_name("url")
+                            .short("u")
+                            .long("url")
+                            .value_name("RSS_URL")
+                            .help("URL of the feed")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .value_name("FILE")
+                            .help("File to write the icon to, - prints it as a data URI")
+                            .default_value("-"),
+                    ),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("parse")
+            .about("Parse a feed and print its items without storing anything")
+            .arg(
+                Arg::with_name("source")
+                    .value_name("SOURCE")
+                    .help("URL of the feed, a file:// URL, or - for stdin")
+                    .default_value("-"),
+            )
+            .arg(
+                Arg::with_name("max-items")
+                    .long("max-items")
+                    .value_name("COUNT")
+                    .help("Stop reading the feed after COUNT items")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("json")
+                    .long("json")
+                    .help("Print the parsed feed as JSON"),
+            ),
+    )
+}
+
+/// The function `filter_args` returns the filters shared by the `search` command and the saved
+/// searches.
+fn filter_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("regex")
+            .short("r")
+            .long("regex")
+            .value_name("REGEX")
+            .help("Regex pattern to search the blogs with [but uses LIKE SQL statement]")
+            .takes_value(true),
+        Arg::with_name("limit")
+            .short("l")
+            .long("limit")
+            .value_name("LIMIT")
+            .help("limit number of responses")
+            .takes_value(true),
+        Arg::with_name("tag")
+            .short("t")
+            .long("tag")
+            .value_name("TAG")
+            .help("Only entries with this tag, on the entry or its feed, can be repeated")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true),
+        Arg::with_name("author")
+            .long("author")
+            .value_name("AUTHOR")
+            .help("Only entries whose author contains AUTHOR, ignoring the case")
+            .takes_value(true),
+        Arg::with_name("category")
+            .long("category")
+            .value_name("CATEGORY")
+            .help("Only entries filed under this category by their feed, can be repeated")
+            .multiple(true)
+            .number_of_values(1)
+            .takes_value(true),
+        Arg::with_name("profile")
+            .long("profile")
+            .value_name("PROFILE")
+            .help("Only entries of the feeds of this profile")
+            .takes_value(true),
+        Arg::with_name("feed")
+            .short("u")
+            .long("feed")
+            .value_name("RSS_URL")
+            .help("Only entries of this feed")
+            .takes_value(true),
+        Arg::with_name("unread")
+            .long("unread")
+            .help("Only entries not marked as read"),
+        Arg::with_name("starred")
+            .long("starred")
+            .help("Only starred entries"),
+        Arg::with_name("since")
+            .long("since")
+            .value_name("DATE")
+            .help("Only entries published since DATE: Nd for N days ago, YYYY-MM-DD or YYYY-MM-DD HH:MM:SS")
+            .takes_value(true),
+        Arg::with_name("until")
+            .long("until")
+            .value_name("DATE")
+            .help("Only entries published until DATE, same formats as --since")
+            .takes_value(true),
+        Arg::with_name("sort")
+            .long("sort")
+            .value_name("ORDER")
+            .help("Order of the entries, relevance ranks the titles matching --regex first")
+            .possible_values(&["newest", "oldest", "title", "feed", "relevance"])
+            .takes_value(true),
+    ]
 }
@@ -1,3 +1,6 @@
 pub mod args;
+pub mod query;
+pub mod report;
 pub mod structs;
+pub mod template;
 pub mod traits;
@@ -0,0 +1,167 @@
+use sqlite::Value;
+
+/// The `SqlQuery` struct builds a `SELECT` statement from the filters and orderings that apply,
+/// keeping every value out of the SQL text: conditions and orderings use `?` placeholders and their
+/// values are bound in the order they were added.
+///
+/// Properties:
+///
+/// * `from`: The `FROM` clause, tables and joins, without the keyword.
+/// * `conditions`: The conditions joined with `AND` in the `WHERE` clause.
+/// * `condition_values`: The values of the placeholders of `conditions`.
+/// * `orderings`: The expressions of the `ORDER BY` clause.
+/// * `ordering_values`: The values of the placeholders of `orderings`.
+#[derive(Debug, Clone)]
+pub struct SqlQuery {
+    from: String,
+    conditions: Vec<String>,
+    condition_values: Vec<Value>,
+    orderings: Vec<String>,
+    ordering_values: Vec<Value>,
+}
+
+impl SqlQuery {
+    /// The function `new` returns a query of `from` without any condition or ordering.
+    pub fn new(from: &str) -> SqlQuery {
+        SqlQuery {
+            from: from.to_string(),
+            conditions: vec![],
+            condition_values: vec![],
+            orderings: vec![],
+            ordering_values: vec![],
+        }
+    }
+
+    /// The function `filter` adds a condition the rows must meet.
+    ///
+    /// Arguments:
+    ///
+    /// * `condition`: The SQL condition, with a `?` for each value.
+    /// * `values`: The values of the placeholders, in order.
+    pub fn filter<I: IntoIterator<Item = Value>>(
+        &mut self,
+        condition: &str,
+        values: I,
+    ) -> &mut SqlQuery {
+        self.conditions.push(condition.to_string());
+        self.condition_values.extend(values);
+        self
+    }
+
+    /// The function `order_by` adds an expression the rows are sorted by, after the ones already
+    /// added.
+    ///
+    /// Arguments:
+    ///
+    /// * `ordering`: The SQL expression and its direction, with a `?` for each value.
+    /// * `values`: The values of the placeholders, in order.
+    pub fn order_by<I: IntoIterator<Item = Value>>(
+        &mut self,
+        ordering: &str,
+        values: I,
+    ) -> &mut SqlQuery {
+        self.orderings.push(ordering.to_string());
+        self.ordering_values.extend(values);
+        self
+    }
+
+    fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", self.conditions.join(" AND "))
+        }
+    }
+
+    /// The function `select` returns the statement selecting `columns` from the matching rows in
+    /// order, skipping `offset` rows and returning at most `limit`, with the values to bind.
+    pub fn select(&self, columns: &str, limit: u64, offset: u64) -> (String, Vec<Value>) {
+        let mut sql = format!(
+            "SELECT {} FROM {}{}",
+            columns,
+            self.from,
+            self.where_clause()
+        );
+        if !self.orderings.is_empty() {
+            sql.push_str(&format!(" ORDER BY {}", self.orderings.join(", ")));
+        }
+        sql.push_str(" LIMIT ? OFFSET ?");
+        let mut values = self.condition_values.clone();
+        values.extend(self.ordering_values.iter().cloned());
+        values.push(Value::Integer(limit.min(i64::MAX as u64) as i64));
+        values.push(Value::Integer(offset.min(i64::MAX as u64) as i64));
+        (sql, values)
+    }
+
+    /// The function `count` returns the statement counting the matching rows as `count`, with the
+    /// values to bind.
+    pub fn count(&self) -> (String, Vec<Value>) {
+        (
+            format!(
+                "SELECT COUNT(*) AS count FROM {}{}",
+                self.from,
+                self.where_clause()
+            ),
+            self.condition_values.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_database;
+    use sqlite::{Connection, State};
+
+    fn database() -> Connection {
+        let connection = open_database(":memory:").unwrap();
+        connection
+            .execute(
+                "INSERT INTO rss_entries (hash, title, link) VALUES
+                    ('a', 'apple', ''), ('b', 'banana', ''), ('c', 'cherry', ''), ('d', 'date', '')",
+            )
+            .unwrap();
+        connection
+    }
+
+    fn run(connection: &Connection, (sql, values): (String, Vec<Value>)) -> Vec<String> {
+        let mut statement = connection.prepare(sql).unwrap();
+        statement.bind(&values[..]).unwrap();
+        let mut rows = vec![];
+        while let Ok(State::Row) = statement.next() {
+            rows.push(statement.read::<String, _>(0).unwrap());
+        }
+        rows
+    }
+
+    #[test]
+    fn select_without_conditions_pages_every_row() {
+        let connection = database();
+        let query = SqlQuery::new("rss_entries");
+        let (sql, values) = query.select("hash", 2, 1);
+        assert_eq!(sql, "SELECT hash FROM rss_entries LIMIT ? OFFSET ?");
+        assert_eq!(values, [Value::Integer(2), Value::Integer(1)]);
+        assert_eq!(run(&connection, (sql, values)).len(), 2);
+        assert_eq!(run(&connection, query.count()), ["4"]);
+        // a limit past `i64::MAX` is clamped instead of wrapping around to a negative one
+        assert_eq!(run(&connection, query.select("hash", u64::MAX, 0)).len(), 4);
+    }
+
+    #[test]
+    fn values_are_bound_in_the_order_of_their_clauses() {
+        let connection = database();
+        let mut query = SqlQuery::new("rss_entries");
+        // the ordering is added first, its value must still follow the ones of the conditions
+        query
+            .order_by(
+                "CASE WHEN title = ? THEN 0 ELSE 1 END",
+                [Value::String("date".into())],
+            )
+            .filter("title LIKE ?", [Value::String("%a%".into())])
+            .filter("hash <> ?", [Value::String("b".into())])
+            .order_by("title DESC", []);
+        assert_eq!(run(&connection, query.select("hash", 10, 0)), ["d", "a"]);
+        assert_eq!(run(&connection, query.select("hash", 1, 1)), ["a"]);
+        assert_eq!(run(&connection, query.count()), ["2"]);
+    }
+}
@@ -1,9 +1,78 @@
-#[derive(Debug)]
+use serde::Serialize;
+use std::{fmt::Display, str::FromStr};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::report::SyncProgress;
+
+#[derive(Debug, Clone)]
 pub struct GetManyOptions {
     pub query: String,
     pub page: u64,
     pub limit: u64,
     pub skip: u64,
+    pub rss_url: String,
+    pub profile: String,
+    pub tags: Vec<String>,
+    pub author: String,
+    pub categories: Vec<String>,
+    pub unread_only: bool,
+    pub starred_only: bool,
+    pub since: String,
+    pub until: String,
+    pub sort: EntrySort,
+}
+
+/// The `EntrySort` enum is the order `RssEntry::get_many` returns the entries in.
+///
+/// Variants:
+///
+/// * `Newest`: The most recently published first.
+/// * `Oldest`: The least recently published first.
+/// * `Title`: By title, ignoring the case.
+/// * `Feed`: By the name the feed is listed under, the newest first within a feed.
+/// * `Relevance`: The titles starting with the query first, then the titles containing it, then the
+///   entries only matched by their full article text, the newest first within each group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EntrySort {
+    #[default]
+    Newest,
+    Oldest,
+    Title,
+    Feed,
+    Relevance,
+}
+
+impl Display for EntrySort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntrySort::Newest => write!(f, "newest"),
+            EntrySort::Oldest => write!(f, "oldest"),
+            EntrySort::Title => write!(f, "title"),
+            EntrySort::Feed => write!(f, "feed"),
+            EntrySort::Relevance => write!(f, "relevance"),
+        }
+    }
+}
+
+impl FromStr for EntrySort {
+    type Err = String;
+
+    fn from_str(sort: &str) -> Result<Self, Self::Err> {
+        match sort {
+            "newest" => Ok(EntrySort::Newest),
+            "oldest" => Ok(EntrySort::Oldest),
+            "title" => Ok(EntrySort::Title),
+            "feed" => Ok(EntrySort::Feed),
+            "relevance" => Ok(EntrySort::Relevance),
+            _ => Err(format!("Unknown sort order {}", sort)),
+        }
+    }
+}
+
+impl Default for GetManyOptions {
+    fn default() -> Self {
+        GetManyOptions::new()
+    }
 }
 
 impl GetManyOptions {
@@ -13,30 +82,165 @@ impl GetManyOptions {
             page: 1,
             limit: 10,
             skip: 0,
+            rss_url: "".to_string(),
+            profile: "".to_string(),
+            tags: vec![],
+            author: "".to_string(),
+            categories: vec![],
+            unread_only: false,
+            starred_only: false,
+            since: "".to_string(),
+            until: "".to_string(),
+            sort: EntrySort::Newest,
         }
     }
 
-    pub fn set_page(self: &mut GetManyOptions, page: u64) -> &GetManyOptions {
+    pub fn set_page(self: &mut GetManyOptions, page: u64) -> &mut GetManyOptions {
         self.page = page;
         self.skip = (self.page - 1) * self.limit;
         self
     }
 
-    pub fn set_skip(self: &mut GetManyOptions, skip: u64) -> &GetManyOptions {
+    pub fn set_skip(self: &mut GetManyOptions, skip: u64) -> &mut GetManyOptions {
         self.skip = skip;
         self
     }
 
-    pub fn set_limit(self: &mut GetManyOptions, limit: u64) -> &GetManyOptions {
+    pub fn set_limit(self: &mut GetManyOptions, limit: u64) -> &mut GetManyOptions {
         self.limit = limit;
         self
     }
 
-    pub fn as_prepared_tuple(self: &GetManyOptions) -> (String, String, String) {
-        (
-            format!("%{}%", self.query),
-            format!("{}", self.limit),
-            format!("{}", self.skip),
-        )
+    /// The function `set_rss_url` limits the results to the entries of one feed, an empty URL
+    /// removes the filter.
+    pub fn set_rss_url(self: &mut GetManyOptions, rss_url: &str) -> &mut GetManyOptions {
+        self.rss_url = rss_url.to_string();
+        self
+    }
+
+    /// The function `set_profile` limits the results to the entries of the feeds of one profile, an
+    /// empty profile removes the filter.
+    pub fn set_profile(self: &mut GetManyOptions, profile: &str) -> &mut GetManyOptions {
+        self.profile = profile.to_string();
+        self
+    }
+
+    /// The function `set_tags` limits the results to the entries carrying all the given tags, an
+    /// empty list removes the filter. The tags are compared once normalized, see `normalize_tag`,
+    /// and an invalid tag is ignored.
+    pub fn set_tags(self: &mut GetManyOptions, tags: Vec<String>) -> &mut GetManyOptions {
+        self.tags = tags;
+        self
+    }
+
+    /// The function `set_author` limits the results to the entries whose author contains `author`,
+    /// ignoring the case, an empty author removes the filter.
+    pub fn set_author(self: &mut GetManyOptions, author: &str) -> &mut GetManyOptions {
+        self.author = author.to_string();
+        self
+    }
+
+    /// The function `set_categories` limits the results to the entries filed under all the given
+    /// categories, ignoring the case, an empty list removes the filter.
+    pub fn set_categories(
+        self: &mut GetManyOptions,
+        categories: Vec<String>,
+    ) -> &mut GetManyOptions {
+        self.categories = categories;
+        self
+    }
+
+    /// The function `set_unread_only` limits the results to the entries not marked as read.
+    pub fn set_unread_only(self: &mut GetManyOptions, unread_only: bool) -> &mut GetManyOptions {
+        self.unread_only = unread_only;
+        self
+    }
+
+    /// The function `set_starred_only` limits the results to the starred entries.
+    pub fn set_starred_only(self: &mut GetManyOptions, starred_only: bool) -> &mut GetManyOptions {
+        self.starred_only = starred_only;
+        self
+    }
+
+    /// The function `set_date_range` limits the results to the entries published between `since`
+    /// and `until`, both `YYYY-MM-DD HH:MM:SS` and inclusive, an empty bound is open.
+    pub fn set_date_range(
+        self: &mut GetManyOptions,
+        since: &str,
+        until: &str,
+    ) -> &mut GetManyOptions {
+        self.since = since.to_string();
+        self.until = until.to_string();
+        self
+    }
+
+    pub fn set_sort(self: &mut GetManyOptions, sort: EntrySort) -> &mut GetManyOptions {
+        self.sort = sort;
+        self
+    }
+}
+
+/// The `SyncOptions` struct holds the per command settings of `RssEntity::sync`.
+///
+/// Properties:
+///
+/// * `full_text`: Fetch the full article text of new entries for every feed, not only for the feeds
+///   that enabled it.
+/// * `progress`: An optional channel receiving a `SyncProgress` before and after each feed.
+/// * `max_items`: The optional number of items read from each feed, the rest of the feed is not
+///   downloaded.
+#[derive(Debug, Default, Clone)]
+pub struct SyncOptions {
+    pub full_text: bool,
+    pub progress: Option<UnboundedSender<SyncProgress>>,
+    pub max_items: Option<usize>,
+}
+
+impl SyncOptions {
+    pub fn new() -> SyncOptions {
+        SyncOptions {
+            full_text: false,
+            progress: None,
+            max_items: None,
+        }
+    }
+
+    pub fn set_progress(
+        self: &mut SyncOptions,
+        progress: UnboundedSender<SyncProgress>,
+    ) -> &SyncOptions {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn set_max_items(self: &mut SyncOptions, max_items: Option<usize>) -> &SyncOptions {
+        self.max_items = max_items;
+        self
     }
+
+    pub fn set_full_text(self: &mut SyncOptions, full_text: bool) -> &SyncOptions {
+        self.full_text = full_text;
+        self
+    }
+}
+
+/// The `SaveOutcome` enum reports what a call to `CrudAble::save` actually did to the database, so
+/// that callers like `sync` can tell new rows apart from ones that were already stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+/// The `EntryPayload` struct is the serializable view of a newly inserted entry that is handed to
+/// hooks, together with the feed it came from.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPayload {
+    pub hash: String,
+    pub title: String,
+    pub link: String,
+    pub publish_date: String,
+    pub feed_url: String,
+    pub profile: String,
 }
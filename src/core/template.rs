@@ -0,0 +1,46 @@
+/// The function `fill` replaces the `{name}` placeholders of `template` with their value. The
+/// template is read in a single pass, so braces in a value are not taken for placeholders, and
+/// unknown placeholders are kept as they are.
+///
+/// Arguments:
+///
+/// * `template`: The text holding the placeholders.
+/// * `values`: The name and value of every known placeholder.
+///
+/// Returns:
+///
+/// the filled template.
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let placeholder = rest[start + 1..].split_once('}').and_then(|(name, after)| {
+            values
+                .iter()
+                .find(|(value_name, _)| *value_name == name)
+                .map(|(_, value)| (value, after))
+        });
+        match placeholder {
+            Some((value, after)) => {
+                filled.push_str(value);
+                rest = after;
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// The function `escape_html` escapes the characters with a meaning in HTML text and attribute
+/// values.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,26 +1,26 @@
-use sqlite::Connection;
+use sqlite::{Connection, Error};
 use std::vec::Vec;
 use thread_safe::ThreadSafe;
 
-use super::structs::GetManyOptions;
+use super::structs::{GetManyOptions, SaveOutcome};
 
 /// The `Connectable` trait is defining a method called `set_connection` that takes a mutable reference
 /// to `self` and a reference to a `Connection` object. The `'c` and `'conn` lifetimes are used to
 /// specify the lifetime of the references. This trait can be implemented by types that need to
-/// establish a connection to a database and set that connection for future use.
+/// establish a connection to a database and set that connection for future use. It is SQLite only,
+/// code that should also run on other backends goes through `crate::storage::Storage`.
 pub trait Connectable<'c> {
     fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection);
 }
 
-/// The code defines a trait called `CrudAble` with two associated functions: `create` and `get_many`.
-
+/// The code defines a trait called `CrudAble` with two associated functions: `save` and `get_many`.
+/// `save` reports whether the row was inserted, updated or left unchanged. It maps entities to their
+/// SQLite tables: `SqliteStorage` uses it to implement `crate::storage::Storage`, which feeds, entries
+/// and tags are read and written through, and the SQLite only tables use it directly.
 pub trait CrudAble<'a, T>
 where
     T: Connectable<'a>,
 {
-    fn save<'c>(&'c self) -> &'c T;
-    fn get_many<'conn>(
-        connection: &ThreadSafe<&'conn Connection>,
-        options: GetManyOptions,
-    ) -> Vec<T>;
+    fn save(&self) -> Result<SaveOutcome, Error>;
+    fn get_many(connection: &ThreadSafe<&Connection>, options: GetManyOptions) -> Vec<T>;
 }
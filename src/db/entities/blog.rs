@@ -1,16 +1,23 @@
 use digest::Digest;
-use sqlite::{Connection, State};
-use std::fmt::Display;
+use sqlite::{Connection, Error, State, Value};
+use std::{collections::BTreeSet, fmt::Display};
 use thread_safe::ThreadSafe;
 
 use sha2::Sha256;
 
 use crate::{
     core::{
-        structs::GetManyOptions,
+        query::SqlQuery,
+        structs::{EntryPayload, EntrySort, GetManyOptions, SaveOutcome},
         traits::{Connectable, CrudAble},
     },
-    rss::dto::Item,
+    rss::dto::{parse_date, Item},
+};
+
+use super::tag::normalize_tag;
+
+use super::{
+    enclosure::EntryEnclosure, episode::EntryEpisode, media::EntryMedia, rss_entity::RssEntity,
 };
 
 /// The `RssEntry` struct represents an RSS entry with a title, link, publish date, and an optional
@@ -21,14 +28,23 @@ use crate::{
 /// * `title`: A string representing the title of the RSS entry.
 /// * `link`: A string representing the URL or link associated with the RSS entry.
 /// * `publish_date`: The `publish_date` property is a string that represents the date when the RSS
-/// entry was published.
+///   entry was published.
+/// * `rss_url`: The URL of the feed the entry was found in, empty for entries stored before it was
+///   recorded.
+/// * `description`: The HTML description (summary) of the entry as shipped by the feed.
+/// * `is_read`: Whether the entry was marked as read.
+/// * `is_starred`: Whether the entry was starred.
 /// * `connection`: The `connection` property is an optional field that holds a reference to a
-/// `ThreadSafe` wrapper around a `&Connection` object. The `ThreadSafe` wrapper ensures that the
-/// reference to the `Connection` object can be safely shared across multiple threads.
+///   `ThreadSafe` wrapper around a `&Connection` object. The `ThreadSafe` wrapper ensures that the
+///   reference to the `Connection` object can be safely shared across multiple threads.
 pub struct RssEntry<'c> {
     pub title: String,
     pub link: String,
     pub publish_date: String,
+    pub rss_url: String,
+    pub description: String,
+    pub is_read: bool,
+    pub is_starred: bool,
     pub connection: Option<ThreadSafe<&'c Connection>>,
 }
 
@@ -39,8 +55,11 @@ impl<'c> Display for RssEntry<'c> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Title: {}\nLink: {}\nDate: {}\n",
-            self.title, self.link, self.publish_date
+            "Id: {}\nTitle: {}\nLink: {}\nDate: {}\n",
+            &self.hash()[..12],
+            self.title,
+            self.link,
+            self.publish_date
         )
     }
 }
@@ -53,132 +72,614 @@ impl<'c> Connectable<'c> for RssEntry<'c> {
 
 impl<'c> CrudAble<'c, RssEntry<'c>> for RssEntry<'c> {
     /// The `save` function saves an RSS entry to a database if a database connection is available,
-    /// using a hash of the entry's title as the primary key.
+    /// using a hash of the entry's title as the primary key. An entry whose title is already stored
+    /// but whose link changed is updated in place. The publish date given by the feed is stored in
+    /// UTC, the time of the sync when the feed gives none.
     ///
     /// Returns:
     ///
-    /// a reference to an `RssEntry` object.
-    fn save<'d>(&'d self) -> &'d RssEntry<'c> {
-        // database insertion here
-        match self.connection.clone() {
-            Some(connection) => {
-                let mut hasher = Sha256::new();
-                let connection = connection.get_ref();
-                hasher.update(self.title.clone());
-                let result = hasher.finalize();
-                let hash = format!("{:x}", result);
-
-                let count_query = format!(
-                    "
-                    SELECT COUNT(*) AS \"count\" 
-                    FROM rss_entries
-                    WHERE hash = ?
-                    LIMIT 1
-                    "
-                );
-                let insert_query = format!(
-                    "
-                    INSERT INTO rss_entries (
-                        hash, title,
-                        publish_date, link
-                    )VALUES(
-                        ?, ?, CURRENT_TIMESTAMP, ?
-                    )"
-                );
-
-                let mut count_statement = connection.prepare(count_query).unwrap();
-                count_statement.bind((1, hash.as_str())).unwrap();
-
-                let mut insert_statement = connection.prepare(insert_query).unwrap();
-                insert_statement.bind((1, hash.as_str())).unwrap();
-                insert_statement.bind((2, self.title.as_str())).unwrap();
-                insert_statement.bind((3, self.link.as_str())).unwrap();
-
-                match count_statement.next() {
-                    Ok(State::Row) | Ok(State::Done) => {
-                        match count_statement.read::<String, _>("count").as_deref() {
-                            Ok("1") => &self,
-                            Ok("0") | Ok(_) => {
-                                return match insert_statement.next() {
-                                    Ok(_) => self,
-                                    Err(_) => self,
-                                }
-                            }
-
-                            Err(_) => panic!("Insert into rss_entries failed"),
-                        }
-                    }
-                    Err(_) => &self,
-                }
-            }
+    /// a `SaveOutcome` describing whether the entry was inserted, updated or already stored.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
             None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let hash = self.hash();
+
+        let select_query = "
+            SELECT link
+            FROM rss_entries
+            WHERE hash = ?
+            LIMIT 1
+        ";
+        let mut select_statement = connection.prepare(select_query)?;
+        select_statement.bind((1, hash.as_str()))?;
+
+        if let State::Row = select_statement.next()? {
+            let stored_link = select_statement.read::<String, _>("link")?;
+            if stored_link == self.link {
+                return Ok(SaveOutcome::Unchanged);
+            }
+
+            let update_query = "UPDATE rss_entries SET link = ? WHERE hash = ?";
+            let mut update_statement = connection.prepare(update_query)?;
+            update_statement.bind_iter([(1, self.link.as_str()), (2, hash.as_str())])?;
+            update_statement.next()?;
+            return Ok(SaveOutcome::Updated);
         }
-    }
 
-    /// The function creates a new entry in a database table if a matching entry does not already exist,
-    /// and returns a reference to the created or existing entry.
-    ///
-    /// Arguments:
-    ///
-    /// * `entity`: The `entity` parameter is a reference to an `RssEntry` struct.
-    ///
-    /// Returns:
-    ///
-    /// a reference to an `RssEntry` object.
+        // the date given by the feed, the time of the sync when it has none or it is not readable
+        let publish_date =
+            parse_date(&self.publish_date).map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string());
+        let insert_query = "
+            INSERT INTO rss_entries (
+                hash, title,
+                publish_date, link, description, rss_url
+            )VALUES(
+                ?, ?, IFNULL(?, CURRENT_TIMESTAMP), ?, ?, ?
+            )";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, hash.as_str()))?;
+        insert_statement.bind((2, self.title.as_str()))?;
+        insert_statement.bind((3, publish_date.as_deref()))?;
+        insert_statement.bind((4, self.link.as_str()))?;
+        insert_statement.bind((5, self.description.as_str()))?;
+        insert_statement.bind((6, self.rss_url.as_str()))?;
+        insert_statement.next()?;
+        Ok(SaveOutcome::Inserted)
+    }
 
     /// The function retrieves multiple rows from a database table and returns them as a vector of
-    /// RssEntry structs.
+    /// RssEntry structs. The query matches the title and the stored full article text, and the
+    /// results can be limited to one feed or profile, to the entries carrying all of
+    /// `options.tags` on the entry itself or on its feed, to an author, to the entries filed under
+    /// all of `options.categories`, to unread or starred entries and to a range of publish dates,
+    /// in the order of `options.sort`. See `entry_query`.
     ///
     /// Arguments:
     ///
     /// * `connection`: The `connection` parameter is a reference to a `Connection` object. It
-    /// represents a connection to a database and is used to execute SQL queries and interact with the
-    /// database.
+    ///   represents a connection to a database and is used to execute SQL queries and interact with the
+    ///   database.
     ///
     /// Returns:
     ///
     /// The function `get_many` returns a vector of `RssEntry` structs.
-    fn get_many<'conn>(
-        connection: &ThreadSafe<&'conn Connection>,
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
         options: GetManyOptions,
     ) -> Vec<RssEntry<'c>> {
         let connection = connection.get_ref();
+        let (query, values) =
+            entry_query(&options).select("rss_entries.*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+
+        let mut rss_entity_vec: Vec<RssEntry> = vec![];
+        while let Ok(State::Row) = statement.next() {
+            rss_entity_vec.push(read_entry(&statement))
+        }
+        rss_entity_vec
+    }
+}
+
+impl<'c> RssEntry<'c> {
+    /// The function `count` returns how many entries match the filters of `options`, ignoring its
+    /// page and limit.
+    pub fn count(connection: &ThreadSafe<&Connection>, options: &GetManyOptions) -> u64 {
+        let (query, values) = entry_query(options).count();
+        let mut statement = connection
+            .get_ref()
+            .prepare(query)
+            .expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        match statement.next() {
+            Ok(State::Row) => statement.read::<i64, _>("count").unwrap_or(0) as u64,
+            _ => 0,
+        }
+    }
+
+    /// The function `is_pruned` tells whether the entry with the given hash was deleted by `prune`,
+    /// so sync does not store it again.
+    pub fn is_pruned(connection: &ThreadSafe<&Connection>, hash: &str) -> bool {
+        let mut statement = connection
+            .get_ref()
+            .prepare("SELECT 1 FROM pruned_entries WHERE hash = ?")
+            .expect("Statement is not built");
+        statement.bind((1, hash)).expect("Binding failed");
+        matches!(statement.next(), Ok(State::Row))
+    }
+
+    /// The function `is_mailed` tells whether the entry with the given hash was already sent to
+    /// `recipient` in an email digest.
+    pub fn is_mailed(connection: &ThreadSafe<&Connection>, recipient: &str, hash: &str) -> bool {
+        let mut statement = connection
+            .get_ref()
+            .prepare("SELECT 1 FROM mailed_entries WHERE recipient = ? AND hash = ?")
+            .expect("Statement is not built");
+        statement
+            .bind_iter::<_, (_, &str)>([(1, recipient), (2, hash)])
+            .expect("Binding failed");
+        matches!(statement.next(), Ok(State::Row))
+    }
+
+    /// The function `set_mailed` records that the entries with the given hashes were sent to
+    /// `recipient`, so the next email digest leaves them out.
+    pub fn set_mailed(
+        connection: &ThreadSafe<&Connection>,
+        recipient: &str,
+        hashes: &[String],
+    ) -> Result<(), Error> {
+        let mut statement = connection
+            .get_ref()
+            .prepare("INSERT OR IGNORE INTO mailed_entries (recipient, hash) VALUES (?, ?)")?;
+        for hash in hashes {
+            statement.reset()?;
+            statement.bind_iter::<_, (_, &str)>([(1, recipient), (2, hash.as_str())])?;
+            statement.next()?;
+        }
+        Ok(())
+    }
+
+    /// The function `with_connection` returns a copy of the entry using `connection`, `None` for a
+    /// copy detached from any database.
+    pub fn with_connection<'d>(
+        &self,
+        connection: Option<ThreadSafe<&'d Connection>>,
+    ) -> RssEntry<'d> {
+        RssEntry {
+            title: self.title.clone(),
+            link: self.link.clone(),
+            publish_date: self.publish_date.clone(),
+            rss_url: self.rss_url.clone(),
+            description: self.description.clone(),
+            is_read: self.is_read,
+            is_starred: self.is_starred,
+            connection,
+        }
+    }
+
+    /// The function `hash` returns the hex encoded SHA-256 of the entry's title, which is used as the
+    /// primary key of the `rss_entries` table.
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.title.clone());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The function `find` returns the entry whose hash starts with `key`, or the newest entry whose
+    /// title contains `key` when no hash matches.
+    pub fn find(connection: &ThreadSafe<&'c Connection>, key: &str) -> Option<RssEntry<'c>> {
         let query = "
-            SELECT * FROM rss_entries 
-            WHERE title LIKE ?
-            ORDER BY publish_date DESC
-            LIMIT ?
-            OFFSET ?
+            SELECT * FROM rss_entries
+            WHERE hash LIKE ?1 OR title LIKE ?2
+            ORDER BY hash LIKE ?1 DESC, publish_date DESC
+            LIMIT 1
         ";
-
-        let (regex, limit, offset) = options.as_prepared_tuple();
-        let mut statement = connection.prepare(query).expect("Statement is not built");
+        let mut statement = connection
+            .get_ref()
+            .prepare(query)
+            .expect("Statement is not built");
         statement
-            .bind_iter::<_, (_, &str)>([
-                ((1, regex.as_str())),
-                ((2, limit.as_str())),
-                ((3, offset.as_str())),
+            .bind_iter([
+                (1, format!("{}%", key).as_str()),
+                (2, format!("%{}%", key).as_str()),
             ])
             .expect("Binding failed");
 
-        let mut rss_entity_vec: Vec<RssEntry> = vec![];
+        match statement.next() {
+            Ok(State::Row) => {
+                let mut rss_entry = read_entry(&statement);
+                rss_entry.connection = Some(connection.clone());
+                Some(rss_entry)
+            }
+            _ => None,
+        }
+    }
+
+    /// The function `set_read` marks the stored entry as read or unread.
+    pub fn set_read(&self, is_read: bool) -> Result<(), Error> {
+        self.set_flag("is_read", is_read)
+    }
+
+    /// The function `set_starred` stars or unstars the stored entry.
+    pub fn set_starred(&self, is_starred: bool) -> Result<(), Error> {
+        self.set_flag("is_starred", is_starred)
+    }
+
+    fn set_flag(&self, column: &str, value: bool) -> Result<(), Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let query = format!("UPDATE rss_entries SET {} = ? WHERE hash = ?", column);
+        let mut statement = connection.prepare(query)?;
+        statement.bind((1, value as i64))?;
+        statement.bind((2, self.hash().as_str()))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `add_tag` attaches a tag to the stored entry, tagging twice is a no-op. The tag
+    /// is normalized first, see `normalize_tag`, and ignored if it is not valid.
+    pub fn add_tag(&self, tag: &str) -> Result<(), Error> {
+        self.update_tag(
+            "INSERT OR IGNORE INTO entry_tags (hash, tag) VALUES (?, ?)",
+            tag,
+        )
+    }
+
+    /// The function `remove_tag` detaches a tag from the stored entry. A tag the entry inherits from
+    /// its feed stays until it is removed from the feed.
+    pub fn remove_tag(&self, tag: &str) -> Result<(), Error> {
+        self.update_tag("DELETE FROM entry_tags WHERE hash = ? AND tag = ?", tag)
+    }
+
+    fn update_tag(&self, query: &str, tag: &str) -> Result<(), Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let tag = match normalize_tag(tag) {
+            Some(tag) => tag,
+            None => return Ok(()),
+        };
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare(query)?;
+        statement.bind_iter([(1, self.hash().as_str()), (2, tag.as_str())])?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `tags` returns the tags of the entry, its own ones followed by the ones it
+    /// inherits from its feed, without duplicates.
+    pub fn tags(&self, connection: &ThreadSafe<&Connection>) -> Vec<String> {
+        let query = "
+            SELECT tag FROM entry_tags WHERE hash = ?1
+            UNION
+            SELECT tag FROM feed_tags WHERE rss_url = ?2
+            ORDER BY tag
+        ";
+        let mut statement = connection
+            .get_ref()
+            .prepare(query)
+            .expect("Statement is not built");
+        statement
+            .bind_iter([(1, self.hash().as_str()), (2, self.rss_url.as_str())])
+            .expect("Binding failed");
+        let mut tags = vec![];
         while let Ok(State::Row) = statement.next() {
-            rss_entity_vec.push(RssEntry {
-                title: statement
-                    .read::<String, _>("title")
-                    .expect("TITLE NOT FOUND")
-                    .to_string(),
-                link: statement
-                    .read::<String, _>("link")
-                    .expect("LINK NOT FOUND")
-                    .to_string(),
-                publish_date: statement
-                    .read::<String, _>("publish_date")
-                    .expect("PUBLISH DATE NOT FOUND"),
-                connection: None,
+            tags.push(statement.read::<String, _>("tag").unwrap());
+        }
+        tags
+    }
+
+    /// The function `save_podcast` stores the enclosures and the iTunes episode metadata of the
+    /// item the entry was built from, replacing what was stored for a previous version of it.
+    pub fn save_podcast(&self, item: &Item) -> Result<(), Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let hash = self.hash();
+        EntryEnclosure::replace_for_entry(&connection, &hash, &item.enclosures)?;
+        if let Some(episode) = &item.episode {
+            EntryEpisode {
+                hash,
+                episode: episode.clone(),
+                connection: Some(connection),
+            }
+            .save()?;
+        }
+        Ok(())
+    }
+
+    /// The function `save_details` stores the author and the categories of the item the entry was
+    /// built from, so the entries can be filtered on them, replacing what was stored for a previous
+    /// version of it.
+    pub fn save_details(&self, item: &Item) -> Result<(), Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let hash = self.hash();
+        let author = item.author.trim();
+        let mut statement =
+            connection.prepare("UPDATE rss_entries SET author = ? WHERE hash = ?")?;
+        statement.bind((1, Some(author).filter(|author| !author.is_empty())))?;
+        statement.bind((2, hash.as_str()))?;
+        statement.next()?;
+
+        let mut statement = connection.prepare("DELETE FROM entry_categories WHERE hash = ?")?;
+        statement.bind((1, hash.as_str()))?;
+        statement.next()?;
+        let mut statement = connection
+            .prepare("INSERT OR IGNORE INTO entry_categories (hash, category) VALUES (?, ?)")?;
+        for category in item.category.iter().map(|category| category.trim()) {
+            if category.is_empty() {
+                continue;
+            }
+            statement.reset()?;
+            statement.bind_iter([(1, hash.as_str()), (2, category)])?;
+            statement.next()?;
+        }
+        Ok(())
+    }
+
+    /// The function `details` returns the author stored for the entry, if any, and its categories
+    /// sorted.
+    pub fn details(&self, connection: &ThreadSafe<&Connection>) -> (Option<String>, Vec<String>) {
+        let connection = connection.get_ref();
+        let hash = self.hash();
+        let mut statement = connection
+            .prepare("SELECT author FROM rss_entries WHERE hash = ?")
+            .expect("Statement is not built");
+        statement.bind((1, hash.as_str())).expect("Binding failed");
+        let author = match statement.next() {
+            Ok(State::Row) => statement
+                .read::<Option<String>, _>("author")
+                .unwrap_or_default(),
+            _ => None,
+        };
+        let mut statement = connection
+            .prepare("SELECT category FROM entry_categories WHERE hash = ? ORDER BY category")
+            .expect("Statement is not built");
+        statement.bind((1, hash.as_str())).expect("Binding failed");
+        let mut categories = vec![];
+        while let Ok(State::Row) = statement.next() {
+            categories.push(statement.read::<String, _>("category").unwrap());
+        }
+        (author, categories)
+    }
+
+    /// The function `podcast` returns the episode metadata and the enclosures stored for the
+    /// entry, formatted one per line, or an empty string for an entry without any.
+    pub fn podcast(&self, connection: &ThreadSafe<&Connection>) -> String {
+        let hash = self.hash();
+        let mut lines = vec![];
+        if let Some(episode) = EntryEpisode::get(connection, &hash) {
+            lines.push(episode.to_string());
+        }
+        for enclosure in EntryEnclosure::for_entry(connection, &hash) {
+            lines.push(enclosure.to_string());
+        }
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// The function `save_media` stores the Media RSS data of the item the entry was built from,
+    /// if it has any.
+    pub fn save_media(&self, item: &Item) -> Result<(), Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        if let Some(media) = &item.media {
+            EntryMedia {
+                hash: self.hash(),
+                media: media.clone(),
+                connection: Some(connection),
+            }
+            .save()?;
+        }
+        Ok(())
+    }
+
+    /// The function `media` returns the Media RSS data stored for the entry, formatted one per
+    /// line, or an empty string for an entry without any.
+    pub fn media(&self, connection: &ThreadSafe<&Connection>) -> String {
+        match EntryMedia::get(connection, &self.hash()) {
+            Some(media) if !media.to_string().is_empty() => format!("{}\n", media),
+            _ => String::new(),
+        }
+    }
+
+    /// The function `to_json` exports the entry along with the enclosures, the episode metadata and
+    /// the Media RSS data stored for it.
+    pub fn to_json(&self, connection: &ThreadSafe<&Connection>) -> serde_json::Value {
+        let hash = self.hash();
+        let enclosures: Vec<serde_json::Value> = EntryEnclosure::for_entry(connection, &hash)
+            .iter()
+            .map(|enclosure| {
+                serde_json::json!({
+                    "url": enclosure.url,
+                    "length": enclosure.length,
+                    "type": enclosure.mime_type,
+                    "file_path": enclosure.file_path,
+                })
             })
+            .collect();
+        let (author, categories) = self.details(connection);
+        serde_json::json!({
+            "id": hash,
+            "title": self.title,
+            "link": self.link,
+            "publish_date": self.publish_date,
+            "feed": self.rss_url,
+            "is_read": self.is_read,
+            "is_starred": self.is_starred,
+            "author": author,
+            "categories": categories,
+            "tags": self.tags(connection),
+            "enclosures": enclosures,
+            "episode": EntryEpisode::get(connection, &hash).map(|episode| episode.episode),
+            "media": EntryMedia::get(connection, &hash).map(|media| media.media),
+        })
+    }
+
+    /// The function `to_payload` builds the `EntryPayload` handed to hooks for this entry, which was
+    /// fetched from `rss_entity`.
+    pub fn to_payload(&self, rss_entity: &RssEntity) -> EntryPayload {
+        EntryPayload {
+            hash: self.hash(),
+            title: self.title.clone(),
+            link: self.link.clone(),
+            publish_date: self.publish_date.clone(),
+            feed_url: rss_entity.rss_url.clone(),
+            profile: rss_entity.profile.clone(),
         }
-        return rss_entity_vec;
+    }
+}
+
+/// The function `entry_query` builds the query of the entries matching the filters of `options`,
+/// in the order of `options.sort`. Only the filters that are set become conditions, and every value
+/// is bound rather than written into the SQL. A URL can be subscribed under several profiles, so
+/// the feeds are only looked up through subqueries, which never repeat an entry.
+fn entry_query(options: &GetManyOptions) -> SqlQuery {
+    let text = |value: String| Value::String(value);
+    let mut query = SqlQuery::new(
+        "rss_entries LEFT JOIN entry_contents ON entry_contents.hash = rss_entries.hash",
+    );
+    if !options.query.is_empty() {
+        let pattern = format!("%{}%", options.query);
+        query.filter(
+            "(rss_entries.title LIKE ? OR entry_contents.text LIKE ?)",
+            [text(pattern.clone()), text(pattern)],
+        );
+    }
+    if !options.rss_url.is_empty() {
+        query.filter("rss_entries.rss_url = ?", [text(options.rss_url.clone())]);
+    }
+    if !options.profile.is_empty() {
+        query.filter(
+            "EXISTS (
+                SELECT 1 FROM rss_table
+                WHERE rss_table.rss_url = rss_entries.rss_url AND rss_table.profile = ?
+            )",
+            [text(options.profile.clone())],
+        );
+    }
+    // stored tags are normalized, so are the filters, and one that cannot be stored is dropped
+    let tags: BTreeSet<String> = options
+        .tags
+        .iter()
+        .filter_map(|tag| normalize_tag(tag))
+        .collect();
+    if !tags.is_empty() {
+        let condition = format!(
+            "(
+                SELECT COUNT(*) FROM (
+                    SELECT tag FROM entry_tags WHERE entry_tags.hash = rss_entries.hash
+                    UNION
+                    SELECT tag FROM feed_tags WHERE feed_tags.rss_url = rss_entries.rss_url
+                ) WHERE tag IN ({})
+            ) = ?",
+            placeholders(tags.len())
+        );
+        let count = Value::Integer(tags.len() as i64);
+        query.filter(&condition, tags.into_iter().map(text).chain([count]));
+    }
+    if !options.author.is_empty() {
+        query.filter(
+            "rss_entries.author LIKE ?",
+            [text(format!("%{}%", options.author))],
+        );
+    }
+    let categories: BTreeSet<String> = options
+        .categories
+        .iter()
+        .map(|category| category.trim().to_ascii_lowercase())
+        .collect();
+    if !categories.is_empty() {
+        let condition = format!(
+            "(
+                SELECT COUNT(DISTINCT lower(category)) FROM entry_categories
+                WHERE entry_categories.hash = rss_entries.hash AND lower(category) IN ({})
+            ) = ?",
+            placeholders(categories.len())
+        );
+        let count = Value::Integer(categories.len() as i64);
+        query.filter(&condition, categories.into_iter().map(text).chain([count]));
+    }
+    if options.unread_only {
+        query.filter("rss_entries.is_read = 0", []);
+    }
+    if options.starred_only {
+        query.filter("rss_entries.is_starred = 1", []);
+    }
+    if !options.since.is_empty() {
+        query.filter(
+            "rss_entries.publish_date >= ?",
+            [text(options.since.clone())],
+        );
+    }
+    if !options.until.is_empty() {
+        query.filter(
+            "rss_entries.publish_date <= ?",
+            [text(options.until.clone())],
+        );
+    }
+
+    match options.sort {
+        EntrySort::Newest => query.order_by("rss_entries.publish_date DESC", []),
+        EntrySort::Oldest => query.order_by("rss_entries.publish_date ASC", []),
+        EntrySort::Title => query.order_by("rss_entries.title COLLATE NOCASE ASC", []),
+        EntrySort::Feed => query
+            .order_by(
+                // the first subscription of a URL names its entries, as in `MemoryStorage`
+                "COALESCE(
+                    (
+                        SELECT COALESCE(display_title, NULLIF(TRIM(title), '')) FROM rss_table
+                        WHERE rss_table.rss_url = rss_entries.rss_url ORDER BY id LIMIT 1
+                    ),
+                    rss_entries.rss_url
+                ) COLLATE NOCASE ASC",
+                [],
+            )
+            .order_by("rss_entries.publish_date DESC", []),
+        EntrySort::Relevance if options.query.is_empty() => {
+            query.order_by("rss_entries.publish_date DESC", [])
+        }
+        EntrySort::Relevance => query
+            .order_by(
+                "CASE
+                    WHEN rss_entries.title LIKE ? THEN 0
+                    WHEN rss_entries.title LIKE ? THEN 1
+                    ELSE 2
+                END ASC",
+                [
+                    text(format!("{}%", options.query)),
+                    text(format!("%{}%", options.query)),
+                ],
+            )
+            .order_by("rss_entries.publish_date DESC", []),
+    };
+    query
+}
+
+/// The function `placeholders` returns `count` comma separated placeholders, for an `IN` list.
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// The function `read_entry` builds an `RssEntry` from the current row of a statement selecting
+/// `rss_entries` columns.
+fn read_entry<'c>(statement: &sqlite::Statement) -> RssEntry<'c> {
+    RssEntry {
+        title: statement
+            .read::<String, _>("title")
+            .expect("TITLE NOT FOUND")
+            .to_string(),
+        link: statement
+            .read::<String, _>("link")
+            .expect("LINK NOT FOUND")
+            .to_string(),
+        publish_date: statement
+            .read::<String, _>("publish_date")
+            .expect("PUBLISH DATE NOT FOUND"),
+        rss_url: statement
+            .read::<Option<String>, _>("rss_url")
+            .unwrap_or_default()
+            .unwrap_or_default(),
+        description: statement
+            .read::<Option<String>, _>("description")
+            .unwrap_or_default()
+            .unwrap_or_default(),
+        is_read: statement.read::<i64, _>("is_read").unwrap_or(0) == 1,
+        is_starred: statement.read::<i64, _>("is_starred").unwrap_or(0) == 1,
+        connection: None,
     }
 }
 
@@ -191,6 +692,10 @@ impl<'c> Clone for RssEntry<'c> {
             title: self.title.clone(),
             link: self.link.clone(),
             publish_date: self.publish_date.clone(),
+            rss_url: self.rss_url.clone(),
+            description: self.description.clone(),
+            is_read: self.is_read,
+            is_starred: self.is_starred,
             connection: self.connection.clone(),
         }
     }
@@ -208,7 +713,222 @@ impl<'c> From<Item> for RssEntry<'c> {
             title: item.title,
             link: item.link,
             publish_date: item.publish_date,
+            rss_url: String::new(),
+            description: item.description,
+            is_read: false,
+            is_starred: false,
             connection: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_database;
+
+    const A: &str = "https://a.test/rss";
+    const B: &str = "https://b.test/rss";
+
+    /// The function `database` subscribes to `A` under two profiles, named "Zeta" then "Alpha", and
+    /// to `B` named "Beta", with two entries each. The first entry of `A` has an author, two
+    /// categories and a tag, `A` carries the tag "dev" and the last entry of `B` has a full text.
+    fn database() -> Connection {
+        let connection = open_database(":memory:").unwrap();
+        connection
+            .execute(format!(
+                "
+                INSERT INTO rss_table (rss_url, profile, title, display_title) VALUES
+                    ('{a}', 'work', 'Zeta', NULL),
+                    ('{a}', 'home', 'Zeta', 'Alpha'),
+                    ('{b}', 'home', 'Beta', NULL);
+                INSERT INTO rss_entries (hash, title, publish_date, link, rss_url, author) VALUES
+                    ('e1', 'Rust news', '2024-01-01 00:00:00', '', '{a}', 'Jane Doe'),
+                    ('e2', 'Learning rust', '2024-02-01 00:00:00', '', '{a}', NULL),
+                    ('e3', 'Bread', '2024-03-01 00:00:00', '', '{b}', 'John'),
+                    ('e4', 'Garden', '2024-04-01 00:00:00', '', '{b}', NULL);
+                INSERT INTO entry_categories (hash, category) VALUES
+                    ('e1', 'Rust'), ('e1', 'News'), ('e2', 'rust'), ('e3', 'Food');
+                INSERT INTO entry_tags (hash, tag) VALUES ('e1', 'to-read');
+                INSERT INTO feed_tags (rss_url, tag) VALUES ('{a}', 'dev');
+                INSERT INTO entry_contents (hash, html, text, word_count, reading_minutes)
+                    VALUES ('e4', '', 'rust spots on the garden tools', 6, 1);
+                ",
+                a = A,
+                b = B
+            ))
+            .unwrap();
+        connection
+    }
+
+    fn options(update: impl Fn(&mut GetManyOptions)) -> GetManyOptions {
+        let mut options = GetManyOptions::new();
+        options.set_limit(100);
+        update(&mut options);
+        options
+    }
+
+    /// The function `found` returns the titles of the entries `options` matches, after checking
+    /// that `RssEntry::count` agrees with them.
+    fn found(connection: &ThreadSafe<&Connection>, options: GetManyOptions) -> Vec<String> {
+        let count = RssEntry::count(connection, &options);
+        let titles: Vec<String> = RssEntry::get_many(connection, options)
+            .into_iter()
+            .map(|rss_entry| rss_entry.title)
+            .collect();
+        assert_eq!(count, titles.len() as u64);
+        titles
+    }
+
+    #[test]
+    fn entry_query_filters_entries() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let found = |update: &dyn Fn(&mut GetManyOptions)| found(&connection, options(update));
+
+        assert_eq!(
+            found(&|options| options.query = String::from("rust")),
+            ["Garden", "Learning rust", "Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_author("jane");
+            }),
+            ["Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_categories(vec![String::from(" RUST ")]);
+            }),
+            ["Learning rust", "Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_categories(vec![String::from("rust"), String::from("news")]);
+            }),
+            ["Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_categories(vec![String::from("rust"), String::from("food")]);
+            }),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_tags(vec![String::from("dev")]);
+            }),
+            ["Learning rust", "Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_tags(vec![String::from("dev"), String::from("to-read")]);
+            }),
+            ["Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_date_range("2024-02-01 00:00:00", "2024-03-01 00:00:00");
+            }),
+            ["Bread", "Learning rust"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_date_range("2024-03-01 00:00:00", "");
+            }),
+            ["Garden", "Bread"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_date_range("", "2024-01-31 00:00:00");
+            }),
+            ["Rust news"]
+        );
+        assert_eq!(
+            found(&|options| {
+                options.set_rss_url(B);
+            }),
+            ["Garden", "Bread"]
+        );
+    }
+
+    #[test]
+    fn entry_query_sorts_entries() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let sorted = |sort: EntrySort, query: &str| {
+            found(
+                &connection,
+                options(|options| {
+                    options.set_sort(sort);
+                    options.query = query.to_string();
+                }),
+            )
+        };
+
+        assert_eq!(
+            sorted(EntrySort::Newest, ""),
+            ["Garden", "Bread", "Learning rust", "Rust news"]
+        );
+        assert_eq!(
+            sorted(EntrySort::Oldest, ""),
+            ["Rust news", "Learning rust", "Bread", "Garden"]
+        );
+        assert_eq!(
+            sorted(EntrySort::Title, ""),
+            ["Bread", "Garden", "Learning rust", "Rust news"]
+        );
+        // the entries of `A` are named after its first subscription, "Zeta"
+        assert_eq!(
+            sorted(EntrySort::Feed, ""),
+            ["Garden", "Bread", "Learning rust", "Rust news"]
+        );
+        assert_eq!(
+            sorted(EntrySort::Relevance, "rust"),
+            ["Rust news", "Learning rust", "Garden"]
+        );
+        assert_eq!(
+            sorted(EntrySort::Relevance, ""),
+            ["Garden", "Bread", "Learning rust", "Rust news"]
+        );
+    }
+
+    #[test]
+    fn entry_query_counts_every_page() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let mut pages = vec![];
+        for page in 1..=3 {
+            let mut options = GetManyOptions::new();
+            options.set_limit(3).set_page(page);
+            assert_eq!(RssEntry::count(&connection, &options), 4);
+            pages.push(RssEntry::get_many(&connection, options).len());
+        }
+        assert_eq!(pages, [3, 1, 0]);
+    }
+
+    #[test]
+    fn entry_query_does_not_repeat_entries_subscribed_twice() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        assert_eq!(found(&connection, options(|_| {})).len(), 4);
+        assert_eq!(
+            found(
+                &connection,
+                options(|options| {
+                    options.set_profile("home");
+                })
+            ),
+            ["Garden", "Bread", "Learning rust", "Rust news"]
+        );
+        assert_eq!(
+            found(
+                &connection,
+                options(|options| {
+                    options.set_profile("work").set_sort(EntrySort::Feed);
+                })
+            ),
+            ["Learning rust", "Rust news"]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::core::{
+    structs::{GetManyOptions, SaveOutcome},
+    traits::{Connectable, CrudAble},
+};
+
+/// The directory enclosures are downloaded to when no setting overrides it.
+pub const DEFAULT_DIRECTORY: &str = "./downloads";
+
+/// The file name template used when no setting overrides it, see `crate::download::target_path`.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{profile}/{feed}/{date} {title}.{ext}";
+
+/// The number of simultaneous downloads when no setting overrides it.
+pub const DEFAULT_CONCURRENCY: u64 = 2;
+
+/// The `DownloadSettings` struct holds the options of the enclosure downloads, stored as the
+/// single row of the `download_settings` table.
+///
+/// Properties:
+///
+/// * `directory`: The directory the files are written to.
+/// * `filename_template`: The path of a file inside `directory`, with `{profile}`, `{feed}`,
+///   `{date}`, `{title}`, `{hash}`, `{filename}` and `{ext}` placeholders.
+/// * `concurrency`: The number of files downloaded at the same time.
+/// * `max_total_bytes`: The size of all downloaded files after which the oldest are deleted.
+/// * `max_files`: The number of downloaded files after which the oldest are deleted.
+/// * `max_file_bytes`: The size after which a single download is rejected.
+/// * `connection`: The optional database connection used by `save`.
+#[derive(Clone)]
+pub struct DownloadSettings<'c> {
+    pub directory: String,
+    pub filename_template: String,
+    pub concurrency: u64,
+    pub max_total_bytes: Option<u64>,
+    pub max_files: Option<u64>,
+    pub max_file_bytes: Option<u64>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Default for DownloadSettings<'c> {
+    fn default() -> Self {
+        DownloadSettings {
+            directory: DEFAULT_DIRECTORY.to_string(),
+            filename_template: DEFAULT_FILENAME_TEMPLATE.to_string(),
+            concurrency: DEFAULT_CONCURRENCY,
+            max_total_bytes: None,
+            max_files: None,
+            max_file_bytes: None,
+            connection: None,
+        }
+    }
+}
+
+impl<'c> Display for DownloadSettings<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn show(value: &Option<u64>) -> String {
+            value
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| String::from("-"))
+        }
+        write!(
+            f,
+            "(  directory = {}\ttemplate = {}\tconcurrency = {}\tmax_total_bytes = {}\tmax_files = {}\tmax_file_bytes = {}  )",
+            self.directory,
+            self.filename_template,
+            self.concurrency,
+            show(&self.max_total_bytes),
+            show(&self.max_files),
+            show(&self.max_file_bytes)
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for DownloadSettings<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, DownloadSettings<'c>> for DownloadSettings<'c> {
+    /// The function stores the settings, replacing the settings previously stored.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if settings were already stored, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = !DownloadSettings::get_many(&connection, GetManyOptions::new()).is_empty();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO download_settings (
+                id, directory, filename_template, concurrency,
+                max_total_bytes, max_files, max_file_bytes
+            )VALUES (
+                1, ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.directory.as_str()))?;
+        insert_statement.bind((2, self.filename_template.as_str()))?;
+        insert_statement.bind((3, self.concurrency as i64))?;
+        insert_statement.bind((4, self.max_total_bytes.map(|bytes| bytes as i64)))?;
+        insert_statement.bind((5, self.max_files.map(|files| files as i64)))?;
+        insert_statement.bind((6, self.max_file_bytes.map(|bytes| bytes as i64)))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the stored settings, at most one row.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<DownloadSettings<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM download_settings")
+            .expect("Statement is not built");
+        let number = |statement: &sqlite::Statement, column: &str| {
+            statement
+                .read::<Option<i64>, _>(column)
+                .unwrap()
+                .map(|number| number as u64)
+        };
+        let mut settings = vec![];
+        while let Ok(State::Row) = statement.next() {
+            settings.push(DownloadSettings {
+                directory: statement.read::<String, _>("directory").unwrap(),
+                filename_template: statement.read::<String, _>("filename_template").unwrap(),
+                concurrency: statement.read::<i64, _>("concurrency").unwrap() as u64,
+                max_total_bytes: number(&statement, "max_total_bytes"),
+                max_files: number(&statement, "max_files"),
+                max_file_bytes: number(&statement, "max_file_bytes"),
+                connection: None,
+            })
+        }
+        settings
+    }
+}
+
+impl<'c> DownloadSettings<'c> {
+    /// The function `get` returns the stored settings, or the defaults when none were stored.
+    pub fn get(connection: &ThreadSafe<&Connection>) -> DownloadSettings<'c> {
+        DownloadSettings::get_many(connection, GetManyOptions::new())
+            .pop()
+            .unwrap_or_default()
+    }
+}
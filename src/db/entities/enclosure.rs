@@ -0,0 +1,320 @@
+use sqlite::{Connection, Error, State, Value};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        query::SqlQuery,
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    rss::dto::Enclosure,
+};
+
+/// The `EntryEnclosure` struct is a media file attached to an `RssEntry`, like the audio of a
+/// podcast episode, stored in the `enclosures` table.
+///
+/// Properties:
+///
+/// * `id`: The id of the row, 0 until it is saved.
+/// * `hash`: The hash of the `RssEntry` the file is attached to.
+/// * `url`: The URL of the file.
+/// * `length`: The size of the file in bytes, 0 when the feed does not tell.
+/// * `mime_type`: The MIME type of the file, like `audio/mpeg`.
+/// * `file_path`: The local copy of the file, `None` until it is downloaded or once it is pruned.
+/// * `sha256`: The hex encoded SHA-256 of the downloaded file.
+/// * `downloaded_at`: When the file was downloaded, kept after it is pruned so it is not
+///   downloaded again.
+/// * `download_error`: Why the last download attempt failed.
+/// * `connection`: The optional database connection used by `save`.
+pub struct EntryEnclosure<'c> {
+    pub id: i64,
+    pub hash: String,
+    pub url: String,
+    pub length: u64,
+    pub mime_type: String,
+    pub file_path: Option<String>,
+    pub sha256: Option<String>,
+    pub downloaded_at: Option<String>,
+    pub download_error: Option<String>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for EntryEnclosure<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Enclosure: {}", self.url)?;
+        let mut details = vec![];
+        if !self.mime_type.is_empty() {
+            details.push(self.mime_type.clone());
+        }
+        if self.length > 0 {
+            details.push(format!("{:.1} MB", self.length as f64 / 1_000_000.0));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        if let Some(file_path) = &self.file_path {
+            write!(f, "\nDownloaded: {}", file_path)?;
+        } else if let Some(error) = &self.download_error {
+            write!(f, "\nDownload failed: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'c> Connectable<'c> for EntryEnclosure<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, EntryEnclosure<'c>> for EntryEnclosure<'c> {
+    /// The function stores the enclosure, an entry can have several of them.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Inserted` once the row is written.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT INTO enclosures (
+                hash, url, length, mime_type
+            )VALUES (
+                ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.hash.as_str()))?;
+        insert_statement.bind((2, self.url.as_str()))?;
+        insert_statement.bind((3, self.length as i64))?;
+        insert_statement.bind((4, self.mime_type.as_str()))?;
+        insert_statement.next()?;
+        Ok(SaveOutcome::Inserted)
+    }
+
+    /// The function `get_many` returns the stored enclosures whose URL matches `options.query`.
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<EntryEnclosure<'c>> {
+        let connection = connection.get_ref();
+        let mut query = SqlQuery::new("enclosures");
+        query
+            .filter(
+                "url LIKE ?",
+                [Value::String(format!("%{}%", options.query))],
+            )
+            .order_by("id DESC", []);
+        let (query, values) = query.select("*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_enclosures(&mut statement)
+    }
+}
+
+impl<'c> EntryEnclosure<'c> {
+    /// The function `from_enclosure` wraps a parsed `Enclosure` for the entry with the given hash.
+    pub fn from_enclosure(hash: &str, enclosure: &Enclosure) -> EntryEnclosure<'c> {
+        EntryEnclosure {
+            id: 0,
+            hash: hash.to_string(),
+            url: enclosure.url.clone(),
+            length: enclosure.length,
+            mime_type: enclosure.mime_type.clone(),
+            file_path: None,
+            sha256: None,
+            downloaded_at: None,
+            download_error: None,
+            connection: None,
+        }
+    }
+
+    /// The function `for_entry` returns the enclosures of the entry with the given hash, in feed
+    /// order.
+    pub fn for_entry(connection: &ThreadSafe<&Connection>, hash: &str) -> Vec<EntryEnclosure<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM enclosures WHERE hash = ? ORDER BY id")
+            .expect("Statement is not built");
+        statement.bind((1, hash)).expect("Binding failed");
+        read_enclosures(&mut statement)
+    }
+
+    /// The function `replace_for_entry` replaces the stored enclosures of the entry with the given
+    /// hash by the ones of its latest version in the feed. Enclosures whose URL did not change are
+    /// kept with their download state.
+    pub fn replace_for_entry(
+        connection: &ThreadSafe<&Connection>,
+        hash: &str,
+        enclosures: &[Enclosure],
+    ) -> Result<(), Error> {
+        let stored = EntryEnclosure::for_entry(connection, hash);
+        for entry_enclosure in stored.iter() {
+            if !enclosures
+                .iter()
+                .any(|enclosure| enclosure.url == entry_enclosure.url)
+            {
+                let mut statement = connection
+                    .get_ref()
+                    .prepare("DELETE FROM enclosures WHERE id = ?")?;
+                statement.bind((1, entry_enclosure.id))?;
+                statement.next()?;
+            }
+        }
+        for enclosure in enclosures {
+            if stored
+                .iter()
+                .any(|entry_enclosure| entry_enclosure.url == enclosure.url)
+            {
+                continue;
+            }
+            let mut entry_enclosure = EntryEnclosure::from_enclosure(hash, enclosure);
+            entry_enclosure.connection = Some(connection.clone());
+            entry_enclosure.save()?;
+        }
+        Ok(())
+    }
+
+    /// The function `pending` returns the enclosures never downloaded, of the newest entries first.
+    /// `options.query` matches the entry title and `options.rss_url` limits them to one feed.
+    pub fn pending(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<EntryEnclosure<'c>> {
+        let connection = connection.get_ref();
+        let mut query =
+            SqlQuery::new("enclosures JOIN rss_entries ON rss_entries.hash = enclosures.hash");
+        query.filter("enclosures.downloaded_at IS NULL", []).filter(
+            "rss_entries.title LIKE ?",
+            [Value::String(format!("%{}%", options.query))],
+        );
+        if !options.rss_url.is_empty() {
+            query.filter(
+                "rss_entries.rss_url = ?",
+                [Value::String(options.rss_url.clone())],
+            );
+        }
+        query
+            .order_by("rss_entries.publish_date DESC", [])
+            .order_by("enclosures.id", []);
+        let (query, values) = query.select("enclosures.*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_enclosures(&mut statement)
+    }
+
+    /// The function `downloaded` returns the enclosures that have a local copy, oldest download
+    /// first, with whether their entry is starred.
+    pub fn downloaded(connection: &ThreadSafe<&Connection>) -> Vec<(EntryEnclosure<'c>, bool)> {
+        let connection = connection.get_ref();
+        let query = "
+            SELECT enclosures.*, rss_entries.is_starred AS entry_starred FROM enclosures
+            LEFT JOIN rss_entries ON rss_entries.hash = enclosures.hash
+            WHERE enclosures.file_path IS NOT NULL
+            ORDER BY enclosures.downloaded_at, enclosures.id
+        ";
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        let mut downloaded = vec![];
+        while let Ok(State::Row) = statement.next() {
+            let starred = statement
+                .read::<Option<i64>, _>("entry_starred")
+                .unwrap_or_default()
+                == Some(1);
+            downloaded.push((read_enclosure(&statement), starred));
+        }
+        downloaded
+    }
+
+    /// The function `set_downloaded` records the local copy of the enclosure with the given id.
+    pub fn set_downloaded(
+        connection: &ThreadSafe<&Connection>,
+        id: i64,
+        file_path: &str,
+        sha256: &str,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare(
+            "UPDATE enclosures SET file_path = ?, sha256 = ?, downloaded_at = CURRENT_TIMESTAMP,
+            download_error = NULL WHERE id = ?",
+        )?;
+        statement.bind((1, file_path))?;
+        statement.bind((2, sha256))?;
+        statement.bind((3, id))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `set_download_error` records why downloading the enclosure with the given id
+    /// failed, it stays pending.
+    pub fn set_download_error(
+        connection: &ThreadSafe<&Connection>,
+        id: i64,
+        error: &str,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE enclosures SET download_error = ? WHERE id = ?")?;
+        statement.bind((1, error))?;
+        statement.bind((2, id))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `reset_download` forgets the local copy of the enclosure with the given id so it
+    /// is downloaded again, like after it failed verification.
+    pub fn reset_download(connection: &ThreadSafe<&Connection>, id: i64) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare(
+            "UPDATE enclosures SET file_path = NULL, sha256 = NULL, downloaded_at = NULL
+            WHERE id = ?",
+        )?;
+        statement.bind((1, id))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `set_pruned` forgets the local copy of the enclosure with the given id, which
+    /// is not downloaded again.
+    pub fn set_pruned(connection: &ThreadSafe<&Connection>, id: i64) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE enclosures SET file_path = NULL WHERE id = ?")?;
+        statement.bind((1, id))?;
+        statement.next()?;
+        Ok(())
+    }
+}
+
+fn read_enclosures<'c>(statement: &mut sqlite::Statement) -> Vec<EntryEnclosure<'c>> {
+    let mut enclosures = vec![];
+    while let Ok(State::Row) = statement.next() {
+        enclosures.push(read_enclosure(statement))
+    }
+    enclosures
+}
+
+/// The function `read_enclosure` builds an `EntryEnclosure` from the current row of a statement
+/// selecting `enclosures` columns.
+fn read_enclosure<'c>(statement: &sqlite::Statement) -> EntryEnclosure<'c> {
+    let text = |column: &str| {
+        statement
+            .read::<Option<String>, _>(column)
+            .unwrap_or_default()
+    };
+    EntryEnclosure {
+        id: statement.read::<i64, _>("id").unwrap(),
+        hash: statement.read::<String, _>("hash").unwrap(),
+        url: statement.read::<String, _>("url").unwrap(),
+        length: statement.read::<i64, _>("length").unwrap_or(0) as u64,
+        mime_type: text("mime_type").unwrap_or_default(),
+        file_path: text("file_path"),
+        sha256: text("sha256"),
+        downloaded_at: text("downloaded_at"),
+        download_error: text("download_error"),
+        connection: None,
+    }
+}
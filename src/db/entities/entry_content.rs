@@ -0,0 +1,179 @@
+use sqlite::{Connection, Error, State, Value};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    article::Article,
+    core::{
+        query::SqlQuery,
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+};
+
+/// The `EntryContent` struct holds the full article text fetched from the link of an `RssEntry`,
+/// stored in the `entry_contents` table under the same hash.
+///
+/// Properties:
+///
+/// * `hash`: The hash of the `RssEntry` the content belongs to.
+/// * `html`: The cleaned HTML of the article.
+/// * `text`: The plain text of the article.
+/// * `word_count`: The number of words in `text`.
+/// * `reading_minutes`: The estimated reading time in minutes.
+/// * `connection`: The optional database connection used by `save`.
+pub struct EntryContent<'c> {
+    pub hash: String,
+    pub html: String,
+    pub text: String,
+    pub word_count: u64,
+    pub reading_minutes: u64,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for EntryContent<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Words: {}\nReading time: {} min\n\n{}\n",
+            self.word_count, self.reading_minutes, self.text
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for EntryContent<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, EntryContent<'c>> for EntryContent<'c> {
+    /// The function stores the content, replacing the content previously fetched for the entry.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if the entry already had content, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = EntryContent::get(&connection, &self.hash).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO entry_contents (
+                hash, html, text, word_count,
+                reading_minutes, fetched_at
+            )VALUES (
+                ?, ?, ?, ?, ?, CURRENT_TIMESTAMP
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.hash.as_str()))?;
+        insert_statement.bind((2, self.html.as_str()))?;
+        insert_statement.bind((3, self.text.as_str()))?;
+        insert_statement.bind((4, self.word_count as i64))?;
+        insert_statement.bind((5, self.reading_minutes as i64))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the stored contents whose text matches `options.query`.
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<EntryContent<'c>> {
+        let connection = connection.get_ref();
+        let mut query = SqlQuery::new("entry_contents");
+        query
+            .filter(
+                "text LIKE ?",
+                [Value::String(format!("%{}%", options.query))],
+            )
+            .order_by("fetched_at DESC", []);
+        let (query, values) = query.select("*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_contents(&mut statement)
+    }
+}
+
+impl<'c> EntryContent<'c> {
+    /// The function `from_article` wraps an extracted `Article` for the entry with the given hash.
+    pub fn from_article(hash: &str, article: Article) -> EntryContent<'c> {
+        EntryContent {
+            hash: hash.to_string(),
+            html: article.html,
+            text: article.text,
+            word_count: article.word_count as u64,
+            reading_minutes: article.reading_minutes as u64,
+            connection: None,
+        }
+    }
+
+    /// The function `get` returns the content stored for the entry with the given hash.
+    pub fn get(connection: &ThreadSafe<&Connection>, hash: &str) -> Option<EntryContent<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM entry_contents WHERE hash = ?")
+            .expect("Statement is not built");
+        statement.bind((1, hash)).expect("Binding failed");
+        read_contents(&mut statement).pop()
+    }
+
+    /// The function `missing` returns `(hash, link)` of the newest entries that have no stored
+    /// content yet, or of every entry when `refetch` is set.
+    pub fn missing(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+        refetch: bool,
+    ) -> Vec<(String, String)> {
+        let connection = connection.get_ref();
+        let mut query = SqlQuery::new(
+            "rss_entries LEFT JOIN entry_contents ON entry_contents.hash = rss_entries.hash",
+        );
+        query.filter(
+            "rss_entries.title LIKE ?",
+            [Value::String(format!("%{}%", options.query))],
+        );
+        if !refetch {
+            query.filter("entry_contents.hash IS NULL", []);
+        }
+        query.order_by("rss_entries.publish_date DESC", []);
+        let (query, values) = query.select(
+            "rss_entries.hash, rss_entries.link",
+            options.limit,
+            options.skip,
+        );
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+
+        let mut missing = vec![];
+        while let Ok(State::Row) = statement.next() {
+            missing.push((
+                statement.read::<String, _>("hash").unwrap(),
+                statement.read::<String, _>("link").unwrap(),
+            ));
+        }
+        missing
+    }
+}
+
+fn read_contents<'c>(statement: &mut sqlite::Statement) -> Vec<EntryContent<'c>> {
+    let mut contents = vec![];
+    while let Ok(State::Row) = statement.next() {
+        contents.push(EntryContent {
+            hash: statement.read::<String, _>("hash").unwrap(),
+            html: statement.read::<String, _>("html").unwrap(),
+            text: statement.read::<String, _>("text").unwrap(),
+            word_count: statement.read::<i64, _>("word_count").unwrap() as u64,
+            reading_minutes: statement.read::<i64, _>("reading_minutes").unwrap() as u64,
+            connection: None,
+        })
+    }
+    contents
+}
@@ -0,0 +1,145 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        query::SqlQuery,
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    rss::dto::Episode,
+};
+
+/// The `EntryEpisode` struct holds the podcast metadata of an `RssEntry`, stored in the `episodes`
+/// table under the same hash.
+///
+/// Properties:
+///
+/// * `hash`: The hash of the `RssEntry` the metadata belongs to.
+/// * `episode`: The parsed iTunes metadata.
+/// * `connection`: The optional database connection used by `save`.
+pub struct EntryEpisode<'c> {
+    pub hash: String,
+    pub episode: Episode,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for EntryEpisode<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut details = vec![];
+        match (self.episode.season, self.episode.episode) {
+            (Some(season), Some(episode)) => details.push(format!("S{}E{}", season, episode)),
+            (Some(season), None) => details.push(format!("Season {}", season)),
+            (None, Some(episode)) => details.push(format!("Episode {}", episode)),
+            (None, None) => {}
+        }
+        if let Some(duration) = self.episode.duration() {
+            details.push(duration);
+        }
+        if self.episode.explicit == Some(true) {
+            details.push(String::from("explicit"));
+        }
+        write!(f, "Episode: {}", details.join(", "))?;
+        if let Some(image) = &self.episode.image {
+            write!(f, "\nArtwork: {}", image)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'c> Connectable<'c> for EntryEpisode<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, EntryEpisode<'c>> for EntryEpisode<'c> {
+    /// The function stores the metadata, replacing the metadata previously stored for the entry.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if the entry already had metadata, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = EntryEpisode::get(&connection, &self.hash).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO episodes (
+                hash, duration_secs, episode, season, image, explicit
+            )VALUES (
+                ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.hash.as_str()))?;
+        insert_statement.bind((2, self.episode.duration_secs.map(|secs| secs as i64)))?;
+        insert_statement.bind((3, self.episode.episode.map(|episode| episode as i64)))?;
+        insert_statement.bind((4, self.episode.season.map(|season| season as i64)))?;
+        insert_statement.bind((5, self.episode.image.as_deref()))?;
+        insert_statement.bind((6, self.episode.explicit.map(|explicit| explicit as i64)))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the metadata of the stored episodes.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<EntryEpisode<'c>> {
+        let connection = connection.get_ref();
+        let (query, values) = SqlQuery::new("episodes").select("*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_episodes(&mut statement)
+    }
+}
+
+impl<'c> EntryEpisode<'c> {
+    /// The function `get` returns the metadata stored for the entry with the given hash.
+    pub fn get(connection: &ThreadSafe<&Connection>, hash: &str) -> Option<EntryEpisode<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM episodes WHERE hash = ?")
+            .expect("Statement is not built");
+        statement.bind((1, hash)).expect("Binding failed");
+        read_episodes(&mut statement).pop()
+    }
+}
+
+fn read_episodes<'c>(statement: &mut sqlite::Statement) -> Vec<EntryEpisode<'c>> {
+    let number = |statement: &sqlite::Statement, column: &str| {
+        statement
+            .read::<Option<i64>, _>(column)
+            .unwrap_or_default()
+            .map(|number| number as u64)
+    };
+    let mut episodes = vec![];
+    while let Ok(State::Row) = statement.next() {
+        episodes.push(EntryEpisode {
+            hash: statement.read::<String, _>("hash").unwrap(),
+            episode: Episode {
+                duration_secs: number(statement, "duration_secs"),
+                episode: number(statement, "episode"),
+                season: number(statement, "season"),
+                image: statement
+                    .read::<Option<String>, _>("image")
+                    .unwrap_or_default(),
+                explicit: statement
+                    .read::<Option<i64>, _>("explicit")
+                    .unwrap_or_default()
+                    .map(|explicit| explicit == 1),
+            },
+            connection: None,
+        })
+    }
+    episodes
+}
@@ -0,0 +1,230 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    http::Credentials,
+    secrets::{errors::SecretError, redact_url, Keyring, Secret},
+};
+
+/// The `FeedAuth` struct holds the credentials sent when fetching one feed, stored in the
+/// `feed_auth` table. Every credential is a `Secret`, so it is either encrypted or read from the
+/// environment, and it is never printed.
+///
+/// Properties:
+///
+/// * `rss_url`: The feed the credentials are sent to.
+/// * `username`: The user name for basic authentication.
+/// * `password`: The password for basic authentication.
+/// * `token`: The bearer token sent in the `Authorization` header.
+/// * `headers`: Extra headers, the name in clear and the value as a secret.
+/// * `cookie_header`: The `Cookie` header sent as is with every request, like
+///   `session=abc; theme=dark`. There is no cookie jar: cookies set by the server are ignored.
+/// * `connection`: The optional database connection used by `save`.
+#[derive(Clone)]
+pub struct FeedAuth<'c> {
+    pub rss_url: String,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub token: Option<Secret>,
+    pub headers: Vec<(String, Secret)>,
+    pub cookie_header: Option<Secret>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for FeedAuth<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn show(secret: &Option<Secret>) -> String {
+            secret
+                .as_ref()
+                .map(|secret| secret.to_string())
+                .unwrap_or_else(|| String::from("-"))
+        }
+        let headers: Vec<String> = self
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        write!(
+            f,
+            "(  feed = {}\tusername = {}\tpassword = {}\ttoken = {}\theaders = [{}]\tcookie_header = {}  )",
+            redact_url(&self.rss_url),
+            self.username.as_deref().unwrap_or("-"),
+            show(&self.password),
+            show(&self.token),
+            headers.join(", "),
+            show(&self.cookie_header)
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for FeedAuth<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, FeedAuth<'c>> for FeedAuth<'c> {
+    /// The function stores the credentials, replacing the credentials previously stored for the
+    /// feed.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if the feed already had credentials, `SaveOutcome::Inserted`
+    /// otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = !matches!(FeedAuth::get(&connection, &self.rss_url), Ok(None));
+        let connection = connection.get_ref();
+        let headers: Vec<(String, String)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.stored()))
+            .collect();
+        let insert_query = "
+            INSERT OR REPLACE INTO feed_auth (
+                rss_url, username, password, token, headers, cookie_header
+            )VALUES (
+                ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.rss_url.as_str()))?;
+        insert_statement.bind((2, self.username.as_deref()))?;
+        insert_statement.bind((3, self.password.as_ref().map(Secret::stored).as_deref()))?;
+        insert_statement.bind((4, self.token.as_ref().map(Secret::stored).as_deref()))?;
+        insert_statement.bind((
+            5,
+            serde_json::to_string(&headers).unwrap_or_default().as_str(),
+        ))?;
+        insert_statement.bind((
+            6,
+            self.cookie_header.as_ref().map(Secret::stored).as_deref(),
+        ))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the credentials of every feed.
+    ///
+    /// It panics if a stored credential is corrupt.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<FeedAuth<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM feed_auth ORDER BY rss_url")
+            .expect("Statement is not built");
+        read_auths(&mut statement).expect("A stored credential is corrupt")
+    }
+}
+
+impl<'c> FeedAuth<'c> {
+    /// The function `new` returns empty credentials for the feed with the given URL.
+    pub fn new(rss_url: &str) -> FeedAuth<'c> {
+        FeedAuth {
+            rss_url: rss_url.to_string(),
+            username: None,
+            password: None,
+            token: None,
+            headers: vec![],
+            cookie_header: None,
+            connection: None,
+        }
+    }
+
+    /// The function `get` returns the credentials stored for the feed with the given URL.
+    ///
+    /// Returns:
+    ///
+    /// `None` if the feed has no credentials, or `SecretError::Corrupt` if one of them can not be
+    /// read, so the feed is never fetched without the credentials it was given.
+    pub fn get(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+    ) -> Result<Option<FeedAuth<'c>>, SecretError> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM feed_auth WHERE rss_url = ?")
+            .expect("Statement is not built");
+        statement.bind((1, rss_url)).expect("Binding failed");
+        Ok(read_auths(&mut statement)?.pop())
+    }
+
+    /// The function `delete` removes the credentials of the feed with the given URL.
+    pub fn delete(connection: &ThreadSafe<&Connection>, rss_url: &str) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare("DELETE FROM feed_auth WHERE rss_url = ?")?;
+        statement.bind((1, rss_url))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `credentials` reveals the secrets to build the `Credentials` sent with the
+    /// request.
+    pub fn credentials(&self, keyring: &mut Keyring) -> Result<Credentials, SecretError> {
+        let reveal = |secret: &Option<Secret>, keyring: &mut Keyring| match secret {
+            Some(secret) => secret.reveal(keyring).map(Some),
+            None => Ok(None),
+        };
+        let mut headers = vec![];
+        for (name, value) in self.headers.iter() {
+            headers.push((name.clone(), value.reveal(keyring)?));
+        }
+        Ok(Credentials {
+            basic: match &self.username {
+                Some(username) => Some((username.clone(), reveal(&self.password, keyring)?)),
+                None => None,
+            },
+            bearer: reveal(&self.token, keyring)?,
+            headers,
+            cookie_header: reveal(&self.cookie_header, keyring)?,
+        })
+    }
+}
+
+fn read_auths<'c>(statement: &mut sqlite::Statement) -> Result<Vec<FeedAuth<'c>>, SecretError> {
+    let secret = |statement: &sqlite::Statement, column: &str| {
+        statement
+            .read::<Option<String>, _>(column)
+            .unwrap()
+            .map(|stored| Secret::from_stored(&stored))
+            .transpose()
+    };
+    let mut auths = vec![];
+    while let Ok(State::Row) = statement.next() {
+        let headers: Vec<(String, String)> = match statement
+            .read::<Option<String>, _>("headers")
+            .unwrap()
+        {
+            Some(headers) => serde_json::from_str(&headers).map_err(|_| SecretError::Corrupt)?,
+            None => vec![],
+        };
+        auths.push(FeedAuth {
+            rss_url: statement.read::<String, _>("rss_url").unwrap(),
+            username: statement.read::<Option<String>, _>("username").unwrap(),
+            password: secret(statement, "password")?,
+            token: secret(statement, "token")?,
+            headers: headers
+                .into_iter()
+                .map(|(name, value)| Ok((name, Secret::from_stored(&value)?)))
+                .collect::<Result<_, SecretError>>()?,
+            cookie_header: secret(statement, "cookie_header")?,
+            connection: None,
+        })
+    }
+    Ok(auths)
+}
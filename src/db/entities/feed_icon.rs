@@ -0,0 +1,194 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sqlite::{Connection, Error, State, Value};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        query::SqlQuery,
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    secrets::redact_url,
+};
+
+/// The `FeedIcon` struct is the cached image of a subscribed feed, its channel `<image>` or the
+/// favicon of its site, stored in the `feed_icons` table. A failed refresh is stored too, with an
+/// empty `data` or the image of the previous refresh, so it is only retried once the icon is
+/// stale.
+///
+/// Properties:
+///
+/// * `rss_url`: The URL of the feed the icon belongs to.
+/// * `source_url`: The URL the icon was downloaded from, empty if none could be.
+/// * `mime_type`: The MIME type detected from the content of the icon.
+/// * `data`: The bytes of the icon.
+/// * `fetched_at`: When the icon was last refreshed, set by `save`.
+/// * `error`: Why the last refresh failed.
+/// * `connection`: The optional database connection used by `save`.
+pub struct FeedIcon<'c> {
+    pub rss_url: String,
+    pub source_url: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+    pub fetched_at: Option<String>,
+    pub error: Option<String>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for FeedIcon<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(  rss_url = {}\tsource = {}\ttype = {}\tsize = {}\tfetched_at = {}\terror = {}  )",
+            redact_url(&self.rss_url),
+            if self.source_url.is_empty() {
+                "-"
+            } else {
+                &self.source_url
+            },
+            if self.mime_type.is_empty() {
+                "-"
+            } else {
+                &self.mime_type
+            },
+            self.data.len(),
+            self.fetched_at.as_deref().unwrap_or("-"),
+            self.error.as_deref().unwrap_or("-")
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for FeedIcon<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, FeedIcon<'c>> for FeedIcon<'c> {
+    /// The function stores the icon, replacing the one previously cached for the feed, and records
+    /// the time of the refresh.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if the feed already had an icon, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = FeedIcon::get(&connection, &self.rss_url).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO feed_icons (
+                rss_url, source_url, mime_type, data, fetched_at, error
+            )VALUES (
+                ?, ?, ?, ?, CURRENT_TIMESTAMP, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.rss_url.as_str()))?;
+        insert_statement.bind((2, self.source_url.as_str()))?;
+        insert_statement.bind((3, self.mime_type.as_str()))?;
+        insert_statement.bind((4, self.data.as_slice()))?;
+        insert_statement.bind((5, self.error.as_deref()))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the cached icons of the feeds whose URL matches
+    /// `options.query`.
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<FeedIcon<'c>> {
+        let connection = connection.get_ref();
+        let mut query = SqlQuery::new("feed_icons");
+        query
+            .filter(
+                "rss_url LIKE ?",
+                [Value::String(format!("%{}%", options.query))],
+            )
+            .order_by("rss_url", []);
+        let (query, values) = query.select("*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_icons(&mut statement)
+    }
+}
+
+impl<'c> FeedIcon<'c> {
+    /// The function `get` returns the icon cached for the feed with the given URL, whether its last
+    /// refresh succeeded or not.
+    pub fn get(connection: &ThreadSafe<&Connection>, rss_url: &str) -> Option<FeedIcon<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM feed_icons WHERE rss_url = ?")
+            .expect("Statement is not built");
+        statement.bind((1, rss_url)).expect("Binding failed");
+        read_icons(&mut statement).pop()
+    }
+
+    /// The function `stale` returns the URLs of the subscribed feeds without a cached icon or whose
+    /// icon was refreshed more than `max_age_days` days ago.
+    pub fn stale(connection: &ThreadSafe<&Connection>, max_age_days: u64) -> Vec<String> {
+        let connection = connection.get_ref();
+        let query = "
+            SELECT DISTINCT rss_table.rss_url FROM rss_table
+            LEFT JOIN feed_icons ON feed_icons.rss_url = rss_table.rss_url
+            WHERE feed_icons.fetched_at IS NULL OR feed_icons.fetched_at <= datetime('now', ?)
+        ";
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement
+            .bind((1, format!("-{} days", max_age_days).as_str()))
+            .expect("Binding failed");
+        let mut rss_urls = vec![];
+        while let Ok(State::Row) = statement.next() {
+            rss_urls.push(statement.read::<String, _>("rss_url").unwrap());
+        }
+        rss_urls
+    }
+
+    /// The function `is_available` tells whether the last refresh stored an image.
+    pub fn is_available(&self) -> bool {
+        !self.data.is_empty()
+    }
+
+    /// The function `data_uri` returns the icon as a `data:` URI, to embed it in HTML pages.
+    pub fn data_uri(&self) -> Option<String> {
+        if !self.is_available() {
+            return None;
+        }
+        Some(format!(
+            "data:{};base64,{}",
+            self.mime_type,
+            STANDARD.encode(&self.data)
+        ))
+    }
+}
+
+fn read_icons<'c>(statement: &mut sqlite::Statement) -> Vec<FeedIcon<'c>> {
+    let mut icons = vec![];
+    while let Ok(State::Row) = statement.next() {
+        icons.push(FeedIcon {
+            rss_url: statement.read::<String, _>("rss_url").unwrap(),
+            source_url: statement
+                .read::<String, _>("source_url")
+                .unwrap_or_default(),
+            mime_type: statement.read::<String, _>("mime_type").unwrap_or_default(),
+            data: statement.read::<Vec<u8>, _>("data").unwrap_or_default(),
+            fetched_at: statement
+                .read::<Option<String>, _>("fetched_at")
+                .unwrap_or_default(),
+            error: statement
+                .read::<Option<String>, _>("error")
+                .unwrap_or_default(),
+            connection: None,
+        })
+    }
+    icons
+}
@@ -0,0 +1,161 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::core::{
+    structs::{GetManyOptions, SaveOutcome},
+    traits::{Connectable, CrudAble},
+};
+
+/// The `Hook` struct represents a user command that is run for every entry newly inserted by `sync`.
+///
+/// Properties:
+///
+/// * `id`: The id of the hook in the `hooks` table.
+/// * `profile`: The profile the hook is limited to, `None` runs the hook for every profile.
+/// * `command`: The shell command to run, it is executed through `sh -c`.
+/// * `timeout_secs`: The number of seconds the command may run before it is killed.
+/// * `connection`: The optional database connection used by `save`.
+pub struct Hook<'c> {
+    pub id: i64,
+    pub profile: Option<String>,
+    pub command: String,
+    pub timeout_secs: u64,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for Hook<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(  id = {}\tprofile = {}\ttimeout = {}s\tcommand = {}  )",
+            self.id,
+            self.profile.as_deref().unwrap_or("*"),
+            self.timeout_secs,
+            self.command
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for Hook<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, Hook<'c>> for Hook<'c> {
+    /// The function inserts the hook into the `hooks` table.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Inserted` once the row is written.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT INTO hooks (
+                profile, command, timeout_secs
+            )VALUES (
+                ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.profile.as_deref()))?;
+        insert_statement.bind((2, self.command.as_str()))?;
+        insert_statement.bind((3, self.timeout_secs as i64))?;
+        insert_statement.next()?;
+        Ok(SaveOutcome::Inserted)
+    }
+
+    /// The function `get_many` returns every configured hook.
+    #[allow(unused)]
+    fn get_many(connection: &ThreadSafe<&Connection>, options: GetManyOptions) -> Vec<Hook<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM hooks ORDER BY id")
+            .expect("Statement is not built");
+        read_hooks(&mut statement)
+    }
+}
+
+impl<'c> Hook<'c> {
+    /// The function `for_profile` returns the hooks that apply to feeds of the given profile, which
+    /// are the hooks of that profile plus the global hooks.
+    pub fn for_profile(connection: &ThreadSafe<&Connection>, profile: &str) -> Vec<Hook<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM hooks WHERE profile IS NULL OR profile = ? ORDER BY id")
+            .expect("Statement is not built");
+        statement.bind((1, profile)).expect("Binding failed");
+        read_hooks(&mut statement)
+    }
+
+    /// The function `delete` removes the hook with the given id.
+    pub fn delete(connection: &ThreadSafe<&Connection>, id: i64) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare("DELETE FROM hooks WHERE id = ?")?;
+        statement.bind((1, id))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `log_failure` records a failed hook run into the `hook_failures` table, so that
+    /// a broken hook never aborts the sync.
+    pub fn log_failure(&self, entry_hash: &str, error: &str) -> Result<(), Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("INSERT INTO hook_failures (hook_id, entry_hash, error) VALUES (?, ?, ?)")?;
+        statement.bind((1, self.id))?;
+        statement.bind((2, entry_hash))?;
+        statement.bind((3, error))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `get_failures` returns the latest hook failures as
+    /// `(failed_at, hook_id, entry_hash, error)` tuples.
+    pub fn get_failures(
+        connection: &ThreadSafe<&Connection>,
+        limit: u64,
+    ) -> Vec<(String, i64, String, String)> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM hook_failures ORDER BY id DESC LIMIT ?")
+            .expect("Statement is not built");
+        statement.bind((1, limit as i64)).expect("Binding failed");
+
+        let mut failures = vec![];
+        while let Ok(State::Row) = statement.next() {
+            failures.push((
+                statement.read::<String, _>("failed_at").unwrap_or_default(),
+                statement.read::<i64, _>("hook_id").unwrap_or_default(),
+                statement
+                    .read::<String, _>("entry_hash")
+                    .unwrap_or_default(),
+                statement.read::<String, _>("error").unwrap_or_default(),
+            ));
+        }
+        failures
+    }
+}
+
+fn read_hooks<'c>(statement: &mut sqlite::Statement) -> Vec<Hook<'c>> {
+    let mut hooks = vec![];
+    while let Ok(State::Row) = statement.next() {
+        hooks.push(Hook {
+            id: statement.read::<i64, _>("id").unwrap(),
+            profile: statement.read::<Option<String>, _>("profile").unwrap(),
+            command: statement.read::<String, _>("command").unwrap(),
+            timeout_secs: statement.read::<i64, _>("timeout_secs").unwrap() as u64,
+            connection: None,
+        })
+    }
+    hooks
+}
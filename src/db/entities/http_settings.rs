@@ -0,0 +1,225 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::core::{
+    structs::{GetManyOptions, SaveOutcome},
+    traits::{Connectable, CrudAble},
+};
+
+/// The `HttpSettings` struct holds the HTTP client options stored in the `http_settings` table,
+/// either globally or for one feed. Every option is optional, an unset option falls back to the
+/// global settings and then to the defaults of `crate::http`.
+///
+/// Properties:
+///
+/// * `rss_url`: The feed the settings apply to, `None` for the global settings.
+/// * `connect_timeout_secs`: The number of seconds to wait for a connection.
+/// * `read_timeout_secs`: The number of seconds to wait for the response and for every chunk of
+///   its body.
+/// * `user_agent`: The `User-Agent` header sent with every request.
+/// * `proxy`: The URL of an HTTP, HTTPS or SOCKS5 proxy.
+/// * `ca_certs`: Paths of extra PEM encoded CA certificates to trust, added to the global ones.
+/// * `compression`: Whether gzip, brotli and deflate responses are requested and decoded.
+/// * `max_body_bytes`: The size after which a response body is rejected.
+/// * `connection`: The optional database connection used by `save`.
+#[derive(Default, Clone)]
+pub struct HttpSettings<'c> {
+    pub rss_url: Option<String>,
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+    pub ca_certs: Vec<String>,
+    pub compression: Option<bool>,
+    pub max_body_bytes: Option<u64>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for HttpSettings<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn show<T: ToString>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| String::from("-"))
+        }
+        write!(
+            f,
+            "(  feed = {}\tconnect_timeout = {}\tread_timeout = {}\tuser_agent = {}\tproxy = {}\tca_certs = [{}]\tcompression = {}\tmax_body_bytes = {}  )",
+            self.rss_url.as_deref().unwrap_or("*"),
+            show(&self.connect_timeout_secs),
+            show(&self.read_timeout_secs),
+            show(&self.user_agent),
+            show(&self.proxy),
+            self.ca_certs.join(", "),
+            show(&self.compression),
+            show(&self.max_body_bytes)
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for HttpSettings<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, HttpSettings<'c>> for HttpSettings<'c> {
+    /// The function stores the settings, replacing the settings previously stored for the same
+    /// feed.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if settings were already stored, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = HttpSettings::get(&connection, self.rss_url.as_deref()).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO http_settings (
+                rss_url, connect_timeout_secs, read_timeout_secs, user_agent,
+                proxy, ca_certs, compression, max_body_bytes
+            )VALUES (
+                ?, ?, ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.rss_url.as_deref().unwrap_or("")))?;
+        insert_statement.bind((2, self.connect_timeout_secs.map(|secs| secs as i64)))?;
+        insert_statement.bind((3, self.read_timeout_secs.map(|secs| secs as i64)))?;
+        insert_statement.bind((4, self.user_agent.as_deref()))?;
+        insert_statement.bind((5, self.proxy.as_deref()))?;
+        insert_statement.bind((6, self.ca_certs.join("\n").as_str()))?;
+        insert_statement.bind((7, self.compression.map(|compression| compression as i64)))?;
+        insert_statement.bind((8, self.max_body_bytes.map(|bytes| bytes as i64)))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the global settings and the settings of every feed.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<HttpSettings<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM http_settings ORDER BY rss_url")
+            .expect("Statement is not built");
+        read_settings(&mut statement)
+    }
+}
+
+impl<'c> HttpSettings<'c> {
+    /// The function `get` returns the settings stored for the feed with the given URL, or the
+    /// global settings when `rss_url` is `None`.
+    pub fn get(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: Option<&str>,
+    ) -> Option<HttpSettings<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM http_settings WHERE rss_url = ?")
+            .expect("Statement is not built");
+        statement
+            .bind((1, rss_url.unwrap_or("")))
+            .expect("Binding failed");
+        read_settings(&mut statement).pop()
+    }
+
+    /// The function `for_feed` returns the settings used to fetch the feed with the given URL: the
+    /// settings of the feed on top of the global settings.
+    pub fn for_feed(connection: &ThreadSafe<&Connection>, rss_url: &str) -> HttpSettings<'c> {
+        let global = HttpSettings::get(connection, None).unwrap_or_default();
+        match HttpSettings::get(connection, Some(rss_url)) {
+            Some(feed) => feed.overlay(global),
+            None => global,
+        }
+    }
+
+    /// The function `delete` removes the settings of the feed with the given URL, or the global
+    /// settings when `rss_url` is `None`.
+    pub fn delete(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: Option<&str>,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare("DELETE FROM http_settings WHERE rss_url = ?")?;
+        statement.bind((1, rss_url.unwrap_or("")))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `overlay` fills the options unset in `self` from `base`. CA certificates are
+    /// added up instead of replaced.
+    pub fn overlay(self, base: HttpSettings<'c>) -> HttpSettings<'c> {
+        let mut ca_certs = base.ca_certs;
+        for ca_cert in self.ca_certs {
+            if !ca_certs.contains(&ca_cert) {
+                ca_certs.push(ca_cert);
+            }
+        }
+        HttpSettings {
+            rss_url: self.rss_url,
+            connect_timeout_secs: self.connect_timeout_secs.or(base.connect_timeout_secs),
+            read_timeout_secs: self.read_timeout_secs.or(base.read_timeout_secs),
+            user_agent: self.user_agent.or(base.user_agent),
+            proxy: self.proxy.or(base.proxy),
+            ca_certs,
+            compression: self.compression.or(base.compression),
+            max_body_bytes: self.max_body_bytes.or(base.max_body_bytes),
+            connection: self.connection,
+        }
+    }
+}
+
+fn read_settings<'c>(statement: &mut sqlite::Statement) -> Vec<HttpSettings<'c>> {
+    let mut settings = vec![];
+    while let Ok(State::Row) = statement.next() {
+        let rss_url = statement.read::<String, _>("rss_url").unwrap();
+        let ca_certs = statement
+            .read::<Option<String>, _>("ca_certs")
+            .unwrap()
+            .unwrap_or_default();
+        settings.push(HttpSettings {
+            rss_url: if rss_url.is_empty() {
+                None
+            } else {
+                Some(rss_url)
+            },
+            connect_timeout_secs: statement
+                .read::<Option<i64>, _>("connect_timeout_secs")
+                .unwrap()
+                .map(|secs| secs as u64),
+            read_timeout_secs: statement
+                .read::<Option<i64>, _>("read_timeout_secs")
+                .unwrap()
+                .map(|secs| secs as u64),
+            user_agent: statement.read::<Option<String>, _>("user_agent").unwrap(),
+            proxy: statement.read::<Option<String>, _>("proxy").unwrap(),
+            ca_certs: ca_certs
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect(),
+            compression: statement
+                .read::<Option<i64>, _>("compression")
+                .unwrap()
+                .map(|compression| compression == 1),
+            max_body_bytes: statement
+                .read::<Option<i64>, _>("max_body_bytes")
+                .unwrap()
+                .map(|bytes| bytes as u64),
+            connection: None,
+        })
+    }
+    settings
+}
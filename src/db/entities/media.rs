@@ -0,0 +1,237 @@
+use sqlite::{Connection, Error, State, Value};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        query::SqlQuery,
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    rss::dto::{Media, MediaCommunity},
+};
+
+/// The `EntryMedia` struct holds the Media RSS data of an `RssEntry`, stored in the `entry_media`
+/// table under the same hash. The contents, thumbnails and credits are stored as JSON.
+///
+/// Properties:
+///
+/// * `hash`: The hash of the `RssEntry` the media belongs to.
+/// * `media`: The parsed Media RSS data.
+/// * `connection`: The optional database connection used by `save`.
+pub struct EntryMedia<'c> {
+    pub hash: String,
+    pub media: Media,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for EntryMedia<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines = vec![];
+        if let Some(title) = &self.media.title {
+            lines.push(format!("Media: {}", title));
+        }
+        for thumbnail in self.media.thumbnails.iter() {
+            match (thumbnail.width, thumbnail.height) {
+                (Some(width), Some(height)) => lines.push(format!(
+                    "Thumbnail: {} ({}x{})",
+                    thumbnail.url, width, height
+                )),
+                _ => lines.push(format!("Thumbnail: {}", thumbnail.url)),
+            }
+        }
+        for content in self.media.contents.iter() {
+            let details: Vec<String> = [
+                content.medium.clone(),
+                content.mime_type.clone(),
+                content
+                    .width
+                    .zip(content.height)
+                    .map(|(width, height)| format!("{}x{}", width, height)),
+                content
+                    .duration_secs
+                    .map(|secs| format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if details.is_empty() {
+                lines.push(format!("Media content: {}", content.url));
+            } else {
+                lines.push(format!(
+                    "Media content: {} ({})",
+                    content.url,
+                    details.join(", ")
+                ));
+            }
+        }
+        for credit in self.media.credits.iter() {
+            match &credit.role {
+                Some(role) => lines.push(format!("Credit: {} ({})", credit.name, role)),
+                None => lines.push(format!("Credit: {}", credit.name)),
+            }
+        }
+        if let Some(community) = &self.media.community {
+            let mut statistics = vec![];
+            if let Some(average) = community.star_average {
+                let mut rating = format!("rated {:.1}", average);
+                if let Some(max) = community.star_max {
+                    rating.push_str(&format!("/{}", max));
+                }
+                if let Some(count) = community.star_count {
+                    rating.push_str(&format!(" by {}", count));
+                }
+                statistics.push(rating);
+            }
+            if let Some(views) = community.views {
+                statistics.push(format!("{} views", views));
+            }
+            if let Some(favorites) = community.favorites {
+                statistics.push(format!("{} favorites", favorites));
+            }
+            if !statistics.is_empty() {
+                lines.push(format!("Community: {}", statistics.join(", ")));
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl<'c> Connectable<'c> for EntryMedia<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, EntryMedia<'c>> for EntryMedia<'c> {
+    /// The function stores the media, replacing the media previously stored for the entry.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if the entry already had media, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = EntryMedia::get(&connection, &self.hash).is_some();
+        let connection = connection.get_ref();
+        let community = self.media.community.clone().unwrap_or_default();
+        let insert_query = "
+            INSERT OR REPLACE INTO entry_media (
+                hash, title, description, contents, thumbnails,
+                star_average, star_count, star_max, views, favorites, credits
+            )VALUES (
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.hash.as_str()))?;
+        insert_statement.bind((2, self.media.title.as_deref()))?;
+        insert_statement.bind((3, self.media.description.as_deref()))?;
+        insert_statement.bind((
+            4,
+            serde_json::to_string(&self.media.contents)
+                .unwrap_or_default()
+                .as_str(),
+        ))?;
+        insert_statement.bind((
+            5,
+            serde_json::to_string(&self.media.thumbnails)
+                .unwrap_or_default()
+                .as_str(),
+        ))?;
+        insert_statement.bind((6, community.star_average))?;
+        insert_statement.bind((7, community.star_count.map(|count| count as i64)))?;
+        insert_statement.bind((8, community.star_max))?;
+        insert_statement.bind((9, community.views.map(|views| views as i64)))?;
+        insert_statement.bind((10, community.favorites.map(|favorites| favorites as i64)))?;
+        insert_statement.bind((
+            11,
+            serde_json::to_string(&self.media.credits)
+                .unwrap_or_default()
+                .as_str(),
+        ))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the stored media whose description matches `options.query`.
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<EntryMedia<'c>> {
+        let connection = connection.get_ref();
+        let mut query = SqlQuery::new("entry_media");
+        query.filter(
+            "IFNULL(description, '') LIKE ?",
+            [Value::String(format!("%{}%", options.query))],
+        );
+        let (query, values) = query.select("*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_media(&mut statement)
+    }
+}
+
+impl<'c> EntryMedia<'c> {
+    /// The function `get` returns the media stored for the entry with the given hash.
+    pub fn get(connection: &ThreadSafe<&Connection>, hash: &str) -> Option<EntryMedia<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM entry_media WHERE hash = ?")
+            .expect("Statement is not built");
+        statement.bind((1, hash)).expect("Binding failed");
+        read_media(&mut statement).pop()
+    }
+}
+
+fn read_media<'c>(statement: &mut sqlite::Statement) -> Vec<EntryMedia<'c>> {
+    let number = |statement: &sqlite::Statement, column: &str| {
+        statement
+            .read::<Option<i64>, _>(column)
+            .unwrap_or_default()
+            .map(|number| number as u64)
+    };
+    let decimal = |statement: &sqlite::Statement, column: &str| {
+        statement.read::<Option<f64>, _>(column).unwrap_or_default()
+    };
+    let json = |statement: &sqlite::Statement, column: &str| {
+        statement
+            .read::<Option<String>, _>(column)
+            .unwrap_or_default()
+            .unwrap_or_default()
+    };
+    let mut media = vec![];
+    while let Ok(State::Row) = statement.next() {
+        let community = MediaCommunity {
+            star_average: decimal(statement, "star_average"),
+            star_count: number(statement, "star_count"),
+            star_max: decimal(statement, "star_max"),
+            views: number(statement, "views"),
+            favorites: number(statement, "favorites"),
+        };
+        media.push(EntryMedia {
+            hash: statement.read::<String, _>("hash").unwrap(),
+            media: Media {
+                contents: serde_json::from_str(&json(statement, "contents")).unwrap_or_default(),
+                thumbnails: serde_json::from_str(&json(statement, "thumbnails"))
+                    .unwrap_or_default(),
+                title: statement.read::<Option<String>, _>("title").unwrap(),
+                description: statement.read::<Option<String>, _>("description").unwrap(),
+                community: if community == MediaCommunity::default() {
+                    None
+                } else {
+                    Some(community)
+                },
+                credits: serde_json::from_str(&json(statement, "credits")).unwrap_or_default(),
+            },
+            connection: None,
+        })
+    }
+    media
+}
@@ -1,5 +1,35 @@
 pub mod blog;
+pub mod download_settings;
+pub mod enclosure;
+pub mod entry_content;
+pub mod episode;
+pub mod feed_auth;
+pub mod feed_icon;
+pub mod hook;
+pub mod http_settings;
+pub mod media;
+pub mod notification_sink;
+pub mod retention_policy;
 pub mod rss_entity;
+pub mod rule;
+pub mod saved_search;
+pub mod smtp_settings;
+pub mod tag;
 
 pub use blog::RssEntry;
+pub use download_settings::DownloadSettings;
+pub use enclosure::EntryEnclosure;
+pub use entry_content::EntryContent;
+pub use episode::EntryEpisode;
+pub use feed_auth::FeedAuth;
+pub use feed_icon::FeedIcon;
+pub use hook::Hook;
+pub use http_settings::HttpSettings;
+pub use media::EntryMedia;
+pub use notification_sink::NotificationSink;
+pub use retention_policy::RetentionPolicy;
 pub use rss_entity::RssEntity;
+pub use rule::Rule;
+pub use saved_search::SavedSearch;
+pub use smtp_settings::SmtpSettings;
+pub use tag::TagCount;
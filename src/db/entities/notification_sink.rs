@@ -0,0 +1,173 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use std::str::FromStr;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        structs::{EntryPayload, GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    secrets::redact_path,
+};
+
+/// The `SinkKind` enum lists the payload shapes a `NotificationSink` can post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkKind {
+    Slack,
+    Discord,
+    Matrix,
+    Json,
+}
+
+impl SinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SinkKind::Slack => "slack",
+            SinkKind::Discord => "discord",
+            SinkKind::Matrix => "matrix",
+            SinkKind::Json => "json",
+        }
+    }
+}
+
+impl FromStr for SinkKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "slack" => Ok(SinkKind::Slack),
+            "discord" => Ok(SinkKind::Discord),
+            "matrix" => Ok(SinkKind::Matrix),
+            "json" => Ok(SinkKind::Json),
+            other => Err(format!("Unknown notification sink kind: {}", other)),
+        }
+    }
+}
+
+/// The `NotificationSink` struct represents a webhook that receives the entries inserted by `sync`.
+///
+/// Properties:
+///
+/// * `id`: The id of the sink in the `notification_sinks` table.
+/// * `kind`: The payload shape posted to the webhook.
+/// * `url`: The webhook URL.
+/// * `profile`: Only entries of feeds with this profile are sent, `None` matches every profile.
+/// * `rss_url`: Only entries of this feed are sent, `None` matches every feed.
+/// * `max_retries`: The number of times a failed post is retried.
+/// * `connection`: The optional database connection used by `save`.
+pub struct NotificationSink<'c> {
+    pub id: i64,
+    pub kind: SinkKind,
+    pub url: String,
+    pub profile: Option<String>,
+    pub rss_url: Option<String>,
+    pub max_retries: u32,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for NotificationSink<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(  id = {}\tkind = {}\tprofile = {}\tfeed = {}\tretries = {}\turl = {}  )",
+            self.id,
+            self.kind.as_str(),
+            self.profile.as_deref().unwrap_or("*"),
+            self.rss_url.as_deref().unwrap_or("*"),
+            self.max_retries,
+            redact_path(&self.url)
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for NotificationSink<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, NotificationSink<'c>> for NotificationSink<'c> {
+    /// The function inserts the sink into the `notification_sinks` table.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Inserted` once the row is written.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT INTO notification_sinks (
+                kind, url, profile, rss_url, max_retries
+            )VALUES (
+                ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.kind.as_str()))?;
+        insert_statement.bind((2, self.url.as_str()))?;
+        insert_statement.bind((3, self.profile.as_deref()))?;
+        insert_statement.bind((4, self.rss_url.as_deref()))?;
+        insert_statement.bind((5, self.max_retries as i64))?;
+        insert_statement.next()?;
+        Ok(SaveOutcome::Inserted)
+    }
+
+    /// The function `get_many` returns every configured notification sink.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<NotificationSink<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM notification_sinks ORDER BY id")
+            .expect("Statement is not built");
+
+        let mut sinks = vec![];
+        while let Ok(State::Row) = statement.next() {
+            sinks.push(NotificationSink {
+                id: statement.read::<i64, _>("id").unwrap(),
+                kind: statement
+                    .read::<String, _>("kind")
+                    .unwrap()
+                    .parse()
+                    .unwrap_or(SinkKind::Json),
+                url: statement.read::<String, _>("url").unwrap(),
+                profile: statement.read::<Option<String>, _>("profile").unwrap(),
+                rss_url: statement.read::<Option<String>, _>("rss_url").unwrap(),
+                max_retries: statement.read::<i64, _>("max_retries").unwrap() as u32,
+                connection: None,
+            })
+        }
+        sinks
+    }
+}
+
+impl<'c> NotificationSink<'c> {
+    /// The function `delete` removes the sink with the given id.
+    pub fn delete(connection: &ThreadSafe<&Connection>, id: i64) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare("DELETE FROM notification_sinks WHERE id = ?")?;
+        statement.bind((1, id))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `matches` tells whether an entry should be sent to this sink, based on the
+    /// profile and feed filters of the sink.
+    pub fn matches(&self, payload: &EntryPayload) -> bool {
+        let profile_matches = match &self.profile {
+            Some(profile) => profile == &payload.profile,
+            None => true,
+        };
+        let feed_matches = match &self.rss_url {
+            Some(rss_url) => rss_url == &payload.feed_url,
+            None => true,
+        };
+        profile_matches && feed_matches
+    }
+}
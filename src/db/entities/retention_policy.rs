@@ -0,0 +1,189 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::core::{
+    structs::{GetManyOptions, SaveOutcome},
+    traits::{Connectable, CrudAble},
+};
+
+/// The `RetentionPolicy` struct holds which entries `prune` deletes, stored in the
+/// `retention_policies` table either globally or for one feed. Every option is optional, an unset
+/// option falls back to the global policy and then to keeping everything.
+///
+/// Properties:
+///
+/// * `rss_url`: The feed the policy applies to, `None` for the global policy.
+/// * `keep_newest`: The number of newest entries of the feed that are kept, 0 for no limit.
+/// * `max_age_days`: The age in days after which entries are deleted, 0 for no limit.
+/// * `keep_starred`: Whether starred entries are never deleted, `true` when unset.
+/// * `keep_tagged`: Whether entries with a tag of their own are never deleted, `true` when unset.
+/// * `connection`: The optional database connection used by `save`.
+#[derive(Default, Clone)]
+pub struct RetentionPolicy<'c> {
+    pub rss_url: Option<String>,
+    pub keep_newest: Option<u64>,
+    pub max_age_days: Option<u64>,
+    pub keep_starred: Option<bool>,
+    pub keep_tagged: Option<bool>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for RetentionPolicy<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn show<T: ToString>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| String::from("-"))
+        }
+        write!(
+            f,
+            "(  feed = {}\tkeep_newest = {}\tmax_age_days = {}\tkeep_starred = {}\tkeep_tagged = {}  )",
+            self.rss_url.as_deref().unwrap_or("*"),
+            show(&self.keep_newest),
+            show(&self.max_age_days),
+            show(&self.keep_starred),
+            show(&self.keep_tagged)
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for RetentionPolicy<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, RetentionPolicy<'c>> for RetentionPolicy<'c> {
+    /// The function stores the policy, replacing the policy previously stored for the same feed.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if a policy was already stored, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = RetentionPolicy::get(&connection, self.rss_url.as_deref()).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO retention_policies (
+                rss_url, keep_newest, max_age_days, keep_starred, keep_tagged
+            )VALUES (
+                ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.rss_url.as_deref().unwrap_or("")))?;
+        insert_statement.bind((2, self.keep_newest.map(|count| count as i64)))?;
+        insert_statement.bind((3, self.max_age_days.map(|days| days as i64)))?;
+        insert_statement.bind((4, self.keep_starred.map(|keep| keep as i64)))?;
+        insert_statement.bind((5, self.keep_tagged.map(|keep| keep as i64)))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the global policy and the policies of every feed.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<RetentionPolicy<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM retention_policies ORDER BY rss_url")
+            .expect("Statement is not built");
+        read_policies(&mut statement)
+    }
+}
+
+impl<'c> RetentionPolicy<'c> {
+    /// The function `get` returns the policy stored for the feed with the given URL, or the global
+    /// policy when `rss_url` is `None`.
+    pub fn get(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: Option<&str>,
+    ) -> Option<RetentionPolicy<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM retention_policies WHERE rss_url = ?")
+            .expect("Statement is not built");
+        statement
+            .bind((1, rss_url.unwrap_or("")))
+            .expect("Binding failed");
+        read_policies(&mut statement).pop()
+    }
+
+    /// The function `for_feed` returns the policy applied to the feed with the given URL: the
+    /// policy of the feed on top of the global policy.
+    pub fn for_feed(connection: &ThreadSafe<&Connection>, rss_url: &str) -> RetentionPolicy<'c> {
+        let global = RetentionPolicy::get(connection, None).unwrap_or_default();
+        match RetentionPolicy::get(connection, Some(rss_url)) {
+            Some(feed) => feed.overlay(global),
+            None => global,
+        }
+    }
+
+    /// The function `delete` removes the policy of the feed with the given URL, or the global
+    /// policy when `rss_url` is `None`.
+    pub fn delete(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: Option<&str>,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("DELETE FROM retention_policies WHERE rss_url = ?")?;
+        statement.bind((1, rss_url.unwrap_or("")))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `overlay` fills the options unset in `self` from `base`.
+    pub fn overlay(self, base: RetentionPolicy<'c>) -> RetentionPolicy<'c> {
+        RetentionPolicy {
+            rss_url: self.rss_url,
+            keep_newest: self.keep_newest.or(base.keep_newest),
+            max_age_days: self.max_age_days.or(base.max_age_days),
+            keep_starred: self.keep_starred.or(base.keep_starred),
+            keep_tagged: self.keep_tagged.or(base.keep_tagged),
+            connection: self.connection,
+        }
+    }
+
+    /// The function `deletes_anything` tells whether the policy has a limit at all.
+    pub fn deletes_anything(&self) -> bool {
+        self.keep_newest.unwrap_or(0) > 0 || self.max_age_days.unwrap_or(0) > 0
+    }
+}
+
+fn read_policies<'c>(statement: &mut sqlite::Statement) -> Vec<RetentionPolicy<'c>> {
+    let mut policies = vec![];
+    while let Ok(State::Row) = statement.next() {
+        let rss_url = statement.read::<String, _>("rss_url").unwrap();
+        let number = |column: &str| {
+            statement
+                .read::<Option<i64>, _>(column)
+                .unwrap()
+                .map(|number| number as u64)
+        };
+        policies.push(RetentionPolicy {
+            rss_url: if rss_url.is_empty() {
+                None
+            } else {
+                Some(rss_url)
+            },
+            keep_newest: number("keep_newest"),
+            max_age_days: number("max_age_days"),
+            keep_starred: number("keep_starred").map(|keep| keep == 1),
+            keep_tagged: number("keep_tagged").map(|keep| keep == 1),
+            connection: None,
+        })
+    }
+    policies
+}
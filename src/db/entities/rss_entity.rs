@@ -1,20 +1,41 @@
 use crate::{
+    article::{self, errors::ArticleError},
     core::{
-        structs::GetManyOptions,
+        report::{FeedSyncReport, SyncProgress, SyncReport},
+        structs::{GetManyOptions, SaveOutcome, SyncOptions},
         traits::{Connectable, CrudAble},
     },
-    rss::Rss,
+    download::{download_enclosures, enforce_retention},
+    hooks::run_hooks,
+    http::{ClientPool, HttpClient},
+    icons::{self, DEFAULT_MAX_ICON_BYTES},
+    notify::dispatch,
+    rss::{dto::ChannelInfo, errors::RssParsingError, FeedSource, Rss},
+    rules::RuleSet,
+    secrets::{redact_url, Keyring},
+    storage::Storage,
 };
 
-use super::blog::RssEntry;
-use sqlite::{Connection, State};
+use super::{
+    blog::RssEntry, download_settings::DownloadSettings, enclosure::EntryEnclosure,
+    entry_content::EntryContent, feed_auth::FeedAuth, hook::Hook,
+    notification_sink::NotificationSink, rule::Rule, tag::normalize_tag,
+};
+use sqlite::{Connection, Error, State};
 use std::fmt::Display;
+use std::time::Instant;
 use thread_safe::ThreadSafe;
 
+#[derive(Clone)]
 pub struct RssEntity<'c> {
     pub id: u8,
     pub rss_url: String,
     pub profile: String,
+    pub full_text: bool,
+    pub encoding: String,
+    pub auto_download: bool,
+    pub channel: ChannelInfo,
+    pub display_title: Option<String>,
     pub connection: Option<ThreadSafe<&'c Connection>>,
 }
 
@@ -22,10 +43,18 @@ impl<'c> Display for RssEntity<'c> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "(  id = {}\trss_url = {}\tprofile = {}\thas_connection = {}  )",
+            "(  id = {}\ttitle = {}\trss_url = {}\tprofile = {}\tfull_text = {}\tauto_download = {}\tencoding = {}\thas_connection = {}  )",
             self.id,
-            self.rss_url,
+            self.name(),
+            redact_url(&self.rss_url),
             self.profile,
+            self.full_text,
+            self.auto_download,
+            if self.encoding.is_empty() {
+                "unknown"
+            } else {
+                &self.encoding
+            },
             self.connection.is_some()
         )
     }
@@ -40,36 +69,32 @@ impl<'c> Connectable<'c> for RssEntity<'c> {
 }
 
 impl<'c> CrudAble<'c, RssEntity<'c>> for RssEntity<'c> {
-    /// The function creates a new entry in the database table for an RSS entity and returns a reference
-    /// to the created entity.
-    ///
-    /// Arguments:
-    ///
-    /// * `entity`: The `entity` parameter is a reference to an `RssEntity` struct.
+    /// The function creates a new entry in the database table for an RSS entity.
     ///
     /// Returns:
     ///
-    /// a reference to the `RssEntity` object that was passed as an argument.
-    fn save<'d>(&'d self) -> &'d RssEntity<'c> {
+    /// `SaveOutcome::Inserted` once the row is written.
+    fn save(&self) -> Result<SaveOutcome, Error> {
         if let Some(connection) = self.connection.clone() {
             // database insertion here
             let connection = connection.get_ref();
             let insert_query = "
             INSERT INTO rss_table (
-                rss_url, profile
+                rss_url, profile, full_text, encoding, auto_download, title, site_link,
+                description, language, image_url, generator, last_build_date, ttl, display_title
             )VALUES (
-                ?, ?
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
             )
             ";
-            let mut insert_statement = connection.prepare(insert_query).unwrap();
-            insert_statement
-                .bind_iter([(1, self.rss_url.as_str()), (2, self.profile.as_str())])
-                .unwrap();
-
-            match insert_statement.next() {
-                Ok(_) => self,
-                Err(_) => &self,
-            }
+            let mut insert_statement = connection.prepare(insert_query)?;
+            insert_statement.bind_iter([(1, self.rss_url.as_str()), (2, self.profile.as_str())])?;
+            insert_statement.bind((3, self.full_text as i64))?;
+            insert_statement.bind((4, self.encoding.as_str()))?;
+            insert_statement.bind((5, self.auto_download as i64))?;
+            bind_channel(&mut insert_statement, 6, &self.channel)?;
+            insert_statement.bind((14, self.display_title.as_deref()))?;
+            insert_statement.next()?;
+            Ok(SaveOutcome::Inserted)
         } else {
             panic!("No connection failed to save")
         }
@@ -81,14 +106,14 @@ impl<'c> CrudAble<'c, RssEntity<'c>> for RssEntity<'c> {
     /// Arguments:
     ///
     /// * `connection`: The `connection` parameter is a reference to a `Connection` object. It represents
-    /// a connection to a database and is used to execute SQL queries and interact with the database.
+    ///   a connection to a database and is used to execute SQL queries and interact with the database.
     ///
     /// Returns:
     ///
     /// The function `get_many` returns a vector of `RssEntity` objects.
     #[allow(unused)]
-    fn get_many<'conn>(
-        connection: &ThreadSafe<&'conn Connection>,
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
         options: GetManyOptions,
     ) -> Vec<RssEntity<'c>> {
         let query = "SELECT * FROM rss_table";
@@ -100,32 +125,516 @@ impl<'c> CrudAble<'c, RssEntity<'c>> for RssEntity<'c> {
                 id: statement.read::<i64, _>("id").unwrap() as u8,
                 rss_url: statement.read::<String, _>("rss_url").unwrap(),
                 profile: statement.read::<String, _>("profile").unwrap(),
+                full_text: statement.read::<i64, _>("full_text").unwrap_or(0) == 1,
+                encoding: statement
+                    .read::<Option<String>, _>("encoding")
+                    .unwrap_or_default()
+                    .unwrap_or_default(),
+                auto_download: statement.read::<i64, _>("auto_download").unwrap_or(0) == 1,
+                channel: ChannelInfo {
+                    title: read_text(&statement, "title"),
+                    link: read_text(&statement, "site_link"),
+                    description: read_text(&statement, "description"),
+                    language: read_text(&statement, "language"),
+                    image_url: read_text(&statement, "image_url"),
+                    generator: read_text(&statement, "generator"),
+                    last_build_date: read_text(&statement, "last_build_date"),
+                    ttl: read_text(&statement, "ttl"),
+                },
+                display_title: statement
+                    .read::<Option<String>, _>("display_title")
+                    .unwrap_or_default(),
                 connection: None,
             })
         }
-        return rss_entity_vec;
+        rss_entity_vec
     }
 }
 
 impl<'c> RssEntity<'c> {
-    /// The function `sync` retrieves multiple RSS entities from a database connection, parses each RSS
-    /// feed, and saves the entries into the database.
+    /// The function `with_connection` returns a copy of the feed using `connection`, `None` for a
+    /// copy detached from any database.
+    pub fn with_connection<'d>(
+        &self,
+        connection: Option<ThreadSafe<&'d Connection>>,
+    ) -> RssEntity<'d> {
+        RssEntity {
+            id: self.id,
+            rss_url: self.rss_url.clone(),
+            profile: self.profile.clone(),
+            full_text: self.full_text,
+            encoding: self.encoding.clone(),
+            auto_download: self.auto_download,
+            channel: self.channel.clone(),
+            display_title: self.display_title.clone(),
+            connection,
+        }
+    }
+
+    /// The function `name` returns the title the feed is listed under: the display title set by
+    /// the user, else the channel title, else the redacted feed URL.
+    pub fn name(&self) -> String {
+        match &self.display_title {
+            Some(display_title) => display_title.clone(),
+            None if !self.channel.title.trim().is_empty() => self.channel.title.trim().to_string(),
+            None => redact_url(&self.rss_url),
+        }
+    }
+
+    /// The function `set_channel` stores the channel metadata last parsed from the feed with the
+    /// given URL.
+    pub fn set_channel(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        channel: &ChannelInfo,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare(
+            "
+            UPDATE rss_table SET
+                title = ?, site_link = ?, description = ?, language = ?, image_url = ?,
+                generator = ?, last_build_date = ?, ttl = ?
+            WHERE rss_url = ?
+            ",
+        )?;
+        bind_channel(&mut statement, 1, channel)?;
+        statement.bind((9, rss_url))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `set_display_title` overrides the title the feed with the given URL is listed
+    /// under, `None` goes back to the channel title.
+    pub fn set_display_title(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        display_title: Option<&str>,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE rss_table SET display_title = ? WHERE rss_url = ?")?;
+        statement.bind((1, display_title))?;
+        statement.bind((2, rss_url))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `set_full_text` turns fetching the full article text on sync on or off for the
+    /// feed with the given URL.
+    pub fn set_full_text(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        full_text: bool,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE rss_table SET full_text = ? WHERE rss_url = ?")?;
+        statement.bind((1, full_text as i64))?;
+        statement.bind((2, rss_url))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `set_auto_download` turns downloading the enclosures of new entries on sync on
+    /// or off for the feed with the given URL.
+    pub fn set_auto_download(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        auto_download: bool,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE rss_table SET auto_download = ? WHERE rss_url = ?")?;
+        statement.bind((1, auto_download as i64))?;
+        statement.bind((2, rss_url))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `set_encoding` records the character encoding the feed with the given URL was
+    /// last decoded from.
+    pub fn set_encoding(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        encoding: &str,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE rss_table SET encoding = ? WHERE rss_url = ?")?;
+        statement.bind((1, encoding))?;
+        statement.bind((2, rss_url))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `add_tag` tags the feed with the given URL, its entries inherit the tag. The tag
+    /// is normalized first, see `normalize_tag`, and ignored if it is not valid.
+    pub fn add_tag(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        tag: &str,
+    ) -> Result<(), Error> {
+        RssEntity::update_tag(
+            connection,
+            "INSERT OR IGNORE INTO feed_tags (rss_url, tag) VALUES (?, ?)",
+            rss_url,
+            tag,
+        )
+    }
+
+    /// The function `remove_tag` removes a tag from the feed with the given URL, and so from the
+    /// entries that only inherited it.
+    pub fn remove_tag(
+        connection: &ThreadSafe<&Connection>,
+        rss_url: &str,
+        tag: &str,
+    ) -> Result<(), Error> {
+        RssEntity::update_tag(
+            connection,
+            "DELETE FROM feed_tags WHERE rss_url = ? AND tag = ?",
+            rss_url,
+            tag,
+        )
+    }
+
+    fn update_tag(
+        connection: &ThreadSafe<&Connection>,
+        query: &str,
+        rss_url: &str,
+        tag: &str,
+    ) -> Result<(), Error> {
+        let tag = match normalize_tag(tag) {
+            Some(tag) => tag,
+            None => return Ok(()),
+        };
+        let mut statement = connection.get_ref().prepare(query)?;
+        statement.bind_iter([(1, rss_url), (2, tag.as_str())])?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `tags` returns the tags of the feed with the given URL.
+    pub fn tags(connection: &ThreadSafe<&Connection>, rss_url: &str) -> Vec<String> {
+        let mut statement = connection
+            .get_ref()
+            .prepare("SELECT tag FROM feed_tags WHERE rss_url = ? ORDER BY tag")
+            .expect("Statement is not built");
+        statement.bind((1, rss_url)).expect("Binding failed");
+        let mut tags = vec![];
+        while let Ok(State::Row) = statement.next() {
+            tags.push(statement.read::<String, _>("tag").unwrap());
+        }
+        tags
+    }
+
+    /// The function `sync` retrieves the subscribed feeds from `storage`, parses each RSS feed, and
+    /// saves the entries the feed's rules do not skip and that were not pruned into `storage`.
+    /// Every newly inserted entry is handed to the hooks of the feed's profile, and all of them are
+    /// batched to the matching notification sinks. The full article text of new entries is fetched
+    /// for feeds with `full_text` enabled, and their enclosures are downloaded for feeds with
+    /// `auto_download` enabled, deleting the oldest downloads past the budget. Missing and stale
+    /// feed icons are cached last. A feed that fails to fetch or save is recorded in the report
+    /// instead of aborting the whole sync.
     ///
     /// Arguments:
     ///
-    /// * `conn`: The `conn` parameter is a reference to an `Arc<Connection>` object. It is likely a
-    /// connection to a database or some other data source that is used to retrieve and save data.
-    pub async fn sync(connection: &ThreadSafe<&'c Connection>) -> () {
-        let rss_vec = RssEntity::get_many(&connection, GetManyOptions::new());
-
-        for rss in rss_vec {
-            let mut rss = Rss::parse(String::from(rss.rss_url)).await.unwrap();
-
-            for item in rss.get_items().iter() {
-                let mut rss_entry = RssEntry::from(item.clone());
-                rss_entry.connection = Some(connection.clone());
-                rss_entry.save();
+    /// * `storage`: The `storage` the subscribed feeds are read from and their entries saved to.
+    /// * `connection`: The optional database holding what `Storage` does not: the http settings,
+    ///   credentials, rules, hooks, notification sinks, podcast and media metadata, downloads and
+    ///   icons. Without it the feeds are fetched with the default settings and only their entries
+    ///   and full articles are stored.
+    /// * `options`: The `options` of the sync command, see `SyncOptions`.
+    ///
+    /// Returns:
+    ///
+    /// a `SyncReport` with the fetched, new and updated counts of every feed.
+    pub async fn sync(
+        storage: &dyn Storage,
+        connection: Option<&ThreadSafe<&'c Connection>>,
+        options: SyncOptions,
+    ) -> SyncReport {
+        let rss_vec = storage.get_feeds();
+        let mut clients = ClientPool::new();
+        let mut keyring = connection.map(Keyring::new);
+        let download_settings = connection.map(DownloadSettings::get).unwrap_or_default();
+        let mut report = SyncReport::new();
+        let mut all_inserted = vec![];
+        let total = rss_vec.len();
+
+        for (done, mut rss_entity) in rss_vec.into_iter().enumerate() {
+            if let Some(progress) = &options.progress {
+                let _ = progress.send(SyncProgress {
+                    done,
+                    total,
+                    rss_url: rss_entity.rss_url.clone(),
+                });
             }
+            let started_at = Instant::now();
+            let mut feed_report = FeedSyncReport::new(&rss_entity.rss_url);
+
+            let parsed = match (connection, keyring.as_mut()) {
+                (Some(connection), Some(keyring)) => {
+                    fetch_feed(
+                        connection,
+                        &mut clients,
+                        keyring,
+                        &rss_entity.rss_url,
+                        options.max_items,
+                    )
+                    .await
+                }
+                _ => {
+                    fetch_with_defaults(&mut clients, &rss_entity.rss_url, options.max_items).await
+                }
+            };
+            match parsed {
+                Ok((client, mut rss)) => {
+                    let channel = rss.channel_info();
+                    if channel != rss_entity.channel {
+                        if let Err(e) = storage.set_channel(&rss_entity.rss_url, &channel) {
+                            feed_report.errors.push(e.to_string());
+                        }
+                    }
+                    rss_entity.channel = channel;
+                    feed_report.title = rss_entity.name();
+                    if rss.encoding() != rss_entity.encoding {
+                        if let Err(e) = storage.set_encoding(&rss_entity.rss_url, rss.encoding()) {
+                            feed_report.errors.push(e.to_string());
+                        }
+                    }
+                    let items = rss.get_items();
+                    feed_report.fetched = items.len();
+
+                    let rule_set = RuleSet::new(
+                        connection
+                            .map(|connection| {
+                                Rule::for_feed(connection, &rss_entity.profile, &rss_entity.rss_url)
+                            })
+                            .unwrap_or_default(),
+                    );
+                    let mut inserted = vec![];
+                    for item in items.iter() {
+                        let verdict = rule_set.evaluate(item);
+                        if verdict.skip {
+                            feed_report.skipped += 1;
+                            continue;
+                        }
+
+                        let mut rss_entry = RssEntry::from(item.clone());
+                        if storage.is_pruned(&rss_entry.hash()) {
+                            feed_report.skipped += 1;
+                            continue;
+                        }
+                        rss_entry.rss_url = rss_entity.rss_url.clone();
+                        rss_entry.connection = connection.cloned();
+                        match storage.save_entry(&rss_entry) {
+                            Ok(outcome) => {
+                                feed_report.record(outcome);
+                                if outcome != SaveOutcome::Unchanged {
+                                    if connection.is_some() {
+                                        if let Err(e) = rss_entry
+                                            .save_podcast(item)
+                                            .and_then(|_| rss_entry.save_media(item))
+                                        {
+                                            feed_report
+                                                .errors
+                                                .push(format!("{}: {}", item.title, e));
+                                        }
+                                    }
+                                    if let Err(e) = storage.save_details(&rss_entry, item) {
+                                        feed_report.errors.push(format!("{}: {}", item.title, e));
+                                    }
+                                }
+                                if outcome == SaveOutcome::Inserted {
+                                    if let Err(e) = verdict.apply(storage, &rss_entry) {
+                                        feed_report.errors.push(format!("{}: {}", item.title, e));
+                                    }
+                                    inserted.push(rss_entry.to_payload(&rss_entity));
+                                }
+                            }
+                            Err(e) => feed_report.errors.push(format!("{}: {}", item.title, e)),
+                        }
+                    }
+
+                    if options.full_text || rss_entity.full_text {
+                        for payload in inserted.iter() {
+                            match fetch_content(&client, storage, &payload.hash, &payload.link)
+                                .await
+                            {
+                                Ok(()) => feed_report.full_texts += 1,
+                                Err(e) => feed_report
+                                    .errors
+                                    .push(format!("full text of {}: {}", payload.link, e)),
+                            }
+                        }
+                    }
+
+                    if let Some(connection) = connection {
+                        if rss_entity.auto_download {
+                            let enclosures: Vec<EntryEnclosure> = inserted
+                                .iter()
+                                .flat_map(|payload| {
+                                    EntryEnclosure::for_entry(connection, &payload.hash)
+                                })
+                                .collect();
+                            if !enclosures.is_empty() {
+                                let downloads = download_enclosures(
+                                    connection,
+                                    &mut clients,
+                                    &download_settings,
+                                    enclosures,
+                                )
+                                .await;
+                                feed_report.downloads = downloads.downloaded.len();
+                                feed_report.errors.extend(downloads.errors);
+                            }
+                        }
+
+                        let mut hooks = Hook::for_profile(connection, &rss_entity.profile);
+                        for hook in hooks.iter_mut() {
+                            hook.connection = Some(connection.clone());
+                        }
+                        feed_report.hook_failures = run_hooks(&hooks, &inserted).await;
+                    }
+                    all_inserted.extend(inserted);
+                }
+                Err(e) => feed_report.errors.push(e.to_string()),
+            }
+
+            feed_report.set_duration(started_at.elapsed());
+            report.feeds.push(feed_report);
         }
+
+        if let Some(connection) = connection {
+            let sinks = NotificationSink::get_many(connection, GetManyOptions::new());
+            report.notification_errors = match clients.global(connection) {
+                Ok(client) => dispatch(&client, &sinks, &all_inserted).await,
+                Err(e) => vec![format!("notification sinks: {}", e)],
+            };
+            if report.total_downloads() > 0 {
+                let retention = enforce_retention(connection, &download_settings);
+                report.pruned_files = retention.removed;
+                report.notification_errors.extend(
+                    retention
+                        .errors
+                        .into_iter()
+                        .map(|e| format!("retention {}", e)),
+                );
+            }
+            // failures are stored on the icon and retried once it is stale, they are not sync
+            // errors
+            icons::refresh_stale(connection, &mut clients, DEFAULT_MAX_ICON_BYTES).await;
+        }
+        if let Some(progress) = &options.progress {
+            let _ = progress.send(SyncProgress {
+                done: total,
+                total,
+                rss_url: String::new(),
+            });
+        }
+        report
+    }
+}
+
+/// The function `fetch_feed` fetches and parses the feed with the given URL, with the http
+/// settings and the credentials stored for it. A `file://` URL or `-` is read locally instead.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` holding the settings and credentials of the feed.
+/// * `clients`: The `clients` shared between the feeds of a run.
+/// * `keyring`: The `keyring` decrypting the credentials.
+/// * `rss_url`: The URL of the feed.
+/// * `max_items`: The optional number of items after which reading stops.
+///
+/// Returns:
+///
+/// the client used for the feed, to fetch its articles with, and the parsed `Rss`.
+pub async fn fetch_feed(
+    connection: &ThreadSafe<&Connection>,
+    clients: &mut ClientPool,
+    keyring: &mut Keyring<'_>,
+    rss_url: &str,
+    max_items: Option<usize>,
+) -> Result<(HttpClient, Rss), RssParsingError> {
+    let client = clients.for_feed(connection, rss_url)?;
+    let source = FeedSource::from(rss_url);
+    if source.is_local() {
+        let rss = Rss::read_source(source, max_items).await?;
+        return Ok((client, rss));
+    }
+    let rss = match FeedAuth::get(connection, rss_url)? {
+        Some(auth) => {
+            // the credentials go with a client that does not follow the feed to another host
+            let authenticated = clients.for_credentials(connection, rss_url)?;
+            let request = auth
+                .credentials(keyring)?
+                .apply(authenticated.client.get(rss_url));
+            Rss::fetch_request(&authenticated, request, max_items).await?
+        }
+        None => Rss::fetch_request(&client, client.client.get(rss_url), max_items).await?,
+    };
+    Ok((client, rss))
+}
+
+/// The function `fetch_with_defaults` is `fetch_feed` without a database: the feed is fetched with
+/// the default http settings and no credentials.
+async fn fetch_with_defaults(
+    clients: &mut ClientPool,
+    rss_url: &str,
+    max_items: Option<usize>,
+) -> Result<(HttpClient, Rss), RssParsingError> {
+    let client = clients.defaults()?;
+    let source = FeedSource::from(rss_url);
+    let rss = if source.is_local() {
+        Rss::read_source(source, max_items).await?
+    } else {
+        Rss::fetch(&client, rss_url, max_items).await?
+    };
+    Ok((client, rss))
+}
+
+/// The function `bind_channel` binds the eight fields of `channel` to `statement`, starting at the
+/// parameter `index`.
+fn bind_channel(
+    statement: &mut sqlite::Statement,
+    index: usize,
+    channel: &ChannelInfo,
+) -> Result<(), Error> {
+    for (offset, value) in [
+        &channel.title,
+        &channel.link,
+        &channel.description,
+        &channel.language,
+        &channel.image_url,
+        &channel.generator,
+        &channel.last_build_date,
+        &channel.ttl,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        statement.bind((index + offset, value.as_str()))?;
     }
+    Ok(())
+}
+
+fn read_text(statement: &sqlite::Statement, column: &str) -> String {
+    statement
+        .read::<Option<String>, _>(column)
+        .unwrap_or_default()
+        .unwrap_or_default()
+}
+
+/// The function `fetch_content` downloads the full article behind `link` and stores it for the entry
+/// with the given hash in `storage`.
+pub async fn fetch_content(
+    client: &HttpClient,
+    storage: &dyn Storage,
+    hash: &str,
+    link: &str,
+) -> Result<(), ArticleError> {
+    let article = article::fetch(client, link).await?;
+    storage.save_content(&EntryContent::from_article(hash, article))?;
+    Ok(())
 }
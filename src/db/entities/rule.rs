@@ -0,0 +1,275 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use std::str::FromStr;
+use thread_safe::ThreadSafe;
+
+use crate::core::{
+    structs::{GetManyOptions, SaveOutcome},
+    traits::{Connectable, CrudAble},
+};
+
+/// The `RuleField` enum lists the parts of an item a `Rule` can look at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleField {
+    Title,
+    Content,
+    Author,
+    Category,
+    Age,
+}
+
+/// The `RuleOperator` enum lists how a `Rule` compares its value with the field. `MinAge` and
+/// `MaxAge` hold a number of days and are only used with `RuleField::Age`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleOperator {
+    Contains,
+    Regex,
+    MinAge,
+    MaxAge,
+}
+
+/// The `RuleAction` enum lists what happens to an item matched by a `Rule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    Skip,
+    MarkRead,
+    Star,
+    Tag,
+}
+
+impl RuleField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleField::Title => "title",
+            RuleField::Content => "content",
+            RuleField::Author => "author",
+            RuleField::Category => "category",
+            RuleField::Age => "age",
+        }
+    }
+}
+
+impl FromStr for RuleField {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "title" => Ok(RuleField::Title),
+            "content" => Ok(RuleField::Content),
+            "author" => Ok(RuleField::Author),
+            "category" => Ok(RuleField::Category),
+            "age" => Ok(RuleField::Age),
+            other => Err(format!("Unknown rule field: {}", other)),
+        }
+    }
+}
+
+impl RuleOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleOperator::Contains => "contains",
+            RuleOperator::Regex => "regex",
+            RuleOperator::MinAge => "min_age",
+            RuleOperator::MaxAge => "max_age",
+        }
+    }
+}
+
+impl FromStr for RuleOperator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "contains" => Ok(RuleOperator::Contains),
+            "regex" => Ok(RuleOperator::Regex),
+            "min_age" => Ok(RuleOperator::MinAge),
+            "max_age" => Ok(RuleOperator::MaxAge),
+            other => Err(format!("Unknown rule operator: {}", other)),
+        }
+    }
+}
+
+impl RuleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleAction::Skip => "skip",
+            RuleAction::MarkRead => "read",
+            RuleAction::Star => "star",
+            RuleAction::Tag => "tag",
+        }
+    }
+}
+
+impl FromStr for RuleAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "skip" => Ok(RuleAction::Skip),
+            "read" => Ok(RuleAction::MarkRead),
+            "star" => Ok(RuleAction::Star),
+            "tag" => Ok(RuleAction::Tag),
+            other => Err(format!("Unknown rule action: {}", other)),
+        }
+    }
+}
+
+/// The `Rule` struct represents a filter rule evaluated against every item of a feed before it is
+/// saved by `sync`.
+///
+/// Properties:
+///
+/// * `id`: The id of the rule in the `rules` table.
+/// * `profile`: The profile the rule is limited to, `None` applies to every profile.
+/// * `rss_url`: The feed the rule is limited to, `None` applies to every feed.
+/// * `field`: The part of the item the rule looks at.
+/// * `operator`: How `value` is compared with the field.
+/// * `value`: The text, regular expression or number of days to compare with.
+/// * `negate`: Inverts the match, which turns a `skip` rule into an include rule: once a feed has
+///   include rules, an item is kept only if it matches at least one of them.
+/// * `action`: What happens to a matched item.
+/// * `tag`: The tag attached by `RuleAction::Tag`.
+/// * `connection`: The optional database connection used by `save`.
+pub struct Rule<'c> {
+    pub id: i64,
+    pub profile: Option<String>,
+    pub rss_url: Option<String>,
+    pub field: RuleField,
+    pub operator: RuleOperator,
+    pub value: String,
+    pub negate: bool,
+    pub action: RuleAction,
+    pub tag: Option<String>,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Display for Rule<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(  id = {}\tprofile = {}\tfeed = {}\twhen = {}{} {} {:?}\tthen = {}{}  )",
+            self.id,
+            self.profile.as_deref().unwrap_or("*"),
+            self.rss_url.as_deref().unwrap_or("*"),
+            if self.negate { "not " } else { "" },
+            self.field.as_str(),
+            self.operator.as_str(),
+            self.value,
+            self.action.as_str(),
+            self.tag
+                .as_ref()
+                .map(|tag| format!(" {}", tag))
+                .unwrap_or_default()
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for Rule<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, Rule<'c>> for Rule<'c> {
+    /// The function inserts the rule into the `rules` table.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Inserted` once the row is written.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT INTO rules (
+                profile, rss_url, field, operator,
+                value, negate, action, tag
+            )VALUES (
+                ?, ?, ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.profile.as_deref()))?;
+        insert_statement.bind((2, self.rss_url.as_deref()))?;
+        insert_statement.bind((3, self.field.as_str()))?;
+        insert_statement.bind((4, self.operator.as_str()))?;
+        insert_statement.bind((5, self.value.as_str()))?;
+        insert_statement.bind((6, self.negate as i64))?;
+        insert_statement.bind((7, self.action.as_str()))?;
+        insert_statement.bind((8, self.tag.as_deref()))?;
+        insert_statement.next()?;
+        Ok(SaveOutcome::Inserted)
+    }
+
+    /// The function `get_many` returns every configured rule.
+    #[allow(unused)]
+    fn get_many(connection: &ThreadSafe<&Connection>, options: GetManyOptions) -> Vec<Rule<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM rules ORDER BY id")
+            .expect("Statement is not built");
+        read_rules(&mut statement)
+    }
+}
+
+impl<'c> Rule<'c> {
+    /// The function `for_feed` returns the rules that apply to a feed, which are the rules of the
+    /// feed itself, the rules of its profile and the global rules.
+    pub fn for_feed(
+        connection: &ThreadSafe<&Connection>,
+        profile: &str,
+        rss_url: &str,
+    ) -> Vec<Rule<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare(
+                "
+                SELECT * FROM rules
+                WHERE (profile IS NULL OR profile = ?)
+                AND (rss_url IS NULL OR rss_url = ?)
+                ORDER BY id
+                ",
+            )
+            .expect("Statement is not built");
+        statement
+            .bind_iter([(1, profile), (2, rss_url)])
+            .expect("Binding failed");
+        read_rules(&mut statement)
+    }
+
+    /// The function `delete` removes the rule with the given id.
+    pub fn delete(connection: &ThreadSafe<&Connection>, id: i64) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare("DELETE FROM rules WHERE id = ?")?;
+        statement.bind((1, id))?;
+        statement.next()?;
+        Ok(())
+    }
+}
+
+fn read_rules<'c>(statement: &mut sqlite::Statement) -> Vec<Rule<'c>> {
+    let mut rules = vec![];
+    while let Ok(State::Row) = statement.next() {
+        let field = statement.read::<String, _>("field").unwrap().parse();
+        let operator = statement.read::<String, _>("operator").unwrap().parse();
+        let action = statement.read::<String, _>("action").unwrap().parse();
+        // rows written by a newer version with unknown variants are ignored
+        if let (Ok(field), Ok(operator), Ok(action)) = (field, operator, action) {
+            rules.push(Rule {
+                id: statement.read::<i64, _>("id").unwrap(),
+                profile: statement.read::<Option<String>, _>("profile").unwrap(),
+                rss_url: statement.read::<Option<String>, _>("rss_url").unwrap(),
+                field,
+                operator,
+                value: statement.read::<String, _>("value").unwrap(),
+                negate: statement.read::<i64, _>("negate").unwrap() == 1,
+                action,
+                tag: statement.read::<Option<String>, _>("tag").unwrap(),
+                connection: None,
+            })
+        }
+    }
+    rules
+}
@@ -0,0 +1,404 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use sqlite::{Connection, Error, State, Value};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::core::{
+    query::SqlQuery,
+    structs::{EntrySort, GetManyOptions, SaveOutcome},
+    traits::{Connectable, CrudAble},
+};
+
+use super::blog::RssEntry;
+
+/// The format of the `publish_date` column of `rss_entries`.
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// The `SavedSearch` struct is a named set of search filters, stored in the `saved_searches` table
+/// and turned into `GetManyOptions` each time it is run.
+///
+/// Properties:
+///
+/// * `id`: The id of the row, 0 until it is saved.
+/// * `name`: The unique name the search is run by.
+/// * `query`: The text matched against the title and the full article text.
+/// * `profile`: The profile of the feeds to search, empty for all.
+/// * `rss_url`: The feed to search, empty for all.
+/// * `tags`: The tags the entries must all carry.
+/// * `author`: The text the author of the entries contains, empty for any author.
+/// * `categories`: The categories the entries must all be filed under.
+/// * `unread_only`: Only match unread entries.
+/// * `starred_only`: Only match starred entries.
+/// * `since`: The oldest publish date, see `resolve_date`, empty for no bound.
+/// * `until`: The newest publish date, see `resolve_date`, empty for no bound.
+/// * `sort`: The order of the results.
+/// * `limit`: The number of results shown.
+/// * `connection`: The optional database connection used by `save`.
+#[derive(Clone)]
+pub struct SavedSearch<'c> {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    pub profile: String,
+    pub rss_url: String,
+    pub tags: Vec<String>,
+    pub author: String,
+    pub categories: Vec<String>,
+    pub unread_only: bool,
+    pub starred_only: bool,
+    pub since: String,
+    pub until: String,
+    pub sort: EntrySort,
+    pub limit: u64,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Default for SavedSearch<'c> {
+    fn default() -> Self {
+        SavedSearch {
+            id: 0,
+            name: String::new(),
+            query: String::new(),
+            profile: String::new(),
+            rss_url: String::new(),
+            tags: vec![],
+            author: String::new(),
+            categories: vec![],
+            unread_only: false,
+            starred_only: false,
+            since: String::new(),
+            until: String::new(),
+            sort: EntrySort::Newest,
+            limit: GetManyOptions::new().limit,
+            connection: None,
+        }
+    }
+}
+
+impl<'c> Display for SavedSearch<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut filters = vec![];
+        if !self.query.is_empty() {
+            filters.push(format!("query = {}", self.query));
+        }
+        if !self.profile.is_empty() {
+            filters.push(format!("profile = {}", self.profile));
+        }
+        if !self.rss_url.is_empty() {
+            filters.push(format!("feed = {}", self.rss_url));
+        }
+        if !self.tags.is_empty() {
+            filters.push(format!("tags = {}", self.tags.join(",")));
+        }
+        if !self.author.is_empty() {
+            filters.push(format!("author = {}", self.author));
+        }
+        if !self.categories.is_empty() {
+            filters.push(format!("categories = {}", self.categories.join(",")));
+        }
+        if self.unread_only {
+            filters.push(String::from("unread"));
+        }
+        if self.starred_only {
+            filters.push(String::from("starred"));
+        }
+        if !self.since.is_empty() {
+            filters.push(format!("since = {}", self.since));
+        }
+        if !self.until.is_empty() {
+            filters.push(format!("until = {}", self.until));
+        }
+        filters.push(format!("sort = {}", self.sort));
+        filters.push(format!("limit = {}", self.limit));
+        write!(f, "{}\t{}", self.name, filters.join("\t"))
+    }
+}
+
+impl<'c> Connectable<'c> for SavedSearch<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, SavedSearch<'c>> for SavedSearch<'c> {
+    /// The function stores the search under its name, replacing the filters of a search already
+    /// saved with that name.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if the name was already used, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = SavedSearch::get(&connection, &self.name).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT INTO saved_searches (
+                name, query, profile, rss_url, tags, unread_only, starred_only, since, until, sort,
+                result_limit, author, categories
+            )VALUES (
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
+            )
+            ON CONFLICT(name) DO UPDATE SET
+                query = excluded.query, profile = excluded.profile, rss_url = excluded.rss_url,
+                tags = excluded.tags, author = excluded.author, categories = excluded.categories,
+                unread_only = excluded.unread_only,
+                starred_only = excluded.starred_only, since = excluded.since,
+                until = excluded.until, sort = excluded.sort, result_limit = excluded.result_limit
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind_iter::<_, (_, &str)>([
+            (1, self.name.as_str()),
+            (2, self.query.as_str()),
+            (3, self.profile.as_str()),
+            (4, self.rss_url.as_str()),
+            (5, self.tags.join(",").as_str()),
+            (8, self.since.as_str()),
+            (9, self.until.as_str()),
+            (10, self.sort.to_string().as_str()),
+            (12, self.author.as_str()),
+            (13, self.categories.join(",").as_str()),
+        ])?;
+        insert_statement.bind_iter::<_, (_, i64)>([
+            (6, self.unread_only as i64),
+            (7, self.starred_only as i64),
+            (11, self.limit as i64),
+        ])?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the saved searches whose name matches `options.query`, by
+    /// name.
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<SavedSearch<'c>> {
+        let connection = connection.get_ref();
+        let mut query = SqlQuery::new("saved_searches");
+        query
+            .filter(
+                "name LIKE ?",
+                [Value::String(format!("%{}%", options.query))],
+            )
+            .order_by("name", []);
+        let (query, values) = query.select("*", options.limit, options.skip);
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        statement.bind(&values[..]).expect("Binding failed");
+        read_searches(&mut statement)
+    }
+}
+
+impl<'c> SavedSearch<'c> {
+    /// The function `get` returns the saved search with the given name.
+    pub fn get(connection: &ThreadSafe<&Connection>, name: &str) -> Option<SavedSearch<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM saved_searches WHERE name = ?")
+            .expect("Statement is not built");
+        statement.bind((1, name)).expect("Binding failed");
+        read_searches(&mut statement).pop()
+    }
+
+    /// The function `rename` changes the name of a saved search.
+    pub fn rename(
+        connection: &ThreadSafe<&Connection>,
+        name: &str,
+        new_name: &str,
+    ) -> Result<(), Error> {
+        let connection = connection.get_ref();
+        let mut statement =
+            connection.prepare("UPDATE saved_searches SET name = ? WHERE name = ?")?;
+        statement.bind_iter([(1, new_name), (2, name)])?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// The function `delete` removes the saved search with the given name.
+    ///
+    /// Returns:
+    ///
+    /// whether a saved search had that name.
+    pub fn delete(connection: &ThreadSafe<&Connection>, name: &str) -> Result<bool, Error> {
+        let connection = connection.get_ref();
+        let mut statement = connection.prepare("DELETE FROM saved_searches WHERE name = ?")?;
+        statement.bind((1, name))?;
+        statement.next()?;
+        Ok(connection.change_count() > 0)
+    }
+
+    /// The function `to_options` turns the filters into the `GetManyOptions` of a search, resolving
+    /// relative dates against the current time.
+    ///
+    /// Returns:
+    ///
+    /// the options, or the error of a date bound that cannot be read.
+    pub fn to_options(&self) -> Result<GetManyOptions, String> {
+        let mut options = GetManyOptions::new();
+        options.query = self.query.clone();
+        options.set_limit(self.limit);
+        options.set_profile(&self.profile);
+        options.set_rss_url(&self.rss_url);
+        options.set_tags(self.tags.clone());
+        options.set_author(&self.author);
+        options.set_categories(self.categories.clone());
+        options.set_unread_only(self.unread_only);
+        options.set_starred_only(self.starred_only);
+        options.set_date_range(
+            &resolve_date(&self.since, false)?,
+            &resolve_date(&self.until, true)?,
+        );
+        options.set_sort(self.sort);
+        Ok(options)
+    }
+
+    /// The function `unread_count` returns how many unread entries the search currently matches,
+    /// regardless of its limit.
+    pub fn unread_count(&self, connection: &ThreadSafe<&Connection>) -> u64 {
+        match self.to_options() {
+            Ok(mut options) => {
+                options.set_unread_only(true);
+                RssEntry::count(connection, &options)
+            }
+            Err(_) => 0,
+        }
+    }
+}
+
+/// The function `resolve_date` turns a date bound into the format of the `publish_date` column.
+///
+/// Arguments:
+///
+/// * `spec`: `Nd` for N days ago, `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS`, or empty for no bound.
+/// * `end_of_day`: Whether a `YYYY-MM-DD` date means the end of the day, for upper bounds.
+///
+/// Returns:
+///
+/// the bound, empty for no bound, or an error message if `spec` cannot be read or is too far in
+/// the past.
+pub fn resolve_date(spec: &str, end_of_day: bool) -> Result<String, String> {
+    let spec = spec.trim();
+    let invalid = || {
+        format!(
+            "Invalid date {}, expected Nd, YYYY-MM-DD or YYYY-MM-DD HH:MM:SS",
+            spec
+        )
+    };
+    if spec.is_empty() {
+        return Ok(String::new());
+    }
+    if let Some(days) = spec.strip_suffix('d') {
+        if let Ok(days) = days.parse::<i64>() {
+            // `Duration::days` panics past its bounds
+            if days.unsigned_abs() > Duration::max_value().num_days().unsigned_abs() {
+                return Err(invalid());
+            }
+            return Utc::now()
+                .checked_sub_signed(Duration::days(days))
+                .map(|date_time| date_time.format(DATE_FORMAT).to_string())
+                .ok_or_else(invalid);
+        }
+    }
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(spec, DATE_FORMAT) {
+        return Ok(date_time.format(DATE_FORMAT).to_string());
+    }
+    match NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        Ok(date) if end_of_day => Ok(format!("{} 23:59:59", date)),
+        Ok(date) => Ok(format!("{} 00:00:00", date)),
+        Err(_) => Err(invalid()),
+    }
+}
+
+fn read_searches<'c>(statement: &mut sqlite::Statement) -> Vec<SavedSearch<'c>> {
+    let mut searches = vec![];
+    while let Ok(State::Row) = statement.next() {
+        let text = |column: &str| statement.read::<String, _>(column).unwrap_or_default();
+        searches.push(SavedSearch {
+            id: statement.read::<i64, _>("id").unwrap(),
+            name: text("name"),
+            query: text("query"),
+            profile: text("profile"),
+            rss_url: text("rss_url"),
+            tags: split_list(&text("tags")),
+            author: text("author"),
+            categories: split_list(&text("categories")),
+            unread_only: statement.read::<i64, _>("unread_only").unwrap_or(0) == 1,
+            starred_only: statement.read::<i64, _>("starred_only").unwrap_or(0) == 1,
+            since: text("since"),
+            until: text("until"),
+            sort: text("sort").parse().unwrap_or_default(),
+            limit: statement.read::<i64, _>("result_limit").unwrap_or(10) as u64,
+            connection: None,
+        })
+    }
+    searches
+}
+
+/// The function `split_list` reads a comma separated column, an empty column being an empty list.
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVALID: &str = "expected Nd, YYYY-MM-DD or YYYY-MM-DD HH:MM:SS";
+
+    #[test]
+    fn days_are_counted_back_from_now() {
+        let before = Utc::now().naive_utc() - Duration::days(3);
+        let since = resolve_date(" 3d ", false).unwrap();
+        let since = NaiveDateTime::parse_from_str(&since, DATE_FORMAT).unwrap();
+        assert!(since >= before - Duration::seconds(1));
+        assert!(since <= Utc::now().naive_utc() - Duration::days(3));
+        assert_eq!(resolve_date("", true), Ok(String::new()));
+    }
+
+    #[test]
+    fn dates_start_or_end_the_day() {
+        assert_eq!(
+            resolve_date("2024-02-29", false).as_deref(),
+            Ok("2024-02-29 00:00:00")
+        );
+        assert_eq!(
+            resolve_date("2024-02-29", true).as_deref(),
+            Ok("2024-02-29 23:59:59")
+        );
+        // a full date is kept as given, whatever the bound
+        assert_eq!(
+            resolve_date("2024-02-29 08:30:00", true).as_deref(),
+            Ok("2024-02-29 08:30:00")
+        );
+    }
+
+    #[test]
+    fn unreadable_or_unreachable_dates_are_errors() {
+        for spec in [
+            "yesterday",
+            "2023-02-29",
+            "2024-01-01T08:30:00",
+            "d",
+            "1.5d",
+        ] {
+            let error = resolve_date(spec, false).unwrap_err();
+            assert!(error.contains(INVALID), "{}", spec);
+        }
+        // too many days to subtract from now, instead of a panic
+        for spec in ["99999999999999d", "106751991167d", "-9223372036854775808d"] {
+            assert_eq!(
+                resolve_date(spec, false),
+                Err(format!("Invalid date {}, {}", spec, INVALID))
+            );
+        }
+    }
+}
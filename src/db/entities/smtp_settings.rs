@@ -0,0 +1,215 @@
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use std::str::FromStr;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{
+        structs::{GetManyOptions, SaveOutcome},
+        traits::{Connectable, CrudAble},
+    },
+    secrets::Secret,
+};
+
+/// The `SmtpSecurity` enum is how the connection to the SMTP server is protected.
+///
+/// Variants:
+///
+/// * `None`: Plain text, for a local relay or a test sink.
+/// * `StartTls`: Plain text upgraded with `STARTTLS` before authenticating, usually on port 587.
+/// * `Tls`: TLS from the first byte, usually on port 465.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    None,
+    #[default]
+    StartTls,
+    Tls,
+}
+
+impl SmtpSecurity {
+    /// The function `default_port` returns the usual port of the security mode.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::None => 25,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+        }
+    }
+}
+
+impl Display for SmtpSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmtpSecurity::None => write!(f, "none"),
+            SmtpSecurity::StartTls => write!(f, "starttls"),
+            SmtpSecurity::Tls => write!(f, "tls"),
+        }
+    }
+}
+
+impl FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(security: &str) -> Result<Self, Self::Err> {
+        match security {
+            "none" => Ok(SmtpSecurity::None),
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            _ => Err(format!("Unknown SMTP security {}", security)),
+        }
+    }
+}
+
+/// The `SmtpSettings` struct holds the server email digests are sent through, stored as the single
+/// row of the `smtp_settings` table. The password is a `Secret`, so it is either encrypted or read
+/// from the environment, and it is never printed.
+///
+/// Properties:
+///
+/// * `host`: The host name of the SMTP server, also checked against its TLS certificate.
+/// * `port`: The port of the server, `None` for the default port of `security`.
+/// * `security`: How the connection is protected.
+/// * `username`: The user name to authenticate with, `None` to send without authenticating.
+/// * `password`: The password to authenticate with.
+/// * `from`: The address the digests are sent from.
+/// * `connection`: The optional database connection used by `save`.
+#[derive(Clone)]
+pub struct SmtpSettings<'c> {
+    pub host: String,
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub from: String,
+    pub connection: Option<ThreadSafe<&'c Connection>>,
+}
+
+impl<'c> Default for SmtpSettings<'c> {
+    fn default() -> Self {
+        SmtpSettings {
+            host: String::from("localhost"),
+            port: None,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            from: String::new(),
+            connection: None,
+        }
+    }
+}
+
+impl<'c> Display for SmtpSettings<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(  host = {}\tport = {}\tsecurity = {}\tusername = {}\tpassword = {}\tfrom = {}  )",
+            self.host,
+            self.port(),
+            self.security,
+            self.username.as_deref().unwrap_or("-"),
+            self.password
+                .as_ref()
+                .map(|password| password.to_string())
+                .unwrap_or_else(|| String::from("-")),
+            if self.from.is_empty() {
+                "-"
+            } else {
+                &self.from
+            }
+        )
+    }
+}
+
+impl<'c> Connectable<'c> for SmtpSettings<'c> {
+    fn set_connection<'conn: 'c>(&mut self, connection: &'conn Connection) {
+        self.connection = Some(ThreadSafe::new(connection));
+    }
+}
+
+impl<'c> CrudAble<'c, SmtpSettings<'c>> for SmtpSettings<'c> {
+    /// The function stores the settings, replacing the settings previously stored.
+    ///
+    /// Returns:
+    ///
+    /// `SaveOutcome::Updated` if settings were already stored, `SaveOutcome::Inserted` otherwise.
+    fn save(&self) -> Result<SaveOutcome, Error> {
+        let connection = match self.connection.clone() {
+            Some(connection) => connection,
+            None => panic!("DB connection not set"),
+        };
+        let existed = SmtpSettings::get(&connection).is_some();
+        let connection = connection.get_ref();
+        let insert_query = "
+            INSERT OR REPLACE INTO smtp_settings (
+                id, host, port, security, username, password, from_address
+            )VALUES (
+                1, ?, ?, ?, ?, ?, ?
+            )
+        ";
+        let mut insert_statement = connection.prepare(insert_query)?;
+        insert_statement.bind((1, self.host.as_str()))?;
+        insert_statement.bind((2, self.port.map(|port| port as i64)))?;
+        insert_statement.bind((3, self.security.to_string().as_str()))?;
+        insert_statement.bind((4, self.username.as_deref()))?;
+        insert_statement.bind((5, self.password.as_ref().map(Secret::stored).as_deref()))?;
+        insert_statement.bind((6, self.from.as_str()))?;
+        insert_statement.next()?;
+        Ok(if existed {
+            SaveOutcome::Updated
+        } else {
+            SaveOutcome::Inserted
+        })
+    }
+
+    /// The function `get_many` returns the stored settings, at most one row.
+    #[allow(unused)]
+    fn get_many(
+        connection: &ThreadSafe<&Connection>,
+        options: GetManyOptions,
+    ) -> Vec<SmtpSettings<'c>> {
+        let connection = connection.get_ref();
+        let mut statement = connection
+            .prepare("SELECT * FROM smtp_settings")
+            .expect("Statement is not built");
+        let mut settings = vec![];
+        while let Ok(State::Row) = statement.next() {
+            settings.push(SmtpSettings {
+                host: statement.read::<String, _>("host").unwrap(),
+                port: statement
+                    .read::<Option<i64>, _>("port")
+                    .unwrap()
+                    .map(|port| port as u16),
+                security: statement
+                    .read::<String, _>("security")
+                    .unwrap()
+                    .parse()
+                    .unwrap_or_default(),
+                username: statement.read::<Option<String>, _>("username").unwrap(),
+                password: statement
+                    .read::<Option<String>, _>("password")
+                    .unwrap()
+                    .and_then(|stored| Secret::from_stored(&stored).ok()),
+                from: statement.read::<String, _>("from_address").unwrap(),
+                connection: None,
+            })
+        }
+        settings
+    }
+}
+
+impl<'c> SmtpSettings<'c> {
+    /// The function `get` returns the stored settings, `None` when the server was never configured.
+    pub fn get(connection: &ThreadSafe<&Connection>) -> Option<SmtpSettings<'c>> {
+        SmtpSettings::get_many(connection, GetManyOptions::new()).pop()
+    }
+
+    /// The function `delete` forgets the server settings.
+    pub fn delete(connection: &ThreadSafe<&Connection>) -> Result<(), Error> {
+        connection.get_ref().execute("DELETE FROM smtp_settings")
+    }
+
+    /// The function `port` returns the configured port, or the default port of the security mode.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.security.default_port())
+    }
+}
@@ -0,0 +1,200 @@
+use sqlite::{Connection, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+/// The `TagCount` struct is one line of the tag listing: how many entries and feeds use a tag.
+///
+/// Properties:
+///
+/// * `tag`: The tag.
+/// * `entries`: The number of entries tagged directly.
+/// * `feeds`: The number of feeds tagged.
+/// * `inherited`: The number of entries found by the tag through their feed only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagCount {
+    pub tag: String,
+    pub entries: u64,
+    pub feeds: u64,
+    pub inherited: u64,
+}
+
+impl Display for TagCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<30} {:>7} {:>5} {:>9}",
+            self.tag, self.entries, self.feeds, self.inherited
+        )
+    }
+}
+
+impl TagCount {
+    /// The function `list` counts every tag used on an entry or a feed, most used first.
+    pub fn list(connection: &ThreadSafe<&Connection>) -> Vec<TagCount> {
+        let connection = connection.get_ref();
+        let query = "
+            SELECT tags.tag,
+                (SELECT COUNT(*) FROM entry_tags WHERE entry_tags.tag = tags.tag) AS entries,
+                (SELECT COUNT(*) FROM feed_tags WHERE feed_tags.tag = tags.tag) AS feeds,
+                (
+                    SELECT COUNT(*) FROM rss_entries
+                    JOIN feed_tags ON feed_tags.rss_url = rss_entries.rss_url
+                    WHERE feed_tags.tag = tags.tag
+                    AND NOT EXISTS (
+                        SELECT 1 FROM entry_tags
+                        WHERE entry_tags.hash = rss_entries.hash AND entry_tags.tag = tags.tag
+                    )
+                ) AS inherited
+            FROM (SELECT tag FROM entry_tags UNION SELECT tag FROM feed_tags) AS tags
+            ORDER BY entries + inherited DESC, tags.tag
+        ";
+        let mut statement = connection.prepare(query).expect("Statement is not built");
+        let mut counts = vec![];
+        while let Ok(State::Row) = statement.next() {
+            counts.push(TagCount {
+                tag: statement.read::<String, _>("tag").unwrap(),
+                entries: statement.read::<i64, _>("entries").unwrap_or(0) as u64,
+                feeds: statement.read::<i64, _>("feeds").unwrap_or(0) as u64,
+                inherited: statement.read::<i64, _>("inherited").unwrap_or(0) as u64,
+            })
+        }
+        counts
+    }
+}
+
+/// The function `normalize_tag` trims and lowercases a tag so `To-Read` and `to-read ` are the same
+/// tag.
+///
+/// Returns:
+///
+/// the normalized tag, or `None` if it is empty or contains a comma, which separates tags in
+/// filters.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() || tag.contains(',') {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::traits::CrudAble,
+        db::{open_database, RssEntity, RssEntry},
+    };
+
+    const A: &str = "https://a.test/rss";
+    const B: &str = "https://b.test/rss";
+
+    fn entry<'c>(
+        connection: &ThreadSafe<&'c Connection>,
+        title: &str,
+        rss_url: &str,
+    ) -> RssEntry<'c> {
+        let rss_entry = RssEntry {
+            title: title.to_string(),
+            link: format!("{}/{}", rss_url, title.len()),
+            publish_date: String::from("2024-01-01 00:00:00"),
+            rss_url: rss_url.to_string(),
+            description: String::new(),
+            is_read: false,
+            is_starred: false,
+            connection: Some(connection.clone()),
+        };
+        rss_entry.save().unwrap();
+        rss_entry
+    }
+
+    #[test]
+    fn tags_are_trimmed_lowercased_and_free_of_commas() {
+        assert_eq!(normalize_tag(" To-Read "), Some(String::from("to-read")));
+        assert_eq!(normalize_tag("ÉTÉ"), Some(String::from("été")));
+        assert_eq!(normalize_tag("a,b"), None);
+        assert_eq!(normalize_tag(" \t"), None);
+    }
+
+    #[test]
+    fn entry_tags_are_added_once_and_removed() {
+        let connection = open_database(":memory:").unwrap();
+        let connection = ThreadSafe::new(&connection);
+        let rust = entry(&connection, "Rust news", A);
+        let bread = entry(&connection, "Bread", B);
+
+        rust.add_tag("Later").unwrap();
+        rust.add_tag("later ").unwrap();
+        rust.add_tag("x,y").unwrap();
+        rust.add_tag("News").unwrap();
+        bread.add_tag("later").unwrap();
+        assert_eq!(rust.tags(&connection), ["later", "news"]);
+
+        rust.remove_tag("LATER").unwrap();
+        rust.remove_tag("unknown").unwrap();
+        assert_eq!(rust.tags(&connection), ["news"]);
+        assert_eq!(bread.tags(&connection), ["later"]);
+    }
+
+    #[test]
+    fn feed_tags_are_inherited_by_their_entries() {
+        let connection = open_database(":memory:").unwrap();
+        let connection = ThreadSafe::new(&connection);
+        let rust = entry(&connection, "Rust news", A);
+        let cargo = entry(&connection, "Cargo tips", A);
+        let bread = entry(&connection, "Bread", B);
+
+        RssEntity::add_tag(&connection, A, "Dev").unwrap();
+        RssEntity::add_tag(&connection, A, "dev").unwrap();
+        RssEntity::add_tag(&connection, A, "").unwrap();
+        rust.add_tag("dev").unwrap();
+        assert_eq!(RssEntity::tags(&connection, A), ["dev"]);
+        assert!(RssEntity::tags(&connection, B).is_empty());
+        // a tag found both on the entry and on its feed is listed once
+        assert_eq!(rust.tags(&connection), ["dev"]);
+        assert_eq!(cargo.tags(&connection), ["dev"]);
+        assert!(bread.tags(&connection).is_empty());
+
+        RssEntity::remove_tag(&connection, A, " DEV").unwrap();
+        assert!(RssEntity::tags(&connection, A).is_empty());
+        assert_eq!(rust.tags(&connection), ["dev"]);
+        assert!(cargo.tags(&connection).is_empty());
+    }
+
+    #[test]
+    fn the_listing_counts_direct_and_inherited_uses() {
+        let connection = open_database(":memory:").unwrap();
+        let connection = ThreadSafe::new(&connection);
+        assert!(TagCount::list(&connection).is_empty());
+
+        let rust = entry(&connection, "Rust news", A);
+        entry(&connection, "Cargo tips", A);
+        let bread = entry(&connection, "Bread", B);
+        RssEntity::add_tag(&connection, A, "dev").unwrap();
+        rust.add_tag("dev").unwrap();
+        rust.add_tag("later").unwrap();
+        bread.add_tag("later").unwrap();
+        RssEntity::add_tag(&connection, B, "food").unwrap();
+
+        let counts = TagCount::list(&connection);
+        let count = |tag: &str, entries, feeds, inherited| TagCount {
+            tag: tag.to_string(),
+            entries,
+            feeds,
+            inherited,
+        };
+        // `dev` is on one entry and inherited by the other one, `food` has no entry of its own
+        assert_eq!(
+            counts,
+            [
+                count("dev", 1, 1, 1),
+                count("later", 2, 0, 0),
+                count("food", 0, 1, 1),
+            ]
+        );
+        assert_eq!(
+            counts[0].to_string(),
+            "dev                                  1     1         1"
+        );
+    }
+}
@@ -1,9 +1,16 @@
-use sqlite::{open, Connection, Error};
+use sqlite::{open, Connection, Error, State};
+use std::path::Path;
 use std::result::Result;
 pub mod entities;
 
 pub use entities::*;
 
+/// Path of the database the command line and the terminal interface use.
+pub const DATABASE_PATH: &str = "./rss_rust.sqlite3";
+
+/// How long a statement waits for another connection to release the database before failing.
+const BUSY_TIMEOUT_MS: usize = 5000;
+
 /// The function `get_database_connection` returns a connection to a SQLite database and asserts the
 /// existence of required tables.
 ///
@@ -11,18 +18,39 @@ pub use entities::*;
 ///
 /// The function `get_database_connection` returns a `Connection` object.
 pub fn get_database_connection() -> Connection {
-    let connection = open("./rss_rust.sqlite3").unwrap();
-    let _ = assert_tables(&connection).expect("DB tables assertion failed");
-    return connection;
+    open_database(DATABASE_PATH).expect("DB tables assertion failed")
 }
 
-/// The function `assert_tables` creates two tables (`rss_table` and `rss_entries`) if they do not
-/// already exist in the database.
+/// The function `open_database` opens the SQLite database at `path` and creates or upgrades its
+/// tables, see `assert_tables`. Statements wait for other connections to the same file, such as
+/// the background sync of the terminal interface, instead of failing at once.
+///
+/// Arguments:
+///
+/// * `path`: The path of the database file, `:memory:` for a database that only lives as long as
+///   the connection.
+///
+/// Returns:
+///
+/// The function `open_database` returns a `Result<Connection, Error>`.
+pub fn open_database<T: AsRef<Path>>(path: T) -> Result<Connection, Error> {
+    let mut connection = open(path)?;
+    connection.set_busy_timeout(BUSY_TIMEOUT_MS)?;
+    assert_tables(&connection)?;
+    Ok(connection)
+}
+
+/// The function `assert_tables` creates the tables (`rss_table`, `rss_entries`, `entry_tags`,
+/// `entry_categories`, `entry_contents`, `hooks`, `hook_failures`, `notification_sinks`, `rules`, `http_settings`,
+/// `feed_auth`, `secret_keys`, `enclosures`, `episodes`, `download_settings`, `entry_media`,
+/// `feed_icons`, `feed_tags`, `saved_searches`, `retention_policies`, `pruned_entries`,
+/// `smtp_settings` and `mailed_entries`) if they do not already exist in the database, and adds the
+/// columns introduced after a table was first created.
 ///
 /// Arguments:
 ///
 /// * `connection`: The `connection` parameter is of type `&Connection`, which represents a connection
-/// to a database. It is used to execute SQL statements and interact with the database.
+///   to a database. It is used to execute SQL statements and interact with the database.
 ///
 /// Returns:
 ///
@@ -48,5 +76,355 @@ fn assert_tables(connection: &Connection) -> Result<(), Error> {
         );
     ",
     )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS hooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile VARCHAR(250),
+            command TEXT NOT NULL,
+            timeout_secs INTEGER NOT NULL DEFAULT 30
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS hook_failures (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hook_id INTEGER NOT NULL,
+            entry_hash VARCHAR(250) NOT NULL,
+            error TEXT NOT NULL,
+            failed_at DATE DEFAULT CURRENT_TIMESTAMP
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS notification_sinks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind VARCHAR(20) NOT NULL,
+            url TEXT NOT NULL,
+            profile VARCHAR(250),
+            rss_url VARCHAR(250),
+            max_retries INTEGER NOT NULL DEFAULT 3
+        );
+        ",
+    )?;
+
+    add_column_if_missing(
+        connection,
+        "rss_entries",
+        "is_read",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        connection,
+        "rss_entries",
+        "is_starred",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(connection, "rss_entries", "description", "TEXT")?;
+    add_column_if_missing(connection, "rss_entries", "rss_url", "VARCHAR(250)")?;
+    add_column_if_missing(connection, "rss_entries", "author", "TEXT")?;
+    add_column_if_missing(
+        connection,
+        "rss_table",
+        "full_text",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(connection, "rss_table", "encoding", "VARCHAR(50)")?;
+    add_column_if_missing(
+        connection,
+        "rss_table",
+        "auto_download",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    for column in [
+        "title",
+        "site_link",
+        "description",
+        "language",
+        "image_url",
+        "generator",
+        "last_build_date",
+        "display_title",
+        "ttl",
+    ] {
+        add_column_if_missing(connection, "rss_table", column, "TEXT")?;
+    }
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS entry_contents (
+            hash VARCHAR(250) PRIMARY KEY,
+            html TEXT NOT NULL,
+            text TEXT NOT NULL,
+            word_count INTEGER NOT NULL,
+            reading_minutes INTEGER NOT NULL,
+            fetched_at DATE DEFAULT CURRENT_TIMESTAMP
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS entry_tags (
+            hash VARCHAR(250) NOT NULL,
+            tag VARCHAR(250) NOT NULL,
+            PRIMARY KEY (hash, tag)
+        );
+        CREATE TABLE IF NOT EXISTS entry_categories (
+            hash VARCHAR(250) NOT NULL,
+            category VARCHAR(250) NOT NULL,
+            PRIMARY KEY (hash, category)
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS feed_tags (
+            rss_url VARCHAR(250) NOT NULL,
+            tag VARCHAR(250) NOT NULL,
+            PRIMARY KEY (rss_url, tag)
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile VARCHAR(250),
+            rss_url VARCHAR(250),
+            field VARCHAR(20) NOT NULL,
+            operator VARCHAR(20) NOT NULL,
+            value TEXT NOT NULL,
+            negate INTEGER NOT NULL DEFAULT 0,
+            action VARCHAR(20) NOT NULL,
+            tag VARCHAR(250)
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS http_settings (
+            rss_url VARCHAR(250) PRIMARY KEY,
+            connect_timeout_secs INTEGER,
+            read_timeout_secs INTEGER,
+            user_agent VARCHAR(250),
+            proxy VARCHAR(250),
+            ca_certs TEXT,
+            compression INTEGER,
+            max_body_bytes INTEGER
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS feed_auth (
+            rss_url VARCHAR(250) PRIMARY KEY,
+            username VARCHAR(250),
+            password TEXT,
+            token TEXT,
+            headers TEXT,
+            cookie_header TEXT
+        );
+        CREATE TABLE IF NOT EXISTS secret_keys (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            salt TEXT NOT NULL,
+            check_value TEXT NOT NULL
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS enclosures (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hash VARCHAR(250) NOT NULL,
+            url TEXT NOT NULL,
+            length INTEGER NOT NULL DEFAULT 0,
+            mime_type VARCHAR(100)
+        );
+        CREATE INDEX IF NOT EXISTS enclosures_hash ON enclosures (hash);
+        CREATE TABLE IF NOT EXISTS episodes (
+            hash VARCHAR(250) PRIMARY KEY,
+            duration_secs INTEGER,
+            episode INTEGER,
+            season INTEGER,
+            image TEXT,
+            explicit INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS download_settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            directory TEXT NOT NULL,
+            filename_template TEXT NOT NULL,
+            concurrency INTEGER NOT NULL,
+            max_total_bytes INTEGER,
+            max_files INTEGER,
+            max_file_bytes INTEGER
+        );
+        ",
+    )?;
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS entry_media (
+            hash VARCHAR(250) PRIMARY KEY,
+            title TEXT,
+            description TEXT,
+            contents TEXT,
+            thumbnails TEXT,
+            star_average REAL,
+            star_count INTEGER,
+            star_max REAL,
+            views INTEGER,
+            favorites INTEGER,
+            credits TEXT
+        );
+        ",
+    )?;
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS feed_icons (
+            rss_url VARCHAR(250) PRIMARY KEY,
+            source_url TEXT NOT NULL,
+            mime_type VARCHAR(100) NOT NULL,
+            data BLOB NOT NULL,
+            fetched_at DATE DEFAULT CURRENT_TIMESTAMP,
+            error TEXT
+        );
+        ",
+    )?;
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS saved_searches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name VARCHAR(250) NOT NULL UNIQUE,
+            query TEXT NOT NULL,
+            profile VARCHAR(250) NOT NULL,
+            rss_url VARCHAR(250) NOT NULL,
+            tags TEXT NOT NULL,
+            unread_only INTEGER NOT NULL DEFAULT 0,
+            starred_only INTEGER NOT NULL DEFAULT 0,
+            since VARCHAR(50) NOT NULL,
+            until VARCHAR(50) NOT NULL,
+            sort VARCHAR(20) NOT NULL,
+            result_limit INTEGER NOT NULL
+        );
+        ",
+    )?;
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS retention_policies (
+            rss_url VARCHAR(250) PRIMARY KEY,
+            keep_newest INTEGER,
+            max_age_days INTEGER,
+            keep_starred INTEGER,
+            keep_tagged INTEGER
+        );
+        ",
+    )?;
+
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS pruned_entries (
+            hash VARCHAR(250) PRIMARY KEY,
+            pruned_at DATE DEFAULT CURRENT_TIMESTAMP
+        );
+        ",
+    )?;
+    connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS smtp_settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            host VARCHAR(250) NOT NULL,
+            port INTEGER,
+            security VARCHAR(20) NOT NULL,
+            username VARCHAR(250),
+            password TEXT,
+            from_address VARCHAR(250) NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS mailed_entries (
+            recipient VARCHAR(250) NOT NULL,
+            hash VARCHAR(250) NOT NULL,
+            mailed_at DATE DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (recipient, hash)
+        );
+        ",
+    )?;
+    rename_column_if_present(connection, "feed_auth", "cookies", "cookie_header")?;
+    add_column_if_missing(connection, "entry_media", "credits", "TEXT")?;
+    add_column_if_missing(connection, "enclosures", "file_path", "TEXT")?;
+    add_column_if_missing(connection, "enclosures", "sha256", "VARCHAR(64)")?;
+    add_column_if_missing(connection, "enclosures", "downloaded_at", "DATE")?;
+    add_column_if_missing(connection, "enclosures", "download_error", "TEXT")?;
+    add_column_if_missing(
+        connection,
+        "saved_searches",
+        "author",
+        "TEXT NOT NULL DEFAULT ''",
+    )?;
+    add_column_if_missing(
+        connection,
+        "saved_searches",
+        "categories",
+        "TEXT NOT NULL DEFAULT ''",
+    )?;
+    Ok(())
+}
+
+/// The function `rename_column_if_present` renames `column` of `table` to `name` in a database
+/// created by an older version, where the column still has its former name.
+fn rename_column_if_present(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let mut statement = connection.prepare(format!("PRAGMA table_info({})", table))?;
+    while let State::Row = statement.next()? {
+        if statement.read::<String, _>("name")? == column {
+            return connection.execute(format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                table, column, name
+            ));
+        }
+    }
     Ok(())
 }
+
+/// The function `add_column_if_missing` adds `column` to `table` when a database created by an older
+/// version does not have it yet.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` to the database holding `table`.
+/// * `table`: The name of the table to alter.
+/// * `column`: The name of the column to add.
+/// * `definition`: The SQL type and constraints of the column.
+///
+/// Returns:
+///
+/// The function `add_column_if_missing` returns a `Result<(), Error>`.
+fn add_column_if_missing(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), Error> {
+    let mut statement = connection.prepare(format!("PRAGMA table_info({})", table))?;
+    while let State::Row = statement.next()? {
+        if statement.read::<String, _>("name")? == column {
+            return Ok(());
+        }
+    }
+    connection.execute(format!(
+        "ALTER TABLE {} ADD COLUMN {} {}",
+        table, column, definition
+    ))
+}
@@ -0,0 +1,799 @@
+use chrono::Utc;
+use scraper::Html;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{
+    core::{
+        structs::{EntrySort, GetManyOptions},
+        template::{self, escape_html},
+    },
+    db::{blog::RssEntry, rss_entity::RssEntity},
+    storage::Storage,
+};
+
+/// The number of characters of the summary of an entry when none is given.
+pub const DEFAULT_SUMMARY_LENGTH: usize = 280;
+
+/// The document a Markdown digest is rendered into, see `Digest::render` for the placeholders.
+pub const MARKDOWN_TEMPLATE: &str = "# {title}
+
+_{entry_count} entries from {feed_count} feeds, {since} to {until}_
+
+{content}";
+
+/// The document an HTML digest is rendered into. The styles are inline so the file can be sent or
+/// uploaded on its own.
+pub const HTML_TEMPLATE: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; max-width: 46rem; margin: 2rem auto; padding: 0 1rem; color: #222; line-height: 1.5; }
+h1 { margin-bottom: 0; }
+h2 { border-bottom: 1px solid #ddd; padding-bottom: .2rem; margin-top: 2.5rem; }
+h3 { margin-bottom: .3rem; }
+ul { padding-left: 1.2rem; }
+li { margin-bottom: .8rem; }
+a { color: #0b57d0; }
+.meta, time { color: #666; font-size: .9rem; }
+.summary { margin: .2rem 0 0; }
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p class=\"meta\">{entry_count} entries from {feed_count} feeds, {since} to {until}</p>
+{content}
+</body>
+</html>
+";
+
+/// The `DigestFormat` enum is the markup a digest is rendered in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl DigestFormat {
+    /// The function `template` returns the built-in document of the format.
+    pub fn template(&self) -> &'static str {
+        match self {
+            DigestFormat::Markdown => MARKDOWN_TEMPLATE,
+            DigestFormat::Html => HTML_TEMPLATE,
+        }
+    }
+}
+
+impl Display for DigestFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DigestFormat::Markdown => write!(f, "markdown"),
+            DigestFormat::Html => write!(f, "html"),
+        }
+    }
+}
+
+impl FromStr for DigestFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "markdown" | "md" => Ok(DigestFormat::Markdown),
+            "html" => Ok(DigestFormat::Html),
+            _ => Err(format!("Unknown digest format {}", format)),
+        }
+    }
+}
+
+/// The `DigestOptions` struct selects the entries of a digest.
+///
+/// Properties:
+///
+/// * `title`: The heading of the digest.
+/// * `profile`: Only the feeds of this profile, empty for every profile.
+/// * `rss_url`: Only this feed, empty for every feed.
+/// * `since`: The start of the time window, in the format of the `publish_date` column.
+/// * `until`: The end of the time window, empty for now.
+/// * `summary_length`: The maximum number of characters of the summary of an entry, 0 to leave the
+///   summaries out.
+#[derive(Debug, Clone)]
+pub struct DigestOptions {
+    pub title: String,
+    pub profile: String,
+    pub rss_url: String,
+    pub since: String,
+    pub until: String,
+    pub summary_length: usize,
+}
+
+impl Default for DigestOptions {
+    fn default() -> Self {
+        DigestOptions {
+            title: String::from("Feed digest"),
+            profile: String::new(),
+            rss_url: String::new(),
+            since: String::new(),
+            until: String::new(),
+            summary_length: DEFAULT_SUMMARY_LENGTH,
+        }
+    }
+}
+
+/// The `DigestEntry` struct is an entry as listed in a digest.
+///
+/// Properties:
+///
+/// * `hash`: The hash of the entry.
+/// * `title`: The title of the entry.
+/// * `link`: The link of the entry.
+/// * `publish_date`: When the entry was published.
+/// * `summary`: The plain text start of the description, or of the stored full text.
+#[derive(Debug, Clone)]
+pub struct DigestEntry {
+    pub hash: String,
+    pub title: String,
+    pub link: String,
+    pub publish_date: String,
+    pub summary: String,
+}
+
+/// The `DigestFeed` struct is a feed and its entries in the time window.
+///
+/// Properties:
+///
+/// * `name`: The display name of the feed.
+/// * `rss_url`: The URL of the feed.
+/// * `site_link`: The website of the feed, from its channel, if known.
+/// * `entries`: The entries of the feed, newest first.
+#[derive(Debug, Clone)]
+pub struct DigestFeed {
+    pub name: String,
+    pub rss_url: String,
+    pub site_link: String,
+    pub entries: Vec<DigestEntry>,
+}
+
+/// The `Digest` struct holds the entries of the time window, grouped by profile and feed.
+///
+/// Properties:
+///
+/// * `options`: The options the digest was collected with.
+/// * `generated_at`: When the digest was collected.
+/// * `profiles`: The feeds with entries in the window, keyed by profile and sorted by name.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub options: DigestOptions,
+    pub generated_at: String,
+    pub profiles: BTreeMap<String, Vec<DigestFeed>>,
+}
+
+impl Digest {
+    /// The function `collect` reads the entries published in the time window of `options` and
+    /// groups them by the profile and the feed they belong to. A URL subscribed under several
+    /// profiles lists its entries under each of them, or only under `options.profile` when it is
+    /// set.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: The `storage` the feeds and entries are read from.
+    /// * `options`: Which entries to collect and how long their summary is.
+    ///
+    /// Returns:
+    ///
+    /// The function `collect` returns a `Digest`, without any profile if nothing was published.
+    pub fn collect(storage: &dyn Storage, options: &DigestOptions) -> Digest {
+        let mut get_many_options = GetManyOptions::new();
+        get_many_options.set_limit(i64::MAX as u64);
+        get_many_options.set_profile(&options.profile);
+        get_many_options.set_rss_url(&options.rss_url);
+        get_many_options.set_date_range(&options.since, &options.until);
+        get_many_options.set_sort(EntrySort::Newest);
+
+        let mut feeds: HashMap<String, Vec<RssEntity>> = HashMap::new();
+        for rss_entity in storage.get_feeds() {
+            if options.profile.is_empty() || rss_entity.profile == options.profile {
+                feeds
+                    .entry(rss_entity.rss_url.clone())
+                    .or_default()
+                    .push(rss_entity);
+            }
+        }
+
+        let mut profiles: BTreeMap<String, Vec<DigestFeed>> = BTreeMap::new();
+        for rss_entry in storage.get_entries(&get_many_options) {
+            let summary = if options.summary_length == 0 {
+                String::new()
+            } else {
+                summarize(storage, &rss_entry, options.summary_length)
+            };
+            let digest_entry = DigestEntry {
+                hash: rss_entry.hash(),
+                title: rss_entry.title.clone(),
+                link: rss_entry.link.clone(),
+                publish_date: rss_entry.publish_date.clone(),
+                summary,
+            };
+            let subscriptions: Vec<Option<&RssEntity>> = match feeds.get(&rss_entry.rss_url) {
+                Some(rss_entities) => rss_entities.iter().map(Some).collect(),
+                None => vec![None],
+            };
+            for rss_entity in subscriptions {
+                let profile = rss_entity
+                    .map(|rss_entity| rss_entity.profile.clone())
+                    .unwrap_or_else(|| options.profile.clone());
+                let digest_feeds = profiles.entry(profile).or_default();
+                let position = match digest_feeds
+                    .iter()
+                    .position(|digest_feed| digest_feed.rss_url == rss_entry.rss_url)
+                {
+                    Some(position) => position,
+                    None => {
+                        digest_feeds.push(DigestFeed {
+                            name: rss_entity
+                                .map(|rss_entity| rss_entity.name())
+                                .unwrap_or_else(|| rss_entry.rss_url.clone()),
+                            rss_url: rss_entry.rss_url.clone(),
+                            site_link: rss_entity
+                                .map(|rss_entity| rss_entity.channel.link.clone())
+                                .unwrap_or_default(),
+                            entries: vec![],
+                        });
+                        digest_feeds.len() - 1
+                    }
+                };
+                digest_feeds[position].entries.push(digest_entry.clone());
+            }
+        }
+        for digest_feeds in profiles.values_mut() {
+            digest_feeds.sort_by_key(|digest_feed| digest_feed.name.to_lowercase());
+        }
+
+        Digest {
+            options: options.clone(),
+            generated_at: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            profiles,
+        }
+    }
+
+    /// The function `retain` keeps only the entries for which `keep` returns true, and drops the
+    /// feeds and profiles left without entries.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&DigestEntry) -> bool,
+    {
+        for digest_feeds in self.profiles.values_mut() {
+            for digest_feed in digest_feeds.iter_mut() {
+                digest_feed.entries.retain(&mut keep);
+            }
+            digest_feeds.retain(|digest_feed| !digest_feed.entries.is_empty());
+        }
+        self.profiles
+            .retain(|_, digest_feeds| !digest_feeds.is_empty());
+    }
+
+    /// The function `hashes` returns the hashes of the entries in the digest, once even for an
+    /// entry listed under several profiles.
+    pub fn hashes(&self) -> Vec<String> {
+        let hashes: BTreeSet<&String> = self
+            .profiles
+            .values()
+            .flatten()
+            .flat_map(|digest_feed| digest_feed.entries.iter())
+            .map(|entry| &entry.hash)
+            .collect();
+        hashes.into_iter().cloned().collect()
+    }
+
+    /// The function `entry_count` returns the number of entries in the digest.
+    pub fn entry_count(&self) -> usize {
+        self.hashes().len()
+    }
+
+    /// The function `feed_count` returns the number of feeds with entries in the digest.
+    pub fn feed_count(&self) -> usize {
+        self.profiles
+            .values()
+            .flatten()
+            .map(|digest_feed| &digest_feed.rss_url)
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// The function `render` writes the digest into a document template.
+    ///
+    /// Placeholders:
+    ///
+    /// * `{title}`: The title of the digest.
+    /// * `{since}` and `{until}`: The time window.
+    /// * `{generated_at}`: When the digest was collected.
+    /// * `{entry_count}` and `{feed_count}`: The number of entries and feeds listed.
+    /// * `{content}`: The entries grouped by profile and feed, in the markup of `format`.
+    ///
+    /// Arguments:
+    ///
+    /// * `format`: The markup of the content, the other values are escaped for it.
+    /// * `template`: The document, `None` for the built-in one of `format`.
+    ///
+    /// Returns:
+    ///
+    /// the rendered document.
+    pub fn render(&self, format: DigestFormat, template: Option<&str>) -> String {
+        let escape = |text: &str| match format {
+            DigestFormat::Markdown => escape_markdown(text),
+            DigestFormat::Html => escape_html(text),
+        };
+        let content = match format {
+            DigestFormat::Markdown => self.markdown_content(),
+            DigestFormat::Html => self.html_content(),
+        };
+        let until = if self.options.until.is_empty() {
+            self.generated_at.as_str()
+        } else {
+            self.options.until.as_str()
+        };
+        let since = if self.options.since.is_empty() {
+            "the first entry"
+        } else {
+            self.options.since.as_str()
+        };
+        let values = [
+            ("title", escape(&self.options.title)),
+            ("since", escape(since)),
+            ("until", escape(until)),
+            ("generated_at", escape(&self.generated_at)),
+            ("entry_count", self.entry_count().to_string()),
+            ("feed_count", self.feed_count().to_string()),
+            ("content", content),
+        ];
+        template::fill(template.unwrap_or(format.template()), &values)
+    }
+
+    fn markdown_content(&self) -> String {
+        if self.profiles.is_empty() {
+            return String::from("Nothing was published in this period.\n");
+        }
+        let mut content = String::new();
+        for (profile, digest_feeds) in self.profiles.iter() {
+            content.push_str(&format!("## {}\n\n", escape_markdown(profile)));
+            for digest_feed in digest_feeds {
+                content.push_str(&format!(
+                    "### {}\n\n",
+                    markdown_link(&digest_feed.name, &digest_feed.site_link)
+                ));
+                for entry in digest_feed.entries.iter() {
+                    content.push_str(&format!(
+                        "- {} — {}\n",
+                        markdown_link(&entry.title, &entry.link),
+                        entry.publish_date
+                    ));
+                    if !entry.summary.is_empty() {
+                        content.push_str(&format!("  {}\n", escape_markdown(&entry.summary)));
+                    }
+                }
+                content.push('\n');
+            }
+        }
+        content
+    }
+
+    fn html_content(&self) -> String {
+        if self.profiles.is_empty() {
+            return String::from("<p>Nothing was published in this period.</p>\n");
+        }
+        let mut content = String::new();
+        for (profile, digest_feeds) in self.profiles.iter() {
+            content.push_str(&format!("<h2>{}</h2>\n", escape_html(profile)));
+            for digest_feed in digest_feeds {
+                content.push_str(&format!(
+                    "<h3>{}</h3>\n",
+                    html_link(&digest_feed.name, &digest_feed.site_link)
+                ));
+                content.push_str("<ul>\n");
+                for entry in digest_feed.entries.iter() {
+                    content.push_str(&format!(
+                        "<li>{} <time>{}</time>",
+                        html_link(&entry.title, &entry.link),
+                        escape_html(&entry.publish_date)
+                    ));
+                    if !entry.summary.is_empty() {
+                        content.push_str(&format!(
+                            "<p class=\"summary\">{}</p>",
+                            escape_html(&entry.summary)
+                        ));
+                    }
+                    content.push_str("</li>\n");
+                }
+                content.push_str("</ul>\n");
+            }
+        }
+        content
+    }
+}
+
+/// The function `summarize` returns the plain text start of the description of an entry, or of its
+/// stored full text when the description is empty, cut after a word at `length` characters.
+fn summarize(storage: &dyn Storage, rss_entry: &RssEntry, length: usize) -> String {
+    let mut text = plain_text(&rss_entry.description);
+    if text.is_empty() {
+        text = storage
+            .get_content(&rss_entry.hash())
+            .map(|entry_content| plain_text(&entry_content.text))
+            .unwrap_or_default();
+    }
+    if text.chars().count() <= length {
+        return text;
+    }
+    // one more character tells whether the last word is whole
+    let cut: String = text.chars().take(length + 1).collect();
+    let cut = match cut.rsplit_once(' ') {
+        Some((words, _)) if !words.is_empty() => words.to_string(),
+        _ => text.chars().take(length).collect(),
+    };
+    format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_ascii_punctuation())
+    )
+}
+
+/// The function `plain_text` returns the text of an HTML fragment on a single line.
+fn plain_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let text: Vec<&str> = fragment.root_element().text().collect();
+    text.join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The function `escape_markdown` escapes the characters that would start Markdown formatting or
+/// links, so titles are shown as they are.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The function `web_link` returns `url` trimmed if it is an `http` or `https` URL, the only links
+/// a digest renders, so a feed cannot slip a `javascript:` link into it.
+fn web_link(url: &str) -> Option<&str> {
+    let url = url.trim();
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme)?;
+    if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") {
+        Some(url)
+    } else {
+        None
+    }
+}
+
+/// The function `markdown_link` returns `text` escaped, as a link to `url` when it is a web link.
+/// The URL is written between `<` and `>`, with the characters that would end it percent-encoded.
+fn markdown_link(text: &str, url: &str) -> String {
+    let url = match web_link(url) {
+        Some(url) => url,
+        None => return escape_markdown(text),
+    };
+    let mut destination = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            '<' | '>' | '\\' => destination.push_str(&format!("%{:02X}", c as u32)),
+            c if c.is_whitespace() || c.is_control() => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    destination.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            c => destination.push(c),
+        }
+    }
+    format!("[{}](<{}>)", escape_markdown(text), destination)
+}
+
+/// The function `html_link` returns `text` escaped, as a link to `url` when it is a web link.
+fn html_link(text: &str, url: &str) -> String {
+    match web_link(url) {
+        Some(url) => format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(text)),
+        None => escape_html(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::open_database, storage::SqliteStorage};
+    use sqlite::Connection;
+    use thread_safe::ThreadSafe;
+
+    const A: &str = "https://a.test/rss";
+    const B: &str = "https://b.test/rss";
+    const C: &str = "https://c.test/rss";
+
+    /// The function `hash` returns the hash an entry with `title` is stored under.
+    fn hash(title: &str) -> String {
+        RssEntry {
+            title: title.to_string(),
+            link: String::new(),
+            publish_date: String::new(),
+            rss_url: String::new(),
+            description: String::new(),
+            is_read: false,
+            is_starred: false,
+            connection: None,
+        }
+        .hash()
+    }
+
+    /// The function `database` subscribes to `A`, named "zeta", and to `B`, named "Alpha" with a
+    /// site link, under "work" and to `C` under "home". The entry of `D` belongs to no feed.
+    fn database() -> Connection {
+        let connection = open_database(":memory:").unwrap();
+        connection
+            .execute(format!(
+                "INSERT INTO rss_table (rss_url, profile, title, site_link) VALUES
+                    ('{a}', 'work', 'zeta', NULL),
+                    ('{b}', 'work', 'Alpha', 'https://b.test/'),
+                    ('{c}', 'home', 'Cooking', NULL);",
+                a = A,
+                b = B,
+                c = C
+            ))
+            .unwrap();
+        for (title, date, rss_url, description) in [
+            ("Old news", "2023-12-31 23:00:00", A, "<p>Too old</p>"),
+            (
+                "Rust 2024",
+                "2024-01-02 10:00:00",
+                A,
+                "<p>The <b>new</b> edition</p>",
+            ),
+            ("Cargo tips", "2024-01-03 10:00:00", A, ""),
+            ("Bread", "2024-01-02 12:00:00", C, "Flour, water and salt"),
+            ("Weekly", "2024-01-04 08:00:00", B, "Links of the week"),
+            ("Stray", "2024-01-05 08:00:00", "https://d.test/rss", ""),
+        ] {
+            let mut statement = connection
+                .prepare(
+                    "INSERT INTO rss_entries (hash, title, publish_date, link, rss_url, description)
+                    VALUES (?, ?, ?, ?, ?, ?)",
+                )
+                .unwrap();
+            let link = format!("https://x.test/{}", title.len());
+            statement
+                .bind(
+                    &[
+                        hash(title).as_str(),
+                        title,
+                        date,
+                        link.as_str(),
+                        rss_url,
+                        description,
+                    ][..],
+                )
+                .unwrap();
+            statement.next().unwrap();
+        }
+        connection
+            .execute(format!(
+                "INSERT INTO entry_contents (hash, html, text, word_count, reading_minutes)
+                VALUES ('{}', '', 'Use cargo tree to read the dependency graph', 8, 1)",
+                hash("Cargo tips")
+            ))
+            .unwrap();
+        connection
+    }
+
+    fn options(since: &str) -> DigestOptions {
+        DigestOptions {
+            since: since.to_string(),
+            ..DigestOptions::default()
+        }
+    }
+
+    /// The function `digest` builds a digest of one entry by hand, for the rendering tests.
+    fn digest(name: &str, site_link: &str, entry: DigestEntry) -> Digest {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(
+            String::from("work"),
+            vec![DigestFeed {
+                name: name.to_string(),
+                rss_url: A.to_string(),
+                site_link: site_link.to_string(),
+                entries: vec![entry],
+            }],
+        );
+        Digest {
+            options: options("2024-01-01 00:00:00"),
+            generated_at: String::from("2024-01-08 00:00:00"),
+            profiles,
+        }
+    }
+
+    fn entry(title: &str, link: &str, summary: &str) -> DigestEntry {
+        DigestEntry {
+            hash: hash(title),
+            title: title.to_string(),
+            link: link.to_string(),
+            publish_date: String::from("2024-01-02 10:00:00"),
+            summary: summary.to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_are_grouped_by_profile_then_feed() {
+        let connection = database();
+        let storage = SqliteStorage::new(&ThreadSafe::new(&connection));
+        let mut digest = Digest::collect(&storage, &options("2024-01-01 00:00:00"));
+
+        let layout: Vec<String> = digest
+            .profiles
+            .iter()
+            .flat_map(|(profile, feeds)| {
+                feeds.iter().map(move |feed| {
+                    let titles: Vec<&str> = feed.entries.iter().map(|e| e.title.as_str()).collect();
+                    format!("{} / {}: {}", profile, feed.name, titles.join(", "))
+                })
+            })
+            .collect();
+        // feeds are sorted by name whatever their case, entries newest first, and an entry of
+        // an unknown feed is listed under its URL with no profile
+        assert_eq!(
+            layout,
+            [
+                " / https://d.test/rss: Stray",
+                "home / Cooking: Bread",
+                "work / Alpha: Weekly",
+                "work / zeta: Cargo tips, Rust 2024",
+            ]
+        );
+        assert_eq!(digest.profiles["work"][0].site_link, "https://b.test/");
+        assert_eq!((digest.entry_count(), digest.feed_count()), (5, 4));
+
+        digest.retain(|entry| entry.title != "Bread" && entry.title != "Stray");
+        assert_eq!(digest.profiles.keys().collect::<Vec<_>>(), ["work"]);
+        assert_eq!(digest.hashes().len(), 3);
+
+        let options = DigestOptions {
+            profile: String::from("home"),
+            until: String::from("2024-01-02 11:00:00"),
+            ..options("2024-01-01 00:00:00")
+        };
+        assert_eq!(Digest::collect(&storage, &options).entry_count(), 0);
+    }
+
+    #[test]
+    fn a_url_in_two_profiles_is_listed_under_each() {
+        let connection = database();
+        connection
+            .execute(format!(
+                "INSERT INTO rss_table (rss_url, profile, title) VALUES ('{}', 'home', 'Weekly home')",
+                B
+            ))
+            .unwrap();
+        let storage = SqliteStorage::new(&ThreadSafe::new(&connection));
+        let layout = |profile: &str| {
+            let options = DigestOptions {
+                profile: profile.to_string(),
+                rss_url: B.to_string(),
+                ..options("2024-01-01 00:00:00")
+            };
+            let digest = Digest::collect(&storage, &options);
+            let layout: Vec<String> = digest
+                .profiles
+                .iter()
+                .flat_map(|(profile, feeds)| {
+                    feeds
+                        .iter()
+                        .map(move |feed| format!("{} / {}", profile, feed.name))
+                })
+                .collect();
+            (layout, digest.entry_count(), digest.feed_count())
+        };
+
+        assert_eq!(
+            layout(""),
+            (
+                vec![
+                    String::from("home / Weekly home"),
+                    String::from("work / Alpha")
+                ],
+                1,
+                1
+            )
+        );
+        assert_eq!(layout("work"), (vec![String::from("work / Alpha")], 1, 1));
+        assert_eq!(
+            layout("home"),
+            (vec![String::from("home / Weekly home")], 1, 1)
+        );
+    }
+
+    #[test]
+    fn summaries_are_plain_text_cut_after_a_word() {
+        let connection = database();
+        let storage = SqliteStorage::new(&ThreadSafe::new(&connection));
+        let summaries = |summary_length| {
+            let options = DigestOptions {
+                rss_url: A.to_string(),
+                summary_length,
+                ..options("2024-01-01 00:00:00")
+            };
+            let digest = Digest::collect(&storage, &options);
+            digest.profiles["work"][0]
+                .entries
+                .iter()
+                .map(|entry| entry.summary.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // the stored full text stands in for an empty description
+        assert_eq!(
+            summaries(DEFAULT_SUMMARY_LENGTH),
+            [
+                "Use cargo tree to read the dependency graph",
+                "The new edition"
+            ]
+        );
+        assert_eq!(summaries(14), ["Use cargo tree…", "The new…"]);
+        // a first word longer than the summary is cut inside it
+        assert_eq!(summaries(2), ["Us…", "Th…"]);
+        assert_eq!(summaries(0), ["", ""]);
+    }
+
+    #[test]
+    fn markdown_is_escaped_and_links_are_kept_whole() {
+        let digest = digest(
+            "Blog *one*",
+            "javascript:alert(1)",
+            entry(
+                "[Rust] 1_000 <tips>",
+                "https://a.test/a post (draft)>",
+                "Use `#` and |",
+            ),
+        );
+        let markdown = digest.render(DigestFormat::Markdown, None);
+        assert!(markdown.contains("### Blog \\*one\\*\n"));
+        assert!(markdown.contains(
+            "- [\\[Rust\\] 1\\_000 \\<tips\\>](<https://a.test/a%20post%20(draft)%3E>) — \
+             2024-01-02 10:00:00\n  Use \\`\\#\\` and \\|\n"
+        ));
+        assert!(!markdown.contains("javascript"));
+    }
+
+    #[test]
+    fn html_is_escaped_and_only_web_links_are_followed() {
+        let digest = digest(
+            "A & B",
+            "HTTPS://b.test/?a=1&b=\"2\"",
+            entry("<script>x</script>", " data:text/html,hi", "1 < 2"),
+        );
+        let html = digest.render(DigestFormat::Html, Some("{title}|{content}"));
+        assert!(html.starts_with("Feed digest|<h2>work</h2>\n"));
+        assert!(html.contains(
+            "<h3><a href=\"HTTPS://b.test/?a=1&amp;b=&quot;2&quot;\">A &amp; B</a></h3>"
+        ));
+        assert!(html.contains(
+            "<li>&lt;script&gt;x&lt;/script&gt; <time>2024-01-02 10:00:00</time>\
+             <p class=\"summary\">1 &lt; 2</p></li>"
+        ));
+        assert!(!html.contains("data:"));
+
+        let empty = Digest {
+            profiles: BTreeMap::new(),
+            ..digest
+        };
+        assert!(empty
+            .render(DigestFormat::Html, None)
+            .contains("<p>Nothing was published in this period.</p>"));
+    }
+}
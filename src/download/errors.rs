@@ -0,0 +1,62 @@
+use std::fmt;
+
+use crate::http::errors::HttpError;
+
+#[derive(Debug)]
+pub enum DownloadError {
+    HttpError(HttpError),
+    IoError(std::io::Error),
+    DbError(sqlite::Error),
+    StatusError(u16),
+    Incomplete(u64, u64),
+    ChecksumMismatch(String, String),
+}
+
+/// The `impl fmt::Display for DownloadError` block formats the reason an enclosure could not be
+/// downloaded or its local copy could not be managed.
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            DownloadError::HttpError(e) => write!(f, "{}", e),
+            DownloadError::IoError(e) => write!(f, "Writing the file failed: {}", e),
+            DownloadError::DbError(e) => write!(f, "Recording the download failed: {}", e),
+            DownloadError::StatusError(status) => {
+                write!(f, "Server responded with status {}", status)
+            }
+            DownloadError::Incomplete(expected, received) => write!(
+                f,
+                "Download stopped after {} of {} bytes, it resumes on the next run",
+                received, expected
+            ),
+            DownloadError::ChecksumMismatch(expected, actual) => write!(
+                f,
+                "Checksum mismatch, expected sha256 {} but got {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(error: reqwest::Error) -> Self {
+        DownloadError::HttpError(HttpError::from(error))
+    }
+}
+
+impl From<HttpError> for DownloadError {
+    fn from(error: HttpError) -> Self {
+        DownloadError::HttpError(error)
+    }
+}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(error: std::io::Error) -> Self {
+        DownloadError::IoError(error)
+    }
+}
+
+impl From<sqlite::Error> for DownloadError {
+    fn from(error: sqlite::Error) -> Self {
+        DownloadError::DbError(error)
+    }
+}
@@ -0,0 +1,942 @@
+pub mod errors;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use digest::Digest;
+use reqwest::{header, StatusCode};
+use sha2::Sha256;
+use sqlite::Connection;
+use std::{
+    collections::HashSet,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use thread_safe::ThreadSafe;
+use tokio::{io::AsyncWriteExt, task::JoinSet};
+
+use crate::{
+    core::{structs::GetManyOptions, template, traits::CrudAble},
+    db::{
+        blog::RssEntry, download_settings::DownloadSettings, enclosure::EntryEnclosure,
+        rss_entity::RssEntity,
+    },
+    http::{ClientPool, HttpClient},
+};
+use errors::DownloadError;
+
+/// Characters that cannot appear in a file name on common file systems.
+const FORBIDDEN_CHARACTERS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Placeholder values are cut to this many characters so paths stay within file system limits.
+const MAX_COMPONENT_LENGTH: usize = 120;
+
+/// The `DownloadJob` struct is one enclosure to download, with everything the download task needs
+/// so it does not touch the database.
+///
+/// Properties:
+///
+/// * `enclosure_id`: The id of the `EntryEnclosure` being downloaded.
+/// * `url`: The URL of the file.
+/// * `path`: The path the file is written to.
+/// * `client`: The client of the feed the enclosure belongs to.
+pub struct DownloadJob {
+    pub enclosure_id: i64,
+    pub url: String,
+    pub path: PathBuf,
+    pub client: HttpClient,
+}
+
+/// The `DownloadedFile` struct describes a completed download.
+///
+/// Properties:
+///
+/// * `path`: The path the file was written to.
+/// * `bytes`: The size of the file.
+/// * `sha256`: The hex encoded SHA-256 of the file.
+#[derive(Debug, Clone)]
+pub struct DownloadedFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// The `DownloadReport` struct sums up a batch of downloads.
+///
+/// Properties:
+///
+/// * `downloaded`: The files that were downloaded.
+/// * `errors`: The error messages of the enclosures that failed, they are retried on the next run.
+#[derive(Debug, Default, Clone)]
+pub struct DownloadReport {
+    pub downloaded: Vec<DownloadedFile>,
+    pub errors: Vec<String>,
+}
+
+/// The `RetentionReport` struct lists what `enforce_retention` deleted.
+///
+/// Properties:
+///
+/// * `removed`: The paths of the deleted files.
+/// * `freed_bytes`: The size of the deleted files.
+/// * `errors`: The files that could not be deleted.
+#[derive(Debug, Default, Clone)]
+pub struct RetentionReport {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+/// The function `download_enclosures` downloads the given enclosures with the concurrency of the
+/// settings and records the outcome of each one on its `EntryEnclosure`.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` holding the enclosures, their entries and feeds.
+/// * `clients`: The `clients` of the feeds, a download uses the client of its feed.
+/// * `settings`: The download `settings`, see `DownloadSettings::get`.
+/// * `enclosures`: The `enclosures` to download.
+///
+/// Returns:
+///
+/// a `DownloadReport` with the downloaded files and the errors.
+pub async fn download_enclosures(
+    connection: &ThreadSafe<&Connection>,
+    clients: &mut ClientPool,
+    settings: &DownloadSettings<'_>,
+    enclosures: Vec<EntryEnclosure<'_>>,
+) -> DownloadReport {
+    let mut report = DownloadReport::default();
+    let feeds = RssEntity::get_many(connection, GetManyOptions::new());
+    let mut taken = HashSet::new();
+    let mut jobs = vec![];
+    for enclosure in enclosures {
+        let rss_entry = match RssEntry::find(connection, &enclosure.hash) {
+            Some(rss_entry) => rss_entry,
+            None => continue,
+        };
+        let profile = feeds
+            .iter()
+            .find(|rss_entity| rss_entity.rss_url == rss_entry.rss_url)
+            .map(|rss_entity| rss_entity.profile.as_str())
+            .unwrap_or_default();
+        let mut client = match clients.for_feed(connection, &rss_entry.rss_url) {
+            Ok(client) => client,
+            Err(e) => {
+                report.errors.push(format!("{}: {}", enclosure.url, e));
+                continue;
+            }
+        };
+        client.max_body_bytes = settings.max_file_bytes.unwrap_or(u64::MAX);
+        let path = unique_path(
+            target_path(settings, &rss_entry, profile, &enclosure),
+            &enclosure.url,
+            &mut taken,
+        );
+        jobs.push(DownloadJob {
+            enclosure_id: enclosure.id,
+            url: enclosure.url,
+            path,
+            client,
+        });
+    }
+
+    for (job, result) in run(jobs, settings.concurrency as usize).await {
+        let recorded = match result {
+            Ok(file) => {
+                let recorded = EntryEnclosure::set_downloaded(
+                    connection,
+                    job.enclosure_id,
+                    &file.path.to_string_lossy(),
+                    &file.sha256,
+                );
+                report.downloaded.push(file);
+                recorded
+            }
+            Err(e) => {
+                report.errors.push(format!("{}: {}", job.url, e));
+                EntryEnclosure::set_download_error(connection, job.enclosure_id, &e.to_string())
+            }
+        };
+        if let Err(e) = recorded {
+            report
+                .errors
+                .push(format!("{}: {}", job.url, DownloadError::from(e)));
+        }
+    }
+    report
+}
+
+/// The function `run` downloads the jobs, at most `concurrency` at the same time.
+///
+/// Returns:
+///
+/// every job with the outcome of its download, in the order they completed.
+pub async fn run(
+    jobs: Vec<DownloadJob>,
+    concurrency: usize,
+) -> Vec<(DownloadJob, Result<DownloadedFile, DownloadError>)> {
+    let mut results = vec![];
+    let mut running = JoinSet::new();
+    for job in jobs {
+        if running.len() >= concurrency.max(1) {
+            if let Some(result) = running.join_next().await {
+                results.push(result.expect("The download task panicked"));
+            }
+        }
+        running.spawn(async move {
+            let result = download(&job).await;
+            (job, result)
+        });
+    }
+    while let Some(result) = running.join_next().await {
+        results.push(result.expect("The download task panicked"));
+    }
+    results
+}
+
+/// The function `download` downloads one file into a `.part` file next to its path and moves it
+/// in place once complete. The URL and the `ETag` or `Last-Modified` of the response are kept in a
+/// `.part.resume` file beside it, so a `.part` file left by an interrupted run of the same URL is
+/// resumed with a range request guarded by `If-Range`: a server whose file changed since sends it
+/// whole and the download starts over. The SHA-256 of the file is checked against the `Digest` or
+/// `Repr-Digest` header when the server sends one.
+///
+/// Returns:
+///
+/// the `DownloadedFile`, or a `DownloadError`. An incomplete download keeps its `.part` file.
+pub async fn download(job: &DownloadJob) -> Result<DownloadedFile, DownloadError> {
+    if let Some(parent) = job.path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let part = part_path(&job.path);
+    let validator = read_resume(&job.path)
+        .filter(|(url, _)| *url == job.url)
+        .map(|(_, validator)| validator);
+    // without a validator a leftover part may belong to another version of the file
+    let mut offset = match validator {
+        Some(_) => tokio::fs::metadata(&part)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    let mut res = job
+        .client
+        .send(request(job, offset, validator.as_deref()))
+        .await?;
+    if res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        if offset > 0 && content_range_total(&res) == Some(offset) {
+            // the previous run stopped right before moving the file in place
+            return complete(&part, &job.path, offset, hash_file(&part)?, None).await;
+        }
+        offset = 0;
+        res = job.client.send(request(job, offset, None)).await?;
+    }
+    if !res.status().is_success() {
+        return Err(DownloadError::StatusError(res.status().as_u16()));
+    }
+
+    let expected_total = if res.status() == StatusCode::PARTIAL_CONTENT {
+        content_range_total(&res)
+    } else {
+        // the server ignored the range or the file changed, it starts over
+        offset = 0;
+        res.content_length()
+    };
+    if offset == 0 {
+        write_resume(&job.path, &job.url, response_validator(&res).as_deref()).await?;
+    }
+    let expected_sha256 = digest_header(&res);
+    let (mut file, mut hasher) = if offset > 0 {
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&part)
+            .await?;
+        (file, hash_file(&part)?)
+    } else {
+        (tokio::fs::File::create(&part).await?, Sha256::new())
+    };
+
+    let mut read_bytes = offset;
+    while let Some(chunk) = job.client.chunk(&mut res, &mut read_bytes).await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    if let Some(expected_total) = expected_total {
+        if read_bytes != expected_total {
+            return Err(DownloadError::Incomplete(expected_total, read_bytes));
+        }
+    }
+    complete(&part, &job.path, read_bytes, hasher, expected_sha256).await
+}
+
+/// The function `enforce_retention` deletes the oldest downloaded files until they fit in the
+/// `max_total_bytes` and `max_files` budgets of the settings. Files of starred entries are kept.
+/// Deleted enclosures are not downloaded again.
+pub fn enforce_retention(
+    connection: &ThreadSafe<&Connection>,
+    settings: &DownloadSettings,
+) -> RetentionReport {
+    let mut report = RetentionReport::default();
+    let mut files = vec![];
+    for (enclosure, starred) in EntryEnclosure::downloaded(connection) {
+        let path = enclosure.file_path.clone().unwrap_or_default();
+        match fs::metadata(&path) {
+            Ok(metadata) => files.push((enclosure, starred, metadata.len())),
+            // deleted by hand, nothing left to account for
+            Err(_) => {
+                if let Err(e) = EntryEnclosure::set_pruned(connection, enclosure.id) {
+                    report.errors.push(format!("{}: {}", path, e));
+                }
+            }
+        }
+    }
+
+    let mut total_bytes: u64 = files.iter().map(|(_, _, bytes)| bytes).sum();
+    let mut count = files.len() as u64;
+    let over_budget = |total_bytes: u64, count: u64| {
+        settings
+            .max_total_bytes
+            .is_some_and(|max_total_bytes| total_bytes > max_total_bytes)
+            || settings
+                .max_files
+                .is_some_and(|max_files| count > max_files)
+    };
+    for (enclosure, starred, bytes) in files {
+        if !over_budget(total_bytes, count) {
+            break;
+        }
+        if starred {
+            continue;
+        }
+        let path = enclosure.file_path.unwrap_or_default();
+        let removed = fs::remove_file(&path)
+            .map_err(DownloadError::from)
+            .and_then(|_| {
+                EntryEnclosure::set_pruned(connection, enclosure.id).map_err(DownloadError::from)
+            });
+        match removed {
+            Ok(()) => {
+                total_bytes -= bytes;
+                count -= 1;
+                report.freed_bytes += bytes;
+                report.removed.push(path);
+            }
+            Err(e) => report.errors.push(format!("{}: {}", path, e)),
+        }
+    }
+    report
+}
+
+/// The function `verify` recomputes the SHA-256 of a downloaded file.
+///
+/// Returns:
+///
+/// whether the file still matches `sha256`, or the error raised while reading it.
+pub fn verify(path: &Path, sha256: &str) -> Result<bool, std::io::Error> {
+    Ok(format!("{:x}", hash_file(path)?.finalize()) == sha256)
+}
+
+/// The function `target_path` fills the file name template of the settings for an enclosure.
+/// Every placeholder value is made safe to use as a single path component.
+///
+/// Placeholders:
+///
+/// * `{profile}`: The profile of the feed.
+/// * `{feed}`: The host name of the feed.
+/// * `{date}`: The day the entry was stored, like `2023-10-02`.
+/// * `{title}`: The title of the entry.
+/// * `{hash}`: The first 12 characters of the entry id.
+/// * `{filename}`: The file name at the end of the enclosure URL.
+/// * `{ext}`: The extension of the file, from the URL or the MIME type.
+pub fn target_path(
+    settings: &DownloadSettings,
+    rss_entry: &RssEntry,
+    profile: &str,
+    enclosure: &EntryEnclosure,
+) -> PathBuf {
+    let url = reqwest::Url::parse(&enclosure.url).ok();
+    let feed = reqwest::Url::parse(&rss_entry.rss_url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_else(|| String::from("local"));
+    let filename = url
+        .as_ref()
+        .and_then(|url| {
+            url.path_segments()?
+                .next_back()
+                .map(|segment| segment.to_string())
+        })
+        .filter(|filename| !filename.is_empty())
+        .unwrap_or_else(|| rss_entry.hash()[..12].to_string());
+    let extension = match filename.rsplit_once('.') {
+        Some((_, extension))
+            if !extension.is_empty()
+                && extension.len() <= 5
+                && extension.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            extension.to_lowercase()
+        }
+        _ => mime_extension(&enclosure.mime_type).to_string(),
+    };
+
+    let date: String = rss_entry.publish_date.chars().take(10).collect();
+    let values = [
+        ("profile", sanitize(profile)),
+        ("feed", sanitize(&feed)),
+        ("date", sanitize(&date)),
+        ("title", sanitize(&rss_entry.title)),
+        ("hash", rss_entry.hash()[..12].to_string()),
+        ("filename", sanitize(&filename)),
+        ("ext", extension),
+    ];
+
+    // a single pass, so braces in a title are not taken for placeholders
+    let relative = template::fill(&settings.filename_template, &values);
+    Path::new(&settings.directory).join(relative)
+}
+
+/// The function `sanitize` turns a placeholder value into a single safe path component.
+fn sanitize(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| {
+            if c.is_control() || FORBIDDEN_CHARACTERS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_COMPONENT_LENGTH)
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        String::from("_")
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// The function `mime_extension` returns the usual extension of a MIME type.
+fn mime_extension(mime_type: &str) -> &'static str {
+    match mime_type.split(';').next().unwrap_or_default().trim() {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/mp4" | "audio/x-m4a" | "audio/m4a" => "m4a",
+        "audio/ogg" => "ogg",
+        "audio/opus" => "opus",
+        "audio/aac" => "aac",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        "video/quicktime" => "mov",
+        "application/pdf" => "pdf",
+        "application/epub+zip" => "epub",
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        _ => "bin",
+    }
+}
+
+/// The function `unique_path` appends ` (2)`, ` (3)`... to the file name while it is already used
+/// by another file of the batch or on disk, or by the `.part` file of an unfinished download of
+/// another URL.
+fn unique_path(path: PathBuf, url: &str, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut candidate = path.clone();
+    let mut counter = 1;
+    let unfinished_elsewhere = |candidate: &Path| {
+        if !part_path(candidate).exists() {
+            return false;
+        }
+        match read_resume(candidate) {
+            Some((resumed_url, _)) => resumed_url != url,
+            None => true,
+        }
+    };
+    while taken.contains(&candidate) || candidate.exists() || unfinished_elsewhere(&candidate) {
+        counter += 1;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name = match path.extension() {
+            Some(extension) => format!("{} ({}).{}", stem, counter, extension.to_string_lossy()),
+            None => format!("{} ({})", stem, counter),
+        };
+        candidate = path.with_file_name(name);
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// The function `part_path` returns the path an unfinished download is written to.
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+/// The function `resume_path` returns the path the URL and the validator of an unfinished
+/// download are kept at.
+fn resume_path(path: &Path) -> PathBuf {
+    let mut resume = part_path(path).into_os_string();
+    resume.push(".resume");
+    PathBuf::from(resume)
+}
+
+/// The function `read_resume` reads the URL and the validator of the unfinished download of
+/// `path`, see `write_resume`.
+fn read_resume(path: &Path) -> Option<(String, String)> {
+    let content = fs::read_to_string(resume_path(path)).ok()?;
+    let (url, validator) = content.split_once('\n')?;
+    Some((url.to_string(), validator.trim_end().to_string()))
+}
+
+/// The function `write_resume` records the URL the `.part` file of `path` is downloaded from, with
+/// the validator of the response when there is one.
+async fn write_resume(
+    path: &Path,
+    url: &str,
+    validator: Option<&str>,
+) -> Result<(), std::io::Error> {
+    match validator {
+        Some(validator) => {
+            tokio::fs::write(resume_path(path), format!("{}\n{}\n", url, validator)).await
+        }
+        None => remove_resume(path).await,
+    }
+}
+
+/// The function `remove_resume` forgets the unfinished download of `path`.
+async fn remove_resume(path: &Path) -> Result<(), std::io::Error> {
+    match tokio::fs::remove_file(resume_path(path)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// The function `response_validator` returns the strong `ETag` of a response, or its
+/// `Last-Modified` date, which `If-Range` accepts to tell whether a file changed.
+fn response_validator(res: &reqwest::Response) -> Option<String> {
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    };
+    header(header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(header::LAST_MODIFIED))
+}
+
+/// The function `request` builds the GET request of a job, asking for the bytes after `offset`
+/// when a part of the file is already there, as long as the file still matches `validator`. The
+/// file is requested uncompressed so the range and the digest apply to the bytes written.
+fn request(job: &DownloadJob, offset: u64, validator: Option<&str>) -> reqwest::RequestBuilder {
+    let request = job
+        .client
+        .client
+        .get(&job.url)
+        .header(header::ACCEPT_ENCODING, "identity");
+    match validator {
+        Some(validator) if offset > 0 => request
+            .header(header::RANGE, format!("bytes={}-", offset))
+            .header(header::IF_RANGE, validator),
+        _ => request,
+    }
+}
+
+/// The function `complete` checks the digest of a finished download and moves it in place.
+async fn complete(
+    part: &Path,
+    path: &Path,
+    bytes: u64,
+    hasher: Sha256,
+    expected_sha256: Option<String>,
+) -> Result<DownloadedFile, DownloadError> {
+    let sha256 = format!("{:x}", hasher.finalize());
+    if let Some(expected_sha256) = expected_sha256 {
+        if expected_sha256 != sha256 {
+            tokio::fs::remove_file(part).await?;
+            remove_resume(path).await?;
+            return Err(DownloadError::ChecksumMismatch(expected_sha256, sha256));
+        }
+    }
+    tokio::fs::rename(part, path).await?;
+    remove_resume(path).await?;
+    Ok(DownloadedFile {
+        path: path.to_path_buf(),
+        bytes,
+        sha256,
+    })
+}
+
+/// The function `content_range_total` reads the full size of the file from a `Content-Range`
+/// header like `bytes 100-199/200` or `bytes */200`.
+fn content_range_total(res: &reqwest::Response) -> Option<u64> {
+    res.headers()
+        .get(header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The function `digest_header` reads the hex encoded SHA-256 announced by the server in a
+/// `Repr-Digest: sha-256=:<base64>:` (RFC 9530) or `Digest: SHA-256=<base64>` (RFC 3230) header.
+fn digest_header(res: &reqwest::Response) -> Option<String> {
+    ["repr-digest", "digest"]
+        .iter()
+        .filter_map(|name| res.headers().get(*name)?.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|digest| {
+            let (algorithm, value) = digest.trim().split_once('=')?;
+            if !algorithm.eq_ignore_ascii_case("sha-256") {
+                return None;
+            }
+            let bytes = STANDARD.decode(value.trim().trim_matches(':')).ok()?;
+            Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+        })
+}
+
+/// The function `hash_file` feeds the content of a file to a new SHA-256 hasher.
+fn hash_file(path: &Path) -> Result<Sha256, std::io::Error> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 {
+            return Ok(hasher);
+        }
+        hasher.update(&buffer[..count]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{http_settings::HttpSettings, open_database};
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    /// The function `serve` answers each connection with the next scripted response and returns
+    /// the URL to request and the lowercased requests received.
+    async fn serve(responses: Vec<Vec<u8>>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/episode.mp3", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let count = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..count]);
+                }
+                requests.push(String::from_utf8(request).unwrap().to_lowercase());
+                stream.write_all(&response).await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            status,
+            body.len()
+        );
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("rss_rust_download_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn job(url: String, path: PathBuf) -> DownloadJob {
+        DownloadJob {
+            enclosure_id: 1,
+            url,
+            path,
+            client: HttpClient::new(&HttpSettings::default()).unwrap(),
+        }
+    }
+
+    fn sha256(content: &[u8]) -> Vec<u8> {
+        Sha256::digest(content).to_vec()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// The function `interrupted` leaves the `.part` file of a download of `url` stopped after
+    /// `content`, as sent with the given validator.
+    fn interrupted(path: &Path, url: &str, content: &str, validator: &str) {
+        fs::write(part_path(path), content).unwrap();
+        fs::write(resume_path(path), format!("{}\n{}\n", url, validator)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn part_file_is_resumed_with_a_range_request() {
+        let directory = directory("resume");
+        let path = directory.join("episode.mp3");
+        let (url, server) = serve(vec![response(
+            "206 Partial Content",
+            &[("Content-Range", "bytes 6-10/11")],
+            b"world",
+        )])
+        .await;
+        interrupted(&path, &url, "hello ", "\"v1\"");
+
+        let file = download(&job(url, path.clone())).await.unwrap();
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("range: bytes=6-"));
+        assert!(requests[0].contains("if-range: \"v1\""));
+        assert!(requests[0].contains("accept-encoding: identity"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        assert!(!part_path(&path).exists());
+        assert!(!resume_path(&path).exists());
+        assert_eq!(file.bytes, 11);
+        assert_eq!(file.sha256, hex(&sha256(b"hello world")));
+        assert!(verify(&path, &file.sha256).unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn changed_file_starts_over() {
+        let directory = directory("changed_file");
+        let path = directory.join("episode.mp3");
+        let (url, server) = serve(vec![response(
+            "200 OK",
+            &[("ETag", "\"v2\"")],
+            b"hello world",
+        )])
+        .await;
+        interrupted(&path, &url, "stale", "\"v1\"");
+
+        // the server answers `If-Range` with the whole new version
+        let file = download(&job(url, path.clone())).await.unwrap();
+        assert!(server.await.unwrap()[0].contains("if-range: \"v1\""));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        assert_eq!(file.bytes, 11);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn part_file_of_another_url_is_not_resumed() {
+        let directory = directory("other_url");
+        let path = directory.join("episode.mp3");
+        let (url, server) = serve(vec![response("200 OK", &[], b"hello world")]).await;
+        interrupted(&path, "https://example.com/other.mp3", "stale", "\"v1\"");
+
+        download(&job(url.clone(), path.clone())).await.unwrap();
+        let requests = server.await.unwrap();
+        assert!(!requests[0].contains("range:"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+
+        // without a validator nothing tells the part is still valid
+        let (url, server) = serve(vec![response("200 OK", &[], b"hello world")]).await;
+        fs::remove_file(&path).unwrap();
+        fs::write(part_path(&path), "stale").unwrap();
+        download(&job(url, path.clone())).await.unwrap();
+        assert!(!server.await.unwrap()[0].contains("range:"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unfinished_downloads_of_other_urls_take_their_path() {
+        let directory = directory("unique_path");
+        let path = directory.join("episode.mp3");
+        let url = "https://example.com/a/episode.mp3";
+        let mut taken = HashSet::new();
+        assert_eq!(unique_path(path.clone(), url, &mut taken), path);
+        // the same path within a batch
+        assert_eq!(
+            unique_path(
+                path.clone(),
+                "https://example.com/b/episode.mp3",
+                &mut taken
+            ),
+            directory.join("episode (2).mp3")
+        );
+
+        let mut taken = HashSet::new();
+        interrupted(&path, url, "hello ", "\"v1\"");
+        assert_eq!(unique_path(path.clone(), url, &mut taken), path);
+        let mut taken = HashSet::new();
+        assert_eq!(
+            unique_path(
+                path.clone(),
+                "https://example.com/b/episode.mp3",
+                &mut taken
+            ),
+            directory.join("episode (2).mp3")
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn complete_part_file_is_moved_in_place() {
+        let directory = directory("complete_part");
+        let path = directory.join("episode.mp3");
+        let (url, server) = serve(vec![response(
+            "416 Range Not Satisfiable",
+            &[("Content-Range", "bytes */11")],
+            b"",
+        )])
+        .await;
+        interrupted(&path, &url, "hello world", "Tue, 02 Jan 2024 03:04:05 GMT");
+
+        let file = download(&job(url, path.clone())).await.unwrap();
+        assert_eq!(server.await.unwrap().len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        assert_eq!(file.sha256, hex(&sha256(b"hello world")));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn short_body_keeps_the_part_file() {
+        let directory = directory("short_body");
+        let path = directory.join("episode.mp3");
+        let (url, server) = serve(vec![response(
+            "206 Partial Content",
+            &[
+                ("Content-Range", "bytes 0-10/20"),
+                ("ETag", "W/\"weak\""),
+                ("Last-Modified", "Tue, 02 Jan 2024 03:04:05 GMT"),
+            ],
+            b"hello world",
+        )])
+        .await;
+
+        // the total of the `Content-Range` is more than the bytes sent
+        match download(&job(url.clone(), path.clone())).await {
+            Err(DownloadError::Incomplete(20, 11)) => {}
+            other => panic!("unexpected result {:?}", other.map(|file| file.bytes)),
+        }
+        server.await.unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(part_path(&path)).unwrap(), "hello world");
+        // a weak `ETag` cannot be used with `If-Range`
+        assert_eq!(
+            read_resume(&path),
+            Some((url, String::from("Tue, 02 Jan 2024 03:04:05 GMT")))
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn digest_header_is_verified() {
+        let directory = directory("digest");
+        let path = directory.join("episode.mp3");
+        let digest = format!("sha-256=:{}:", STANDARD.encode(sha256(b"hello world")));
+        let (url, server) = serve(vec![response(
+            "200 OK",
+            &[("Repr-Digest", &digest)],
+            b"hello world",
+        )])
+        .await;
+        let file = download(&job(url, path.clone())).await.unwrap();
+        server.await.unwrap();
+        assert_eq!(file.sha256, hex(&sha256(b"hello world")));
+
+        let path = directory.join("other.mp3");
+        let digest = format!("SHA-256={}", STANDARD.encode(sha256(b"something else")));
+        let (url, server) = serve(vec![response(
+            "200 OK",
+            &[("Digest", &digest)],
+            b"hello world",
+        )])
+        .await;
+        match download(&job(url, path.clone())).await {
+            Err(DownloadError::ChecksumMismatch(expected, actual)) => {
+                assert_eq!(expected, hex(&sha256(b"something else")));
+                assert_eq!(actual, hex(&sha256(b"hello world")));
+            }
+            other => panic!("unexpected result {:?}", other.map(|file| file.bytes)),
+        }
+        server.await.unwrap();
+        // a corrupt file is not resumed on the next run
+        assert!(!path.exists());
+        assert!(!part_path(&path).exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn retention_removes_the_oldest_unstarred_files() {
+        let directory = directory("retention");
+        let connection = open_database(":memory:").unwrap();
+        let mut paths = vec![];
+        for (id, starred) in [(1, false), (2, true), (3, false), (4, false)] {
+            let path = directory.join(format!("{}.mp3", id));
+            fs::write(&path, "0123456789").unwrap();
+            connection
+                .execute(format!(
+                    "INSERT INTO rss_entries (hash, title, link, is_starred)
+                    VALUES ('hash{id}', 'Entry {id}', 'https://example.com/{id}', {starred});
+                    INSERT INTO enclosures (id, hash, url, file_path, downloaded_at)
+                    VALUES ({id}, 'hash{id}', 'https://example.com/{id}.mp3', '{path}',
+                    '2024-01-0{id} 00:00:00');",
+                    id = id,
+                    starred = starred as i64,
+                    path = path.display()
+                ))
+                .unwrap();
+            paths.push(path);
+        }
+        let connection = ThreadSafe::new(&connection);
+        let settings = DownloadSettings {
+            max_files: Some(2),
+            ..DownloadSettings::default()
+        };
+
+        let report = enforce_retention(&connection, &settings);
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            report.removed,
+            vec![
+                paths[0].display().to_string(),
+                paths[2].display().to_string()
+            ]
+        );
+        assert_eq!(report.freed_bytes, 20);
+        assert!(!paths[0].exists());
+        assert!(paths[1].exists());
+        assert!(!paths[2].exists());
+        assert!(paths[3].exists());
+
+        // within budget, nothing more is removed
+        let report = enforce_retention(&connection, &settings);
+        assert!(report.removed.is_empty());
+        let kept: Vec<i64> = EntryEnclosure::downloaded(&connection)
+            .into_iter()
+            .map(|(enclosure, _)| enclosure.id)
+            .collect();
+        assert_eq!(kept, vec![2, 4]);
+
+        // a file deleted by hand is forgotten without counting against the budget
+        fs::remove_file(&paths[3]).unwrap();
+        let report = enforce_retention(&connection, &settings);
+        assert!(report.removed.is_empty());
+        assert_eq!(EntryEnclosure::downloaded(&connection).len(), 1);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
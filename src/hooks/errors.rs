@@ -0,0 +1,39 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum HookError {
+    IoError(std::io::Error),
+    SerdeError(serde_json::Error),
+    Timeout(u64),
+    ExitStatus(Option<i32>, String),
+}
+
+/// The `impl fmt::Display for HookError` block formats the reason a hook run failed, which is the
+/// message stored in the `hook_failures` table.
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            HookError::IoError(e) => write!(f, "Hook io Error: {}", e),
+            HookError::SerdeError(e) => write!(f, "Hook payload Error: {}", e),
+            HookError::Timeout(secs) => write!(f, "Hook timed out after {}s", secs),
+            HookError::ExitStatus(Some(code), stderr) => {
+                write!(f, "Hook exited with status {}: {}", code, stderr.trim())
+            }
+            HookError::ExitStatus(None, stderr) => {
+                write!(f, "Hook killed by signal: {}", stderr.trim())
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for HookError {
+    fn from(error: std::io::Error) -> Self {
+        HookError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for HookError {
+    fn from(error: serde_json::Error) -> Self {
+        HookError::SerdeError(error)
+    }
+}
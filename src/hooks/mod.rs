@@ -0,0 +1,149 @@
+pub mod errors;
+
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::{core::structs::EntryPayload, db::hook::Hook};
+use errors::HookError;
+
+/// The function `run_hook` executes the hook command through `sh -c` for a single entry. The entry
+/// fields are exported as `RSS_*` environment variables and written as JSON to the command's stdin.
+///
+/// Arguments:
+///
+/// * `hook`: The `hook` whose command is run.
+/// * `payload`: The `payload` describing the newly inserted entry.
+///
+/// Returns:
+///
+/// `Ok(())` if the command exits successfully within `hook.timeout_secs`, otherwise a `HookError`.
+pub async fn run_hook(hook: &Hook<'_>, payload: &EntryPayload) -> Result<(), HookError> {
+    let stdin = serde_json::to_vec(payload)?;
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&hook.command)
+        .env("RSS_HASH", &payload.hash)
+        .env("RSS_TITLE", &payload.title)
+        .env("RSS_LINK", &payload.link)
+        .env("RSS_PUBLISH_DATE", &payload.publish_date)
+        .env("RSS_FEED_URL", &payload.feed_url)
+        .env("RSS_PROFILE", &payload.profile)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin_pipe = child.stdin.take();
+    let run = async move {
+        if let Some(mut child_stdin) = stdin_pipe {
+            // a hook that never reads stdin closes the pipe early, which is not a failure
+            let _ = child_stdin.write_all(&stdin).await;
+        }
+        child.wait_with_output().await
+    };
+
+    let timeout = Duration::from_secs(hook.timeout_secs);
+    match tokio::time::timeout(timeout, run).await {
+        Ok(output) => {
+            let output = output?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(HookError::ExitStatus(
+                    output.status.code(),
+                    String::from_utf8_lossy(&output.stderr).to_string(),
+                ))
+            }
+        }
+        Err(_) => Err(HookError::Timeout(hook.timeout_secs)),
+    }
+}
+
+/// The function `run_hooks` runs every hook for every payload, logging each failure to the
+/// `hook_failures` table instead of returning it.
+///
+/// Returns:
+///
+/// the number of failed hook runs.
+pub async fn run_hooks(hooks: &[Hook<'_>], payloads: &[EntryPayload]) -> usize {
+    let mut failures = 0;
+    for payload in payloads.iter() {
+        for hook in hooks.iter() {
+            if let Err(e) = run_hook(hook, payload).await {
+                failures += 1;
+                let _ = hook.log_failure(&payload.hash, &e.to_string());
+            }
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook<'c>(command: &str, timeout_secs: u64) -> Hook<'c> {
+        Hook {
+            id: 1,
+            profile: None,
+            command: command.to_string(),
+            timeout_secs,
+            connection: None,
+        }
+    }
+
+    fn payload() -> EntryPayload {
+        EntryPayload {
+            hash: String::from("abc"),
+            title: String::from("Rust & \"friends\" $(exit 1)"),
+            link: String::from("https://example.com/post?a=1&b=2"),
+            publish_date: String::from("2024-01-02 03:04:05"),
+            feed_url: String::from("https://example.com/rss.xml"),
+            profile: String::from("work"),
+        }
+    }
+
+    #[tokio::test]
+    async fn entry_fields_are_exported_without_being_evaluated() {
+        let command = r#"test "$RSS_HASH" = abc &&
+            test "$RSS_TITLE" = 'Rust & "friends" $(exit 1)' &&
+            test "$RSS_LINK" = 'https://example.com/post?a=1&b=2' &&
+            test "$RSS_PUBLISH_DATE" = '2024-01-02 03:04:05' &&
+            test "$RSS_FEED_URL" = https://example.com/rss.xml &&
+            test "$RSS_PROFILE" = work"#;
+        run_hook(&hook(command, 5), &payload()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_entry_is_written_as_json_to_stdin() {
+        let command = r#"grep -q '"hash":"abc","title":"Rust & '"#;
+        run_hook(&hook(command, 5), &payload()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_hook_ignoring_stdin_succeeds() {
+        run_hook(&hook("true", 5), &payload()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_failing_command_reports_its_status_and_stderr() {
+        let error = run_hook(&hook("echo oops >&2; exit 3", 5), &payload())
+            .await
+            .unwrap_err();
+        assert!(matches!(&error, HookError::ExitStatus(Some(3), stderr) if stderr == "oops\n"));
+        assert_eq!(error.to_string(), "Hook exited with status 3: oops");
+    }
+
+    #[tokio::test]
+    async fn a_command_running_past_the_timeout_is_killed() {
+        let started = std::time::Instant::now();
+        let error = run_hook(&hook("sleep 30", 1), &payload())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, HookError::Timeout(1)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
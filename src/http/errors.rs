@@ -0,0 +1,63 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum HttpError {
+    RequestError(reqwest::Error),
+    Timeout(u64),
+    BodyTooLarge(u64),
+    InvalidSetting(String),
+    StatusError(u16),
+    RedirectRefused(String),
+}
+
+/// The `impl fmt::Display for HttpError` block formats the reason a request failed.
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            HttpError::RequestError(e) => write!(f, "Http request Error: {}", e),
+            HttpError::Timeout(secs) => write!(f, "No response data for {}s", secs),
+            HttpError::BodyTooLarge(max_bytes) => {
+                write!(f, "Response body is larger than {} bytes", max_bytes)
+            }
+            HttpError::InvalidSetting(e) => write!(f, "Invalid http setting: {}", e),
+            HttpError::StatusError(status) => {
+                write!(f, "Server responded with status {}", status)
+            }
+            HttpError::RedirectRefused(origin) => write!(
+                f,
+                "Redirect to another origin refused, credentials not forwarded to {}",
+                origin
+            ),
+        }
+    }
+}
+
+/// The `CrossOriginRedirect` struct is the reason the redirect policy of
+/// `HttpClient::for_credentials` gives when it stops at another origin, turned into
+/// `HttpError::RedirectRefused` by `From<reqwest::Error>`.
+#[derive(Debug)]
+pub struct CrossOriginRedirect(pub String);
+
+impl fmt::Display for CrossOriginRedirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "redirect to {} refused", self.0)
+    }
+}
+
+impl std::error::Error for CrossOriginRedirect {}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(error: reqwest::Error) -> Self {
+        if let Some(refused) = std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<CrossOriginRedirect>())
+        {
+            return HttpError::RedirectRefused(refused.0.clone());
+        }
+        // the message would otherwise print the password of a URL with credentials
+        if error.url().is_some_and(|url| url.password().is_some()) {
+            HttpError::RequestError(error.without_url())
+        } else {
+            HttpError::RequestError(error)
+        }
+    }
+}
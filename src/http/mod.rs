@@ -0,0 +1,575 @@
+pub mod errors;
+
+use std::{collections::HashMap, time::Duration};
+
+use crate::{db::http_settings::HttpSettings, rss::encoding};
+use errors::{CrossOriginRedirect, HttpError};
+
+/// Number of seconds to wait for a connection when no setting overrides it.
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Number of seconds to wait for the response and each chunk of its body when no setting
+/// overrides it.
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 30;
+
+/// Response bodies larger than this are rejected when no setting overrides it.
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 50 * 1024 * 1024;
+
+/// The `User-Agent` sent when no setting overrides it.
+pub const DEFAULT_USER_AGENT: &str = concat!("rss_rust/", env!("CARGO_PKG_VERSION"));
+
+/// Number of times a GET request is sent again after a transient failure.
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// The wait before the first retry, doubled for every following one.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Number of redirects followed before a request fails, as `reqwest` does by default.
+const MAX_REDIRECTS: usize = 10;
+
+/// The `HttpClient` struct is a `reqwest::Client` built from `HttpSettings` together with the
+/// limits `reqwest` does not enforce by itself. Cloning it is cheap and shares the connection pool.
+///
+/// Properties:
+///
+/// * `client`: The configured `reqwest` client.
+/// * `read_timeout`: The time to wait for the response and for every chunk of its body.
+/// * `max_body_bytes`: The size after which a response body is rejected.
+/// * `retries`: The number of times a GET request is retried, see `send`.
+/// * `retry_delay`: The wait before the first retry.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    pub client: reqwest::Client,
+    pub read_timeout: Duration,
+    pub max_body_bytes: u64,
+    pub retries: u32,
+    pub retry_delay: Duration,
+}
+
+impl HttpClient {
+    /// The function `new` builds a client from the given settings, unset options use the
+    /// defaults of this module.
+    ///
+    /// Returns:
+    ///
+    /// the client, or an `HttpError::InvalidSetting` if the proxy URL or a CA certificate cannot be
+    /// used.
+    pub fn new(settings: &HttpSettings) -> Result<HttpClient, HttpError> {
+        HttpClient::build(settings, false)
+    }
+
+    /// The function `for_credentials` builds a client like `new` for requests carrying
+    /// `Credentials`: redirects are only followed within the origin of the request, so the
+    /// credentials are never sent to another host. A redirect elsewhere fails the request with an
+    /// `HttpError::RedirectRefused`.
+    pub fn for_credentials(settings: &HttpSettings) -> Result<HttpClient, HttpError> {
+        HttpClient::build(settings, true)
+    }
+
+    fn build(settings: &HttpSettings, same_origin: bool) -> Result<HttpClient, HttpError> {
+        let compression = settings.compression.unwrap_or(true);
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(
+                settings
+                    .connect_timeout_secs
+                    .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+            ))
+            .user_agent(
+                settings
+                    .user_agent
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            )
+            .gzip(compression)
+            .brotli(compression)
+            .deflate(compression);
+        if same_origin {
+            builder = builder.redirect(reqwest::redirect::Policy::custom(|attempt| {
+                let origin = attempt.previous().first().map(|first| first.origin());
+                if attempt.previous().len() > MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if origin.is_some_and(|origin| origin != attempt.url().origin()) {
+                    let origin = attempt.url().origin().ascii_serialization();
+                    attempt.error(CrossOriginRedirect(origin))
+                } else {
+                    attempt.follow()
+                }
+            }));
+        }
+        if let Some(proxy) = &settings.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| HttpError::InvalidSetting(format!("proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        for path in settings.ca_certs.iter() {
+            let pem = std::fs::read(path).map_err(|e| {
+                HttpError::InvalidSetting(format!("CA certificate {}: {}", path, e))
+            })?;
+            let certificate = reqwest::Certificate::from_pem(&pem).map_err(|e| {
+                HttpError::InvalidSetting(format!("CA certificate {}: {}", path, e))
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        Ok(HttpClient {
+            client: builder.build()?,
+            read_timeout: Duration::from_secs(
+                settings
+                    .read_timeout_secs
+                    .unwrap_or(DEFAULT_READ_TIMEOUT_SECS),
+            ),
+            max_body_bytes: settings.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES),
+            retries: DEFAULT_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        })
+    }
+
+    /// The function `send` sends a request built from `client`, giving up when the response does
+    /// not start within the read timeout or announces a body over the size limit. A GET or HEAD
+    /// request failing to connect, timing out or answered with a 429 or 5xx status is sent again
+    /// up to `retries` times, waiting `retry_delay` and then twice as long every time. Other
+    /// requests are sent once, their callers decide whether they can be repeated.
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, HttpError> {
+        let mut request = request.build()?;
+        let idempotent = matches!(
+            *request.method(),
+            reqwest::Method::GET | reqwest::Method::HEAD
+        );
+        let mut attempt: u32 = 0;
+        loop {
+            let retry = if idempotent && attempt < self.retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let result =
+                match tokio::time::timeout(self.read_timeout, self.client.execute(request)).await {
+                    Ok(result) => result.map_err(HttpError::from),
+                    Err(_) => Err(HttpError::Timeout(self.read_timeout.as_secs())),
+                };
+            let transient = match &result {
+                Ok(res) => res.status().is_server_error() || res.status().as_u16() == 429,
+                Err(HttpError::RequestError(e)) => e.is_connect() || e.is_timeout(),
+                Err(HttpError::Timeout(_)) => true,
+                Err(_) => false,
+            };
+            request = match retry {
+                Some(retry) if transient => retry,
+                _ => return self.check_length(result?),
+            };
+            tokio::time::sleep(self.retry_delay * 2u32.pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// The function `check_length` rejects a response announcing a body over the size limit.
+    fn check_length(&self, res: reqwest::Response) -> Result<reqwest::Response, HttpError> {
+        match res.content_length() {
+            Some(length) if length > self.max_body_bytes => {
+                Err(HttpError::BodyTooLarge(self.max_body_bytes))
+            }
+            _ => Ok(res),
+        }
+    }
+
+    /// The function `get` sends a GET request to `url`, see `send`.
+    pub async fn get(&self, url: &str) -> Result<reqwest::Response, HttpError> {
+        self.send(self.client.get(url)).await
+    }
+
+    /// The function `chunk` reads the next chunk of a response body, enforcing the read timeout and
+    /// the body size limit.
+    ///
+    /// Arguments:
+    ///
+    /// * `res`: The response being read.
+    /// * `read_bytes`: The number of bytes read so far, increased by the size of the chunk.
+    ///
+    /// Returns:
+    ///
+    /// the chunk, or `None` at the end of the body.
+    pub async fn chunk(
+        &self,
+        res: &mut reqwest::Response,
+        read_bytes: &mut u64,
+    ) -> Result<Option<bytes::Bytes>, HttpError> {
+        let chunk = tokio::time::timeout(self.read_timeout, res.chunk())
+            .await
+            .map_err(|_| HttpError::Timeout(self.read_timeout.as_secs()))??;
+        if let Some(chunk) = &chunk {
+            *read_bytes += chunk.len() as u64;
+            if *read_bytes > self.max_body_bytes {
+                return Err(HttpError::BodyTooLarge(self.max_body_bytes));
+            }
+        }
+        Ok(chunk)
+    }
+
+    /// The function `text` reads a whole response body within the limits and decodes it with the
+    /// charset of its `Content-Type`, UTF-8 otherwise.
+    pub async fn text(&self, mut res: reqwest::Response) -> Result<String, HttpError> {
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let mut body = vec![];
+        let mut read_bytes = 0;
+        while let Some(chunk) = self.chunk(&mut res, &mut read_bytes).await? {
+            body.extend_from_slice(&chunk);
+        }
+        let (text, _, _) = encoding::detect(&body, content_type.as_deref()).decode(&body);
+        Ok(text.into_owned())
+    }
+}
+
+/// The `Credentials` struct holds the revealed credentials of a feed while its request is built,
+/// see `FeedAuth::credentials`.
+///
+/// Properties:
+///
+/// * `basic`: The user name and optional password for basic authentication.
+/// * `bearer`: The bearer token.
+/// * `headers`: Extra headers.
+/// * `cookie_header`: The `Cookie` header.
+#[derive(Default)]
+pub struct Credentials {
+    pub basic: Option<(String, Option<String>)>,
+    pub bearer: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub cookie_header: Option<String>,
+}
+
+impl Credentials {
+    /// The function `apply` adds the credentials to a request. The request must be sent with a
+    /// client of `ClientPool::for_credentials`, which does not follow redirects to another origin
+    /// with them.
+    pub fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some((username, password)) = &self.basic {
+            request = request.basic_auth(username, password.as_ref());
+        }
+        if let Some(token) = &self.bearer {
+            request = request.bearer_auth(token);
+        }
+        for (name, value) in self.headers.iter() {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(cookie_header) = &self.cookie_header {
+            request = request.header(reqwest::header::COOKIE, cookie_header.as_str());
+        }
+        request
+    }
+}
+
+/// The `ClientKey` struct holds the settings an `HttpClient` is built from, the feed they apply to
+/// left out, so feeds configured alike share a client.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    connect_timeout_secs: Option<u64>,
+    read_timeout_secs: Option<u64>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    ca_certs: Vec<String>,
+    compression: Option<bool>,
+    max_body_bytes: Option<u64>,
+    same_origin: bool,
+}
+
+impl ClientKey {
+    fn new(settings: &HttpSettings, same_origin: bool) -> ClientKey {
+        ClientKey {
+            connect_timeout_secs: settings.connect_timeout_secs,
+            read_timeout_secs: settings.read_timeout_secs,
+            user_agent: settings.user_agent.clone(),
+            proxy: settings.proxy.clone(),
+            ca_certs: settings.ca_certs.clone(),
+            compression: settings.compression,
+            max_body_bytes: settings.max_body_bytes,
+            same_origin,
+        }
+    }
+}
+
+/// The `ClientPool` struct shares one `HttpClient` between all the feeds whose effective settings
+/// are the same, so a sync only builds a client per distinct configuration.
+#[derive(Debug, Default)]
+pub struct ClientPool {
+    clients: HashMap<ClientKey, HttpClient>,
+}
+
+impl ClientPool {
+    pub fn new() -> ClientPool {
+        ClientPool::default()
+    }
+
+    /// The function `for_feed` returns the client for the effective settings of the feed with the
+    /// given URL, building it on first use.
+    pub fn for_feed(
+        &mut self,
+        connection: &thread_safe::ThreadSafe<&sqlite::Connection>,
+        rss_url: &str,
+    ) -> Result<HttpClient, HttpError> {
+        self.get(&HttpSettings::for_feed(connection, rss_url), false)
+    }
+
+    /// The function `for_credentials` returns the client for the feed with the given URL when its
+    /// request carries credentials, see `HttpClient::for_credentials`.
+    pub fn for_credentials(
+        &mut self,
+        connection: &thread_safe::ThreadSafe<&sqlite::Connection>,
+        rss_url: &str,
+    ) -> Result<HttpClient, HttpError> {
+        self.get(&HttpSettings::for_feed(connection, rss_url), true)
+    }
+
+    /// The function `defaults` returns the client for the default settings, when there is no
+    /// database to read the settings from.
+    pub fn defaults(&mut self) -> Result<HttpClient, HttpError> {
+        self.get(&HttpSettings::default(), false)
+    }
+
+    /// The function `global` returns the client for the global settings.
+    pub fn global(
+        &mut self,
+        connection: &thread_safe::ThreadSafe<&sqlite::Connection>,
+    ) -> Result<HttpClient, HttpError> {
+        self.get(
+            &HttpSettings::get(connection, None).unwrap_or_default(),
+            false,
+        )
+    }
+
+    fn get(&mut self, settings: &HttpSettings, same_origin: bool) -> Result<HttpClient, HttpError> {
+        let key = ClientKey::new(settings, same_origin);
+        if let Some(client) = self.clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = HttpClient::build(settings, same_origin)?;
+        self.clients.insert(key, client.clone());
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// The function `serve` answers every request with the next of `responses`, the last one once
+    /// they run out, and returns the URL to request with the number of requests received. An
+    /// empty response never answers.
+    async fn serve(responses: Vec<Vec<u8>>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let index = received.fetch_add(1, Ordering::SeqCst);
+                let response = responses[index.min(responses.len() - 1)].clone();
+                tokio::spawn(async move {
+                    let mut request = vec![];
+                    let mut buffer = [0; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        let count = stream.read(&mut buffer).await.unwrap();
+                        request.extend_from_slice(&buffer[..count]);
+                    }
+                    if response.is_empty() {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                    }
+                    let _ = stream.write_all(&response).await;
+                });
+            }
+        });
+        (url, requests)
+    }
+
+    fn response(status: &str, content_length: bool, body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        if content_length {
+            response.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn client(settings: HttpSettings) -> HttpClient {
+        let mut client = HttpClient::new(&settings).unwrap();
+        client.retry_delay = Duration::from_millis(1);
+        client
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_limit_are_rejected() {
+        let settings = HttpSettings {
+            max_body_bytes: Some(16),
+            ..HttpSettings::default()
+        };
+        let body = [b'x'; 17];
+
+        let (url, _) = serve(vec![response("200 OK", true, &body)]).await;
+        assert!(matches!(
+            client(settings.clone()).get(&url).await,
+            Err(HttpError::BodyTooLarge(16))
+        ));
+
+        let (url, _) = serve(vec![response("200 OK", false, &body)]).await;
+        let res = client(settings.clone()).get(&url).await.unwrap();
+        assert!(matches!(
+            client(settings.clone()).text(res).await,
+            Err(HttpError::BodyTooLarge(16))
+        ));
+
+        let (url, _) = serve(vec![response("200 OK", false, &body[1..])]).await;
+        let client = client(settings);
+        let res = client.get(&url).await.unwrap();
+        assert_eq!(client.text(res).await.unwrap(), "x".repeat(16));
+    }
+
+    #[tokio::test]
+    async fn a_silent_server_times_out_after_the_retries() {
+        let settings = HttpSettings {
+            read_timeout_secs: Some(1),
+            ..HttpSettings::default()
+        };
+        let (url, requests) = serve(vec![vec![]]).await;
+        let mut client = client(settings);
+        client.retries = 1;
+        assert!(matches!(client.get(&url).await, Err(HttpError::Timeout(1))));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_with_a_growing_delay() {
+        let (url, requests) = serve(vec![
+            response("503 Service Unavailable", true, b""),
+            response("429 Too Many Requests", true, b""),
+            response("200 OK", true, b"<rss/>"),
+        ])
+        .await;
+        let mut client = client(HttpSettings::default());
+        client.retry_delay = Duration::from_millis(100);
+        let started = std::time::Instant::now();
+        let res = client.get(&url).await.unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        // 100ms before the first retry, 200ms before the second
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        // the last failure is returned once the retries are used up
+        let (url, requests) = serve(vec![response("502 Bad Gateway", true, b"")]).await;
+        let res = client.get(&url).await.unwrap();
+        assert_eq!(res.status().as_u16(), 502);
+        assert_eq!(
+            requests.load(Ordering::SeqCst),
+            1 + DEFAULT_RETRIES as usize
+        );
+
+        // client errors are final
+        let (url, requests) = serve(vec![response("404 Not Found", true, b"")]).await;
+        assert_eq!(client.get(&url).await.unwrap().status().as_u16(), 404);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn other_methods_are_sent_once() {
+        let (url, requests) = serve(vec![response("503 Service Unavailable", true, b"")]).await;
+        let client = client(HttpSettings::default());
+        let res = client.send(client.client.post(&url).body("{}")).await;
+        assert_eq!(res.unwrap().status().as_u16(), 503);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    fn redirect(location: &str) -> Vec<u8> {
+        response(&format!("302 Found\r\nLocation: {}", location), true, b"")
+    }
+
+    #[tokio::test]
+    async fn credentials_do_not_follow_redirects_to_another_origin() {
+        let (elsewhere, followed) = serve(vec![response("200 OK", true, b"<rss/>")]).await;
+        let (url, _) = serve(vec![redirect(&elsewhere)]).await;
+        let credentials = Credentials {
+            bearer: Some(String::from("secret")),
+            headers: vec![(String::from("PRIVATE-TOKEN"), String::from("secret"))],
+            ..Credentials::default()
+        };
+
+        let client = HttpClient::for_credentials(&HttpSettings::default()).unwrap();
+        let refused = client
+            .send(credentials.apply(client.client.get(&url)))
+            .await
+            .unwrap_err();
+        let origin = elsewhere.trim_end_matches("/feed.xml");
+        assert!(matches!(&refused, HttpError::RedirectRefused(to) if to == origin));
+        assert_eq!(
+            refused.to_string(),
+            format!(
+                "Redirect to another origin refused, credentials not forwarded to {}",
+                origin
+            )
+        );
+        assert_eq!(followed.load(Ordering::SeqCst), 0);
+
+        // within the origin the redirect is followed
+        let (url, requests) = serve(vec![
+            redirect("/moved.xml"),
+            response("200 OK", true, b"<rss/>"),
+        ])
+        .await;
+        let res = client
+            .send(credentials.apply(client.client.get(&url)))
+            .await
+            .unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // requests without credentials follow it anywhere
+        let (url, _) = serve(vec![redirect(&elsewhere)]).await;
+        let client = HttpClient::new(&HttpSettings::default()).unwrap();
+        assert_eq!(client.get(&url).await.unwrap().status().as_u16(), 200);
+        assert_eq!(followed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn feeds_configured_alike_share_a_client() {
+        let mut pool = ClientPool::new();
+        let feed = |rss_url: &str, user_agent: Option<&str>| HttpSettings {
+            rss_url: Some(rss_url.to_string()),
+            user_agent: user_agent.map(String::from),
+            ..HttpSettings::default()
+        };
+        pool.get(&feed("https://a.test/rss", None), false).unwrap();
+        pool.get(&feed("https://b.test/rss", None), false).unwrap();
+        assert_eq!(pool.clients.len(), 1);
+        pool.get(&feed("https://b.test/rss", Some("reader/1.0")), false)
+            .unwrap();
+        pool.get(&feed("https://c.test/rss", Some("reader/1.0")), false)
+            .unwrap();
+        assert_eq!(pool.clients.len(), 2);
+        pool.get(&feed("https://a.test/rss", None), true).unwrap();
+        assert_eq!(pool.clients.len(), 3);
+
+        let invalid = HttpSettings {
+            proxy: Some(String::from("not a proxy")),
+            ..HttpSettings::default()
+        };
+        assert!(matches!(
+            pool.get(&invalid, false),
+            Err(HttpError::InvalidSetting(_))
+        ));
+        assert_eq!(pool.clients.len(), 3);
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+
+use crate::http::errors::HttpError;
+
+#[derive(Debug)]
+pub enum IconError {
+    HttpError(HttpError),
+    DbError(sqlite::Error),
+    StatusError(u16),
+    InvalidUrl(String),
+    UnsupportedType(String),
+    NotFound,
+}
+
+/// The `impl fmt::Display for IconError` block formats the reason the icon of a feed could not be
+/// cached.
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            IconError::HttpError(e) => write!(f, "{}", e),
+            IconError::DbError(e) => write!(f, "Storing the icon failed: {}", e),
+            IconError::StatusError(status) => {
+                write!(f, "Server responded with status {}", status)
+            }
+            IconError::InvalidUrl(url) => write!(f, "Invalid icon URL: {}", url),
+            IconError::UnsupportedType(content_type) => {
+                write!(f, "Not a supported image, served as {}", content_type)
+            }
+            IconError::NotFound => write!(f, "The feed has no image and its site no favicon"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for IconError {
+    fn from(error: reqwest::Error) -> Self {
+        IconError::HttpError(HttpError::from(error))
+    }
+}
+
+impl From<HttpError> for IconError {
+    fn from(error: HttpError) -> Self {
+        IconError::HttpError(error)
+    }
+}
+
+impl From<sqlite::Error> for IconError {
+    fn from(error: sqlite::Error) -> Self {
+        IconError::DbError(error)
+    }
+}
@@ -0,0 +1,416 @@
+pub mod errors;
+
+use reqwest::{header, Url};
+use scraper::{Html, Selector};
+use sqlite::Connection;
+use thread_safe::ThreadSafe;
+
+use crate::{
+    core::{structs::GetManyOptions, traits::CrudAble},
+    db::{feed_icon::FeedIcon, rss_entity::RssEntity},
+    http::{ClientPool, HttpClient},
+    rss::dto::ChannelInfo,
+};
+use errors::IconError;
+
+/// Icons larger than this are rejected when no limit is given.
+pub const DEFAULT_MAX_ICON_BYTES: u64 = 512 * 1024;
+
+/// Cached icons are refreshed on sync once they are older than this many days.
+pub const REFRESH_AFTER_DAYS: u64 = 7;
+
+/// The site page is only read up to this size when looking for its icon links.
+const MAX_PAGE_BYTES: u64 = 1024 * 1024;
+
+/// The `IconReport` struct sums up a refresh of the feed icons.
+///
+/// Properties:
+///
+/// * `cached`: The URLs of the feeds whose icon was stored.
+/// * `errors`: The feeds whose icon could not be found or downloaded, with the reason.
+#[derive(Debug, Default, Clone)]
+pub struct IconReport {
+    pub cached: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// The function `detect_mime` recognizes the image formats used for icons from the first bytes of
+/// `data`, ignoring whatever `Content-Type` the server claimed.
+///
+/// Returns:
+///
+/// the MIME type of the image, or `None` if `data` is not an image.
+pub fn detect_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"\x00\x00\x01\x00") || data.starts_with(b"\x00\x00\x02\x00") {
+        Some("image/x-icon")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        let head = String::from_utf8_lossy(&data[..data.len().min(1024)]).to_lowercase();
+        let head = head.trim_start_matches('\u{feff}').trim_start();
+        if head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")) {
+            Some("image/svg+xml")
+        } else {
+            None
+        }
+    }
+}
+
+/// The function `candidates` lists the URLs the icon of a channel may be found at, best first: the
+/// channel `<image>`, the icons linked from the site page, then `/favicon.ico` of the site.
+///
+/// Arguments:
+///
+/// * `client`: The `client` used to read the site page.
+/// * `rss_url`: The URL of the feed, whose root is used as the site when the channel has no link.
+/// * `channel`: The stored metadata of the channel.
+pub async fn candidates(client: &HttpClient, rss_url: &str, channel: &ChannelInfo) -> Vec<String> {
+    let mut urls = vec![];
+    let site = Url::parse(channel.link.trim())
+        .or_else(|_| Url::parse(rss_url).and_then(|rss_url| rss_url.join("/")))
+        .ok()
+        .filter(|site| matches!(site.scheme(), "http" | "https"));
+    if !channel.image_url.trim().is_empty() {
+        // relative image URLs are resolved against the site
+        let image = match &site {
+            Some(site) => site.join(channel.image_url.trim()).ok(),
+            None => Url::parse(channel.image_url.trim()).ok(),
+        };
+        urls.extend(image.map(|image| image.to_string()));
+    }
+    if let Some(site) = site {
+        if let Ok(page) = read_page(client, site.as_str()).await {
+            for href in icon_links(&page) {
+                if let Ok(icon) = site.join(&href) {
+                    urls.push(icon.to_string());
+                }
+            }
+        }
+        if let Ok(favicon) = site.join("/favicon.ico") {
+            urls.push(favicon.to_string());
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    urls.retain(|url| seen.insert(url.clone()));
+    urls
+}
+
+/// The function `icon_links` returns the `href` of the `<link rel="icon">`, `shortcut icon` and
+/// `apple-touch-icon` elements of an HTML page, in document order.
+pub fn icon_links(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel][href]").expect("Selector is valid");
+    document
+        .select(&selector)
+        .filter(|link| {
+            link.value()
+                .attr("rel")
+                .unwrap_or("")
+                .split_ascii_whitespace()
+                .any(|rel| {
+                    rel.eq_ignore_ascii_case("icon") || rel.eq_ignore_ascii_case("apple-touch-icon")
+                })
+        })
+        .filter_map(|link| link.value().attr("href"))
+        .map(|href| href.trim().to_string())
+        .filter(|href| !href.is_empty())
+        .collect()
+}
+
+/// The function `fetch` downloads the image at `url` and checks it is one.
+///
+/// Arguments:
+///
+/// * `client`: The `client` to download with.
+/// * `url`: The URL of the image.
+/// * `max_bytes`: The size after which the image is rejected.
+///
+/// Returns:
+///
+/// the detected MIME type and the bytes of the image.
+pub async fn fetch(
+    client: &HttpClient,
+    url: &str,
+    max_bytes: u64,
+) -> Result<(String, Vec<u8>), IconError> {
+    let url = Url::parse(url).map_err(|_| IconError::InvalidUrl(url.to_string()))?;
+    let mut client = client.clone();
+    client.max_body_bytes = client.max_body_bytes.min(max_bytes);
+    let mut res = client.get(url.as_str()).await?;
+    if !res.status().is_success() {
+        return Err(IconError::StatusError(res.status().as_u16()));
+    }
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("no content type")
+        .to_string();
+    let mut data = vec![];
+    let mut read_bytes = 0;
+    while let Some(chunk) = client.chunk(&mut res, &mut read_bytes).await? {
+        data.extend_from_slice(&chunk);
+    }
+    match detect_mime(&data) {
+        Some(mime_type) => Ok((mime_type.to_string(), data)),
+        None => Err(IconError::UnsupportedType(content_type)),
+    }
+}
+
+/// The function `refresh` downloads and stores the icon of each given feed from the first of its
+/// `candidates` that is an image. A feed without any is stored with the error of its best
+/// candidate, keeping the image of the previous refresh, so it is not retried before it is stale.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` holding the feeds and their icons.
+/// * `clients`: The `clients` of the feeds, an icon is downloaded with the client of its feed.
+/// * `rss_urls`: The URLs of the feeds to refresh.
+/// * `max_bytes`: The size after which an icon is rejected.
+pub async fn refresh(
+    connection: &ThreadSafe<&Connection>,
+    clients: &mut ClientPool,
+    rss_urls: &[String],
+    max_bytes: u64,
+) -> IconReport {
+    let mut report = IconReport::default();
+    let rss_entities = RssEntity::get_many(connection, GetManyOptions::new());
+    for rss_url in rss_urls {
+        let channel = rss_entities
+            .iter()
+            .find(|rss_entity| &rss_entity.rss_url == rss_url)
+            .map(|rss_entity| rss_entity.channel.clone())
+            .unwrap_or_default();
+        let mut icon = FeedIcon {
+            rss_url: rss_url.clone(),
+            source_url: String::new(),
+            mime_type: String::new(),
+            data: vec![],
+            fetched_at: None,
+            error: None,
+            connection: Some(connection.clone()),
+        };
+        match find_icon(connection, clients, rss_url, &channel, max_bytes).await {
+            Ok((source_url, mime_type, data)) => {
+                icon.source_url = source_url;
+                icon.mime_type = mime_type;
+                icon.data = data;
+            }
+            Err(e) => {
+                // the image of an earlier refresh is better than none
+                if let Some(previous) = FeedIcon::get(connection, rss_url) {
+                    icon.source_url = previous.source_url;
+                    icon.mime_type = previous.mime_type;
+                    icon.data = previous.data;
+                }
+                icon.error = Some(e.to_string());
+            }
+        }
+        match icon.save() {
+            Ok(_) if icon.error.is_none() => report.cached.push(rss_url.clone()),
+            Ok(_) => report
+                .errors
+                .push(format!("{}: {}", rss_url, icon.error.unwrap_or_default())),
+            Err(e) => report.errors.push(format!("{}: {}", rss_url, e)),
+        }
+    }
+    report
+}
+
+/// The function `refresh_stale` refreshes the icons of the feeds without one or whose icon is
+/// older than `REFRESH_AFTER_DAYS`, see `refresh`.
+pub async fn refresh_stale(
+    connection: &ThreadSafe<&Connection>,
+    clients: &mut ClientPool,
+    max_bytes: u64,
+) -> IconReport {
+    let rss_urls = FeedIcon::stale(connection, REFRESH_AFTER_DAYS);
+    refresh(connection, clients, &rss_urls, max_bytes).await
+}
+
+/// The function `icon_for` returns the cached icon of the feed with the given URL, `None` if it
+/// was never refreshed or no image could be found for it.
+pub fn icon_for<'c>(connection: &ThreadSafe<&Connection>, rss_url: &str) -> Option<FeedIcon<'c>> {
+    FeedIcon::get(connection, rss_url).filter(|icon| icon.is_available())
+}
+
+async fn find_icon(
+    connection: &ThreadSafe<&Connection>,
+    clients: &mut ClientPool,
+    rss_url: &str,
+    channel: &ChannelInfo,
+    max_bytes: u64,
+) -> Result<(String, String, Vec<u8>), IconError> {
+    let client = clients.for_feed(connection, rss_url)?;
+    let mut first_error = None;
+    for url in candidates(&client, rss_url, channel).await {
+        match fetch(&client, &url, max_bytes).await {
+            Ok((mime_type, data)) => return Ok((url, mime_type, data)),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or(IconError::NotFound))
+}
+
+async fn read_page(client: &HttpClient, url: &str) -> Result<String, IconError> {
+    let mut client = client.clone();
+    client.max_body_bytes = client.max_body_bytes.min(MAX_PAGE_BYTES);
+    let res = client.get(url).await?;
+    if !res.status().is_success() {
+        return Err(IconError::StatusError(res.status().as_u16()));
+    }
+    Ok(client.text(res).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::http_settings::HttpSettings, http::errors::HttpError};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+
+    /// The function `serve` answers one request with the raw `response` and returns the URL to
+    /// request.
+    async fn serve(response: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/favicon.ico", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let count = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..count]);
+            }
+            // the client may hang up once the body is over its limit
+            let _ = stream.write_all(&response).await;
+        });
+        url
+    }
+
+    fn response(content_type: &str, content_length: bool, body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nConnection: close\r\n",
+            content_type
+        );
+        if content_length {
+            response.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn client() -> HttpClient {
+        HttpClient::new(&HttpSettings::default()).unwrap()
+    }
+
+    #[test]
+    fn mime_is_sniffed_from_the_content() {
+        assert_eq!(detect_mime(PNG), Some("image/png"));
+        assert_eq!(
+            detect_mime(b"\xff\xd8\xff\xe0\x00\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(detect_mime(b"GIF89a\x01\x00"), Some("image/gif"));
+        assert_eq!(
+            detect_mime(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(
+            detect_mime(b"\x00\x00\x01\x00\x01\x00"),
+            Some("image/x-icon")
+        );
+        assert_eq!(detect_mime(b"BM\x36\x00"), Some("image/bmp"));
+        assert_eq!(
+            detect_mime(b"\xef\xbb\xbf  <SVG xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(
+            detect_mime(b"<?xml version=\"1.0\"?>\n<svg/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(detect_mime(b"<?xml version=\"1.0\"?>\n<rss/>"), None);
+        assert_eq!(detect_mime(b"<!DOCTYPE html><html></html>"), None);
+        assert_eq!(detect_mime(b"RIFF\x24\x00\x00\x00WAVE"), None);
+        assert_eq!(detect_mime(b""), None);
+    }
+
+    #[test]
+    fn icon_links_are_read_in_document_order() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="Shortcut Icon" href=" /favicon.png ">
+            <link rel="apple-touch-icon" href="/touch.png">
+            <link rel="icon" href="">
+            <link rel="icon">
+            <link rel="iconic" href="/not-an-icon.png">
+        </head></html>"#;
+        assert_eq!(icon_links(html), vec!["/favicon.png", "/touch.png"]);
+    }
+
+    #[tokio::test]
+    async fn content_type_of_the_server_is_ignored() {
+        let url = serve(response("text/html", true, PNG)).await;
+        let (mime_type, data) = fetch(&client(), &url, DEFAULT_MAX_ICON_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(mime_type, "image/png");
+        assert_eq!(data, PNG);
+
+        let url = serve(response("image/png", true, b"<html>Not found</html>")).await;
+        match fetch(&client(), &url, DEFAULT_MAX_ICON_BYTES).await {
+            Err(IconError::UnsupportedType(content_type)) => {
+                assert_eq!(content_type, "image/png")
+            }
+            other => panic!("unexpected result {:?}", other.map(|(mime, _)| mime)),
+        }
+    }
+
+    #[tokio::test]
+    async fn icons_over_the_size_limit_are_rejected() {
+        let mut large = PNG.to_vec();
+        large.resize(2048, 0);
+
+        // announced by the `Content-Length`
+        let url = serve(response("image/png", true, &large)).await;
+        assert!(matches!(
+            fetch(&client(), &url, 1024).await,
+            Err(IconError::HttpError(HttpError::BodyTooLarge(1024)))
+        ));
+
+        // only noticed while reading the body
+        let url = serve(response("image/png", false, &large)).await;
+        assert!(matches!(
+            fetch(&client(), &url, 1024).await,
+            Err(IconError::HttpError(HttpError::BodyTooLarge(1024)))
+        ));
+
+        let url = serve(response("image/png", false, &large)).await;
+        let (_, data) = fetch(&client(), &url, 2048).await.unwrap();
+        assert_eq!(data.len(), 2048);
+    }
+
+    #[tokio::test]
+    async fn invalid_url_is_rejected_before_any_request() {
+        assert!(matches!(
+            fetch(&client(), "not a url", DEFAULT_MAX_ICON_BYTES).await,
+            Err(IconError::InvalidUrl(_))
+        ));
+    }
+}
@@ -1,3 +1,18 @@
+pub mod article;
+pub mod backup;
 pub mod core;
 pub mod db;
+pub mod digest;
+pub mod download;
+pub mod hooks;
+pub mod http;
+pub mod icons;
+pub mod mail;
+pub mod notify;
+pub mod prune;
+pub mod render;
 pub mod rss;
+pub mod rules;
+pub mod secrets;
+pub mod storage;
+pub mod tui;
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use crate::secrets::errors::SecretError;
+
+#[derive(Debug)]
+pub enum MailError {
+    IoError(std::io::Error),
+    TlsError(native_tls::Error),
+    SecretError(SecretError),
+    DbError(sqlite::Error),
+    SmtpError(u16, String),
+    InvalidAddress(String),
+    Timeout,
+}
+
+/// The `impl fmt::Display for MailError` block formats the reason an email could not be sent.
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            MailError::IoError(e) => write!(f, "Connection to the SMTP server failed: {}", e),
+            MailError::TlsError(e) => write!(f, "TLS with the SMTP server failed: {}", e),
+            MailError::SecretError(e) => write!(f, "{}", e),
+            MailError::DbError(e) => write!(f, "Recording the mailed entries failed: {}", e),
+            MailError::SmtpError(code, message) => {
+                write!(f, "SMTP server replied {} {}", code, message)
+            }
+            MailError::InvalidAddress(address) => write!(f, "Invalid email address: {}", address),
+            MailError::Timeout => write!(f, "The SMTP server did not answer in time"),
+        }
+    }
+}
+
+impl From<std::io::Error> for MailError {
+    fn from(error: std::io::Error) -> Self {
+        MailError::IoError(error)
+    }
+}
+
+impl From<native_tls::Error> for MailError {
+    fn from(error: native_tls::Error) -> Self {
+        MailError::TlsError(error)
+    }
+}
+
+impl From<SecretError> for MailError {
+    fn from(error: SecretError) -> Self {
+        MailError::SecretError(error)
+    }
+}
+
+impl From<sqlite::Error> for MailError {
+    fn from(error: sqlite::Error) -> Self {
+        MailError::DbError(error)
+    }
+}
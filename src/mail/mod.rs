@@ -0,0 +1,651 @@
+pub mod errors;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use sqlite::Connection;
+use std::time::Duration;
+use thread_safe::ThreadSafe;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::{
+    db::{
+        blog::RssEntry,
+        smtp_settings::{SmtpSecurity, SmtpSettings},
+    },
+    digest::{Digest, DigestFormat},
+};
+use errors::MailError;
+
+/// The time the whole SMTP conversation may take before it is abandoned.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The length of the base64 lines of a message body, as recommended by RFC 2045.
+const BASE64_LINE_LENGTH: usize = 76;
+
+/// The `Message` struct is an email with a plain text and an HTML version of the same content.
+///
+/// Properties:
+///
+/// * `from`: The address of the sender.
+/// * `to`: The address of the recipient.
+/// * `subject`: The subject of the email.
+/// * `text`: The plain text version, shown by clients without HTML.
+/// * `html`: The HTML version.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+impl Message {
+    /// The function `to_mime` returns the message as a `multipart/alternative` MIME document with
+    /// CRLF line endings, ready to be sent after `DATA`.
+    pub fn to_mime(&self) -> String {
+        let mut random = [0u8; 12];
+        let _ = getrandom::getrandom(&mut random);
+        let token: String = random.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let domain = self
+            .from
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or("localhost");
+        let boundary = format!("rss_rust-{}", token);
+        let headers = [
+            format!("Date: {}", Utc::now().to_rfc2822()),
+            format!("From: {}", self.from),
+            format!("To: {}", self.to),
+            format!("Subject: {}", encode_header(&self.subject)),
+            format!("Message-ID: <{}@{}>", token, domain),
+            String::from("MIME-Version: 1.0"),
+            format!(
+                "Content-Type: multipart/alternative; boundary=\"{}\"",
+                boundary
+            ),
+        ];
+        let mut mime = headers.join("\r\n");
+        mime.push_str("\r\n\r\n");
+        for (content_type, body) in [("text/plain", &self.text), ("text/html", &self.html)] {
+            mime.push_str(&format!(
+                "--{}\r\nContent-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n",
+                boundary, content_type
+            ));
+            let encoded = STANDARD.encode(body.as_bytes());
+            for line in encoded.as_bytes().chunks(BASE64_LINE_LENGTH) {
+                mime.push_str(std::str::from_utf8(line).unwrap_or_default());
+                mime.push_str("\r\n");
+            }
+        }
+        mime.push_str(&format!("--{}--\r\n", boundary));
+        mime
+    }
+}
+
+/// The function `encode_header` returns a header value as it is, or as RFC 2047 encoded words when
+/// it is not plain ASCII. Line breaks are removed so the value cannot add headers.
+fn encode_header(value: &str) -> String {
+    let value: String = value.chars().filter(|c| *c != '\r' && *c != '\n').collect();
+    if value.is_ascii() {
+        return value;
+    }
+    // encoded words are at most 75 characters, 45 bytes of text fit in one
+    let mut words = vec![];
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > 45 {
+            words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    }
+    words.join("\r\n ")
+}
+
+/// The function `check_address` rejects what cannot be used as a bare email address in an SMTP
+/// command or a header.
+///
+/// Arguments:
+///
+/// * `address`: The address, like `team@example.com`.
+///
+/// Returns:
+///
+/// The function `check_address` returns `MailError::InvalidAddress` for an invalid address.
+pub fn check_address(address: &str) -> Result<(), MailError> {
+    let valid = match address.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.is_empty()
+                && !domain.contains('@')
+                && !address
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ','))
+        }
+        None => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(MailError::InvalidAddress(address.to_string()))
+    }
+}
+
+trait SmtpStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> SmtpStream for T {}
+
+/// The `SmtpClient` struct is a connection to an SMTP server, plain or encrypted.
+struct SmtpClient {
+    stream: BufReader<Box<dyn SmtpStream>>,
+}
+
+impl SmtpClient {
+    /// The function `reply` reads a reply of the server, joining the lines of a multi-line reply.
+    async fn reply(&mut self) -> Result<(u16, Vec<String>), MailError> {
+        let mut lines = vec![];
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(MailError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "the server closed the connection",
+                )));
+            }
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| MailError::SmtpError(0, line.to_string()))?;
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, lines));
+            }
+        }
+    }
+
+    /// The function `expect` reads a reply and fails unless its code is one of `expected`.
+    async fn expect(&mut self, expected: &[u16]) -> Result<Vec<String>, MailError> {
+        let (code, lines) = self.reply().await?;
+        if expected.contains(&code) {
+            Ok(lines)
+        } else {
+            Err(MailError::SmtpError(code, lines.join(" ")))
+        }
+    }
+
+    /// The function `command` sends a command and reads its reply, see `expect`.
+    async fn command(&mut self, command: &str, expected: &[u16]) -> Result<Vec<String>, MailError> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .await?;
+        self.stream.get_mut().flush().await?;
+        self.expect(expected).await
+    }
+
+    /// The function `upgrade` wraps the connection in TLS, checking the certificate of `host`.
+    async fn upgrade(self, host: &str) -> Result<SmtpClient, MailError> {
+        let connector = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+        let stream = connector.connect(host, self.stream.into_inner()).await?;
+        Ok(SmtpClient {
+            stream: BufReader::new(Box::new(stream)),
+        })
+    }
+}
+
+/// The function `send` delivers a message through the SMTP server of `settings`.
+///
+/// Arguments:
+///
+/// * `settings`: The server to send through.
+/// * `password`: The revealed password of `settings`, if it has one.
+/// * `message`: The message to send.
+///
+/// Returns:
+///
+/// The function `send` returns a `Result<(), MailError>`, the server having accepted the message
+/// on success.
+pub async fn send(
+    settings: &SmtpSettings<'_>,
+    password: Option<&str>,
+    message: &Message,
+) -> Result<(), MailError> {
+    check_address(&message.from)?;
+    check_address(&message.to)?;
+    tokio::time::timeout(SMTP_TIMEOUT, converse(settings, password, message))
+        .await
+        .map_err(|_| MailError::Timeout)?
+}
+
+async fn converse(
+    settings: &SmtpSettings<'_>,
+    password: Option<&str>,
+    message: &Message,
+) -> Result<(), MailError> {
+    let stream = TcpStream::connect((settings.host.as_str(), settings.port())).await?;
+    let mut client = SmtpClient {
+        stream: BufReader::new(Box::new(stream)),
+    };
+    if settings.security == SmtpSecurity::Tls {
+        client = client.upgrade(&settings.host).await?;
+    }
+    client.expect(&[220]).await?;
+
+    let hello = format!(
+        "EHLO {}",
+        message
+            .from
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or("localhost")
+    );
+    let mut extensions = client.command(&hello, &[250]).await?;
+    if settings.security == SmtpSecurity::StartTls {
+        if !extensions
+            .iter()
+            .any(|extension| extension.eq_ignore_ascii_case("STARTTLS"))
+        {
+            return Err(MailError::SmtpError(
+                0,
+                String::from("the server does not offer STARTTLS"),
+            ));
+        }
+        client.command("STARTTLS", &[220]).await?;
+        client = client.upgrade(&settings.host).await?;
+        extensions = client.command(&hello, &[250]).await?;
+    }
+
+    if let Some(username) = &settings.username {
+        let password = password.unwrap_or_default();
+        let mechanisms = extensions
+            .iter()
+            .find_map(|extension| {
+                let (name, mechanisms) = extension.split_once(' ')?;
+                name.eq_ignore_ascii_case("AUTH")
+                    .then(|| mechanisms.to_uppercase())
+            })
+            .unwrap_or_default();
+        if mechanisms
+            .split_whitespace()
+            .any(|mechanism| mechanism == "LOGIN")
+            && !mechanisms
+                .split_whitespace()
+                .any(|mechanism| mechanism == "PLAIN")
+        {
+            client.command("AUTH LOGIN", &[334]).await?;
+            client.command(&STANDARD.encode(username), &[334]).await?;
+            client.command(&STANDARD.encode(password), &[235]).await?;
+        } else {
+            let credentials = STANDARD.encode(format!("\0{}\0{}", username, password));
+            client
+                .command(&format!("AUTH PLAIN {}", credentials), &[235])
+                .await?;
+        }
+    }
+
+    client
+        .command(&format!("MAIL FROM:<{}>", message.from), &[250])
+        .await?;
+    client
+        .command(&format!("RCPT TO:<{}>", message.to), &[250, 251])
+        .await?;
+    client.command("DATA", &[354]).await?;
+    client
+        .command(&dot_stuff(&message.to_mime()), &[250])
+        .await?;
+    let _ = client.command("QUIT", &[221]).await;
+    Ok(())
+}
+
+/// The function `dot_stuff` returns the content sent after `DATA`: the lines of `mime` with a `.`
+/// doubled at the start of a line, so the server does not take it for the end of the message,
+/// followed by the terminating `.` line, whose CRLF is added by `SmtpClient::command`.
+fn dot_stuff(mime: &str) -> String {
+    let mut data = String::new();
+    for line in mime.split_terminator("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push('.');
+    data
+}
+
+/// The function `send_digest` mails the entries of `digest` that were not mailed to `recipient`
+/// yet, as a plain text and an HTML version, and records them as mailed once the server accepted
+/// the message.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` to the database tracking the mailed entries.
+/// * `settings`: The server to send through.
+/// * `password`: The revealed password of `settings`, if it has one.
+/// * `digest`: The digest of the period.
+/// * `recipient`: The address to send to.
+/// * `template`: A document template replacing the built-in one of its format.
+///
+/// Returns:
+///
+/// The function `send_digest` returns the number of entries sent, 0 when there was nothing new and
+/// no email was sent.
+pub async fn send_digest(
+    connection: &ThreadSafe<&Connection>,
+    settings: &SmtpSettings<'_>,
+    password: Option<&str>,
+    digest: &Digest,
+    recipient: &str,
+    template: Option<(DigestFormat, &str)>,
+) -> Result<usize, MailError> {
+    let mut digest = digest.clone();
+    digest.retain(|entry| !RssEntry::is_mailed(connection, recipient, &entry.hash));
+    if digest.entry_count() == 0 {
+        return Ok(0);
+    }
+    let render = |format: DigestFormat| {
+        let template = template
+            .filter(|(template_format, _)| *template_format == format)
+            .map(|(_, template)| template);
+        digest.render(format, template)
+    };
+    let message = Message {
+        from: settings.from.clone(),
+        to: recipient.to_string(),
+        subject: digest.options.title.clone(),
+        text: render(DigestFormat::Markdown),
+        html: render(DigestFormat::Html),
+    };
+    send(settings, password, &message).await?;
+    RssEntry::set_mailed(connection, recipient, &digest.hashes())?;
+    Ok(digest.entry_count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::traits::CrudAble, db::open_database, digest::DigestOptions, rss::dto::Item,
+        storage::SqliteStorage,
+    };
+    use tokio::net::TcpListener;
+
+    /// The `Script` struct is how the fake SMTP server of `serve` answers.
+    ///
+    /// Properties:
+    ///
+    /// * `extensions`: The extensions listed in the reply to `EHLO`.
+    /// * `rcpt`: The reply to `RCPT TO`.
+    /// * `queued`: The reply to the end of the message.
+    struct Script {
+        extensions: &'static [&'static str],
+        rcpt: &'static str,
+        queued: &'static str,
+    }
+
+    const ACCEPT: Script = Script {
+        extensions: &["8BITMIME"],
+        rcpt: "250 2.1.5 Ok",
+        queued: "250 2.0.0 Ok: queued",
+    };
+
+    /// The function `serve` runs a fake SMTP server for one conversation and returns its port and
+    /// the lines it received.
+    async fn serve(script: Script) -> (u16, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut received = vec![];
+            let mut in_data = false;
+            let mut login = 0;
+            stream
+                .get_mut()
+                .write_all(b"220 fake ESMTP\r\n")
+                .await
+                .unwrap();
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end_matches("\r\n").to_string();
+                received.push(line.clone());
+                let command = line.to_uppercase();
+                let reply = if in_data {
+                    if line != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    script.queued.to_string()
+                } else if login > 0 {
+                    login -= 1;
+                    if login == 1 {
+                        String::from("334 UGFzc3dvcmQ6")
+                    } else {
+                        String::from("235 2.7.0 Authenticated")
+                    }
+                } else if command.starts_with("EHLO") {
+                    let mut reply = String::from("250-fake");
+                    for extension in script.extensions {
+                        reply.push_str(&format!("\r\n250-{}", extension));
+                    }
+                    reply.push_str("\r\n250 SMTPUTF8");
+                    reply
+                } else if command == "AUTH LOGIN" {
+                    login = 2;
+                    String::from("334 VXNlcm5hbWU6")
+                } else if command.starts_with("AUTH PLAIN") {
+                    String::from("235 2.7.0 Authenticated")
+                } else if command.starts_with("MAIL FROM") {
+                    String::from("250 2.1.0 Ok")
+                } else if command.starts_with("RCPT TO") {
+                    script.rcpt.to_string()
+                } else if command == "DATA" {
+                    in_data = true;
+                    String::from("354 End data with <CR><LF>.<CR><LF>")
+                } else if command == "QUIT" {
+                    let _ = stream.get_mut().write_all(b"221 2.0.0 Bye\r\n").await;
+                    break;
+                } else {
+                    String::from("502 5.5.2 Command not recognized")
+                };
+                stream
+                    .get_mut()
+                    .write_all(format!("{}\r\n", reply).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    fn settings<'c>(port: u16, username: Option<&str>) -> SmtpSettings<'c> {
+        SmtpSettings {
+            host: String::from("127.0.0.1"),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: username.map(|username| username.to_string()),
+            from: String::from("feeds@example.com"),
+            ..Default::default()
+        }
+    }
+
+    fn message() -> Message {
+        Message {
+            from: String::from("feeds@example.com"),
+            to: String::from("team@example.com"),
+            subject: String::from("Weekly digest"),
+            text: String::from("Hello"),
+            html: String::from("<p>Hello</p>"),
+        }
+    }
+
+    #[test]
+    fn lines_starting_with_a_dot_are_stuffed() {
+        assert_eq!(
+            dot_stuff("Hello\r\n.hidden\r\n..two\r\n\r\nend.\r\n"),
+            "Hello\r\n..hidden\r\n...two\r\n\r\nend.\r\n."
+        );
+        assert_eq!(dot_stuff(".\r\n"), "..\r\n.");
+    }
+
+    #[tokio::test]
+    async fn message_is_sent_without_authentication() {
+        let (port, server) = serve(ACCEPT).await;
+        send(&settings(port, None), None, &message()).await.unwrap();
+        let received = server.await.unwrap();
+
+        assert_eq!(received[0], "EHLO example.com");
+        assert!(!received.iter().any(|line| line.starts_with("AUTH")));
+        let data = received.iter().position(|line| line == "DATA").unwrap();
+        assert_eq!(
+            received[1..data],
+            [
+                "MAIL FROM:<feeds@example.com>",
+                "RCPT TO:<team@example.com>"
+            ]
+        );
+        assert!(received.contains(&String::from("Subject: Weekly digest")));
+        assert!(received.contains(&String::from("To: team@example.com")));
+        assert!(received.contains(&STANDARD.encode("<p>Hello</p>")));
+        assert_eq!(received[received.len() - 2..], [".", "QUIT"]);
+    }
+
+    #[tokio::test]
+    async fn auth_plain_is_preferred() {
+        let (port, server) = serve(Script {
+            extensions: &["AUTH LOGIN PLAIN"],
+            ..ACCEPT
+        })
+        .await;
+        send(&settings(port, Some("user")), Some("secret"), &message())
+            .await
+            .unwrap();
+        let received = server.await.unwrap();
+        assert_eq!(
+            received[1],
+            format!("AUTH PLAIN {}", STANDARD.encode("\0user\0secret"))
+        );
+        assert_eq!(received[2], "MAIL FROM:<feeds@example.com>");
+    }
+
+    #[tokio::test]
+    async fn auth_login_is_used_when_plain_is_not_offered() {
+        let (port, server) = serve(Script {
+            extensions: &["AUTH LOGIN"],
+            ..ACCEPT
+        })
+        .await;
+        send(&settings(port, Some("user")), Some("secret"), &message())
+            .await
+            .unwrap();
+        let received = server.await.unwrap();
+        assert_eq!(
+            received[1..4],
+            [
+                String::from("AUTH LOGIN"),
+                STANDARD.encode("user"),
+                STANDARD.encode("secret")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_recipient_stops_the_conversation() {
+        let (port, server) = serve(Script {
+            rcpt: "550 5.1.1 No such user",
+            ..ACCEPT
+        })
+        .await;
+        match send(&settings(port, None), None, &message()).await {
+            Err(MailError::SmtpError(code, reply)) => {
+                assert_eq!(code, 550);
+                assert_eq!(reply, "5.1.1 No such user");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let received = server.await.unwrap();
+        assert!(!received.contains(&String::from("DATA")));
+    }
+
+    #[tokio::test]
+    async fn digest_entries_are_only_marked_mailed_once_sent() {
+        let connection = open_database(":memory:").unwrap();
+        let connection = ThreadSafe::new(&connection);
+        for title in ["First", "Second"] {
+            let mut rss_entry = RssEntry::from(Item {
+                title: title.to_string(),
+                link: format!("https://a.test/{}", title),
+                ..Default::default()
+            });
+            rss_entry.rss_url = String::from("https://a.test/rss");
+            rss_entry.connection = Some(connection.clone());
+            rss_entry.save().unwrap();
+        }
+        let digest = Digest::collect(&SqliteStorage::new(&connection), &DigestOptions::default());
+        assert_eq!(digest.entry_count(), 2);
+        let mailed = |recipient: &str| {
+            digest
+                .hashes()
+                .iter()
+                .filter(|hash| RssEntry::is_mailed(&connection, recipient, hash))
+                .count()
+        };
+
+        let (port, server) = serve(Script {
+            queued: "554 5.7.1 Rejected as spam",
+            ..ACCEPT
+        })
+        .await;
+        let result = send_digest(
+            &connection,
+            &settings(port, None),
+            None,
+            &digest,
+            "team@example.com",
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(MailError::SmtpError(554, _))));
+        server.await.unwrap();
+        assert_eq!(mailed("team@example.com"), 0);
+
+        let (port, server) = serve(ACCEPT).await;
+        let sent = send_digest(
+            &connection,
+            &settings(port, None),
+            None,
+            &digest,
+            "team@example.com",
+            None,
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        assert_eq!(sent, 2);
+        assert_eq!(mailed("team@example.com"), 2);
+        assert_eq!(mailed("other@example.com"), 0);
+
+        // nothing new for this recipient, the server is not even contacted
+        let sent = send_digest(
+            &connection,
+            &settings(port, None),
+            None,
+            &digest,
+            "team@example.com",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(sent, 0);
+    }
+}
@@ -1,14 +1,50 @@
+use clap::ArgMatches;
 use rss_rust::{
-    core::{args::get_command_line_args, structs::GetManyOptions, traits::CrudAble},
-    db::{blog::RssEntry, rss_entity::RssEntity},
-    rss::Rss,
+    backup::{self, BackupLayout, RestoreMode},
+    core::{
+        args::get_command_line_args,
+        structs::{GetManyOptions, SaveOutcome, SyncOptions},
+        traits::CrudAble,
+    },
+    db::{
+        blog::RssEntry,
+        download_settings::DownloadSettings,
+        enclosure::EntryEnclosure,
+        entry_content::EntryContent,
+        feed_auth::FeedAuth,
+        feed_icon::FeedIcon,
+        hook::Hook,
+        http_settings::HttpSettings,
+        media::EntryMedia,
+        notification_sink::NotificationSink,
+        retention_policy::RetentionPolicy,
+        rss_entity::{fetch_content, fetch_feed, RssEntity},
+        rule::{Rule, RuleField, RuleOperator},
+        saved_search::{resolve_date, SavedSearch},
+        smtp_settings::SmtpSettings,
+        tag::normalize_tag,
+    },
+    digest::{Digest, DigestFormat, DigestOptions},
+    download,
+    http::{ClientPool, HttpClient},
+    icons, mail, notify,
+    prune::{self, PruneOptions},
+    render::{self, RenderOptions},
+    rss::{FeedSource, Rss},
+    rules::{self, RuleSet},
+    secrets::{redact_path, Keyring, Secret},
+    storage::{SqliteStorage, Storage},
+    tui,
 };
+use sqlite::Connection;
+use std::path::{Path, PathBuf};
 use thread_safe::ThreadSafe;
 
 #[tokio::main]
 async fn main() {
     let raw_connection = rss_rust::db::get_database_connection();
     let connection = ThreadSafe::new(&raw_connection);
+    let storage = SqliteStorage::new(&connection);
 
     let matches = get_command_line_args().get_matches();
     let mut options = GetManyOptions::new();
@@ -16,49 +52,1146 @@ async fn main() {
     if let Some(matches) = matches.subcommand_matches("add") {
         let url = matches.value_of("url").expect("URL IS REQUIRED");
         let profile = matches.value_of("profile").expect("PROFILE IS REQUIRED");
+        if FeedSource::from(url) == FeedSource::Stdin {
+            // stdin cannot be read again on sync, so its entries are imported once
+            let mut rss = Rss::read_source(FeedSource::Stdin, None)
+                .await
+                .expect("Parsing of the standard input failed");
+            let mut imported = 0;
+            for item in rss.get_items().iter() {
+                let mut rss_entry = RssEntry::from(item.clone());
+                rss_entry.rss_url = url.to_string();
+                rss_entry.connection = Some(connection.clone());
+                if storage
+                    .save_entry(&rss_entry)
+                    .expect("Saving the entry failed")
+                    == SaveOutcome::Inserted
+                {
+                    rss_entry
+                        .save_podcast(item)
+                        .expect("Saving the podcast metadata failed");
+                    rss_entry
+                        .save_media(item)
+                        .expect("Saving the media metadata failed");
+                    storage
+                        .save_details(&rss_entry, item)
+                        .expect("Saving the author and categories failed");
+                    imported += 1;
+                }
+            }
+            println!("{}\n{} entries imported", rss.title(), imported);
+            return;
+        }
+        let (_, rss) = fetch_feed(
+            &connection,
+            &mut ClientPool::new(),
+            &mut Keyring::new(&connection),
+            url,
+            None,
+        )
+        .await
+        .expect("Parsing of the given rss url failed");
 
-        let rss_entity = RssEntity {
-            connection: Some(connection),
+        let mut rss_entity = RssEntity {
+            connection: None,
             id: 12,
             profile: profile.to_string(),
             rss_url: url.to_string(),
+            full_text: matches.is_present("full-text"),
+            encoding: String::new(),
+            auto_download: matches.is_present("auto-download"),
+            channel: rss.channel_info(),
+            display_title: matches
+                .value_of("title")
+                .map(|title| title.trim().to_string())
+                .filter(|title| !title.is_empty()),
         };
-        let _ = Rss::parse(String::from(&rss_entity.rss_url))
+        println!(
+            "{}\n{}",
+            render::strip_controls(rss.title()),
+            render::strip_controls(rss.description())
+        );
+        rss_entity.encoding = rss.encoding().to_string();
+        storage
+            .save_feed(&rss_entity)
+            .expect("Saving the rss feed failed");
+    } else if let Some(matches) = matches.subcommand_matches("search") {
+        let options = search_from_args(matches, SavedSearch::default())
+            .to_options()
+            .expect("Invalid search");
+        print_entries(&connection, &storage, matches, options);
+    } else if let Some(matches) = matches.subcommand_matches("saved") {
+        if let Some(matches) = matches.subcommand_matches("add") {
+            let name = matches.value_of("name").expect("NAME IS REQUIRED");
+            if SavedSearch::get(&connection, name).is_some() {
+                panic!("A saved search is already named {}, use saved edit", name);
+            }
+            let mut saved_search = search_from_args(matches, SavedSearch::default());
+            saved_search.name = name.to_string();
+            saved_search.connection = Some(connection);
+            saved_search.save().expect("Saving the search failed");
+            println!("{}", saved_search);
+        } else if let Some(matches) = matches.subcommand_matches("edit") {
+            let name = matches.value_of("name").expect("NAME IS REQUIRED");
+            let saved_search =
+                SavedSearch::get(&connection, name).expect("No saved search has the given name");
+            let base = if matches.is_present("keep") {
+                saved_search.clone()
+            } else {
+                SavedSearch {
+                    id: saved_search.id,
+                    name: saved_search.name.clone(),
+                    ..SavedSearch::default()
+                }
+            };
+            let mut saved_search = search_from_args(matches, base);
+            saved_search.connection = Some(connection.clone());
+            saved_search.save().expect("Saving the search failed");
+            if let Some(new_name) = matches.value_of("rename") {
+                SavedSearch::rename(&connection, name, new_name)
+                    .expect("Renaming the search failed");
+                saved_search.name = new_name.to_string();
+            }
+            println!("{}", saved_search);
+        } else if matches.subcommand_matches("list").is_some() {
+            options.set_limit(u32::MAX as u64);
+            for saved_search in SavedSearch::get_many(&connection, options) {
+                println!(
+                    "{} unread\t{}",
+                    saved_search.unread_count(&connection),
+                    saved_search
+                );
+            }
+        } else if let Some(matches) = matches.subcommand_matches("remove") {
+            let name = matches.value_of("name").expect("NAME IS REQUIRED");
+            if !SavedSearch::delete(&connection, name).expect("Removing the search failed") {
+                println!("No saved search is named {}", name);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("run") {
+            let name = matches.value_of("name").expect("NAME IS REQUIRED");
+            let options = SavedSearch::get(&connection, name)
+                .expect("No saved search has the given name")
+                .to_options()
+                .expect("Invalid saved search");
+            print_entries(&connection, &storage, matches, options);
+        }
+    } else if let Some(tag_matches) = matches
+        .subcommand_matches("tag")
+        .or_else(|| matches.subcommand_matches("untag"))
+    {
+        let untag = matches.subcommand_matches("untag").is_some();
+        let tags: Vec<String> = tag_matches
+            .values_of("tags")
+            .expect("TAGS ARE REQUIRED")
+            .map(|tag| normalize_tag(tag).expect("TAG MUST NOT BE EMPTY OR HAVE A COMMA"))
+            .collect();
+        if let Some(key) = tag_matches.value_of("entry") {
+            let rss_entry = storage
+                .find_entry(key)
+                .expect("No entry matches the given id");
+            for tag in tags.iter() {
+                if untag {
+                    storage.remove_entry_tag(&rss_entry, tag)
+                } else {
+                    storage.add_entry_tag(&rss_entry, tag)
+                }
+                .expect("Updating the tags failed");
+            }
+            println!(
+                "{}\t{}",
+                rss_entry.title,
+                storage.entry_tags(&rss_entry).join(", ")
+            );
+        } else if let Some(url) = tag_matches.value_of("feed") {
+            storage
+                .get_feeds()
+                .into_iter()
+                .find(|rss_entity| rss_entity.rss_url == url)
+                .expect("No subscribed feed has the given url");
+            for tag in tags.iter() {
+                if untag {
+                    storage.remove_feed_tag(url, tag)
+                } else {
+                    storage.add_feed_tag(url, tag)
+                }
+                .expect("Updating the tags failed");
+            }
+            println!("{}\t{}", url, storage.feed_tags(url).join(", "));
+        }
+    } else if matches.subcommand_matches("tags").is_some() {
+        println!(
+            "{:<30} {:>7} {:>5} {:>9}",
+            "TAG", "ENTRIES", "FEEDS", "INHERITED"
+        );
+        for tag_count in storage.tag_counts() {
+            println!("{}", tag_count);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("sync") {
+        let mut sync_options = SyncOptions::new();
+        sync_options.set_full_text(matches.is_present("full-text"));
+        sync_options.set_max_items(matches.value_of("max-items").map(|max_items| {
+            max_items
+                .parse::<usize>()
+                .expect("MAX ITEMS MUST BE A NUMBER")
+        }));
+        let report = RssEntity::sync(&storage, Some(&connection), sync_options).await;
+        match matches.value_of("format") {
+            Some("json") => println!("{}", report.to_json()),
+            _ => print!("{}", report),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("feed") {
+        if let Some(matches) = matches.subcommand_matches("list") {
+            for rss_entity in storage.get_feeds() {
+                println!("{}", rss_entity);
+                let tags = storage.feed_tags(&rss_entity.rss_url);
+                if !tags.is_empty() {
+                    println!("    Tags: {}", tags.join(", "));
+                }
+                if matches.is_present("verbose") {
+                    let channel = &rss_entity.channel;
+                    for (label, value) in [
+                        ("Channel title", &channel.title),
+                        ("Site", &channel.link),
+                        ("Description", &channel.description),
+                        ("Language", &channel.language),
+                        ("Image", &channel.image_url),
+                        ("Generator", &channel.generator),
+                        ("Last build", &channel.last_build_date),
+                        ("TTL (minutes)", &channel.ttl),
+                    ] {
+                        if !value.trim().is_empty() {
+                            println!("    {}: {}", label, value.trim());
+                        }
+                    }
+                }
+            }
+        } else if let Some(matches) = matches.subcommand_matches("set") {
+            let url = matches.value_of("url").expect("URL IS REQUIRED");
+            if let Some(full_text) = matches.value_of("full-text") {
+                storage
+                    .set_full_text(url, full_text == "on")
+                    .expect("Updating the feed failed");
+            }
+            if let Some(auto_download) = matches.value_of("auto-download") {
+                storage
+                    .set_auto_download(url, auto_download == "on")
+                    .expect("Updating the feed failed");
+            }
+            if let Some(title) = matches.value_of("title") {
+                let title = Some(title.trim()).filter(|title| !title.is_empty());
+                storage
+                    .set_display_title(url, title)
+                    .expect("Updating the feed failed");
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("fulltext") {
+        options.query = matches.value_of("regex").unwrap_or("").to_string();
+        options.set_limit(
+            matches
+                .value_of("limit")
+                .unwrap_or("20")
+                .parse::<u64>()
+                .expect("LIMIT MUST BE A NUMBER"),
+        );
+        let client = ClientPool::new()
+            .global(&connection)
+            .expect("Building the http client failed");
+        for (hash, link) in
+            EntryContent::missing(&connection, options, matches.is_present("refetch"))
+        {
+            match fetch_content(&client, &storage, &hash, &link).await {
+                Ok(()) => println!("fetched {}", link),
+                Err(e) => println!("failed {}: {}", link, e),
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("hook") {
+        if let Some(matches) = matches.subcommand_matches("add") {
+            let hook = Hook {
+                id: 0,
+                profile: matches
+                    .value_of("profile")
+                    .map(|profile| profile.to_string()),
+                command: matches
+                    .value_of("command")
+                    .expect("COMMAND IS REQUIRED")
+                    .to_string(),
+                timeout_secs: matches
+                    .value_of("timeout")
+                    .unwrap_or("30")
+                    .parse::<u64>()
+                    .expect("TIMEOUT MUST BE A NUMBER"),
+                connection: Some(connection),
+            };
+            hook.save().expect("Saving the hook failed");
+        } else if matches.subcommand_matches("list").is_some() {
+            for hook in Hook::get_many(&connection, options) {
+                println!("{}", hook);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("remove") {
+            let id = matches
+                .value_of("id")
+                .expect("ID IS REQUIRED")
+                .parse::<i64>()
+                .expect("ID MUST BE A NUMBER");
+            Hook::delete(&connection, id).expect("Removing the hook failed");
+        } else if let Some(matches) = matches.subcommand_matches("failures") {
+            let limit = matches
+                .value_of("limit")
+                .unwrap_or("20")
+                .parse::<u64>()
+                .unwrap_or(20);
+            for (failed_at, hook_id, entry_hash, error) in Hook::get_failures(&connection, limit) {
+                println!(
+                    "{}\thook = {}\tentry = {}\t{}",
+                    failed_at, hook_id, entry_hash, error
+                );
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("notify") {
+        if let Some(matches) = matches.subcommand_matches("add") {
+            let sink = NotificationSink {
+                id: 0,
+                kind: matches
+                    .value_of("kind")
+                    .unwrap_or("json")
+                    .parse()
+                    .expect("UNKNOWN KIND"),
+                url: matches
+                    .value_of("url")
+                    .expect("URL IS REQUIRED")
+                    .to_string(),
+                profile: matches
+                    .value_of("profile")
+                    .map(|profile| profile.to_string()),
+                rss_url: matches.value_of("feed").map(|feed| feed.to_string()),
+                max_retries: matches
+                    .value_of("retries")
+                    .unwrap_or("3")
+                    .parse::<u32>()
+                    .expect("RETRIES MUST BE A NUMBER"),
+                connection: Some(connection),
+            };
+            sink.save().expect("Saving the notification sink failed");
+        } else if matches.subcommand_matches("list").is_some() {
+            for sink in NotificationSink::get_many(&connection, options) {
+                println!("{}", sink);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("remove") {
+            let id = matches
+                .value_of("id")
+                .expect("ID IS REQUIRED")
+                .parse::<i64>()
+                .expect("ID MUST BE A NUMBER");
+            NotificationSink::delete(&connection, id).expect("Removing the sink failed");
+        } else if let Some(matches) = matches.subcommand_matches("test") {
+            let sink = match matches.value_of("id") {
+                Some(id) => {
+                    let id = id.parse::<i64>().expect("ID MUST BE A NUMBER");
+                    NotificationSink::get_many(&connection, options)
+                        .into_iter()
+                        .find(|sink| sink.id == id)
+                        .expect("No notification sink with the given id")
+                }
+                None => NotificationSink {
+                    id: 0,
+                    kind: matches
+                        .value_of("kind")
+                        .unwrap_or("json")
+                        .parse()
+                        .expect("UNKNOWN KIND"),
+                    url: matches
+                        .value_of("url")
+                        .expect("URL IS REQUIRED")
+                        .to_string(),
+                    profile: None,
+                    rss_url: None,
+                    max_retries: 0,
+                    connection: None,
+                },
+            };
+            let client = ClientPool::new()
+                .global(&connection)
+                .expect("Building the http client failed");
+            match notify::send(&client, &sink, &notify::sample_entries()).await {
+                Ok(()) => println!("Sample notification sent to {}", redact_path(&sink.url)),
+                Err(e) => println!("Sending the sample notification failed: {}", e),
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("rules") {
+        if let Some(matches) = matches.subcommand_matches("add") {
+            let (operator, value) = if let Some(value) = matches.value_of("contains") {
+                (RuleOperator::Contains, value)
+            } else if let Some(value) = matches.value_of("regex") {
+                rules::compile(value).expect("REGEX IS INVALID");
+                (RuleOperator::Regex, value)
+            } else if let Some(value) = matches.value_of("min-age") {
+                (RuleOperator::MinAge, value)
+            } else {
+                (
+                    RuleOperator::MaxAge,
+                    matches.value_of("max-age").expect("CONDITION IS REQUIRED"),
+                )
+            };
+            let field = match operator {
+                RuleOperator::MinAge | RuleOperator::MaxAge => {
+                    value.parse::<u64>().expect("DAYS MUST BE A NUMBER");
+                    RuleField::Age
+                }
+                _ => matches
+                    .value_of("field")
+                    .unwrap_or("title")
+                    .parse()
+                    .expect("UNKNOWN FIELD"),
+            };
+            let rule = Rule {
+                id: 0,
+                profile: matches
+                    .value_of("profile")
+                    .map(|profile| profile.to_string()),
+                rss_url: matches.value_of("feed").map(|feed| feed.to_string()),
+                field,
+                operator,
+                value: value.to_string(),
+                negate: matches.is_present("not"),
+                action: matches
+                    .value_of("action")
+                    .unwrap_or("skip")
+                    .parse()
+                    .expect("UNKNOWN ACTION"),
+                tag: matches.value_of("tag").map(|tag| tag.to_string()),
+                connection: Some(connection),
+            };
+            rule.save().expect("Saving the rule failed");
+        } else if matches.subcommand_matches("list").is_some() {
+            for rule in Rule::get_many(&connection, options) {
+                println!("{}", rule);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("remove") {
+            let id = matches
+                .value_of("id")
+                .expect("ID IS REQUIRED")
+                .parse::<i64>()
+                .expect("ID MUST BE A NUMBER");
+            Rule::delete(&connection, id).expect("Removing the rule failed");
+        } else if let Some(matches) = matches.subcommand_matches("test") {
+            let url = matches.value_of("feed").expect("RSS_URL IS REQUIRED");
+            let profile = match matches.value_of("profile") {
+                Some(profile) => profile.to_string(),
+                None => storage
+                    .get_feeds()
+                    .into_iter()
+                    .find(|rss_entity| rss_entity.rss_url == url)
+                    .map(|rss_entity| rss_entity.profile)
+                    .unwrap_or_default(),
+            };
+            let rule_set = RuleSet::new(Rule::for_feed(&connection, &profile, url));
+            let max_items = matches.value_of("max-items").map(|max_items| {
+                max_items
+                    .parse::<usize>()
+                    .expect("MAX ITEMS MUST BE A NUMBER")
+            });
+            let (_, mut rss) = fetch_feed(
+                &connection,
+                &mut ClientPool::new(),
+                &mut Keyring::new(&connection),
+                url,
+                max_items,
+            )
             .await
             .expect("Parsing of the given rss url failed");
-        rss_entity.save();
-    } else if let Some(matches) = matches.subcommand_matches("search") {
-        let page = matches
-            .value_of("page")
-            .unwrap_or("0")
-            .parse::<u64>()
-            .unwrap_or(0);
-        let limit = matches
-            .value_of("limit")
-            .unwrap_or("0")
-            .parse::<u64>()
-            .unwrap_or(0);
-        let regex = matches
-            .value_of("regex")
-            .unwrap_or("")
-            .parse::<String>()
-            .unwrap_or("".to_string());
+            for item in rss.get_items().iter() {
+                let verdict = rule_set.evaluate(item);
+                println!(
+                    "[{}]\t{}\t{:?}",
+                    verdict.describe(),
+                    item.title,
+                    verdict.matched
+                );
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("icon") {
+        if let Some(matches) = matches.subcommand_matches("refresh") {
+            let max_bytes = matches
+                .value_of("max-size")
+                .map(|max_bytes| max_bytes.parse::<u64>().expect("MAX SIZE MUST BE A NUMBER"))
+                .unwrap_or(icons::DEFAULT_MAX_ICON_BYTES);
+            // a single feed is refreshed even when its icon is not stale
+            let max_age_days = if matches.is_present("force") || matches.is_present("url") {
+                0
+            } else {
+                icons::REFRESH_AFTER_DAYS
+            };
+            let mut rss_urls = FeedIcon::stale(&connection, max_age_days);
+            if let Some(url) = matches.value_of("url") {
+                rss_urls.retain(|rss_url| rss_url == url);
+            }
+            let report =
+                icons::refresh(&connection, &mut ClientPool::new(), &rss_urls, max_bytes).await;
+            for rss_url in report.cached.iter() {
+                println!("cached {}", rss_url);
+            }
+            for error in report.errors.iter() {
+                println!("failed {}", error);
+            }
+        } else if matches.subcommand_matches("list").is_some() {
+            for icon in FeedIcon::get_many(&connection, options) {
+                println!("{}", icon);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("export") {
+            let url = matches.value_of("url").expect("URL IS REQUIRED");
+            let icon = icons::icon_for(&connection, url).expect("No icon is cached for this feed");
+            match matches.value_of("output") {
+                Some("-") | None => println!("{}", icon.data_uri().unwrap_or_default()),
+                Some(path) => std::fs::write(path, &icon.data).expect("Writing the icon failed"),
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("download") {
+        if let Some(matches) = matches.subcommand_matches("run") {
+            let enclosures = match matches.value_of("entry") {
+                Some(key) => {
+                    let rss_entry = storage
+                        .find_entry(key)
+                        .expect("No entry matches the given id");
+                    EntryEnclosure::for_entry(&connection, &rss_entry.hash())
+                        .into_iter()
+                        .filter(|enclosure| enclosure.file_path.is_none())
+                        .collect()
+                }
+                None => {
+                    if let Some(url) = matches.value_of("url") {
+                        options.set_rss_url(url);
+                    }
+                    options.set_limit(
+                        matches
+                            .value_of("limit")
+                            .unwrap_or("10")
+                            .parse::<u64>()
+                            .expect("LIMIT MUST BE A NUMBER"),
+                    );
+                    EntryEnclosure::pending(&connection, options)
+                }
+            };
+            let settings = DownloadSettings::get(&connection);
+            let report = download::download_enclosures(
+                &connection,
+                &mut ClientPool::new(),
+                &settings,
+                enclosures,
+            )
+            .await;
+            for file in report.downloaded.iter() {
+                println!("downloaded {} ({} bytes)", file.path.display(), file.bytes);
+            }
+            for error in report.errors.iter() {
+                println!("failed {}", error);
+            }
+            for path in download::enforce_retention(&connection, &settings).removed {
+                println!("deleted {}", path);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("set") {
+            let mut settings = DownloadSettings::get(&connection);
+            let limit = |name: &str| {
+                matches.value_of(name).map(|value| {
+                    value
+                        .parse::<u64>()
+                        .unwrap_or_else(|_| panic!("{} MUST BE A NUMBER", name.to_uppercase()))
+                })
+            };
+            if let Some(directory) = matches.value_of("dir") {
+                settings.directory = directory.to_string();
+            }
+            if let Some(template) = matches.value_of("template") {
+                settings.filename_template = template.to_string();
+            }
+            if let Some(concurrency) = limit("concurrency") {
+                settings.concurrency = concurrency.max(1);
+            }
+            if let Some(max_total_bytes) = limit("max-size") {
+                settings.max_total_bytes = Some(max_total_bytes).filter(|bytes| *bytes > 0);
+            }
+            if let Some(max_files) = limit("max-files") {
+                settings.max_files = Some(max_files).filter(|files| *files > 0);
+            }
+            if let Some(max_file_bytes) = limit("max-file-size") {
+                settings.max_file_bytes = Some(max_file_bytes).filter(|bytes| *bytes > 0);
+            }
+            settings.connection = Some(connection);
+            settings
+                .save()
+                .expect("Saving the download settings failed");
+            println!("{}", settings);
+        } else if matches.subcommand_matches("show").is_some() {
+            println!("{}", DownloadSettings::get(&connection));
+        } else if matches.subcommand_matches("list").is_some() {
+            for (enclosure, _) in EntryEnclosure::downloaded(&connection) {
+                println!(
+                    "{}\t{}\t{}",
+                    enclosure.downloaded_at.unwrap_or_default(),
+                    enclosure.file_path.unwrap_or_default(),
+                    enclosure.url
+                );
+            }
+        } else if matches.subcommand_matches("verify").is_some() {
+            for (enclosure, _) in EntryEnclosure::downloaded(&connection) {
+                let path = enclosure.file_path.clone().unwrap_or_default();
+                let sha256 = enclosure.sha256.clone().unwrap_or_default();
+                match download::verify(Path::new(&path), &sha256) {
+                    Ok(true) => println!("ok {}", path),
+                    Ok(false) => {
+                        println!("checksum mismatch {}", path);
+                        let _ = std::fs::remove_file(&path);
+                        EntryEnclosure::reset_download(&connection, enclosure.id)
+                            .expect("Resetting the download failed");
+                    }
+                    Err(e) => {
+                        println!("unreadable {}: {}", path, e);
+                        EntryEnclosure::reset_download(&connection, enclosure.id)
+                            .expect("Resetting the download failed");
+                    }
+                }
+            }
+        } else if matches.subcommand_matches("prune").is_some() {
+            let report =
+                download::enforce_retention(&connection, &DownloadSettings::get(&connection));
+            for path in report.removed.iter() {
+                println!("deleted {}", path);
+            }
+            for error in report.errors.iter() {
+                println!("failed {}", error);
+            }
+            println!(
+                "{} files deleted, {} bytes freed",
+                report.removed.len(),
+                report.freed_bytes
+            );
+        }
+    } else if let Some(matches) = matches.subcommand_matches("parse") {
+        let source = matches.value_of("source").expect("SOURCE IS REQUIRED");
+        let max_items = matches.value_of("max-items").map(|max_items| {
+            max_items
+                .parse::<usize>()
+                .expect("MAX ITEMS MUST BE A NUMBER")
+        });
+        let mut rss = match FeedSource::from(source) {
+            FeedSource::Http(_) => fetch_feed(
+                &connection,
+                &mut ClientPool::new(),
+                &mut Keyring::new(&connection),
+                source,
+                max_items,
+            )
+            .await
+            .map(|(_, rss)| rss),
+            local => Rss::read_source(local, max_items).await,
+        }
+        .expect("Parsing of the given feed failed");
+        rss.get_items();
+        if matches.is_present("json") {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&rss).expect("Serializing the feed failed")
+            );
+        } else {
+            println!(
+                "{}\n{}\nencoding = {}\n",
+                render::strip_controls(rss.title()),
+                render::strip_controls(rss.description()),
+                rss.encoding()
+            );
+            for item in rss.get_items().iter() {
+                println!(
+                    "{}",
+                    render::strip_controls(&format!(
+                        "{}\t{}\t{}",
+                        item.publish_date, item.title, item.link
+                    ))
+                );
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("show") {
+        let key = matches.value_of("entry").expect("ENTRY IS REQUIRED");
+        let rss_entry = storage
+            .find_entry(key)
+            .expect("No entry matches the given id");
+
+        let mut render_options = RenderOptions::for_terminal();
+        match matches.value_of("color") {
+            Some("always") => render_options.set_color(true),
+            Some("never") => render_options.set_color(false),
+            _ => &render_options,
+        };
+        if let Some(width) = matches.value_of("width") {
+            render_options.width = width.parse::<usize>().expect("WIDTH MUST BE A NUMBER");
+        }
 
-        if limit > 0 {
-            options.set_limit(limit);
+        let content = storage.get_content(&rss_entry.hash());
+        let mut text = format!(
+            "{}\n{}\n{}\n",
+            render::style(
+                &render::strip_controls(&rss_entry.title),
+                "\x1b[1m",
+                render_options
+            ),
+            render::strip_controls(&rss_entry.link),
+            render::strip_controls(&rss_entry.publish_date)
+        );
+        if let Some(content) = &content {
+            text.push_str(&format!(
+                "{} words, {} min read\n",
+                content.word_count, content.reading_minutes
+            ));
+        }
+        let tags = storage.entry_tags(&rss_entry);
+        if !tags.is_empty() {
+            text.push_str(&format!("Tags: {}\n", tags.join(", ")));
+        }
+        text.push_str(&render::strip_controls(&rss_entry.podcast(&connection)));
+        text.push_str(&render::strip_controls(&rss_entry.media(&connection)));
+        text.push('\n');
+        // media only items like videos usually carry their text in media:description
+        let media_description = EntryMedia::get(&connection, &rss_entry.hash())
+            .and_then(|media| media.media.description)
+            .unwrap_or_default();
+        let html = match &content {
+            Some(content) => &content.html,
+            None if rss_entry.description.trim().is_empty() => &media_description,
+            None => &rss_entry.description,
+        };
+        text.push_str(&render::render_html(html, render_options));
+
+        if matches.is_present("no-pager") {
+            print!("{}", text);
+        } else {
+            render::page(&text);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("http") {
+        if let Some(matches) = matches.subcommand_matches("set") {
+            let rss_url = matches.value_of("url");
+            let mut settings = HttpSettings::get(&connection, rss_url).unwrap_or_default();
+            settings.rss_url = rss_url.map(|rss_url| rss_url.to_string());
+            if let Some(secs) = matches.value_of("connect-timeout") {
+                settings.connect_timeout_secs =
+                    Some(secs.parse::<u64>().expect("TIMEOUT MUST BE A NUMBER"));
+            }
+            if let Some(secs) = matches.value_of("read-timeout") {
+                settings.read_timeout_secs =
+                    Some(secs.parse::<u64>().expect("TIMEOUT MUST BE A NUMBER"));
+            }
+            if let Some(user_agent) = matches.value_of("user-agent") {
+                settings.user_agent = Some(user_agent.to_string());
+            }
+            if let Some(proxy) = matches.value_of("proxy") {
+                settings.proxy = Some(proxy.to_string());
+            }
+            if let Some(ca_certs) = matches.values_of("ca-cert") {
+                settings.ca_certs = ca_certs.map(|path| path.to_string()).collect();
+            }
+            if let Some(compression) = matches.value_of("compression") {
+                settings.compression = Some(compression == "on");
+            }
+            if let Some(bytes) = matches.value_of("max-body-size") {
+                settings.max_body_bytes =
+                    Some(bytes.parse::<u64>().expect("SIZE MUST BE A NUMBER"));
+            }
+            settings.connection = Some(connection.clone());
+            // fail now rather than on the next sync
+            HttpClient::new(
+                &settings
+                    .clone()
+                    .overlay(HttpSettings::get(&connection, None).unwrap_or_default()),
+            )
+            .expect("The http settings are invalid");
+            settings.save().expect("Saving the http settings failed");
+        } else if matches.subcommand_matches("list").is_some() {
+            for settings in HttpSettings::get_many(&connection, options) {
+                println!("{}", settings);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("reset") {
+            HttpSettings::delete(&connection, matches.value_of("url"))
+                .expect("Removing the http settings failed");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("retention") {
+        if let Some(matches) = matches.subcommand_matches("set") {
+            let rss_url = matches.value_of("url");
+            let mut policy = RetentionPolicy::get(&connection, rss_url).unwrap_or_default();
+            policy.rss_url = rss_url.map(|rss_url| rss_url.to_string());
+            if let Some(count) = matches.value_of("keep-newest") {
+                policy.keep_newest = Some(count.parse::<u64>().expect("COUNT MUST BE A NUMBER"));
+            }
+            if let Some(days) = matches.value_of("max-age") {
+                policy.max_age_days = Some(days.parse::<u64>().expect("DAYS MUST BE A NUMBER"));
+            }
+            if let Some(keep_starred) = matches.value_of("keep-starred") {
+                policy.keep_starred = Some(keep_starred == "on");
+            }
+            if let Some(keep_tagged) = matches.value_of("keep-tagged") {
+                policy.keep_tagged = Some(keep_tagged == "on");
+            }
+            policy.connection = Some(connection.clone());
+            policy.save().expect("Saving the retention policy failed");
+            println!("{}", policy);
+        } else if matches.subcommand_matches("list").is_some() {
+            for policy in RetentionPolicy::get_many(&connection, options) {
+                println!("{}", policy);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("reset") {
+            RetentionPolicy::delete(&connection, matches.value_of("url"))
+                .expect("Removing the retention policy failed");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("prune") {
+        let prune_options = PruneOptions {
+            rss_url: matches.value_of("url").map(|url| url.to_string()),
+            dry_run: matches.is_present("dry-run"),
+            vacuum: matches.is_present("vacuum"),
+            analyze: matches.is_present("analyze"),
+        };
+        let report = prune::prune(&connection, &prune_options).expect("Pruning failed");
+        for candidate in report.candidates.iter() {
+            println!("{}", candidate);
+        }
+        if prune_options.dry_run {
+            println!(
+                "{} entries would be deleted, {} kept as starred, tagged or downloaded",
+                report.candidates.len(),
+                report.protected
+            );
+        } else {
+            println!(
+                "{} entries deleted, {} kept as starred, tagged or downloaded",
+                report.deleted, report.protected
+            );
+        }
+        println!(
+            "database size: {} bytes before, {} bytes after",
+            report.size_before, report.size_after
+        );
+    } else if let Some(matches) = matches.subcommand_matches("digest") {
+        let mut digest_options = DigestOptions::default();
+        let period = matches.value_of("period").expect("PERIOD IS REQUIRED");
+        let since = matches
+            .value_of("since")
+            .unwrap_or(if period == "day" { "1d" } else { "7d" });
+        digest_options.since = resolve_date(since, false).expect("Invalid date");
+        digest_options.until = resolve_date(matches.value_of("until").unwrap_or_default(), true)
+            .expect("Invalid date");
+        if let Some(profile) = matches.value_of("profile") {
+            digest_options.profile = profile.to_string();
         }
-        if page > 0 {
-            options.set_page(page);
+        if let Some(url) = matches.value_of("feed") {
+            digest_options.rss_url = url.to_string();
         }
-        options.query = regex.to_string();
+        if let Some(title) = matches.value_of("title") {
+            digest_options.title = title.to_string();
+        } else if period == "day" && !matches.is_present("since") {
+            digest_options.title = String::from("Daily feed digest");
+        } else if !matches.is_present("since") {
+            digest_options.title = String::from("Weekly feed digest");
+        }
+        if let Some(length) = matches.value_of("summary-length") {
+            digest_options.summary_length = length
+                .parse::<usize>()
+                .expect("SUMMARY LENGTH MUST BE A NUMBER");
+        }
+        let format = matches
+            .value_of("format")
+            .expect("FORMAT IS REQUIRED")
+            .parse::<DigestFormat>()
+            .expect("FORMAT MUST BE markdown OR html");
+        let template = matches
+            .value_of("template")
+            .map(|path| std::fs::read_to_string(path).expect("Reading the template failed"));
+        let digest = Digest::collect(&storage, &digest_options);
+        if let Some(recipients) = matches.values_of("to") {
+            let settings =
+                SmtpSettings::get(&connection).expect("SMTP IS NOT CONFIGURED, SEE smtp set");
+            let password = settings
+                .password
+                .as_ref()
+                .map(|password| password.reveal(&mut Keyring::new(&connection)))
+                .transpose()
+                .expect("Reading the SMTP password failed");
+            for recipient in recipients {
+                match mail::send_digest(
+                    &connection,
+                    &settings,
+                    password.as_deref(),
+                    &digest,
+                    recipient,
+                    template.as_deref().map(|template| (format, template)),
+                )
+                .await
+                {
+                    Ok(0) => println!("{}: nothing new", recipient),
+                    Ok(sent) => println!("{}: {} entries sent", recipient, sent),
+                    Err(e) => println!("{}: failed {}", recipient, e),
+                }
+            }
+        } else {
+            let document = digest.render(format, template.as_deref());
+            match matches.value_of("output") {
+                Some("-") | None => print!("{}", document),
+                Some(path) => std::fs::write(path, document).expect("Writing the digest failed"),
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("smtp") {
+        if let Some(matches) = matches.subcommand_matches("set") {
+            let mut settings = SmtpSettings::get(&connection).unwrap_or_default();
+            if let Some(host) = matches.value_of("host") {
+                settings.host = host.to_string();
+            }
+            if let Some(security) = matches.value_of("security") {
+                settings.security = security
+                    .parse()
+                    .expect("SECURITY MUST BE none, starttls OR tls");
+            }
+            if let Some(port) = matches.value_of("port") {
+                let port = port.parse::<u16>().expect("PORT MUST BE A NUMBER");
+                settings.port = Some(port).filter(|port| *port > 0);
+            }
+            if let Some(username) = matches.value_of("username") {
+                settings.username = Some(username.to_string()).filter(|name| !name.is_empty());
+            }
+            if let Some(password) = matches.value_of("password") {
+                let mut keyring = Keyring::new(&connection);
+                settings.password =
+                    Some(Secret::new(password, &mut keyring).expect("Storing the password failed"));
+            }
+            if let Some(from) = matches.value_of("from") {
+                mail::check_address(from).expect("INVALID FROM ADDRESS");
+                settings.from = from.to_string();
+            }
+            if settings.from.is_empty() {
+                panic!("A FROM ADDRESS IS REQUIRED, SET IT WITH --from");
+            }
+            settings.connection = Some(connection);
+            settings.save().expect("Saving the SMTP settings failed");
+            println!("{}", settings);
+        } else if matches.subcommand_matches("show").is_some() {
+            match SmtpSettings::get(&connection) {
+                Some(settings) => println!("{}", settings),
+                None => println!("SMTP is not configured"),
+            }
+        } else if matches.subcommand_matches("reset").is_some() {
+            SmtpSettings::delete(&connection).expect("Deleting the SMTP settings failed");
+        } else if let Some(matches) = matches.subcommand_matches("test") {
+            let to = matches.value_of("to").expect("ADDRESS IS REQUIRED");
+            let settings =
+                SmtpSettings::get(&connection).expect("SMTP IS NOT CONFIGURED, SEE smtp set");
+            let password = settings
+                .password
+                .as_ref()
+                .map(|password| password.reveal(&mut Keyring::new(&connection)))
+                .transpose()
+                .expect("Reading the SMTP password failed");
+            let message = mail::Message {
+                from: settings.from.clone(),
+                to: to.to_string(),
+                subject: String::from("rss_rust test email"),
+                text: String::from("The SMTP settings of rss_rust work.\n"),
+                html: String::from("<p>The SMTP settings of rss_rust work.</p>\n"),
+            };
+            mail::send(&settings, password.as_deref(), &message)
+                .await
+                .expect("Sending the test email failed");
+            println!("sent to {}", to);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("backup") {
+        let layout = matches
+            .value_of("format")
+            .expect("FORMAT IS REQUIRED")
+            .parse::<BackupLayout>()
+            .expect("FORMAT MUST BE json OR jsonl");
+        let header = match matches.value_of("output") {
+            Some("-") => backup::backup(&connection, layout, std::io::stdout().lock()),
+            output => {
+                let path = output
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| backup::default_path(layout));
+                let file = std::fs::File::create(&path).expect("Creating the archive failed");
+                let header = backup::backup(&connection, layout, file);
+                if header.is_ok() {
+                    eprintln!("written {}", path.display());
+                }
+                header
+            }
+        }
+        .expect("Backup failed");
+        for table in header.tables.iter().filter(|table| table.rows > 0) {
+            eprintln!("{}\t{} rows", table.name, table.rows);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("restore") {
+        let path = matches.value_of("file").expect("FILE IS REQUIRED");
+        let mode = if matches.is_present("replace") {
+            RestoreMode::Replace
+        } else {
+            RestoreMode::Merge
+        };
+        let archive = backup::open_archive(Path::new(path)).expect("Opening the archive failed");
+        let report = backup::restore(&connection, archive, mode).expect("Restore failed");
+        for table in report.skipped_tables.iter() {
+            println!("skipped unknown table {}", table);
+        }
+        println!(
+            "restored backup of {} (version {}): {} rows added, {} already present",
+            report.header.created_at, report.header.version, report.inserted, report.ignored
+        );
+    } else if let Some(matches) = matches.subcommand_matches("auth") {
+        if let Some(matches) = matches.subcommand_matches("set") {
+            let url = matches.value_of("url").expect("URL IS REQUIRED");
+            let mut keyring = Keyring::new(&connection);
+            let mut auth = FeedAuth::get(&connection, url)
+                .expect("Reading the stored credentials failed")
+                .unwrap_or_else(|| FeedAuth::new(url));
+            if let Some(username) = matches.value_of("username") {
+                auth.username = Some(username.to_string());
+            }
+            if let Some(password) = matches.value_of("password") {
+                auth.password =
+                    Some(Secret::new(password, &mut keyring).expect("Storing the password failed"));
+            }
+            if let Some(token) = matches.value_of("token") {
+                auth.token =
+                    Some(Secret::new(token, &mut keyring).expect("Storing the token failed"));
+            }
+            if let Some(headers) = matches.values_of("header") {
+                auth.headers = headers
+                    .map(|header| {
+                        let (name, value) = header
+                            .split_once(':')
+                            .expect("HEADER MUST LOOK LIKE 'Name: value'");
+                        let value = Secret::new(value.trim(), &mut keyring)
+                            .expect("Storing the header failed");
+                        (name.trim().to_string(), value)
+                    })
+                    .collect();
+            }
+            if let Some(cookie_header) = matches.value_of("cookie-header") {
+                auth.cookie_header = Some(
+                    Secret::new(cookie_header, &mut keyring)
+                        .expect("Storing the cookie header failed"),
+                );
+            }
+            auth.connection = Some(connection.clone());
+            auth.save().expect("Saving the credentials failed");
+        } else if matches.subcommand_matches("list").is_some() {
+            for auth in FeedAuth::get_many(&connection, options) {
+                println!("{}", auth);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("remove") {
+            let url = matches.value_of("url").expect("URL IS REQUIRED");
+            FeedAuth::delete(&connection, url).expect("Removing the credentials failed");
+        }
+    } else if matches.subcommand_matches("tui").is_some() {
+        tui::run(&connection)
+            .await
+            .expect("Running the terminal interface failed");
+    }
+}
+
+/// The function `search_from_args` applies the filters given to `search` or `saved` on top of
+/// `search`, filters that are not given keep their value.
+fn search_from_args<'c>(matches: &ArgMatches, mut search: SavedSearch<'c>) -> SavedSearch<'c> {
+    if let Some(regex) = matches.value_of("regex") {
+        search.query = regex.to_string();
+    }
+    if let Some(limit) = matches.value_of("limit") {
+        // 0 keeps the default limit, like before saved searches existed
+        match limit.parse::<u64>().unwrap_or(0) {
+            0 => {}
+            limit => search.limit = limit,
+        }
+    }
+    if let Some(tags) = matches.values_of("tag") {
+        search.tags = tags
+            .map(|tag| normalize_tag(tag).expect("TAG MUST NOT BE EMPTY OR HAVE A COMMA"))
+            .collect();
+    }
+    if let Some(author) = matches.value_of("author") {
+        search.author = author.trim().to_string();
+    }
+    if let Some(categories) = matches.values_of("category") {
+        search.categories = categories
+            .map(|category| {
+                let category = category.trim();
+                if category.is_empty() || category.contains(',') {
+                    panic!("CATEGORY MUST NOT BE EMPTY OR HAVE A COMMA");
+                }
+                category.to_string()
+            })
+            .collect();
+    }
+    if let Some(profile) = matches.value_of("profile") {
+        search.profile = profile.to_string();
+    }
+    if let Some(rss_url) = matches.value_of("feed") {
+        search.rss_url = rss_url.to_string();
+    }
+    if matches.is_present("unread") {
+        search.unread_only = true;
+    }
+    if matches.is_present("starred") {
+        search.starred_only = true;
+    }
+    if let Some(since) = matches.value_of("since") {
+        resolve_date(since, false).expect("Invalid date");
+        search.since = since.trim().to_string();
+    }
+    if let Some(until) = matches.value_of("until") {
+        resolve_date(until, true).expect("Invalid date");
+        search.until = until.trim().to_string();
+    }
+    if let Some(sort) = matches.value_of("sort") {
+        search.sort = sort.parse().expect("Invalid sort order");
+    }
+    search
+}
 
-        println!("{:?}", options);
-        let rss_entries = RssEntry::get_many(&connection, options);
-        println!("{} blogs found\n\n", rss_entries.len());
-        for rss_entry in rss_entries {
-            println!("{}", rss_entry);
-        }
-    } else if matches.subcommand_matches("sync").is_some() {
-        RssEntity::sync(&connection).await;
+/// The function `print_entries` prints the page of entries `options` matches, as text or as JSON
+/// depending on the `--format` of the command.
+fn print_entries(
+    connection: &ThreadSafe<&Connection>,
+    storage: &dyn Storage,
+    matches: &ArgMatches,
+    mut options: GetManyOptions,
+) {
+    let page = matches
+        .value_of("page")
+        .unwrap_or("0")
+        .parse::<u64>()
+        .unwrap_or(0);
+    if page > 0 {
+        options.set_page(page);
+    }
+
+    if matches.value_of("format") == Some("json") {
+        let rss_entries: Vec<serde_json::Value> = storage
+            .get_entries(&options)
+            .iter()
+            .map(|rss_entry| rss_entry.to_json(connection))
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&rss_entries).expect("Serializing the entries failed")
+        );
+        return;
+    }
+    println!("{:?}", options);
+    let total = storage.count_entries(&options);
+    let rss_entries = storage.get_entries(&options);
+    println!("{} of {} blogs found\n\n", rss_entries.len(), total);
+    for rss_entry in rss_entries {
+        let tags = storage.entry_tags(&rss_entry);
+        let text = format!(
+            "{}{}{}{}",
+            rss_entry,
+            if tags.is_empty() {
+                String::new()
+            } else {
+                format!("Tags: {}\n", tags.join(", "))
+            },
+            rss_entry.podcast(connection),
+            rss_entry.media(connection)
+        );
+        println!("{}", render::strip_controls(&text));
     }
 }
@@ -0,0 +1,38 @@
+use std::fmt;
+
+use crate::http::errors::HttpError;
+
+#[derive(Debug)]
+pub enum NotifyError {
+    HttpError(HttpError),
+    StatusError(u16, String),
+}
+
+/// The `impl fmt::Display for NotifyError` block formats the reason a webhook post failed.
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            NotifyError::HttpError(e) => write!(f, "{}", e),
+            NotifyError::StatusError(status, body) => {
+                write!(
+                    f,
+                    "Webhook responded with status {}: {}",
+                    status,
+                    body.trim()
+                )
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for NotifyError {
+    fn from(error: reqwest::Error) -> Self {
+        NotifyError::HttpError(HttpError::from(error))
+    }
+}
+
+impl From<HttpError> for NotifyError {
+    fn from(error: HttpError) -> Self {
+        NotifyError::HttpError(error)
+    }
+}
@@ -0,0 +1,335 @@
+pub mod errors;
+
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::{
+    core::{structs::EntryPayload, template::escape_html},
+    db::notification_sink::{NotificationSink, SinkKind},
+    http::{errors::HttpError, HttpClient},
+};
+use errors::NotifyError;
+
+/// Discord rejects messages with more than 10 embeds.
+const DISCORD_MAX_EMBEDS: usize = 10;
+
+/// Discord rejects embeds with a title longer than 256 characters.
+const DISCORD_MAX_TITLE: usize = 256;
+
+/// The longest wait between two attempts to post to a sink, in seconds.
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// The function `build_payloads` shapes a batch of entries into the JSON bodies expected by the
+/// given kind of webhook: a single message, except for Discord which gets one message per 10
+/// entries.
+///
+/// Arguments:
+///
+/// * `kind`: The `kind` of webhook the bodies are built for.
+/// * `entries`: The `entries` to announce.
+///
+/// Returns:
+///
+/// the JSON `Value`s to post, in order.
+pub fn build_payloads(kind: SinkKind, entries: &[EntryPayload]) -> Vec<Value> {
+    let heading = format!("{} new entries", entries.len());
+    if kind == SinkKind::Discord {
+        let chunks: Vec<&[EntryPayload]> = entries.chunks(DISCORD_MAX_EMBEDS).collect();
+        return chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let heading = if chunks.len() > 1 {
+                    format!("{} ({}/{})", heading, index + 1, chunks.len())
+                } else {
+                    heading.clone()
+                };
+                build_payload(kind, &heading, chunk)
+            })
+            .collect();
+    }
+    vec![build_payload(kind, &heading, entries)]
+}
+
+/// The function `build_payload` shapes entries into a single message under `heading`.
+fn build_payload(kind: SinkKind, heading: &str, entries: &[EntryPayload]) -> Value {
+    match kind {
+        SinkKind::Slack => {
+            let lines: Vec<String> = entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "• <{}|{}>",
+                        escape_slack(&entry.link).replace('|', "%7C"),
+                        escape_slack(&entry.title)
+                    )
+                })
+                .collect();
+            json!({ "text": format!("*{}*\n{}", heading, lines.join("\n")) })
+        }
+        SinkKind::Discord => {
+            let embeds: Vec<Value> = entries
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "title": cut(&entry.title, DISCORD_MAX_TITLE),
+                        "url": entry.link,
+                        "footer": { "text": entry.feed_url },
+                    })
+                })
+                .collect();
+            json!({ "content": heading, "embeds": embeds })
+        }
+        SinkKind::Matrix => {
+            let body: Vec<String> = entries
+                .iter()
+                .map(|entry| format!("- {} {}", entry.title, entry.link))
+                .collect();
+            let html: Vec<String> = entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>",
+                        escape_html(&entry.link),
+                        escape_html(&entry.title)
+                    )
+                })
+                .collect();
+            json!({
+                "msgtype": "m.text",
+                "body": format!("{}\n{}", heading, body.join("\n")),
+                "format": "org.matrix.custom.html",
+                "formatted_body": format!("<p>{}</p><ul>{}</ul>", heading, html.join("")),
+            })
+        }
+        SinkKind::Json => json!({ "count": entries.len(), "entries": entries }),
+    }
+}
+
+/// The function `escape_slack` escapes the characters Slack reads as markup in message text.
+fn escape_slack(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The function `cut` shortens `value` to at most `length` characters, ending the cut with `…`.
+fn cut(value: &str, length: usize) -> String {
+    if value.chars().count() <= length {
+        return value.to_string();
+    }
+    let mut cut: String = value.chars().take(length - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// The function `send` posts a batch of entries to a sink, see `build_payloads`. A post failing
+/// with a network error, a 429 or a 5xx status is retried up to `sink.max_retries` times with an
+/// exponential backoff starting at one second and capped at a minute, any other failure is
+/// returned at once.
+pub async fn send(
+    client: &HttpClient,
+    sink: &NotificationSink<'_>,
+    entries: &[EntryPayload],
+) -> Result<(), NotifyError> {
+    for body in build_payloads(sink.kind, entries) {
+        post(client, sink, &body).await?;
+    }
+    Ok(())
+}
+
+async fn post(
+    client: &HttpClient,
+    sink: &NotificationSink<'_>,
+    body: &Value,
+) -> Result<(), NotifyError> {
+    let mut attempt: u32 = 0;
+    loop {
+        let result = match client.send(client.client.post(&sink.url).json(&body)).await {
+            Ok(res) if res.status().is_success() => Ok(()),
+            Ok(res) => {
+                let status = res.status().as_u16();
+                Err(NotifyError::StatusError(
+                    status,
+                    res.text().await.unwrap_or_default(),
+                ))
+            }
+            Err(e) => Err(NotifyError::from(e)),
+        };
+
+        match result {
+            Err(e) if is_retryable(&e) && attempt < sink.max_retries => {
+                tokio::time::sleep(backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The function `dispatch` sends every sink the entries that match its profile and feed filters, one
+/// batched message per sink. Sinks without matching entries are skipped.
+///
+/// Returns:
+///
+/// the error messages of the sinks that could not be notified.
+pub async fn dispatch(
+    client: &HttpClient,
+    sinks: &[NotificationSink<'_>],
+    entries: &[EntryPayload],
+) -> Vec<String> {
+    let mut errors = vec![];
+    for sink in sinks.iter() {
+        let matching: Vec<EntryPayload> = entries
+            .iter()
+            .filter(|entry| sink.matches(entry))
+            .cloned()
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+        if let Err(e) = send(client, sink, &matching).await {
+            errors.push(format!("notification sink {}: {}", sink.id, e));
+        }
+    }
+    errors
+}
+
+/// The function `is_retryable` tells whether a failed post may succeed later: the network failed,
+/// or the webhook was rate limited or failed on its side. A rejected URL or payload is not retried.
+fn is_retryable(error: &NotifyError) -> bool {
+    match error {
+        NotifyError::HttpError(HttpError::RequestError(e)) => {
+            e.is_connect() || e.is_timeout() || e.is_request() || e.is_body()
+        }
+        NotifyError::HttpError(HttpError::Timeout(_)) => true,
+        NotifyError::HttpError(_) => false,
+        NotifyError::StatusError(status, _) => *status == 429 || *status >= 500,
+    }
+}
+
+/// The function `backoff` returns the wait before retrying after the failed `attempt`, doubling
+/// from one second up to `MAX_BACKOFF_SECS`.
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs((1u64 << attempt.min(6)).min(MAX_BACKOFF_SECS))
+}
+
+/// The function `sample_entries` returns a single made up entry used by `notify test`.
+pub fn sample_entries() -> Vec<EntryPayload> {
+    vec![EntryPayload {
+        hash: String::from("0000000000000000000000000000000000000000000000000000000000000000"),
+        title: String::from("Blog Blunter test notification"),
+        link: String::from("https://example.com/blog-blunter-test"),
+        publish_date: String::new(),
+        feed_url: String::from("https://example.com/rss.xml"),
+        profile: String::from("test"),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(count: usize) -> Vec<EntryPayload> {
+        (0..count)
+            .map(|index| EntryPayload {
+                hash: format!("{:064}", index),
+                title: format!("Entry <{}> & co", index),
+                link: format!("https://example.com/{}?a=1&b=2", index),
+                publish_date: String::from("2024-01-02 03:04:05"),
+                feed_url: String::from("https://example.com/rss.xml"),
+                profile: String::from("work"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slack_lists_every_entry_in_one_message() {
+        let payloads = build_payloads(SinkKind::Slack, &entries(2));
+        assert_eq!(payloads.len(), 1);
+        let text = payloads[0]["text"].as_str().unwrap();
+        assert!(text.starts_with("*2 new entries*\n"));
+        assert!(text.contains("• <https://example.com/1?a=1&amp;b=2|Entry &lt;1&gt; &amp; co>"));
+
+        let mut entry = entries(1).remove(0);
+        entry.link = String::from("https://example.com/a|b");
+        let payloads = build_payloads(SinkKind::Slack, &[entry]);
+        assert!(payloads[0]["text"]
+            .as_str()
+            .unwrap()
+            .ends_with("• <https://example.com/a%7Cb|Entry &lt;0&gt; &amp; co>"));
+    }
+
+    #[test]
+    fn discord_splits_entries_into_messages_of_ten_embeds() {
+        let payloads = build_payloads(SinkKind::Discord, &entries(23));
+        assert_eq!(payloads.len(), 3);
+        let embeds: Vec<usize> = payloads
+            .iter()
+            .map(|payload| payload["embeds"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(embeds, [10, 10, 3]);
+        assert_eq!(payloads[2]["content"], "23 new entries (3/3)");
+        assert_eq!(payloads[2]["embeds"][2]["title"], "Entry <22> & co");
+
+        let payloads = build_payloads(SinkKind::Discord, &entries(1));
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0]["content"], "1 new entries");
+        assert_eq!(
+            payloads[0]["embeds"][0]["footer"]["text"],
+            "https://example.com/rss.xml"
+        );
+    }
+
+    #[test]
+    fn discord_titles_are_cut_to_256_characters() {
+        let mut entry = entries(1).remove(0);
+        entry.title = "é".repeat(300);
+        let payloads = build_payloads(SinkKind::Discord, &[entry]);
+        let title = payloads[0]["embeds"][0]["title"].as_str().unwrap();
+        assert_eq!(title.chars().count(), 256);
+        assert!(title.ends_with("é…"));
+    }
+
+    #[test]
+    fn matrix_escapes_the_html_body() {
+        let payloads = build_payloads(SinkKind::Matrix, &entries(1));
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0]["msgtype"], "m.text");
+        assert_eq!(
+            payloads[0]["formatted_body"],
+            "<p>1 new entries</p><ul><li><a href=\"https://example.com/0?a=1&amp;b=2\">\
+             Entry &lt;0&gt; &amp; co</a></li></ul>"
+        );
+    }
+
+    #[test]
+    fn json_carries_the_entries() {
+        let payloads = build_payloads(SinkKind::Json, &entries(2));
+        assert_eq!(payloads[0]["count"], 2);
+        assert_eq!(payloads[0]["entries"][1]["profile"], "work");
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        let status = |code| NotifyError::StatusError(code, String::new());
+        assert!(is_retryable(&status(429)));
+        assert!(is_retryable(&status(503)));
+        assert!(!is_retryable(&status(400)));
+        assert!(!is_retryable(&status(404)));
+        assert!(is_retryable(&NotifyError::HttpError(HttpError::Timeout(5))));
+        assert!(!is_retryable(&NotifyError::HttpError(
+            HttpError::BodyTooLarge(1)
+        )));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_minute() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(6), Duration::from_secs(MAX_BACKOFF_SECS));
+        assert_eq!(backoff(64), Duration::from_secs(MAX_BACKOFF_SECS));
+        assert_eq!(backoff(u32::MAX), Duration::from_secs(MAX_BACKOFF_SECS));
+    }
+}
@@ -0,0 +1,479 @@
+use chrono::{Duration, Utc};
+use sqlite::{Connection, Error, State};
+use std::fmt::Display;
+use thread_safe::ThreadSafe;
+
+use crate::db::retention_policy::RetentionPolicy;
+
+/// The tables holding a row per entry, deleted along with the entry.
+const ENTRY_TABLES: [&str; 8] = [
+    "entry_contents",
+    "entry_tags",
+    "entry_categories",
+    "enclosures",
+    "episodes",
+    "entry_media",
+    "mailed_entries",
+    "rss_entries",
+];
+
+/// The `PruneOptions` struct holds the settings of a single `prune` run.
+///
+/// Properties:
+///
+/// * `rss_url`: Only prune the entries of this feed, `None` for every feed.
+/// * `dry_run`: Only report what would be deleted.
+/// * `vacuum`: Rebuild the database file afterwards to give the freed space back.
+/// * `analyze`: Refresh the statistics of the query planner afterwards.
+#[derive(Debug, Default, Clone)]
+pub struct PruneOptions {
+    pub rss_url: Option<String>,
+    pub dry_run: bool,
+    pub vacuum: bool,
+    pub analyze: bool,
+}
+
+/// The `PruneCandidate` struct is an entry the retention policy of its feed deletes.
+///
+/// Properties:
+///
+/// * `hash`: The hash of the entry.
+/// * `title`: The title of the entry.
+/// * `rss_url`: The feed of the entry.
+/// * `publish_date`: When the entry was published.
+/// * `reason`: Which limit of the policy the entry is past.
+#[derive(Debug, Clone)]
+pub struct PruneCandidate {
+    pub hash: String,
+    pub title: String,
+    pub rss_url: String,
+    pub publish_date: String,
+    pub reason: String,
+}
+
+impl Display for PruneCandidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            &self.hash[..12.min(self.hash.len())],
+            self.publish_date,
+            self.title,
+            self.reason
+        )
+    }
+}
+
+/// The `PruneReport` struct sums up a `prune` run.
+///
+/// Properties:
+///
+/// * `candidates`: The entries deleted, or that would be deleted by a dry run.
+/// * `protected`: The number of entries past a limit that are kept because they are starred,
+///   tagged or have downloaded enclosures.
+/// * `deleted`: The number of entries deleted, 0 for a dry run.
+/// * `size_before`: The size of the database before the run, in bytes.
+/// * `size_after`: The size of the database after the run, in bytes.
+#[derive(Debug, Default, Clone)]
+pub struct PruneReport {
+    pub candidates: Vec<PruneCandidate>,
+    pub protected: usize,
+    pub deleted: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// The function `prune` deletes the entries past the retention policy of their feed, see
+/// `RetentionPolicy::for_feed`. Deleted entries are remembered so the next sync does not store them
+/// again while their feed still lists them.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` holding the entries.
+/// * `options`: The `options` of the run, see `PruneOptions`.
+///
+/// Returns:
+///
+/// a `PruneReport`, or the database error that stopped the run, in which case nothing is deleted.
+pub fn prune(
+    connection: &ThreadSafe<&Connection>,
+    options: &PruneOptions,
+) -> Result<PruneReport, Error> {
+    let mut report = PruneReport {
+        size_before: database_size(connection)?,
+        ..Default::default()
+    };
+    for rss_url in entry_feeds(connection)? {
+        if options
+            .rss_url
+            .as_ref()
+            .is_some_and(|only| *only != rss_url)
+        {
+            continue;
+        }
+        let policy = RetentionPolicy::for_feed(connection, &rss_url);
+        if policy.deletes_anything() {
+            plan_feed(connection, &rss_url, &policy, &mut report)?;
+        }
+    }
+    if !options.dry_run {
+        let hashes: Vec<String> = report
+            .candidates
+            .iter()
+            .map(|candidate| candidate.hash.clone())
+            .collect();
+        delete_entries(connection, &hashes)?;
+        report.deleted = report.candidates.len();
+        if options.vacuum {
+            connection.get_ref().execute("VACUUM")?;
+        }
+        if options.analyze {
+            connection.get_ref().execute("ANALYZE")?;
+        }
+    }
+    report.size_after = database_size(connection)?;
+    Ok(report)
+}
+
+/// The function `database_size` returns the size of the database in bytes, free pages included.
+pub fn database_size(connection: &ThreadSafe<&Connection>) -> Result<u64, Error> {
+    let pragma = |name: &str| -> Result<i64, Error> {
+        let mut statement = connection.get_ref().prepare(format!("PRAGMA {}", name))?;
+        match statement.next()? {
+            State::Row => statement.read::<i64, _>(0),
+            State::Done => Ok(0),
+        }
+    };
+    Ok((pragma("page_count")? * pragma("page_size")?) as u64)
+}
+
+/// The function `entry_feeds` lists the feeds that have stored entries, including feeds that are
+/// no longer subscribed, which only follow the global policy.
+fn entry_feeds(connection: &ThreadSafe<&Connection>) -> Result<Vec<String>, Error> {
+    let mut statement = connection
+        .get_ref()
+        .prepare("SELECT DISTINCT IFNULL(rss_url, '') AS rss_url FROM rss_entries ORDER BY 1")?;
+    let mut rss_urls = vec![];
+    while let State::Row = statement.next()? {
+        rss_urls.push(statement.read::<String, _>("rss_url")?);
+    }
+    Ok(rss_urls)
+}
+
+/// The function `plan_feed` adds the entries of one feed that are past its retention policy to
+/// the candidates of the report, newest first. An entry is past the policy when it is beyond the
+/// `keep_newest` newest entries or published more than `max_age_days` ago. Starred and tagged
+/// entries are only counted as protected unless the policy says otherwise, and entries with
+/// downloaded enclosures always are.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` holding the entries.
+/// * `rss_url`: The feed of the entries, empty for the entries stored without one.
+/// * `policy`: The retention `policy` of the feed, see `RetentionPolicy::for_feed`.
+/// * `report`: The `report` the candidates and the protected entries are added to.
+fn plan_feed(
+    connection: &ThreadSafe<&Connection>,
+    rss_url: &str,
+    policy: &RetentionPolicy,
+    report: &mut PruneReport,
+) -> Result<(), Error> {
+    let query = "
+        SELECT hash, title, publish_date, is_starred,
+            EXISTS (SELECT 1 FROM entry_tags WHERE entry_tags.hash = rss_entries.hash) AS tagged,
+            EXISTS (
+                SELECT 1 FROM enclosures
+                WHERE enclosures.hash = rss_entries.hash AND enclosures.file_path IS NOT NULL
+            ) AS downloaded
+        FROM rss_entries
+        WHERE IFNULL(rss_url, '') = ?
+        ORDER BY publish_date DESC
+    ";
+    let mut statement = connection.get_ref().prepare(query)?;
+    statement.bind((1, rss_url))?;
+    let keep_newest = policy.keep_newest.unwrap_or(0);
+    let max_age_days = policy.max_age_days.unwrap_or(0);
+    let cutoff = (Utc::now() - Duration::days(max_age_days as i64))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    let mut position = 0;
+    while let State::Row = statement.next()? {
+        position += 1;
+        let publish_date = statement.read::<String, _>("publish_date")?;
+        let reason = if keep_newest > 0 && position > keep_newest {
+            format!("beyond the {} newest", keep_newest)
+        } else if max_age_days > 0 && publish_date < cutoff {
+            format!("older than {} days", max_age_days)
+        } else {
+            continue;
+        };
+        let starred = statement.read::<i64, _>("is_starred").unwrap_or(0) == 1;
+        let tagged = statement.read::<i64, _>("tagged")? == 1;
+        let downloaded = statement.read::<i64, _>("downloaded")? == 1;
+        // downloaded files are deleted by `download prune`, which needs their entry
+        if (starred && policy.keep_starred.unwrap_or(true))
+            || (tagged && policy.keep_tagged.unwrap_or(true))
+            || downloaded
+        {
+            report.protected += 1;
+            continue;
+        }
+        report.candidates.push(PruneCandidate {
+            hash: statement.read::<String, _>("hash")?,
+            title: statement.read::<String, _>("title")?,
+            rss_url: rss_url.to_string(),
+            publish_date,
+            reason,
+        });
+    }
+    Ok(())
+}
+
+/// The function `delete_entries` deletes the entries with the given hashes and their rows in the
+/// other entry tables, and remembers them in `pruned_entries`, all in one transaction.
+pub fn delete_entries(
+    connection: &ThreadSafe<&Connection>,
+    hashes: &[String],
+) -> Result<(), Error> {
+    let connection = connection.get_ref();
+    connection.execute("BEGIN")?;
+    let result = (|| {
+        for hash in hashes {
+            for table in ENTRY_TABLES {
+                let mut statement =
+                    connection.prepare(format!("DELETE FROM {} WHERE hash = ?", table))?;
+                statement.bind((1, hash.as_str()))?;
+                statement.next()?;
+            }
+            let mut statement =
+                connection.prepare("INSERT OR IGNORE INTO pruned_entries (hash) VALUES (?)")?;
+            statement.bind((1, hash.as_str()))?;
+            statement.next()?;
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => connection.execute("COMMIT"),
+        Err(e) => {
+            let _ = connection.execute("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::traits::CrudAble,
+        db::{blog::RssEntry, open_database},
+        rss::dto::Item,
+    };
+
+    const FEED: &str = "https://example.com/rss.xml";
+
+    fn days_ago(days: i64) -> String {
+        (Utc::now() - Duration::days(days))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    }
+
+    /// The function `database` stores six entries of `FEED`, one day, 10, 40, 50, 60 and 70 days
+    /// old. The 40 days old one is starred, the 50 days old one is tagged and the 60 days old one
+    /// has a downloaded enclosure.
+    fn database() -> Connection {
+        let connection = open_database(":memory:").unwrap();
+        for (hash, days) in [
+            ("e1", 1),
+            ("e2", 10),
+            ("e3", 40),
+            ("e4", 50),
+            ("e5", 60),
+            ("e6", 70),
+        ] {
+            let mut statement = connection
+                .prepare(
+                    "INSERT INTO rss_entries (hash, title, link, publish_date, rss_url)
+                    VALUES (?, ?, 'https://example.com/', ?, ?)",
+                )
+                .unwrap();
+            statement.bind((1, hash)).unwrap();
+            statement
+                .bind((2, format!("Entry {}", hash).as_str()))
+                .unwrap();
+            statement.bind((3, days_ago(days).as_str())).unwrap();
+            statement.bind((4, FEED)).unwrap();
+            statement.next().unwrap();
+        }
+        connection
+            .execute(
+                "UPDATE rss_entries SET is_starred = 1 WHERE hash = 'e3';
+                INSERT INTO entry_tags (hash, tag) VALUES ('e4', 'keep');
+                INSERT INTO enclosures (hash, url, file_path)
+                VALUES ('e5', 'https://example.com/e5.mp3', '/tmp/e5.mp3');
+                INSERT INTO entry_categories (hash, category) VALUES ('e6', 'Rust');",
+            )
+            .unwrap();
+        connection
+    }
+
+    fn plan(connection: &ThreadSafe<&Connection>, policy: RetentionPolicy) -> PruneReport {
+        let mut report = PruneReport::default();
+        plan_feed(connection, FEED, &policy, &mut report).unwrap();
+        report
+    }
+
+    fn hashes(report: &PruneReport) -> Vec<&str> {
+        report
+            .candidates
+            .iter()
+            .map(|candidate| candidate.hash.as_str())
+            .collect()
+    }
+
+    fn count(connection: &ThreadSafe<&Connection>, table: &str) -> i64 {
+        let mut statement = connection
+            .get_ref()
+            .prepare(format!("SELECT COUNT(*) FROM {}", table))
+            .unwrap();
+        statement.next().unwrap();
+        statement.read::<i64, _>(0).unwrap()
+    }
+
+    #[test]
+    fn old_entries_are_planned_and_kept_ones_protected() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let report = plan(
+            &connection,
+            RetentionPolicy {
+                max_age_days: Some(30),
+                ..Default::default()
+            },
+        );
+        assert_eq!(hashes(&report), vec!["e6"]);
+        assert_eq!(report.candidates[0].reason, "older than 30 days");
+        assert_eq!(report.protected, 3);
+    }
+
+    #[test]
+    fn newest_entries_are_kept() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let report = plan(
+            &connection,
+            RetentionPolicy {
+                keep_newest: Some(2),
+                ..Default::default()
+            },
+        );
+        assert_eq!(hashes(&report), vec!["e6"]);
+        assert_eq!(report.candidates[0].reason, "beyond the 2 newest");
+        assert_eq!(report.protected, 3);
+
+        let report = plan(
+            &connection,
+            RetentionPolicy {
+                keep_newest: Some(1),
+                max_age_days: Some(30),
+                ..Default::default()
+            },
+        );
+        assert_eq!(hashes(&report), vec!["e2", "e6"]);
+        assert_eq!(report.candidates[0].reason, "beyond the 1 newest");
+    }
+
+    #[test]
+    fn starred_and_tagged_entries_can_be_pruned() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let report = plan(
+            &connection,
+            RetentionPolicy {
+                max_age_days: Some(30),
+                keep_starred: Some(false),
+                keep_tagged: Some(false),
+                ..Default::default()
+            },
+        );
+        // downloaded enclosures are left to `download prune`
+        assert_eq!(hashes(&report), vec!["e3", "e4", "e6"]);
+        assert_eq!(report.protected, 1);
+    }
+
+    #[test]
+    fn dry_run_leaves_the_entries_untouched() {
+        let connection = database();
+        let connection = ThreadSafe::new(&connection);
+        let mut policy = RetentionPolicy {
+            max_age_days: Some(30),
+            ..Default::default()
+        };
+        policy.connection = Some(connection.clone());
+        policy.save().unwrap();
+
+        let options = PruneOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = prune(&connection, &options).unwrap();
+        assert_eq!(hashes(&report), vec!["e6"]);
+        assert_eq!(report.deleted, 0);
+        assert_eq!(count(&connection, "rss_entries"), 6);
+        assert_eq!(count(&connection, "entry_categories"), 1);
+        assert_eq!(count(&connection, "pruned_entries"), 0);
+
+        let report = prune(&connection, &PruneOptions::default()).unwrap();
+        assert_eq!(report.deleted, 1);
+        assert_eq!(count(&connection, "rss_entries"), 5);
+        assert_eq!(count(&connection, "entry_categories"), 0);
+        assert!(RssEntry::is_pruned(&connection, "e6"));
+
+        // another feed is left alone
+        let options = PruneOptions {
+            rss_url: Some(String::from("https://example.com/other.xml")),
+            ..Default::default()
+        };
+        assert!(prune(&connection, &options).unwrap().candidates.is_empty());
+    }
+
+    #[test]
+    fn entries_age_from_their_publication_date() {
+        let connection = open_database(":memory:").unwrap();
+        let connection = ThreadSafe::new(&connection);
+        let old = Item {
+            title: String::from("Old post"),
+            publish_date: String::from("Tue, 02 Jan 2024 03:04:05 +0100"),
+            ..Default::default()
+        };
+        let undated = Item {
+            title: String::from("Undated post"),
+            ..Default::default()
+        };
+        for item in [old, undated] {
+            let mut rss_entry = RssEntry::from(item);
+            rss_entry.rss_url = FEED.to_string();
+            rss_entry.connection = Some(connection.clone());
+            rss_entry.save().unwrap();
+        }
+
+        let stored = RssEntry::find(&connection, "Old post").unwrap();
+        assert_eq!(stored.publish_date, "2024-01-02 02:04:05");
+        let report = plan(
+            &connection,
+            RetentionPolicy {
+                max_age_days: Some(30),
+                ..Default::default()
+            },
+        );
+        // the entry without a date counts from the sync that stored it
+        assert_eq!(
+            report
+                .candidates
+                .iter()
+                .map(|candidate| candidate.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Old post"]
+        );
+    }
+}
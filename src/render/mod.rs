@@ -0,0 +1,444 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node};
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use unicode_width::UnicodeWidthStr;
+
+const BOLD: &str = "\x1b[1m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const DIM: &str = "\x1b[2m";
+const CYAN: &str = "\x1b[36m";
+const BLUE: &str = "\x1b[34m";
+const RESET: &str = "\x1b[0m";
+
+/// The `RenderOptions` struct holds how HTML is rendered for the terminal.
+///
+/// Properties:
+///
+/// * `width`: The column at which paragraphs are wrapped.
+/// * `color`: Whether ANSI colors and styles are written.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    pub width: usize,
+    pub color: bool,
+}
+
+impl RenderOptions {
+    /// The function `for_terminal` sizes the output for the current terminal and enables colors when
+    /// stdout is a terminal that supports them, following the `NO_COLOR` convention.
+    pub fn for_terminal() -> RenderOptions {
+        let width = terminal_size::terminal_size()
+            .map(|(width, _)| width.0 as usize)
+            .unwrap_or(80)
+            .clamp(20, 100);
+        let color = std::io::stdout().is_terminal()
+            && std::env::var_os("NO_COLOR").is_none()
+            && std::env::var("TERM")
+                .map(|term| term != "dumb")
+                .unwrap_or(true);
+        RenderOptions { width, color }
+    }
+
+    pub fn set_color(self: &mut RenderOptions, color: bool) -> &RenderOptions {
+        self.color = color;
+        self
+    }
+}
+
+/// The function `render_html` converts an HTML fragment to wrapped terminal text. Headings are
+/// emphasized, lists are bulleted or numbered, code blocks keep their layout, block quotes are
+/// prefixed with a bar and links are numbered and listed as footnotes at the end.
+///
+/// Arguments:
+///
+/// * `html`: The HTML fragment, like an entry description or its stored full text.
+/// * `options`: The `options` for width and colors.
+///
+/// Returns:
+///
+/// the rendered text.
+pub fn render_html(html: &str, options: RenderOptions) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut renderer = Renderer::new(options);
+    renderer.walk(fragment.tree.root());
+    renderer.finish()
+}
+
+/// The function `strip_controls` removes the C0 and C1 control characters of a text taken from a
+/// feed, except tabs and line feeds, so escape sequences embedded in it cannot clear the screen,
+/// retitle the terminal or forge links when it is printed.
+pub fn strip_controls(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\t' || *c == '\n')
+        .collect()
+}
+
+/// The function `style` wraps `text` in an ANSI style when colors are enabled.
+pub fn style(text: &str, code: &str, options: RenderOptions) -> String {
+    if options.color {
+        format!("{}{}{}", code, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The function `page` writes `text` to stdout, through `$PAGER` (or `less -R`) when stdout is a
+/// terminal and the text does not fit on one screen.
+pub fn page(text: &str) {
+    let height = terminal_size::terminal_size()
+        .map(|(_, height)| height.0 as usize)
+        .unwrap_or(24);
+    if !std::io::stdout().is_terminal() || text.lines().count() < height {
+        print!("{}", text);
+        return;
+    }
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| String::from("less -R"));
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(&pager)
+        .stdin(Stdio::piped())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // the pager closes its stdin when the user quits early
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            let _ = child.wait();
+        }
+        Err(_) => print!("{}", text),
+    }
+}
+
+struct Renderer {
+    options: RenderOptions,
+    out: String,
+    inline: String,
+    links: Vec<String>,
+    lists: Vec<Option<usize>>,
+    quote_depth: usize,
+    pending_bullet: Option<String>,
+}
+
+impl Renderer {
+    fn new(options: RenderOptions) -> Renderer {
+        Renderer {
+            options,
+            out: String::new(),
+            inline: String::new(),
+            links: vec![],
+            lists: vec![],
+            quote_depth: 0,
+            pending_bullet: None,
+        }
+    }
+
+    fn styled(&mut self, code: &str, node: NodeRef<Node>) {
+        if self.options.color {
+            self.inline.push_str(code);
+        }
+        self.walk_children(node);
+        if self.options.color {
+            self.inline.push_str(RESET);
+        }
+    }
+
+    fn walk_children(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            self.walk(child);
+        }
+    }
+
+    fn walk(&mut self, node: NodeRef<Node>) {
+        let element = match node.value() {
+            Node::Text(text) => {
+                self.inline.push_str(&strip_controls(text));
+                return;
+            }
+            Node::Element(_) => ElementRef::wrap(node).unwrap(),
+            _ => return self.walk_children(node),
+        };
+
+        match element.value().name() {
+            "script" | "style" | "noscript" | "head" | "iframe" | "svg" | "form" => {}
+            "br" => self.inline.push('\n'),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+                if !self.options.color {
+                    let level = element.value().name()[1..].parse::<usize>().unwrap_or(1);
+                    self.inline.push_str(&"#".repeat(level));
+                    self.inline.push(' ');
+                }
+                self.styled(&format!("{}{}", BOLD, UNDERLINE), node);
+                self.flush();
+            }
+            "p" | "div" | "section" | "article" | "main" | "figure" | "figcaption" | "table"
+            | "tr" | "dl" | "dt" | "dd" | "header" | "footer" => {
+                self.flush();
+                self.walk_children(node);
+                self.flush();
+            }
+            "hr" => {
+                self.flush();
+                let rule = "─".repeat(self.options.width.min(40));
+                self.push_block(&style(&rule, DIM, self.options));
+            }
+            "ul" | "ol" => {
+                self.flush();
+                let start = element
+                    .value()
+                    .attr("start")
+                    .and_then(|start| start.parse::<usize>().ok())
+                    .unwrap_or(1);
+                let kind = if element.value().name() == "ol" {
+                    Some(start)
+                } else {
+                    None
+                };
+                self.lists.push(kind);
+                self.walk_children(node);
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            "li" => {
+                self.flush();
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => String::from("• "),
+                };
+                self.pending_bullet = Some(bullet);
+                self.walk_children(node);
+                self.flush();
+            }
+            "blockquote" => {
+                self.flush();
+                self.quote_depth += 1;
+                self.walk_children(node);
+                self.flush();
+                self.quote_depth -= 1;
+            }
+            "pre" => {
+                self.flush();
+                let code: String = element.text().collect();
+                let code = code.trim_end_matches('\n');
+                let indent = self.indent();
+                let block: Vec<String> = code
+                    .lines()
+                    .map(|line| format!("{}    {}", indent, style(line, CYAN, self.options)))
+                    .collect();
+                self.out.push_str(&block.join("\n"));
+                self.out.push_str("\n\n");
+            }
+            "code" => {
+                if self.options.color {
+                    self.styled(CYAN, node);
+                } else {
+                    self.inline.push('`');
+                    self.walk_children(node);
+                    self.inline.push('`');
+                }
+            }
+            "b" | "strong" => self.styled(BOLD, node),
+            "i" | "em" => self.styled(ITALIC, node),
+            "a" => {
+                self.walk_children(node);
+                if let Some(href) = element.value().attr("href") {
+                    if href.starts_with('#') || href.is_empty() {
+                        return;
+                    }
+                    self.links.push(strip_controls(href));
+                    let marker = format!("[{}]", self.links.len());
+                    self.inline.push_str(&style(&marker, BLUE, self.options));
+                }
+            }
+            "img" => {
+                let alt = element.value().attr("alt").unwrap_or("image");
+                let marker = format!("[image: {}]", strip_controls(alt));
+                self.inline.push_str(&style(&marker, DIM, self.options));
+            }
+            _ => self.walk_children(node),
+        }
+    }
+
+    fn indent(&self) -> String {
+        let mut indent = "│ ".repeat(self.quote_depth);
+        indent.push_str(&"  ".repeat(self.lists.len().saturating_sub(1)));
+        indent
+    }
+
+    fn push_block(&mut self, block: &str) {
+        self.out.push_str(block);
+        self.out.push_str("\n\n");
+    }
+
+    /// The function `flush` wraps the pending inline text into lines and appends it to the output.
+    fn flush(&mut self) {
+        let inline = std::mem::take(&mut self.inline);
+        let bullet = self.pending_bullet.take();
+        if inline.trim().is_empty() {
+            if let Some(bullet) = bullet {
+                self.pending_bullet = Some(bullet);
+            }
+            return;
+        }
+
+        let indent = self.indent();
+        let first_prefix = format!("{}{}", indent, bullet.clone().unwrap_or_default());
+        let rest_prefix = format!(
+            "{}{}",
+            indent,
+            " ".repeat(bullet.map(|bullet| bullet.width()).unwrap_or(0))
+        );
+        let available = self
+            .options
+            .width
+            .saturating_sub(visible_width(&first_prefix))
+            .max(10);
+
+        let mut lines: Vec<String> = vec![];
+        for hard_line in inline.split('\n') {
+            let mut line = String::new();
+            for word in hard_line.split_whitespace() {
+                if !line.is_empty() && visible_width(&line) + 1 + visible_width(word) > available {
+                    lines.push(std::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+
+        for (index, line) in lines.iter().enumerate() {
+            let prefix = if index == 0 {
+                &first_prefix
+            } else {
+                &rest_prefix
+            };
+            self.out.push_str(prefix);
+            self.out.push_str(line);
+            self.out.push('\n');
+        }
+        if self.lists.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        let mut out = self.out.trim_end().to_string();
+        out.push('\n');
+        if !self.links.is_empty() {
+            out.push('\n');
+            for (index, link) in self.links.iter().enumerate() {
+                let marker = format!("[{}]", index + 1);
+                out.push_str(&format!(
+                    "{} {}\n",
+                    style(&marker, BLUE, self.options),
+                    link
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// The function `visible_width` measures the columns a string takes on screen, ignoring ANSI
+/// escape sequences.
+fn visible_width(value: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    let mut plain = String::new();
+    for character in value.chars() {
+        if in_escape {
+            if character == 'm' {
+                in_escape = false;
+            }
+            continue;
+        }
+        if character == '\x1b' {
+            in_escape = true;
+            width += plain.width();
+            plain.clear();
+            continue;
+        }
+        plain.push(character);
+    }
+    width + plain.width()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(width: usize) -> RenderOptions {
+        RenderOptions {
+            width,
+            color: false,
+        }
+    }
+
+    #[test]
+    fn control_sequences_from_the_feed_are_stripped() {
+        let html = "<p>before\x1b[2Jafter \x1b]0;owned\x07title \u{9b}31m</p>\
+                    <a href=\"https://example.com/\x1b[2J\">link</a>";
+        let text = render_html(html, plain(80));
+        assert!(!text.contains('\x1b'));
+        assert!(!text.contains('\x07'));
+        assert!(!text.contains('\u{9b}'));
+        assert!(text.contains("before[2Jafter ]0;ownedtitle 31m"));
+        assert!(text.contains("https://example.com/[2J"));
+    }
+
+    #[test]
+    fn strip_controls_keeps_tabs_and_line_feeds() {
+        assert_eq!(strip_controls("a\tb\nc\r\x00\x7f\u{85}d"), "a\tb\ncd");
+    }
+
+    #[test]
+    fn html_is_rendered_as_plain_text_without_colors() {
+        let html = "<h2>Title</h2><p>Some <b>bold</b> text with a \
+                    <a href=\"https://example.com\">link</a>.</p>\
+                    <ul><li>one</li><li>two</li></ul><ol start=\"3\"><li>three</li></ol>\
+                    <script>alert(1)</script><img alt=\"a cat\">";
+        let text = render_html(html, plain(80));
+        assert!(!text.contains('\x1b'));
+        assert!(text.contains("## Title"));
+        assert!(text.contains("Some bold text with a link[1]."));
+        assert!(text.contains("• one"));
+        assert!(text.contains("3. three"));
+        assert!(text.contains("[image: a cat]"));
+        assert!(text.contains("https://example.com"));
+        assert!(!text.contains("alert"));
+    }
+
+    #[test]
+    fn paragraphs_are_wrapped_at_the_width() {
+        let text = render_html(&format!("<p>{}</p>", "word ".repeat(30)), plain(20));
+        assert!(text.lines().count() > 1);
+        assert!(text.lines().all(|line| line.width() <= 20));
+    }
+
+    #[test]
+    fn colors_are_written_when_enabled() {
+        let text = render_html(
+            "<p><b>bold</b></p>",
+            RenderOptions {
+                width: 80,
+                color: true,
+            },
+        );
+        assert!(text.contains(&format!("{}bold{}", BOLD, RESET)));
+    }
+}
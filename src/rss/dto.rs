@@ -1,31 +1,53 @@
-use super::errors::RssParsingError;
+use super::{encoding::TranscodingReader, errors::RssParsingError, source::FeedSource, stream};
+use crate::{db::http_settings::HttpSettings, http::HttpClient};
 use chrono;
-use reqwest;
 use serde::{Deserialize, Serialize};
-use std::result::Result;
+use std::{fs::File, io::Read, result::Result, str::FromStr};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Rss {
-    channel: Channel,
+    pub(super) channel: Channel,
+
+    #[serde(skip)]
+    pub(super) encoding: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Channel {
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct Channel {
     #[serde(default)]
-    title: String,
+    pub(super) title: String,
+
+    #[serde(default)]
+    pub(super) link: String,
+
+    #[serde(default)]
+    pub(super) description: String,
 
     #[serde(default)]
-    description: String,
+    pub(super) language: String,
 
     #[serde(default)]
-    image: Option<Image>,
+    pub(super) generator: String,
+
+    // the `serde` names are the RSS element names, the keys printed by `parse --json`
+    #[serde(default, rename = "lastBuildDate")]
+    pub(super) last_build_date: String,
+
+    #[serde(default)]
+    pub(super) ttl: String,
+
+    #[serde(default)]
+    pub(super) image: Option<Image>,
+
+    #[serde(skip)]
+    pub(super) alternate_link: String,
 
     #[serde(default)]
-    item: Vec<Item>,
+    pub(super) item: Vec<Item>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Image {
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct Image {
     #[serde(default)]
     url: String,
 
@@ -36,7 +58,31 @@ struct Image {
     link: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The `ChannelInfo` struct holds the metadata of a channel that is stored with its subscription.
+///
+/// Properties:
+///
+/// * `title`: The title of the channel.
+/// * `link`: The URL of the site the channel belongs to.
+/// * `description`: The description of the channel.
+/// * `language`: The language code of the channel, like `en-us`.
+/// * `image_url`: The URL of the channel image.
+/// * `generator`: The software that generated the feed.
+/// * `last_build_date`: When the channel content last changed, as shipped by the feed.
+/// * `ttl`: The minutes the channel may be cached before it is fetched again, `ttl`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelInfo {
+    pub title: String,
+    pub link: String,
+    pub description: String,
+    pub language: String,
+    pub image_url: String,
+    pub generator: String,
+    pub last_build_date: String,
+    pub ttl: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Item {
     #[serde(default)]
     pub title: String,
@@ -44,53 +90,364 @@ pub struct Item {
     #[serde(default)]
     pub link: String,
 
-    #[serde(default)]
+    // the RSS element name printed by `parse --json`, `publish_date` is still read
+    #[serde(default, rename = "pubDate", alias = "publish_date")]
     pub publish_date: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default, alias = "creator")]
+    pub author: String,
+
+    #[serde(default)]
+    pub category: Vec<String>,
+
+    #[serde(default, rename = "enclosure")]
+    pub enclosures: Vec<Enclosure>,
+
+    #[serde(default)]
+    pub episode: Option<Episode>,
+
+    #[serde(default)]
+    pub media: Option<Media>,
+}
+
+/// The `Media` struct holds the Media RSS (`media:` namespace) data of an item, the elements of
+/// a `media:group` and the ones placed directly in the item are merged.
+///
+/// Properties:
+///
+/// * `contents`: The media files, `media:content`.
+/// * `thumbnails`: The preview images, `media:thumbnail`.
+/// * `title`: The title of the media, `media:title`.
+/// * `description`: The description of the media, `media:description`.
+/// * `community`: The ratings and statistics, `media:community`.
+/// * `credits`: The people or companies credited, `media:credit`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Media {
+    #[serde(default)]
+    pub contents: Vec<MediaContent>,
+
+    #[serde(default)]
+    pub thumbnails: Vec<MediaThumbnail>,
+
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub community: Option<MediaCommunity>,
+
+    #[serde(default)]
+    pub credits: Vec<MediaCredit>,
+}
+
+/// The `MediaCredit` struct is a `media:credit` element.
+///
+/// Properties:
+///
+/// * `name`: The credited person or company, the text of the element.
+/// * `role`: Their role, like `author` or `producer`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaCredit {
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+/// The `MediaContent` struct is a `media:content` element.
+///
+/// Properties:
+///
+/// * `url`: The URL of the file.
+/// * `mime_type`: The MIME type of the file, `type`.
+/// * `medium`: The kind of media, like `image` or `video`.
+/// * `width`: The width in pixels.
+/// * `height`: The height in pixels.
+/// * `duration_secs`: The length of the media in seconds, `duration`.
+/// * `file_size`: The size of the file in bytes, `fileSize`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaContent {
+    #[serde(default)]
+    pub url: String,
+
+    #[serde(default)]
+    pub mime_type: Option<String>,
+
+    #[serde(default)]
+    pub medium: Option<String>,
+
+    #[serde(default)]
+    pub width: Option<u64>,
+
+    #[serde(default)]
+    pub height: Option<u64>,
+
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+
+    #[serde(default)]
+    pub file_size: Option<u64>,
+}
+
+/// The `MediaThumbnail` struct is a `media:thumbnail` element.
+///
+/// Properties:
+///
+/// * `url`: The URL of the image.
+/// * `width`: The width in pixels.
+/// * `height`: The height in pixels.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaThumbnail {
+    #[serde(default)]
+    pub url: String,
+
+    #[serde(default)]
+    pub width: Option<u64>,
+
+    #[serde(default)]
+    pub height: Option<u64>,
+}
+
+/// The `MediaCommunity` struct holds the `media:starRating` and `media:statistics` of a
+/// `media:community` element.
+///
+/// Properties:
+///
+/// * `star_average`: The average rating.
+/// * `star_count`: The number of ratings.
+/// * `star_max`: The best possible rating.
+/// * `views`: The number of views.
+/// * `favorites`: The number of times the media was added to favorites.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaCommunity {
+    #[serde(default)]
+    pub star_average: Option<f64>,
+
+    #[serde(default)]
+    pub star_count: Option<u64>,
+
+    #[serde(default)]
+    pub star_max: Option<f64>,
+
+    #[serde(default)]
+    pub views: Option<u64>,
+
+    #[serde(default)]
+    pub favorites: Option<u64>,
+}
+
+/// The `Enclosure` struct is a media file attached to an item, like the audio of a podcast
+/// episode.
+///
+/// Properties:
+///
+/// * `url`: The URL of the file.
+/// * `length`: The size of the file in bytes, 0 when the feed does not tell.
+/// * `mime_type`: The MIME type of the file, like `audio/mpeg`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Enclosure {
+    #[serde(default)]
+    pub url: String,
+
+    #[serde(default)]
+    pub length: u64,
+
+    #[serde(default, rename = "type")]
+    pub mime_type: String,
+}
+
+/// The `Episode` struct holds the iTunes podcast metadata of an item.
+///
+/// Properties:
+///
+/// * `duration_secs`: The length of the episode, `itunes:duration` in seconds.
+/// * `episode`: The episode number, `itunes:episode`.
+/// * `season`: The season number, `itunes:season`.
+/// * `image`: The URL of the episode artwork, the `href` of `itunes:image`.
+/// * `explicit`: Whether the episode is marked explicit, `itunes:explicit`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Episode {
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+
+    #[serde(default)]
+    pub episode: Option<u64>,
+
+    #[serde(default)]
+    pub season: Option<u64>,
+
+    #[serde(default)]
+    pub image: Option<String>,
+
+    #[serde(default)]
+    pub explicit: Option<bool>,
 }
 
 impl Rss {
     /// The `parse` function in Rust is used to parse an RSS feed from a given URL and return the parsed
-    /// data as a `Result` of type `Rss` or an `RssParsingError`.
+    /// data as a `Result` of type `Rss` or an `RssParsingError`. A `file://` URL or `-` for stdin
+    /// is read without network access, see `FeedSource`.
     ///
     /// Arguments:
     ///
     /// * `url`: The `url` parameter is a `String` that represents the URL of the RSS feed that you want
-    /// to parse.
+    ///   to parse.
     ///
     /// Returns:
     ///
     /// The function `parse` returns a `Result` type with the success case containing an `Rss` object
     /// and the error case containing an `RssParsingError`.
     pub async fn parse(url: String) -> Result<Rss, RssParsingError> {
-        let res = reqwest::Client::new().get(url).send().await?;
-        let text = &res.text().await?;
-
-        let rss = serde_xml_rs::from_str::<Rss>(text)?;
-        println!("\n===============================");
-        print!("{}\n", rss.channel.title);
-        print!("{}\n", rss.channel.description);
-        print!("Found {} contents.\n", rss.channel.item.len());
-        println!("================================\n");
+        match FeedSource::from(url.as_str()) {
+            FeedSource::Http(url) => {
+                let client = HttpClient::new(&HttpSettings::default())?;
+                Rss::fetch(&client, &url, None).await
+            }
+            source => Rss::read_source(source, None).await,
+        }
+    }
+
+    /// The function `read_source` reads a local feed on a blocking thread.
+    ///
+    /// Arguments:
+    ///
+    /// * `source`: The `source` of the feed, `FeedSource::Http` is rejected as not found.
+    /// * `max_items`: The optional number of items to keep, the first ones in document order.
+    pub async fn read_source(
+        source: FeedSource,
+        max_items: Option<usize>,
+    ) -> Result<Rss, RssParsingError> {
+        tokio::task::spawn_blocking(move || match source {
+            FeedSource::Stdin => Rss::from_reader(std::io::stdin().lock(), max_items),
+            FeedSource::File(path) => Rss::from_reader(File::open(path)?, max_items),
+            FeedSource::Http(url) => Err(RssParsingError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not a local feed", url),
+            ))),
+        })
+        .await
+        .expect("The feed parser panicked")
+    }
+
+    /// The function `from_reader` parses a feed from raw bytes, like a saved file. The encoding
+    /// is detected from a byte order mark or the XML declaration, as for a download without a
+    /// `Content-Type` header.
+    ///
+    /// Arguments:
+    ///
+    /// * `reader`: The `reader` the document is read from.
+    /// * `max_items`: The optional number of items after which reading stops.
+    pub fn from_reader<R: Read>(
+        reader: R,
+        max_items: Option<usize>,
+    ) -> Result<Rss, RssParsingError> {
+        let mut reader = TranscodingReader::new(reader);
+        let mut rss = stream::parse_reader(&mut reader, max_items)?;
+        rss.encoding = reader.encoding().unwrap_or("UTF-8").to_string();
         Ok(rss)
     }
 
+    /// The function `fetch` parses the feed while it downloads and stops reading once `max_items`
+    /// items were parsed, so huge archive feeds are never held in memory as a whole.
+    ///
+    /// Arguments:
+    ///
+    /// * `client`: The `client` configured for the feed, see `HttpSettings::for_feed`.
+    /// * `url`: The `url` of the RSS feed.
+    /// * `max_items`: The optional number of items to keep, the first ones in document order.
+    pub async fn fetch(
+        client: &HttpClient,
+        url: &str,
+        max_items: Option<usize>,
+    ) -> Result<Rss, RssParsingError> {
+        Rss::fetch_request(client, client.client.get(url), max_items).await
+    }
+
+    /// The function `fetch_request` is `fetch` for a request that was already built, like one
+    /// carrying the credentials of the feed.
+    pub async fn fetch_request(
+        client: &HttpClient,
+        request: reqwest::RequestBuilder,
+        max_items: Option<usize>,
+    ) -> Result<Rss, RssParsingError> {
+        let res = client.send(request).await?;
+        stream::parse_response(client, res, max_items).await
+    }
+
+    /// The function `title` returns the title of the parsed channel.
+    pub fn title(&self) -> &str {
+        &self.channel.title
+    }
+
+    /// The function `encoding` returns the name of the character encoding the feed was decoded
+    /// from.
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    /// The function `description` returns the description of the parsed channel.
+    pub fn description(&self) -> &str {
+        &self.channel.description
+    }
+
+    /// The function `channel_info` returns the metadata of the parsed channel.
+    pub fn channel_info(&self) -> ChannelInfo {
+        ChannelInfo {
+            title: self.channel.title.clone(),
+            link: if self.channel.link.is_empty() {
+                self.channel.alternate_link.clone()
+            } else {
+                self.channel.link.clone()
+            },
+            description: self.channel.description.clone(),
+            language: self.channel.language.clone(),
+            image_url: self
+                .channel
+                .image
+                .as_ref()
+                .map(|image| image.url.clone())
+                .unwrap_or_default(),
+            generator: self.channel.generator.clone(),
+            last_build_date: self.channel.last_build_date.clone(),
+            ttl: self.channel.ttl.clone(),
+        }
+    }
+
     /// The function `get_items` returns a reference to a vector of items after parsing and formatting
     /// their publish dates.
     ///
     /// Returns:
     ///
-    /// a mutable reference to a vector of items (`&'c Vec<Item>`).
-    pub fn get_items<'c>(&'c mut self) -> &'c Vec<Item> {
+    /// a reference to a vector of items (`&Vec<Item>`).
+    pub fn get_items(&mut self) -> &Vec<Item> {
         for item in self.channel.item.iter_mut() {
-            match chrono::DateTime::parse_from_rfc2822(&item.publish_date) {
-                Ok(date) => date.format("%Y-%m-%d %H:%M:%S").to_string(),
-                Err(..) => String::from(""),
-            };
+            if let Ok(date) = chrono::DateTime::parse_from_rfc2822(&item.publish_date) {
+                item.publish_date = date
+                    .with_timezone(&chrono::Utc)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string();
+            }
         }
         &self.channel.item
     }
 }
 
+/// The `impl FromStr for Rss` block parses a feed that is already text, so its XML declaration is
+/// not used to decode it.
+impl FromStr for Rss {
+    type Err = RssParsingError;
+
+    fn from_str(xml: &str) -> Result<Self, Self::Err> {
+        stream::parse_reader(xml.as_bytes(), None)
+    }
+}
+
 /// The `impl Clone for Item` block is implementing the `Clone` trait for the `Item` struct. This allows
 /// instances of `Item` to be cloned, creating a new instance with the same values as the original.
 impl Clone for Item {
@@ -99,6 +456,398 @@ impl Clone for Item {
             title: self.title.clone(),
             link: self.link.clone(),
             publish_date: self.publish_date.clone(),
+            description: self.description.clone(),
+            author: self.author.clone(),
+            category: self.category.clone(),
+            enclosures: self.enclosures.clone(),
+            episode: self.episode.clone(),
+            media: self.media.clone(),
         }
     }
 }
+
+impl Channel {
+    /// The function `set_field` stores the text of a direct child element of `<channel>`. An empty
+    /// `link` is an `atom:link`, its URL is read by `set_attributes`.
+    pub(super) fn set_field(&mut self, field: &str, value: String) {
+        match field {
+            "title" => self.title = value,
+            "link" if !value.trim().is_empty() => self.link = value.trim().to_string(),
+            "description" => self.description = value,
+            "language" => self.language = value.trim().to_string(),
+            "generator" => self.generator = value.trim().to_string(),
+            "lastBuildDate" => self.last_build_date = value.trim().to_string(),
+            "ttl" => self.ttl = value.trim().to_string(),
+            _ => {}
+        }
+    }
+
+    /// The function `set_attributes` stores the attributes of a direct child element of
+    /// `<channel>`. The `href` of an `atom:link` to the site, without `rel` or with
+    /// `rel="alternate"`, is the site link when the channel has no `<link>`; the `self` link
+    /// pointing at the feed is skipped.
+    pub(super) fn set_attributes(&mut self, field: &str, attributes: &[(String, String)]) {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.trim())
+        };
+        if field != "link" || !self.alternate_link.is_empty() {
+            return;
+        }
+        if let (Some(href), "alternate") =
+            (attribute("href"), attribute("rel").unwrap_or("alternate"))
+        {
+            self.alternate_link = href.to_string();
+        }
+    }
+}
+
+impl Image {
+    /// The function `set_field` stores the text of a direct child element of `<image>`.
+    pub(super) fn set_field(&mut self, field: &str, value: String) {
+        match field {
+            "url" => self.url = value,
+            "title" => self.title = value,
+            "link" => self.link = value,
+            _ => {}
+        }
+    }
+}
+
+impl Item {
+    /// The function `set_field` stores the text of a direct child element of `<item>`, element
+    /// names are matched without their namespace prefix, so `dc:creator` fills `author`. An empty
+    /// `link` is ignored, as it is usually an `atom:link` carrying its URL in an attribute.
+    pub(super) fn set_field(&mut self, field: &str, value: String) {
+        match field {
+            "title" => self.title = value,
+            "link" if !value.trim().is_empty() => self.link = value.trim().to_string(),
+            "pubDate" | "publish_date" => self.publish_date = value,
+            "description" => self.description = value,
+            "author" | "creator" => self.author = value,
+            "category" => self.category.push(value),
+            "itunes:duration" => self.episode_mut().duration_secs = parse_duration(&value),
+            "itunes:episode" => self.episode_mut().episode = value.parse().ok(),
+            "itunes:season" => self.episode_mut().season = value.parse().ok(),
+            "itunes:explicit" => {
+                self.episode_mut().explicit = match value.to_lowercase().as_str() {
+                    "yes" | "true" | "explicit" => Some(true),
+                    "no" | "false" | "clean" => Some(false),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The function `set_attributes` stores the attributes of an empty child element of `<item>`,
+    /// the `<enclosure>` and the `href` of `<itunes:image>`.
+    pub(super) fn set_attributes(&mut self, field: &str, attributes: &[(String, String)]) {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.clone())
+        };
+        match field {
+            "enclosure" => {
+                if let Some(url) = attribute("url") {
+                    self.enclosures.push(Enclosure {
+                        url,
+                        length: attribute("length")
+                            .and_then(|length| length.trim().parse().ok())
+                            .unwrap_or(0),
+                        mime_type: attribute("type").unwrap_or_default(),
+                    })
+                }
+            }
+            "itunes:image" => {
+                if let Some(href) = attribute("href") {
+                    self.episode_mut().image = Some(href);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The function `set_media_field` stores the text of a `media:` element found anywhere inside
+    /// `<item>`, like in a `media:group`.
+    pub(super) fn set_media_field(&mut self, field: &str, value: String) {
+        if field == "media:credit" {
+            // the credit was opened by `set_media_attributes`, a credit without a name is dropped
+            let credits = &mut self.media_mut().credits;
+            let name = value.trim();
+            if name.is_empty() {
+                credits.pop();
+            } else if let Some(credit) = credits.last_mut() {
+                credit.name = name.to_string();
+            }
+            return;
+        }
+        if value.is_empty() {
+            return;
+        }
+        match field {
+            "media:title" => self.media_mut().title = Some(value),
+            "media:description" => self.media_mut().description = Some(value),
+            _ => {}
+        }
+    }
+
+    /// The function `set_media_attributes` stores the attributes of a `media:` element found
+    /// anywhere inside `<item>`.
+    pub(super) fn set_media_attributes(&mut self, field: &str, attributes: &[(String, String)]) {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.trim().to_string())
+        };
+        let number = |name: &str| attribute(name).and_then(|value| value.parse::<u64>().ok());
+        let decimal = |name: &str| attribute(name).and_then(|value| value.parse::<f64>().ok());
+        match field {
+            "media:content" => {
+                if let Some(url) = attribute("url") {
+                    self.media_mut().contents.push(MediaContent {
+                        url,
+                        mime_type: attribute("type"),
+                        medium: attribute("medium"),
+                        width: number("width"),
+                        height: number("height"),
+                        duration_secs: number("duration"),
+                        file_size: number("fileSize"),
+                    })
+                }
+            }
+            "media:thumbnail" => {
+                if let Some(url) = attribute("url") {
+                    self.media_mut().thumbnails.push(MediaThumbnail {
+                        url,
+                        width: number("width"),
+                        height: number("height"),
+                    })
+                }
+            }
+            "media:starRating" => {
+                let community = self.community_mut();
+                community.star_average = decimal("average");
+                community.star_count = number("count");
+                community.star_max = decimal("max");
+            }
+            "media:statistics" => {
+                let community = self.community_mut();
+                community.views = number("views");
+                community.favorites = number("favorites");
+            }
+            "media:credit" => self.media_mut().credits.push(MediaCredit {
+                name: String::new(),
+                role: attribute("role").filter(|role| !role.is_empty()),
+            }),
+            _ => {}
+        }
+    }
+
+    fn media_mut(&mut self) -> &mut Media {
+        self.media.get_or_insert_with(Media::default)
+    }
+
+    fn community_mut(&mut self) -> &mut MediaCommunity {
+        self.media_mut()
+            .community
+            .get_or_insert_with(MediaCommunity::default)
+    }
+
+    fn episode_mut(&mut self) -> &mut Episode {
+        self.episode.get_or_insert_with(Episode::default)
+    }
+
+    /// The function `published_at` returns the publish date of the item, accepting both the RFC 2822
+    /// dates found in feeds and the `%Y-%m-%d %H:%M:%S` dates produced by `Rss::get_items`.
+    pub fn published_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        parse_date(&self.publish_date)
+    }
+}
+
+/// The function `parse_date` reads a publish date, either an RFC 2822 date as found in feeds or a
+/// `%Y-%m-%d %H:%M:%S` date in UTC as produced by `Rss::get_items` and stored in the database.
+pub fn parse_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value.trim()) {
+        return Some(date.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|date| date.and_utc())
+}
+
+/// The function `parse_duration` reads an `itunes:duration`, either a number of seconds or
+/// `MM:SS` / `HH:MM:SS`.
+fn parse_duration(value: &str) -> Option<u64> {
+    let mut secs = 0;
+    for part in value.trim().split(':') {
+        secs = secs * 60 + part.trim().parse::<u64>().ok()?;
+    }
+    Some(secs)
+}
+
+impl Episode {
+    /// The function `duration` formats the duration of the episode as `H:MM:SS`.
+    pub fn duration(&self) -> Option<String> {
+        self.duration_secs
+            .map(|secs| format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PODCAST: &str = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>A podcast</title>
+    <item>
+      <title>Pilot</title>
+      <enclosure url="https://cdn.test/pilot.mp3" length=" 1234 " type="audio/mpeg"/>
+      <enclosure url="https://cdn.test/pilot.ogg" length="unknown"/>
+      <enclosure length="99" type="audio/mpeg"/>
+      <itunes:duration>1:02:03</itunes:duration>
+      <itunes:episode>1</itunes:episode>
+      <itunes:season>2</itunes:season>
+      <itunes:image href="https://cdn.test/pilot.jpg"/>
+      <itunes:explicit>Clean</itunes:explicit>
+    </item>
+    <item>
+      <title>Blog post</title>
+      <itunes:explicit>maybe</itunes:explicit>
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn durations_are_read_as_seconds() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("01:30"), Some(90));
+        assert_eq!(parse_duration("1:02:03"), Some(3723));
+        assert_eq!(parse_duration(" 45 : 00 "), Some(2700));
+        assert_eq!(parse_duration("abc"), None);
+        assert_eq!(parse_duration("1:xx"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn enclosures_and_episode_metadata_are_read_from_attributes() {
+        let rss: Rss = PODCAST.parse().unwrap();
+        let pilot = &rss.channel.item[0];
+        assert_eq!(
+            pilot.enclosures,
+            [
+                Enclosure {
+                    url: String::from("https://cdn.test/pilot.mp3"),
+                    length: 1234,
+                    mime_type: String::from("audio/mpeg"),
+                },
+                // an unreadable length is unknown, an enclosure without a URL is dropped
+                Enclosure {
+                    url: String::from("https://cdn.test/pilot.ogg"),
+                    length: 0,
+                    mime_type: String::new(),
+                },
+            ]
+        );
+        let episode = pilot.episode.as_ref().unwrap();
+        assert_eq!(episode.duration_secs, Some(3723));
+        assert_eq!(episode.duration().as_deref(), Some("1:02:03"));
+        assert_eq!((episode.episode, episode.season), (Some(1), Some(2)));
+        assert_eq!(episode.image.as_deref(), Some("https://cdn.test/pilot.jpg"));
+        assert_eq!(episode.explicit, Some(false));
+
+        // an unknown `itunes:explicit` value is left unset
+        let post = &rss.channel.item[1];
+        assert!(post.enclosures.is_empty());
+        assert_eq!(post.episode.as_ref().unwrap().explicit, None);
+    }
+
+    const GALLERY: &str = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/">
+  <channel>
+    <title>A gallery</title>
+    <item>
+      <title>Clip</title>
+      <media:group>
+        <media:content url="https://cdn.test/clip.mp4" type="video/mp4" medium="video"
+          width="1280" height="720" duration="95" fileSize="2048"/>
+        <media:content url="https://cdn.test/clip.webm" width="wide"/>
+        <media:content type="video/ogg"/>
+        <media:thumbnail url="https://cdn.test/clip.jpg" width="320" height="180"/>
+      </media:group>
+      <media:thumbnail url="https://cdn.test/poster.jpg"/>
+      <media:credit role="author"> Jane Doe </media:credit>
+      <media:credit role="">Studio</media:credit>
+      <media:credit role="editor"/>
+    </item>
+    <item>
+      <title>Text only</title>
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn media_contents_thumbnails_and_credits_are_read() {
+        let rss: Rss = GALLERY.parse().unwrap();
+        let media = rss.channel.item[0].media.as_ref().unwrap();
+        assert_eq!(
+            media.contents,
+            [
+                MediaContent {
+                    url: String::from("https://cdn.test/clip.mp4"),
+                    mime_type: Some(String::from("video/mp4")),
+                    medium: Some(String::from("video")),
+                    width: Some(1280),
+                    height: Some(720),
+                    duration_secs: Some(95),
+                    file_size: Some(2048),
+                },
+                // an unreadable number is unknown, a content without a URL is dropped
+                MediaContent {
+                    url: String::from("https://cdn.test/clip.webm"),
+                    ..MediaContent::default()
+                },
+            ]
+        );
+        // thumbnails inside and outside `media:group` are kept together
+        assert_eq!(
+            media.thumbnails,
+            [
+                MediaThumbnail {
+                    url: String::from("https://cdn.test/clip.jpg"),
+                    width: Some(320),
+                    height: Some(180),
+                },
+                MediaThumbnail {
+                    url: String::from("https://cdn.test/poster.jpg"),
+                    width: None,
+                    height: None,
+                },
+            ]
+        );
+        // an empty role is unknown, a credit without a name is dropped
+        assert_eq!(
+            media.credits,
+            [
+                MediaCredit {
+                    name: String::from("Jane Doe"),
+                    role: Some(String::from("author")),
+                },
+                MediaCredit {
+                    name: String::from("Studio"),
+                    role: None,
+                },
+            ]
+        );
+        assert_eq!(media.community, None);
+
+        assert_eq!(rss.channel.item[1].media, None);
+    }
+}
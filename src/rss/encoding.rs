@@ -0,0 +1,306 @@
+use encoding_rs::{Decoder, Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+use std::io::{self, Read};
+
+/// Number of bytes looked at to find the XML declaration.
+const SNIFF_LENGTH: usize = 1024;
+
+lazy_static! {
+    static ref DECLARED_ENCODING: Regex =
+        Regex::new(r#"^<\?xml[^>]*?encoding\s*=\s*["']([A-Za-z0-9._:-]+)["']"#).unwrap();
+}
+
+/// The function `detect` picks the character encoding of a feed following RFC 7303: a byte order
+/// mark wins, then the `charset` of the `Content-Type` header, then the `encoding` of the XML
+/// declaration, and UTF-8 otherwise. Unknown labels are skipped.
+///
+/// Arguments:
+///
+/// * `head`: The first bytes of the document, at least `SNIFF_LENGTH` when the document is longer.
+/// * `content_type`: The optional `Content-Type` header of the response.
+///
+/// Returns:
+///
+/// the detected encoding.
+pub fn detect(head: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(head) {
+        return encoding;
+    }
+    if let Some(encoding) = content_type.and_then(charset).and_then(Encoding::for_label) {
+        return encoding;
+    }
+    // a declaration written in UTF-16 without a byte order mark
+    if head.starts_with(&[0, b'<', 0, b'?']) {
+        return UTF_16BE;
+    }
+    if head.starts_with(&[b'<', 0, b'?', 0]) {
+        return UTF_16LE;
+    }
+    DECLARED_ENCODING
+        .captures(&head[..head.len().min(SNIFF_LENGTH)])
+        .and_then(|captures| Encoding::for_label(&captures[1]))
+        // the declaration was readable as ASCII, so it cannot really be UTF-16
+        .filter(|encoding| *encoding != UTF_16BE && *encoding != UTF_16LE)
+        .unwrap_or(UTF_8)
+}
+
+/// The function `charset` returns the `charset` parameter of a `Content-Type` header.
+fn charset(content_type: &str) -> Option<&[u8]> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').as_bytes())
+}
+
+/// The `Transcoder` struct converts a document to UTF-8 chunk by chunk. The first bytes are held
+/// back until enough of the document arrived to detect its encoding.
+///
+/// Properties:
+///
+/// * `content_type`: The optional `Content-Type` header used by `detect`.
+/// * `head`: The bytes received before the encoding was detected.
+/// * `decoder`: The decoder, set once the encoding is known.
+pub struct Transcoder {
+    content_type: Option<String>,
+    head: Vec<u8>,
+    decoder: Option<Decoder>,
+}
+
+impl Transcoder {
+    pub fn new(content_type: Option<String>) -> Transcoder {
+        Transcoder {
+            content_type,
+            head: vec![],
+            decoder: None,
+        }
+    }
+
+    /// The function `encoding` returns the name of the detected encoding, or `None` while it is
+    /// not known yet.
+    pub fn encoding(&self) -> Option<&'static str> {
+        self.decoder
+            .as_ref()
+            .map(|decoder| decoder.encoding().name())
+    }
+
+    /// The function `feed` transcodes the next chunk of the document.
+    ///
+    /// Returns:
+    ///
+    /// the UTF-8 bytes ready to be parsed, empty while the encoding is being detected.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        if self.decoder.is_none() {
+            self.head.extend_from_slice(chunk);
+            if self.head.len() < SNIFF_LENGTH {
+                return vec![];
+            }
+            let head = std::mem::take(&mut self.head);
+            return self.start(&head, false);
+        }
+        self.decode(chunk, false)
+    }
+
+    /// The function `finish` flushes the held back bytes at the end of the document.
+    pub fn finish(&mut self) -> Vec<u8> {
+        if self.decoder.is_none() {
+            let head = std::mem::take(&mut self.head);
+            return self.start(&head, true);
+        }
+        self.decode(&[], true)
+    }
+
+    fn start(&mut self, head: &[u8], last: bool) -> Vec<u8> {
+        let encoding = detect(head, self.content_type.as_deref());
+        self.decoder = Some(encoding.new_decoder_with_bom_removal());
+        self.decode(head, last)
+    }
+
+    fn decode(&mut self, chunk: &[u8], last: bool) -> Vec<u8> {
+        let decoder = self.decoder.as_mut().expect("Encoding is not detected");
+        let capacity = decoder
+            .max_utf8_buffer_length(chunk.len())
+            .unwrap_or(chunk.len() * 3 + 16);
+        let mut decoded = String::with_capacity(capacity);
+        let _ = decoder.decode_to_string(chunk, &mut decoded, last);
+        decoded.into_bytes()
+    }
+}
+
+/// Size of the chunks read from the wrapped reader of a `TranscodingReader`.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// The `TranscodingReader` struct is the `Read` counterpart of `Transcoder`, it yields the UTF-8
+/// transcoding of a document read from a file or stdin.
+///
+/// Properties:
+///
+/// * `inner`: The reader the document is read from.
+/// * `transcoder`: The transcoder the read bytes go through.
+/// * `decoded`: The transcoded bytes not read yet.
+/// * `position`: The position of the next byte to read in `decoded`.
+/// * `finished`: Whether `inner` reached its end.
+pub struct TranscodingReader<R: Read> {
+    inner: R,
+    transcoder: Transcoder,
+    decoded: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> TranscodingReader<R> {
+    pub fn new(inner: R) -> TranscodingReader<R> {
+        TranscodingReader {
+            inner,
+            transcoder: Transcoder::new(None),
+            decoded: vec![],
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// The function `encoding` returns the name of the detected encoding, or `None` while it is
+    /// not known yet.
+    pub fn encoding(&self) -> Option<&'static str> {
+        self.transcoder.encoding()
+    }
+}
+
+impl<R: Read> Read for TranscodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; READ_CHUNK];
+        while self.position >= self.decoded.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            let count = self.inner.read(&mut chunk)?;
+            self.decoded = if count == 0 {
+                self.finished = true;
+                self.transcoder.finish()
+            } else {
+                self.transcoder.feed(&chunk[..count])
+            };
+            self.position = 0;
+        }
+        let count = buf.len().min(self.decoded.len() - self.position);
+        buf[..count].copy_from_slice(&self.decoded[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    const LATIN1_DECLARATION: &[u8] = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><rss/>";
+
+    /// The function `transcode` feeds `document` to a `Transcoder` in chunks of `size` bytes.
+    fn transcode(document: &[u8], content_type: Option<&str>, size: usize) -> (String, String) {
+        let mut transcoder = Transcoder::new(content_type.map(String::from));
+        let mut decoded = vec![];
+        for chunk in document.chunks(size) {
+            decoded.extend(transcoder.feed(chunk));
+        }
+        decoded.extend(transcoder.finish());
+        (
+            String::from_utf8(decoded).unwrap(),
+            transcoder.encoding().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn a_byte_order_mark_wins() {
+        let mut document = vec![0xEF, 0xBB, 0xBF];
+        document.extend_from_slice(LATIN1_DECLARATION);
+        assert_eq!(
+            detect(&document, Some("text/xml; charset=Shift_JIS")),
+            UTF_8
+        );
+        assert_eq!(detect(&[0xFF, 0xFE, b'<', 0], None), UTF_16LE);
+    }
+
+    #[test]
+    fn the_content_type_charset_wins_over_the_declaration() {
+        assert_eq!(
+            detect(
+                LATIN1_DECLARATION,
+                Some("application/rss+xml; charset=\"Shift_JIS\"")
+            ),
+            SHIFT_JIS
+        );
+        assert_eq!(
+            detect(LATIN1_DECLARATION, Some("text/xml; CHARSET=utf-8")),
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn the_declaration_is_used_without_a_charset() {
+        // ISO-8859-1 is decoded as windows-1252, as the WHATWG encoding standard asks
+        assert_eq!(detect(LATIN1_DECLARATION, None), WINDOWS_1252);
+        assert_eq!(detect(LATIN1_DECLARATION, Some("text/xml")), WINDOWS_1252);
+        assert_eq!(
+            detect(LATIN1_DECLARATION, Some("text/xml; charset=bogus")),
+            WINDOWS_1252
+        );
+    }
+
+    #[test]
+    fn utf_8_is_the_default() {
+        assert_eq!(detect(b"<rss/>", None), UTF_8);
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\" encoding=\"bogus\"?><rss/>", None),
+            UTF_8
+        );
+        // an ASCII declaration claiming UTF-16 cannot be right
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\" encoding=\"UTF-16\"?><rss/>", None),
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn utf_16_without_byte_order_mark_is_recognized() {
+        assert_eq!(detect(&[0, b'<', 0, b'?'], None), UTF_16BE);
+        assert_eq!(detect(&[b'<', 0, b'?', 0], None), UTF_16LE);
+    }
+
+    #[test]
+    fn multibyte_sequences_split_across_chunks_are_kept() {
+        let text = format!("<rss>{}</rss>", "é€😀".repeat(400));
+        for size in [1, 2, 3, 5, 1000, 4096] {
+            let (decoded, encoding) = transcode(text.as_bytes(), None, size);
+            assert!(decoded == text, "chunks of {} bytes", size);
+            assert_eq!(encoding, "UTF-8");
+        }
+
+        let text = format!("<rss>{}</rss>", "日本語のフィード".repeat(200));
+        let (shift_jis, _, unmappable) = SHIFT_JIS.encode(&text);
+        assert!(!unmappable);
+        for size in [1, 7, 1025] {
+            let (decoded, encoding) =
+                transcode(&shift_jis, Some("text/xml; charset=Shift_JIS"), size);
+            assert!(decoded == text, "chunks of {} bytes", size);
+            assert_eq!(encoding, "Shift_JIS");
+        }
+    }
+
+    #[test]
+    fn a_short_document_is_flushed_by_finish() {
+        let (decoded, encoding) = transcode(b"\xFF\xFE<\0r\0s\0s\0/\0>\0", None, 3);
+        assert_eq!(decoded, "<rss/>");
+        assert_eq!(encoding, "UTF-16LE");
+
+        let mut reader = TranscodingReader::new(
+            &b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><t>\x80</t>"[..],
+        );
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded).unwrap();
+        assert!(decoded.ends_with("<t>€</t>"));
+        assert_eq!(reader.encoding(), Some("windows-1252"));
+    }
+}
@@ -1,10 +1,14 @@
-use serde_xml_rs;
 use std::fmt;
 
+use crate::{http::errors::HttpError, secrets::errors::SecretError};
+
 #[derive(Debug)]
 pub enum RssParsingError {
-    SerdeError(serde_xml_rs::Error),
-    HttpError(reqwest::Error),
+    XmlError(xml::reader::Error),
+    HttpError(HttpError),
+    AuthError(SecretError),
+    IoError(std::io::Error),
+    MissingChannel,
 }
 
 /// The `impl fmt::Display for RssParsingError` block is implementing the `fmt::Display` trait for the
@@ -16,21 +20,24 @@ impl fmt::Display for RssParsingError {
     /// Arguments:
     ///
     /// * `f`: `f` is a mutable reference to a `fmt::Formatter` object. This object is used for
-    /// formatting and writing output.
+    ///   formatting and writing output.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
-            RssParsingError::HttpError(e) => write!(f, "Http request Error: {:?}", e),
-            RssParsingError::SerdeError(e) => write!(f, "Serde parse Error: {:?}", e),
+            RssParsingError::HttpError(e) => write!(f, "{}", e),
+            RssParsingError::AuthError(e) => write!(f, "Feed credentials: {}", e),
+            RssParsingError::XmlError(e) => write!(f, "Xml parse Error: {}", e),
+            RssParsingError::IoError(e) => write!(f, "Reading the feed failed: {}", e),
+            RssParsingError::MissingChannel => write!(f, "The document has no <channel> element"),
         }
     }
 }
 
-/// The `impl From<serde_xml_rs::Error> for RssParsingError` block is implementing the `From` trait for
-/// the `serde_xml_rs::Error` type. This allows for converting a `serde_xml_rs::Error` into a
+/// The `impl From<xml::reader::Error> for RssParsingError` block is implementing the `From` trait for
+/// the `xml::reader::Error` type. This allows for converting a `xml::reader::Error` into a
 /// `RssParsingError`.
-impl From<serde_xml_rs::Error> for RssParsingError {
-    fn from(error: serde_xml_rs::Error) -> Self {
-        RssParsingError::SerdeError(error)
+impl From<xml::reader::Error> for RssParsingError {
+    fn from(error: xml::reader::Error) -> Self {
+        RssParsingError::XmlError(error)
     }
 }
 
@@ -38,6 +45,24 @@ impl From<serde_xml_rs::Error> for RssParsingError {
 /// `reqwest::Error` type. This allows for converting a `reqwest::Error` into a `RssParsingError`.
 impl From<reqwest::Error> for RssParsingError {
     fn from(error: reqwest::Error) -> Self {
+        RssParsingError::HttpError(HttpError::from(error))
+    }
+}
+
+impl From<HttpError> for RssParsingError {
+    fn from(error: HttpError) -> Self {
         RssParsingError::HttpError(error)
     }
 }
+
+impl From<std::io::Error> for RssParsingError {
+    fn from(error: std::io::Error) -> Self {
+        RssParsingError::IoError(error)
+    }
+}
+
+impl From<SecretError> for RssParsingError {
+    fn from(error: SecretError) -> Self {
+        RssParsingError::AuthError(error)
+    }
+}
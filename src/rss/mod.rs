@@ -1,3 +1,7 @@
-pub mod errors;
 pub mod dto;
+pub mod encoding;
+pub mod errors;
+pub mod source;
+pub mod stream;
 pub use dto::Rss;
+pub use source::FeedSource;
@@ -0,0 +1,139 @@
+use std::{fmt::Display, path::PathBuf};
+
+/// The `FeedSource` enum tells where a feed is read from, parsed from the string given on the
+/// command line or stored as the `rss_url` of a subscription.
+///
+/// Variants:
+///
+/// * `Stdin`: `-`, the feed is read from the standard input.
+/// * `File`: a `file://` URL, the feed is read from the local file at its path.
+/// * `Http`: any other string, the feed is downloaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedSource {
+    Stdin,
+    File(PathBuf),
+    Http(String),
+}
+
+impl FeedSource {
+    /// The function `is_local` tells whether the feed is read without network access.
+    pub fn is_local(&self) -> bool {
+        !matches!(self, FeedSource::Http(_))
+    }
+}
+
+impl From<&str> for FeedSource {
+    fn from(source: &str) -> Self {
+        if source == "-" {
+            FeedSource::Stdin
+        } else if let Some(path) = source.strip_prefix("file://") {
+            // `file://localhost/path` names the same file as `file:///path`
+            let path = path.strip_prefix("localhost").unwrap_or(path);
+            FeedSource::File(PathBuf::from(path))
+        } else {
+            FeedSource::Http(source.to_string())
+        }
+    }
+}
+
+impl Display for FeedSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedSource::Stdin => write!(f, "-"),
+            FeedSource::File(path) => write!(f, "file://{}", path.display()),
+            FeedSource::Http(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rss::{errors::RssParsingError, Rss};
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>Local feed</title>
+    <item><title>First</title><link>https://example.com/first</link></item>
+    <item><title>Second</title><link>https://example.com/second</link></item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn sources_are_resolved_from_their_string() {
+        assert_eq!(FeedSource::from("-"), FeedSource::Stdin);
+        assert_eq!(
+            FeedSource::from("file:///tmp/feed.xml"),
+            FeedSource::File(PathBuf::from("/tmp/feed.xml"))
+        );
+        assert_eq!(
+            FeedSource::from("file://localhost/tmp/feed.xml"),
+            FeedSource::File(PathBuf::from("/tmp/feed.xml"))
+        );
+        assert_eq!(
+            FeedSource::from("file://feeds/saved.xml"),
+            FeedSource::File(PathBuf::from("feeds/saved.xml"))
+        );
+        assert_eq!(
+            FeedSource::from("https://example.com/rss.xml"),
+            FeedSource::Http(String::from("https://example.com/rss.xml"))
+        );
+        // only the exact `-` is stdin, a relative path is not a local source by itself
+        assert_eq!(
+            FeedSource::from("-feed.xml"),
+            FeedSource::Http(String::from("-feed.xml"))
+        );
+    }
+
+    #[test]
+    fn only_http_sources_need_the_network() {
+        assert!(FeedSource::Stdin.is_local());
+        assert!(FeedSource::from("file:///tmp/feed.xml").is_local());
+        assert!(!FeedSource::from("https://example.com/rss.xml").is_local());
+    }
+
+    #[test]
+    fn display_gives_back_the_source() {
+        for source in ["-", "file:///tmp/feed.xml", "https://example.com/rss.xml"] {
+            assert_eq!(FeedSource::from(source).to_string(), source);
+        }
+        assert_eq!(
+            FeedSource::from("file://localhost/tmp/feed.xml").to_string(),
+            "file:///tmp/feed.xml"
+        );
+    }
+
+    #[tokio::test]
+    async fn file_url_is_parsed_without_network() {
+        let path = std::env::temp_dir().join(format!("rss_rust_source_{}.xml", std::process::id()));
+        std::fs::write(&path, FEED).unwrap();
+        let url = format!("file://{}", path.display());
+
+        let mut rss = Rss::parse(url.clone()).await.unwrap();
+        assert_eq!(rss.title(), "Local feed");
+        assert_eq!(rss.get_items().len(), 2);
+
+        let mut rss = Rss::read_source(FeedSource::from(url.as_str()), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(rss.get_items().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_file_and_http_are_not_read_locally() {
+        let missing = FeedSource::File(PathBuf::from("/nonexistent/rss_rust/feed.xml"));
+        match Rss::read_source(missing, None).await {
+            Err(RssParsingError::IoError(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::NotFound)
+            }
+            _ => panic!("a missing file must fail with an IO error"),
+        }
+        let http = FeedSource::from("https://example.com/rss.xml");
+        assert!(matches!(
+            Rss::read_source(http, None).await,
+            Err(RssParsingError::IoError(_))
+        ));
+    }
+}
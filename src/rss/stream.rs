@@ -0,0 +1,467 @@
+use std::io::{self, Read};
+use tokio::sync::mpsc::{self, Receiver};
+use xml::{
+    name::OwnedName,
+    reader::{EventReader, ParserConfig, XmlEvent},
+};
+
+use super::{
+    dto::{Channel, Image, Item},
+    encoding::Transcoder,
+    errors::RssParsingError,
+    Rss,
+};
+use crate::http::{errors::HttpError, HttpClient};
+
+/// Number of response chunks buffered between the download and the parser.
+const CHUNK_BUFFER: usize = 16;
+
+/// The namespace of the iTunes podcast elements.
+const ITUNES_NAMESPACE: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+
+/// The namespace of the Media RSS elements.
+const MEDIA_NAMESPACE: &str = "http://search.yahoo.com/mrss/";
+
+/// The `ChunkReader` struct adapts the chunks received from the download task to `Read`, so the
+/// blocking XML parser can consume the response while it is still arriving.
+struct ChunkReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let count = buf.len().min(self.chunk.len() - self.position);
+        buf[..count].copy_from_slice(&self.chunk[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// The function `parse_response` parses a feed while it downloads. The chunks of the response are
+/// transcoded to UTF-8 and handed to a blocking parser thread, which stops reading as soon as
+/// `max_items` items were produced; the download is then dropped instead of being read to the end.
+/// A response without a 2xx status is an `HttpError::StatusError`, its body is not parsed.
+pub async fn parse_response(
+    client: &HttpClient,
+    mut res: reqwest::Response,
+    max_items: Option<usize>,
+) -> Result<Rss, RssParsingError> {
+    if !res.status().is_success() {
+        return Err(HttpError::StatusError(res.status().as_u16()).into());
+    }
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let mut transcoder = Transcoder::new(content_type);
+    let (sender, receiver) = mpsc::channel(CHUNK_BUFFER);
+    let parser = tokio::task::spawn_blocking(move || {
+        parse_reader(
+            ChunkReader {
+                receiver,
+                chunk: vec![],
+                position: 0,
+            },
+            max_items,
+        )
+    });
+
+    let mut parser_done = false;
+    let mut read_bytes = 0;
+    while let Some(chunk) = client.chunk(&mut res, &mut read_bytes).await? {
+        let decoded = transcoder.feed(&chunk);
+        if !decoded.is_empty() && sender.send(decoded).await.is_err() {
+            // the parser is done, the rest of the feed is not needed
+            parser_done = true;
+            break;
+        }
+    }
+    if !parser_done {
+        let _ = sender.send(transcoder.finish()).await;
+    }
+    drop(sender);
+
+    let mut rss = parser.await.expect("The feed parser panicked")?;
+    rss.encoding = transcoder.encoding().unwrap_or("UTF-8").to_string();
+    Ok(rss)
+}
+
+/// The function `parse_reader` builds an `Rss` from the XML events of `reader`, keeping only the
+/// channel and item fields the application uses. The encoding declared in the document is
+/// ignored, `reader` must already yield UTF-8, see `Transcoder`.
+///
+/// Arguments:
+///
+/// * `reader`: The `reader` the UTF-8 RSS document is read from.
+/// * `max_items`: The optional number of items after which reading stops.
+///
+/// Returns:
+///
+/// the parsed `Rss`, or an `RssParsingError` if the document is not well formed or has no channel.
+pub fn parse_reader<R: Read>(reader: R, max_items: Option<usize>) -> Result<Rss, RssParsingError> {
+    let config = ParserConfig::new()
+        .trim_whitespace(true)
+        .whitespace_to_characters(true)
+        .cdata_to_characters(true)
+        .ignore_comments(true)
+        .coalesce_characters(true)
+        .override_encoding(Some(xml::Encoding::Utf8))
+        .ignore_invalid_encoding_declarations(true);
+    let mut events = EventReader::new_with_config(reader, config);
+
+    let mut path: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut channel: Option<Channel> = None;
+    let mut item: Option<Item> = None;
+    let mut image: Option<Image> = None;
+
+    loop {
+        match events.next()? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let name = element_name(&name);
+                let attributes: Vec<(String, String)> = attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect();
+                let parent = path.last().map(|parent| parent.as_str());
+                match (parent, name.as_str()) {
+                    (Some("rss"), "channel") | (None, "channel") => {
+                        channel = Some(Channel::default())
+                    }
+                    (Some("channel"), "item") => item = Some(Item::default()),
+                    (Some("channel"), "image") => image = Some(Image::default()),
+                    (Some("channel"), field) if !attributes.is_empty() => {
+                        if let Some(channel) = channel.as_mut() {
+                            channel.set_attributes(field, &attributes);
+                        }
+                    }
+                    (_, field) if field.starts_with("media:") => {
+                        if let Some(item) = item.as_mut() {
+                            item.set_media_attributes(field, &attributes);
+                        }
+                    }
+                    (Some("item"), field) if !attributes.is_empty() => {
+                        if let Some(item) = item.as_mut() {
+                            item.set_attributes(field, &attributes);
+                        }
+                    }
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            XmlEvent::Characters(characters) => text.push_str(&characters),
+            XmlEvent::EndElement { .. } => {
+                let element = path.pop().unwrap_or_default();
+                let parent = path.last().map(|parent| parent.as_str());
+                let value = std::mem::take(&mut text);
+                match (parent, element.as_str()) {
+                    (_, field) if field.starts_with("media:") => {
+                        if let Some(item) = item.as_mut() {
+                            item.set_media_field(field, value);
+                        }
+                    }
+                    (Some("item"), field) => {
+                        if let Some(item) = item.as_mut() {
+                            item.set_field(field, value);
+                        }
+                    }
+                    (Some("image"), field) => {
+                        if let Some(image) = image.as_mut() {
+                            image.set_field(field, value);
+                        }
+                    }
+                    (Some("channel"), "item") => {
+                        if let (Some(channel), Some(item)) = (channel.as_mut(), item.take()) {
+                            // `--max-items 0` keeps no item, only the channel
+                            if max_items == Some(0) {
+                                break;
+                            }
+                            channel.item.push(item);
+                            if max_items.is_some_and(|max_items| channel.item.len() >= max_items) {
+                                break;
+                            }
+                        }
+                    }
+                    (Some("channel"), "image") => {
+                        if let Some(channel) = channel.as_mut() {
+                            channel.image = image.take();
+                        }
+                    }
+                    (Some("channel"), field) => {
+                        if let Some(channel) = channel.as_mut() {
+                            channel.set_field(field, value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+
+    match channel {
+        Some(channel) => Ok(Rss {
+            channel,
+            encoding: String::from("UTF-8"),
+        }),
+        None => Err(RssParsingError::MissingChannel),
+    }
+}
+
+/// The function `element_name` returns the local name of an element, prefixed with `itunes:` or
+/// `media:` for the iTunes podcast and Media RSS namespaces so their elements do not clash with
+/// the RSS ones, like `itunes:image` and `image`. Other namespaces are dropped, see
+/// `Item::set_field`.
+fn element_name(name: &OwnedName) -> String {
+    let namespace = name.namespace.as_deref().unwrap_or_default();
+    if namespace.eq_ignore_ascii_case(ITUNES_NAMESPACE) {
+        format!("itunes:{}", name.local_name)
+    } else if namespace.trim_end_matches('/') == MEDIA_NAMESPACE.trim_end_matches('/') {
+        format!("media:{}", name.local_name)
+    } else {
+        name.local_name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::http_settings::HttpSettings, rss::dto::ChannelInfo};
+    use tokio::net::TcpListener;
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"
+     xmlns:dc="http://purl.org/dc/elements/1.1/"
+     xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Example blog</title>
+    <atom:link href="https://example.com/rss.xml" rel="self" type="application/rss+xml"/>
+    <link>https://example.com/</link>
+    <description>Posts about things</description>
+    <item>
+      <title>First</title>
+      <link>https://example.com/first</link>
+      <atom:link href="https://example.com/first/amp" rel="amphtml"/>
+      <dc:creator>Alice</dc:creator>
+      <category>Rust</category>
+      <category>Parsing</category>
+      <description><![CDATA[<p>Some <b>bold</b> text</p>]]></description>
+      <pubDate>Tue, 02 Jan 2024 03:04:05 +0100</pubDate>
+    </item>
+    <item>
+      <atom:link href="https://example.com/second/amp" rel="amphtml"/>
+      <link>https://example.com/second</link>
+      <title>Second</title>
+      <itunes:duration>1:02:03</itunes:duration>
+    </item>
+    <item>
+      <title>Third</title>
+      <link>https://example.com/third</link>
+    </item>
+  </channel>
+</rss>"#;
+
+    /// A reader that fails, standing for the part of a feed that must not be read.
+    struct Unreachable;
+
+    impl Read for Unreachable {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("read past the requested items"))
+        }
+    }
+
+    #[test]
+    fn channel_and_item_fields_are_read() {
+        let mut rss = parse_reader(FEED.as_bytes(), None).unwrap();
+        assert_eq!(rss.title(), "Example blog");
+        assert_eq!(rss.description(), "Posts about things");
+        // the `self` atom:link before `<link>` points at the feed, not at the site
+        assert_eq!(rss.channel.link, "https://example.com/");
+
+        let items = rss.get_items();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].title, "First");
+        assert_eq!(items[0].author, "Alice");
+        assert_eq!(items[0].category, ["Rust", "Parsing"]);
+        assert_eq!(items[0].description, "<p>Some <b>bold</b> text</p>");
+        assert_eq!(items[0].publish_date, "2024-01-02 02:04:05");
+        assert_eq!(items[1].episode.as_ref().unwrap().duration_secs, Some(3723));
+    }
+
+    #[test]
+    fn channel_metadata_is_read() {
+        let feed = r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>Podcast</title>
+    <atom:link href="https://pod.test/feed" rel="self"/>
+    <atom:link href="https://pod.test/" rel="alternate" type="text/html"/>
+    <atom:link href="https://pod.test/other"/>
+    <link/>
+    <language> fr-ca </language>
+    <ttl> 60 </ttl>
+    <generator>Hand</generator>
+    <lastBuildDate>Tue, 02 Jan 2024 03:04:05 +0100</lastBuildDate>
+    <image>
+      <url>https://pod.test/logo.png</url>
+      <title>Podcast</title>
+      <link>https://pod.test/</link>
+    </image>
+  </channel>
+</rss>"#;
+        let info = parse_reader(feed.as_bytes(), None).unwrap().channel_info();
+        assert_eq!(
+            info,
+            ChannelInfo {
+                title: String::from("Podcast"),
+                // the first alternate atom:link stands for the empty `<link>`
+                link: String::from("https://pod.test/"),
+                description: String::new(),
+                language: String::from("fr-ca"),
+                image_url: String::from("https://pod.test/logo.png"),
+                generator: String::from("Hand"),
+                last_build_date: String::from("Tue, 02 Jan 2024 03:04:05 +0100"),
+                ttl: String::from("60"),
+            }
+        );
+
+        // a channel with only a `self` link has no site link
+        let feed = r#"<rss xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+    <atom:link href="https://pod.test/feed" rel="self"/>
+</channel></rss>"#;
+        let info = parse_reader(feed.as_bytes(), None).unwrap().channel_info();
+        assert_eq!(info.link, "");
+        assert_eq!(info.image_url, "");
+    }
+
+    #[test]
+    fn an_atom_link_does_not_blank_the_item_link() {
+        let rss = parse_reader(FEED.as_bytes(), None).unwrap();
+        let links: Vec<&str> = rss
+            .channel
+            .item
+            .iter()
+            .map(|item| item.link.as_str())
+            .collect();
+        assert_eq!(
+            links,
+            [
+                "https://example.com/first",
+                "https://example.com/second",
+                "https://example.com/third"
+            ]
+        );
+    }
+
+    #[test]
+    fn reading_stops_after_max_items() {
+        let end = FEED.find("<item>\n      <title>Third").unwrap();
+        let reader = FEED.as_bytes()[..end].chain(Unreachable);
+        let rss = parse_reader(reader, Some(2)).unwrap();
+        let titles: Vec<&str> = rss
+            .channel
+            .item
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect();
+        assert_eq!(titles, ["First", "Second"]);
+
+        let reader = FEED.as_bytes()[..end].chain(Unreachable);
+        assert!(matches!(
+            parse_reader(reader, None),
+            Err(RssParsingError::XmlError(_))
+        ));
+    }
+
+    #[test]
+    fn zero_max_items_reads_the_channel_only() {
+        let end = FEED.find("<item>\n      <atom:link").unwrap();
+        let reader = FEED.as_bytes()[..end].chain(Unreachable);
+        let mut rss = parse_reader(reader, Some(0)).unwrap();
+        assert_eq!(rss.title(), "Example blog");
+        assert!(rss.get_items().is_empty());
+    }
+
+    #[test]
+    fn more_max_items_than_items_keeps_them_all() {
+        let rss = parse_reader(FEED.as_bytes(), Some(10)).unwrap();
+        assert_eq!(rss.channel.item.len(), 3);
+    }
+
+    #[test]
+    fn a_document_without_channel_is_rejected() {
+        let error = parse_reader("<feed><entry/></feed>".as_bytes(), None).unwrap_err();
+        assert!(matches!(error, RssParsingError::MissingChannel));
+        assert!(parse_reader("<rss><channel>".as_bytes(), None).is_err());
+    }
+
+    /// The function `serve` answers one request with the raw `response` and returns the URL to
+    /// request.
+    async fn serve(response: String) -> String {
+        // scoped here, `tokio::io::AsyncReadExt::chain` would clash with `Read::chain`
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let count = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..count]);
+            }
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn responses_without_a_success_status_are_not_parsed() {
+        let client = HttpClient::new(&HttpSettings::default()).unwrap();
+        let not_found = format!(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            FEED.len(),
+            FEED
+        );
+        let url = serve(not_found).await;
+        assert!(matches!(
+            Rss::fetch(&client, &url, None).await,
+            Err(RssParsingError::HttpError(HttpError::StatusError(404)))
+        ));
+
+        // a redirect without a location cannot be followed
+        let url = serve(String::from(
+            "HTTP/1.1 302 Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ))
+        .await;
+        let error = Rss::fetch(&client, &url, None).await.unwrap_err();
+        assert_eq!(error.to_string(), "Server responded with status 302");
+
+        let url = serve(format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            FEED.len(),
+            FEED
+        ))
+        .await;
+        assert_eq!(
+            Rss::fetch(&client, &url, None).await.unwrap().title(),
+            "Example blog"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use chrono::Utc;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    db::{
+        blog::RssEntry,
+        rule::{Rule, RuleAction, RuleField, RuleOperator},
+    },
+    rss::dto::Item,
+    storage::{errors::StorageError, Storage},
+};
+
+/// The `RuleVerdict` struct collects the actions of every rule that matched an item.
+///
+/// Properties:
+///
+/// * `skip`: The item must not be saved.
+/// * `mark_read`: The item is saved as read.
+/// * `star`: The item is saved as starred.
+/// * `tags`: The tags attached to the saved item.
+/// * `matched`: The ids of the rules that matched.
+#[derive(Debug, Default, Clone)]
+pub struct RuleVerdict {
+    pub skip: bool,
+    pub mark_read: bool,
+    pub star: bool,
+    pub tags: Vec<String>,
+    pub matched: Vec<i64>,
+}
+
+impl RuleVerdict {
+    /// The function `describe` lists the actions of the verdict, used by `rules test`.
+    pub fn describe(&self) -> String {
+        let mut actions = vec![];
+        if self.skip {
+            actions.push(String::from("skip"));
+        }
+        if self.mark_read {
+            actions.push(String::from("read"));
+        }
+        if self.star {
+            actions.push(String::from("star"));
+        }
+        for tag in self.tags.iter() {
+            actions.push(format!("tag:{}", tag));
+        }
+        if actions.is_empty() {
+            String::from("keep")
+        } else {
+            actions.join(",")
+        }
+    }
+
+    /// The function `apply` applies the mark read, star and tag actions of the verdict to an entry
+    /// of `storage`, when the entry is first saved.
+    pub fn apply(&self, storage: &dyn Storage, rss_entry: &RssEntry) -> Result<(), StorageError> {
+        if self.mark_read {
+            storage.set_read(rss_entry, true)?;
+        }
+        if self.star {
+            storage.set_starred(rss_entry, true)?;
+        }
+        for tag in self.tags.iter() {
+            storage.add_entry_tag(rss_entry, tag)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `RuleSet` struct holds the rules of a feed with their regular expressions compiled once, so
+/// that evaluating every item of a feed does not recompile them.
+pub struct RuleSet<'c> {
+    rules: Vec<(Rule<'c>, Option<Regex>)>,
+}
+
+impl<'c> RuleSet<'c> {
+    /// The function `new` compiles the regular expressions of the given rules. A rule whose regular
+    /// expression does not compile never matches.
+    pub fn new(rules: Vec<Rule<'c>>) -> RuleSet<'c> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let regex = match rule.operator {
+                    RuleOperator::Regex => compile(&rule.value).ok(),
+                    _ => None,
+                };
+                (rule, regex)
+            })
+            .collect();
+        RuleSet { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The function `evaluate` runs every rule against the item and merges their actions. A negated
+    /// `skip` rule is an include rule, and the include rules are evaluated as a group: the item is
+    /// skipped when there are include rules and it matches none of them, or when it matches an
+    /// exclude rule, a `skip` rule that is not negated. The other actions apply to every rule that
+    /// matches, or that does not match when it is negated.
+    ///
+    /// Arguments:
+    ///
+    /// * `item`: The `item` parsed from the feed, before it is saved.
+    ///
+    /// Returns:
+    ///
+    /// the `RuleVerdict` for the item.
+    pub fn evaluate(&self, item: &Item) -> RuleVerdict {
+        let mut verdict = RuleVerdict::default();
+        let mut includes = vec![];
+        let mut included = false;
+        for (rule, regex) in self.rules.iter() {
+            let matched = matches(rule, regex.as_ref(), item);
+            if rule.action == RuleAction::Skip && rule.negate {
+                includes.push(rule.id);
+                included = included || matched;
+                continue;
+            }
+            if matched == rule.negate {
+                continue;
+            }
+            verdict.matched.push(rule.id);
+            match rule.action {
+                RuleAction::Skip => verdict.skip = true,
+                RuleAction::MarkRead => verdict.mark_read = true,
+                RuleAction::Star => verdict.star = true,
+                RuleAction::Tag => {
+                    if let Some(tag) = &rule.tag {
+                        if !verdict.tags.contains(tag) {
+                            verdict.tags.push(tag.clone());
+                        }
+                    }
+                }
+            }
+        }
+        if !includes.is_empty() && !included {
+            verdict.skip = true;
+            verdict.matched.extend(includes);
+        }
+        verdict
+    }
+}
+
+/// The function `compile` builds the case insensitive regular expression used by `regex` rules.
+pub fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// The function `matches` tells whether a single rule matches an item, ignoring `rule.negate`.
+fn matches(rule: &Rule, regex: Option<&Regex>, item: &Item) -> bool {
+    match rule.operator {
+        RuleOperator::MinAge | RuleOperator::MaxAge => {
+            let days = match rule.value.parse::<i64>() {
+                Ok(days) => days,
+                Err(_) => return false,
+            };
+            let age = match item.published_at() {
+                Some(date) => (Utc::now() - date).num_days(),
+                None => return false,
+            };
+            match rule.operator {
+                RuleOperator::MinAge => age >= days,
+                _ => age <= days,
+            }
+        }
+        RuleOperator::Contains | RuleOperator::Regex => {
+            let values: Vec<&str> = match rule.field {
+                RuleField::Title => vec![&item.title],
+                RuleField::Content => vec![&item.description],
+                RuleField::Author => vec![&item.author],
+                RuleField::Category => item.category.iter().map(|c| c.as_str()).collect(),
+                RuleField::Age => vec![],
+            };
+            let needle = rule.value.to_lowercase();
+            values.iter().any(|value| match rule.operator {
+                RuleOperator::Contains => value.to_lowercase().contains(&needle),
+                _ => regex.map(|regex| regex.is_match(value)).unwrap_or(false),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule<'c>(
+        id: i64,
+        field: RuleField,
+        operator: RuleOperator,
+        value: &str,
+        negate: bool,
+        action: RuleAction,
+    ) -> Rule<'c> {
+        Rule {
+            id,
+            profile: None,
+            rss_url: None,
+            field,
+            operator,
+            value: value.to_string(),
+            negate,
+            action,
+            tag: (action == RuleAction::Tag).then(|| String::from("rust")),
+            connection: None,
+        }
+    }
+
+    fn include<'c>(id: i64, value: &str) -> Rule<'c> {
+        rule(
+            id,
+            RuleField::Title,
+            RuleOperator::Contains,
+            value,
+            true,
+            RuleAction::Skip,
+        )
+    }
+
+    fn item(title: &str) -> Item {
+        Item {
+            title: title.to_string(),
+            description: String::from("<p>Some content</p>"),
+            author: String::from("Alice"),
+            category: vec![String::from("Programming")],
+            ..Item::default()
+        }
+    }
+
+    #[test]
+    fn no_rules_keep_every_item() {
+        let verdict = RuleSet::new(vec![]).evaluate(&item("Anything"));
+        assert!(!verdict.skip);
+        assert_eq!(verdict.describe(), "keep");
+    }
+
+    #[test]
+    fn exclude_rules_skip_matching_items() {
+        let rules = RuleSet::new(vec![rule(
+            1,
+            RuleField::Title,
+            RuleOperator::Contains,
+            "sponsored",
+            false,
+            RuleAction::Skip,
+        )]);
+        let verdict = rules.evaluate(&item("SPONSORED: buy this"));
+        assert!(verdict.skip);
+        assert_eq!(verdict.matched, [1]);
+        assert!(!rules.evaluate(&item("A real post")).skip);
+    }
+
+    #[test]
+    fn include_rules_keep_items_matching_any_of_them() {
+        let rules = RuleSet::new(vec![include(1, "rust"), include(2, "python")]);
+        assert!(!rules.evaluate(&item("Rust 1.75")).skip);
+        assert!(!rules.evaluate(&item("Python 3.12")).skip);
+        let verdict = rules.evaluate(&item("Go 1.22"));
+        assert!(verdict.skip);
+        assert_eq!(verdict.matched, [1, 2]);
+    }
+
+    #[test]
+    fn exclude_rules_win_over_include_rules() {
+        let rules = RuleSet::new(vec![
+            include(1, "rust"),
+            rule(
+                2,
+                RuleField::Category,
+                RuleOperator::Regex,
+                "^program",
+                false,
+                RuleAction::Skip,
+            ),
+        ]);
+        let verdict = rules.evaluate(&item("Rust 1.75"));
+        assert!(verdict.skip);
+        assert_eq!(verdict.matched, [2]);
+    }
+
+    #[test]
+    fn negated_rules_act_on_items_they_do_not_match() {
+        let rules = RuleSet::new(vec![
+            rule(
+                1,
+                RuleField::Author,
+                RuleOperator::Contains,
+                "bob",
+                true,
+                RuleAction::MarkRead,
+            ),
+            rule(
+                2,
+                RuleField::Content,
+                RuleOperator::Regex,
+                "some\\s+content",
+                false,
+                RuleAction::Tag,
+            ),
+            rule(
+                3,
+                RuleField::Title,
+                RuleOperator::Regex,
+                "(unclosed",
+                false,
+                RuleAction::Star,
+            ),
+        ]);
+        let verdict = rules.evaluate(&item("Anything"));
+        assert!(!verdict.skip);
+        assert!(verdict.mark_read && !verdict.star);
+        assert_eq!(verdict.tags, ["rust"]);
+        assert_eq!(verdict.matched, [1, 2]);
+        assert_eq!(verdict.describe(), "read,tag:rust");
+    }
+
+    #[test]
+    fn age_rules_compare_the_publish_date() {
+        let rules = RuleSet::new(vec![rule(
+            1,
+            RuleField::Age,
+            RuleOperator::MinAge,
+            "30",
+            false,
+            RuleAction::Skip,
+        )]);
+        let mut old = item("Old");
+        old.publish_date = String::from("Mon, 06 Sep 2021 16:45:00 +0000");
+        assert!(rules.evaluate(&old).skip);
+        let mut fresh = item("Fresh");
+        fresh.publish_date = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        assert!(!rules.evaluate(&fresh).skip);
+        assert!(!rules.evaluate(&item("Undated")).skip);
+    }
+}
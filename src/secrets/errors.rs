@@ -0,0 +1,36 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum SecretError {
+    MissingPassphrase,
+    WrongPassphrase,
+    MissingEnv(String),
+    Corrupt,
+    DbError(sqlite::Error),
+}
+
+/// The `impl fmt::Display for SecretError` block formats the reason a stored credential could not
+/// be read or written, without ever including the credential itself.
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            SecretError::MissingPassphrase => write!(
+                f,
+                "Credentials are encrypted, set {} to the passphrase",
+                super::PASSPHRASE_ENV
+            ),
+            SecretError::WrongPassphrase => write!(f, "The passphrase is wrong"),
+            SecretError::MissingEnv(name) => {
+                write!(f, "Environment variable {} is not set", name)
+            }
+            SecretError::Corrupt => write!(f, "A stored credential is corrupt"),
+            SecretError::DbError(e) => write!(f, "Reading the encryption key failed: {}", e),
+        }
+    }
+}
+
+impl From<sqlite::Error> for SecretError {
+    fn from(error: sqlite::Error) -> Self {
+        SecretError::DbError(error)
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum StorageError {
+    DbError(sqlite::Error),
+    Poisoned,
+}
+
+/// The `impl fmt::Display for StorageError` block formats the reason a storage could not read or
+/// write a feed or an entry.
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            StorageError::DbError(e) => write!(f, "Database error: {}", e),
+            StorageError::Poisoned => {
+                write!(
+                    f,
+                    "The storage is unusable after a panic while it was written"
+                )
+            }
+        }
+    }
+}
+
+impl From<sqlite::Error> for StorageError {
+    fn from(error: sqlite::Error) -> Self {
+        StorageError::DbError(error)
+    }
+}
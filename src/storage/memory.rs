@@ -0,0 +1,553 @@
+use chrono::Utc;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+use super::{errors::StorageError, Storage};
+use crate::{
+    core::structs::{EntrySort, GetManyOptions, SaveOutcome},
+    db::{
+        blog::RssEntry,
+        entry_content::EntryContent,
+        rss_entity::RssEntity,
+        tag::{normalize_tag, TagCount},
+    },
+    rss::dto::{parse_date, ChannelInfo, Item},
+};
+
+/// The `MemoryState` struct holds what a `MemoryStorage` stores, the rows of the tables the
+/// `SqliteStorage` uses.
+///
+/// Properties:
+///
+/// * `feeds`: The subscribed feeds, in the order they were saved.
+/// * `next_id`: The id given to the next saved feed.
+/// * `entries`: The entries by the hash of their title.
+/// * `order`: The hashes of the entries, in the order they were saved.
+/// * `entry_tags`: The `(hash, tag)` pairs of the tagged entries.
+/// * `feed_tags`: The `(rss_url, tag)` pairs of the tagged feeds.
+/// * `authors`: The authors of the entries by their hash.
+/// * `categories`: The `(hash, category)` pairs of the entries filed under a category.
+/// * `contents`: The full articles of the entries by their hash.
+/// * `pruned`: The hashes of the entries deleted by `prune_entries`.
+#[derive(Default)]
+struct MemoryState {
+    feeds: Vec<RssEntity<'static>>,
+    next_id: u8,
+    entries: HashMap<String, RssEntry<'static>>,
+    order: Vec<String>,
+    entry_tags: BTreeSet<(String, String)>,
+    feed_tags: BTreeSet<(String, String)>,
+    authors: HashMap<String, String>,
+    categories: BTreeSet<(String, String)>,
+    contents: HashMap<String, EntryContent<'static>>,
+    pruned: HashSet<String>,
+}
+
+impl MemoryState {
+    /// The function `feed_tags` returns the tags of the feed with the given URL, sorted.
+    fn feed_tags(&self, rss_url: &str) -> Vec<String> {
+        self.feed_tags
+            .iter()
+            .filter(|(url, _)| url == rss_url)
+            .map(|(_, tag)| tag.clone())
+            .collect()
+    }
+
+    /// The function `entry_tags` returns the tags of the entry with the given hash and of its feed,
+    /// sorted and without duplicates.
+    fn entry_tags(&self, hash: &str, rss_url: &str) -> Vec<String> {
+        let mut tags: BTreeSet<String> = self
+            .entry_tags
+            .iter()
+            .filter(|(entry_hash, _)| entry_hash == hash)
+            .map(|(_, tag)| tag.clone())
+            .collect();
+        tags.extend(self.feed_tags(rss_url));
+        tags.into_iter().collect()
+    }
+
+    /// The function `matches` tells whether an entry passes the filters of `options`, as the
+    /// `WHERE` clause of `RssEntry::get_many` does.
+    fn matches(&self, hash: &str, rss_entry: &RssEntry, options: &GetManyOptions) -> bool {
+        let pattern = format!("%{}%", options.query);
+        if !like(&pattern, &rss_entry.title)
+            && !self
+                .contents
+                .get(hash)
+                .is_some_and(|content| like(&pattern, &content.text))
+        {
+            return false;
+        }
+        if !options.rss_url.is_empty() && rss_entry.rss_url != options.rss_url {
+            return false;
+        }
+        if !options.profile.is_empty()
+            && !self.feeds.iter().any(|rss_entity| {
+                rss_entity.rss_url == rss_entry.rss_url && rss_entity.profile == options.profile
+            })
+        {
+            return false;
+        }
+        let tags: Vec<String> = options
+            .tags
+            .iter()
+            .filter_map(|tag| normalize_tag(tag))
+            .collect();
+        if !tags.is_empty() {
+            let carried = self.entry_tags(hash, &rss_entry.rss_url);
+            if !tags.iter().all(|tag| carried.contains(tag)) {
+                return false;
+            }
+        }
+        if !options.author.is_empty()
+            && !self
+                .authors
+                .get(hash)
+                .is_some_and(|author| like(&format!("%{}%", options.author), author))
+        {
+            return false;
+        }
+        if !options.categories.is_empty() {
+            // categories match regardless of the case of their ASCII letters, as `lower` does
+            let filed: BTreeSet<String> = self
+                .categories
+                .iter()
+                .filter(|(entry_hash, _)| entry_hash == hash)
+                .map(|(_, category)| category.to_ascii_lowercase())
+                .collect();
+            if !options
+                .categories
+                .iter()
+                .all(|category| filed.contains(&category.trim().to_ascii_lowercase()))
+            {
+                return false;
+            }
+        }
+        (!options.unread_only || !rss_entry.is_read)
+            && (!options.starred_only || rss_entry.is_starred)
+            && (options.since.is_empty() || rss_entry.publish_date >= options.since)
+            && (options.until.is_empty() || rss_entry.publish_date <= options.until)
+    }
+
+    /// The function `feed_name` returns the name the feed with the given URL is listed under, as
+    /// `EntrySort::Feed` sorts them.
+    fn feed_name(&self, rss_url: &str) -> String {
+        let name = self
+            .feeds
+            .iter()
+            .find(|rss_entity| rss_entity.rss_url == rss_url)
+            .and_then(|rss_entity| {
+                rss_entity.display_title.clone().or_else(|| {
+                    Some(rss_entity.channel.title.trim().to_string())
+                        .filter(|title| !title.is_empty())
+                })
+            });
+        name.unwrap_or_else(|| rss_url.to_string())
+    }
+
+    /// The function `filter` returns the entries passing the filters of `options`, in the order of
+    /// `options.sort`.
+    fn filter(&self, options: &GetManyOptions) -> Vec<&RssEntry<'static>> {
+        let mut entries: Vec<&RssEntry> = self
+            .order
+            .iter()
+            .filter_map(|hash| {
+                self.entries
+                    .get(hash)
+                    .filter(|rss_entry| self.matches(hash, rss_entry, options))
+            })
+            .collect();
+        let newest = |a: &&RssEntry, b: &&RssEntry| b.publish_date.cmp(&a.publish_date);
+        match options.sort {
+            EntrySort::Newest => entries.sort_by(newest),
+            EntrySort::Oldest => entries.sort_by(|a, b| a.publish_date.cmp(&b.publish_date)),
+            EntrySort::Title => {
+                entries.sort_by_key(|rss_entry| rss_entry.title.to_ascii_lowercase().into_bytes())
+            }
+            EntrySort::Feed => entries.sort_by(|a, b| {
+                let name = |rss_entry: &RssEntry| {
+                    self.feed_name(&rss_entry.rss_url)
+                        .to_ascii_lowercase()
+                        .into_bytes()
+                };
+                name(a).cmp(&name(b)).then_with(|| newest(a, b))
+            }),
+            EntrySort::Relevance => {
+                let prefix = format!("{}%", options.query);
+                let contains = format!("%{}%", options.query);
+                // titles starting with the query first, then titles containing it, then the
+                // entries only matched by their full article text
+                let rank = |rss_entry: &RssEntry| {
+                    if like(&prefix, &rss_entry.title) {
+                        0
+                    } else if like(&contains, &rss_entry.title) {
+                        1
+                    } else {
+                        2
+                    }
+                };
+                entries.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| newest(a, b)))
+            }
+        }
+        entries
+    }
+}
+
+/// The function `like` matches `text` against a pattern of the SQL `LIKE` operator as SQLite does:
+/// `%` matches any sequence of characters, `_` any single character and ASCII letters match
+/// regardless of their case.
+fn like(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // matched[j] tells whether the pattern read so far matches the first j characters of text
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for p in pattern {
+        let mut next = vec![false; text.len() + 1];
+        if p == '%' {
+            let mut any = false;
+            for (j, slot) in next.iter_mut().enumerate() {
+                any = any || matched[j];
+                *slot = any;
+            }
+        } else {
+            for (j, c) in text.iter().enumerate() {
+                next[j + 1] = matched[j] && (p == '_' || p.eq_ignore_ascii_case(c));
+            }
+        }
+        matched = next;
+    }
+    matched[text.len()]
+}
+
+/// The `MemoryStorage` struct keeps the feeds and entries in memory, for embedding the crate
+/// without a database file or for tests. Nothing is kept once it is dropped.
+///
+/// A panic while the storage is written leaves it unusable: writing then fails with
+/// `StorageError::Poisoned` and reading returns nothing.
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryStorage {
+    /// The function `new` returns an empty storage.
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    fn state(&self) -> Result<MutexGuard<'_, MemoryState>, StorageError> {
+        self.state.lock().map_err(|_| StorageError::Poisoned)
+    }
+
+    /// The function `update_feeds` applies `update` to every feed with the given URL.
+    fn update_feeds<F>(&self, rss_url: &str, update: F) -> Result<(), StorageError>
+    where
+        F: Fn(&mut RssEntity<'static>),
+    {
+        self.state()?
+            .feeds
+            .iter_mut()
+            .filter(|rss_entity| rss_entity.rss_url == rss_url)
+            .for_each(update);
+        Ok(())
+    }
+
+    /// The function `update_entry` applies `update` to the stored entry with the title of
+    /// `rss_entry`, if there is one.
+    fn update_entry<F>(&self, rss_entry: &RssEntry, update: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut RssEntry<'static>),
+    {
+        if let Some(stored) = self.state()?.entries.get_mut(&rss_entry.hash()) {
+            update(stored);
+        }
+        Ok(())
+    }
+}
+
+/// The function `detached_content` returns a copy of `content` without a connection.
+fn detached_content<'s>(content: &EntryContent) -> EntryContent<'s> {
+    EntryContent {
+        hash: content.hash.clone(),
+        html: content.html.clone(),
+        text: content.text.clone(),
+        word_count: content.word_count,
+        reading_minutes: content.reading_minutes,
+        connection: None,
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn save_feed(&self, rss_entity: &RssEntity) -> Result<SaveOutcome, StorageError> {
+        let mut state = self.state()?;
+        state.next_id = state.next_id.wrapping_add(1);
+        let mut rss_entity = rss_entity.with_connection(None);
+        rss_entity.id = state.next_id;
+        state.feeds.push(rss_entity);
+        Ok(SaveOutcome::Inserted)
+    }
+
+    fn get_feeds<'s>(&self) -> Vec<RssEntity<'s>> {
+        match self.state() {
+            Ok(state) => state
+                .feeds
+                .iter()
+                .map(|rss_entity| rss_entity.with_connection(None))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn set_channel(&self, rss_url: &str, channel: &ChannelInfo) -> Result<(), StorageError> {
+        self.update_feeds(rss_url, |rss_entity| rss_entity.channel = channel.clone())
+    }
+
+    fn set_display_title(
+        &self,
+        rss_url: &str,
+        display_title: Option<&str>,
+    ) -> Result<(), StorageError> {
+        self.update_feeds(rss_url, |rss_entity| {
+            rss_entity.display_title = display_title.map(String::from)
+        })
+    }
+
+    fn set_full_text(&self, rss_url: &str, full_text: bool) -> Result<(), StorageError> {
+        self.update_feeds(rss_url, |rss_entity| rss_entity.full_text = full_text)
+    }
+
+    fn set_auto_download(&self, rss_url: &str, auto_download: bool) -> Result<(), StorageError> {
+        self.update_feeds(rss_url, |rss_entity| {
+            rss_entity.auto_download = auto_download
+        })
+    }
+
+    fn set_encoding(&self, rss_url: &str, encoding: &str) -> Result<(), StorageError> {
+        self.update_feeds(rss_url, |rss_entity| {
+            rss_entity.encoding = encoding.to_string()
+        })
+    }
+
+    fn add_feed_tag(&self, rss_url: &str, tag: &str) -> Result<(), StorageError> {
+        if let Some(tag) = normalize_tag(tag) {
+            self.state()?.feed_tags.insert((rss_url.to_string(), tag));
+        }
+        Ok(())
+    }
+
+    fn remove_feed_tag(&self, rss_url: &str, tag: &str) -> Result<(), StorageError> {
+        if let Some(tag) = normalize_tag(tag) {
+            self.state()?.feed_tags.remove(&(rss_url.to_string(), tag));
+        }
+        Ok(())
+    }
+
+    fn feed_tags(&self, rss_url: &str) -> Vec<String> {
+        match self.state() {
+            Ok(state) => state.feed_tags(rss_url),
+            Err(_) => vec![],
+        }
+    }
+
+    fn save_entry(&self, rss_entry: &RssEntry) -> Result<SaveOutcome, StorageError> {
+        let mut state = self.state()?;
+        let hash = rss_entry.hash();
+        if let Some(stored) = state.entries.get_mut(&hash) {
+            if stored.link == rss_entry.link {
+                return Ok(SaveOutcome::Unchanged);
+            }
+            stored.link = rss_entry.link.clone();
+            return Ok(SaveOutcome::Updated);
+        }
+        let stored = RssEntry {
+            publish_date: parse_date(&rss_entry.publish_date)
+                .unwrap_or_else(Utc::now)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            is_read: false,
+            is_starred: false,
+            ..rss_entry.with_connection(None)
+        };
+        state.entries.insert(hash.clone(), stored);
+        state.order.push(hash);
+        Ok(SaveOutcome::Inserted)
+    }
+
+    fn save_details(&self, rss_entry: &RssEntry, item: &Item) -> Result<(), StorageError> {
+        let mut state = self.state()?;
+        let hash = rss_entry.hash();
+        let author = item.author.trim();
+        if author.is_empty() {
+            state.authors.remove(&hash);
+        } else if state.entries.contains_key(&hash) {
+            state.authors.insert(hash.clone(), author.to_string());
+        }
+        state
+            .categories
+            .retain(|(entry_hash, _)| *entry_hash != hash);
+        for category in item.category.iter().map(|category| category.trim()) {
+            if !category.is_empty() {
+                state
+                    .categories
+                    .insert((hash.clone(), category.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_details(&self, rss_entry: &RssEntry) -> (Option<String>, Vec<String>) {
+        let hash = rss_entry.hash();
+        match self.state() {
+            Ok(state) => (
+                state.authors.get(&hash).cloned(),
+                state
+                    .categories
+                    .iter()
+                    .filter(|(entry_hash, _)| *entry_hash == hash)
+                    .map(|(_, category)| category.clone())
+                    .collect(),
+            ),
+            Err(_) => (None, vec![]),
+        }
+    }
+
+    fn save_content(&self, content: &EntryContent) -> Result<SaveOutcome, StorageError> {
+        let replaced = self
+            .state()?
+            .contents
+            .insert(content.hash.clone(), detached_content(content));
+        Ok(match replaced {
+            Some(_) => SaveOutcome::Updated,
+            None => SaveOutcome::Inserted,
+        })
+    }
+
+    fn get_content<'s>(&self, hash: &str) -> Option<EntryContent<'s>> {
+        self.state().ok()?.contents.get(hash).map(detached_content)
+    }
+
+    fn get_entries<'s>(&self, options: &GetManyOptions) -> Vec<RssEntry<'s>> {
+        match self.state() {
+            Ok(state) => state
+                .filter(options)
+                .into_iter()
+                .skip(options.skip as usize)
+                .take(options.limit as usize)
+                .map(|rss_entry| rss_entry.with_connection(None))
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn count_entries(&self, options: &GetManyOptions) -> u64 {
+        match self.state() {
+            Ok(state) => state.filter(options).len() as u64,
+            Err(_) => 0,
+        }
+    }
+
+    fn find_entry<'s>(&self, key: &str) -> Option<RssEntry<'s>> {
+        let state = self.state().ok()?;
+        let by_hash = format!("{}%", key);
+        let by_title = format!("%{}%", key);
+        state
+            .order
+            .iter()
+            .filter_map(|hash| state.entries.get(hash).map(|rss_entry| (hash, rss_entry)))
+            .filter(|(hash, rss_entry)| like(&by_hash, hash) || like(&by_title, &rss_entry.title))
+            .max_by(|(a_hash, a), (b_hash, b)| {
+                like(&by_hash, a_hash)
+                    .cmp(&like(&by_hash, b_hash))
+                    .then_with(|| a.publish_date.cmp(&b.publish_date))
+            })
+            .map(|(_, rss_entry)| rss_entry.with_connection(None))
+    }
+
+    fn set_read(&self, rss_entry: &RssEntry, is_read: bool) -> Result<(), StorageError> {
+        self.update_entry(rss_entry, |stored| stored.is_read = is_read)
+    }
+
+    fn set_starred(&self, rss_entry: &RssEntry, is_starred: bool) -> Result<(), StorageError> {
+        self.update_entry(rss_entry, |stored| stored.is_starred = is_starred)
+    }
+
+    fn add_entry_tag(&self, rss_entry: &RssEntry, tag: &str) -> Result<(), StorageError> {
+        if let Some(tag) = normalize_tag(tag) {
+            self.state()?.entry_tags.insert((rss_entry.hash(), tag));
+        }
+        Ok(())
+    }
+
+    fn remove_entry_tag(&self, rss_entry: &RssEntry, tag: &str) -> Result<(), StorageError> {
+        if let Some(tag) = normalize_tag(tag) {
+            self.state()?.entry_tags.remove(&(rss_entry.hash(), tag));
+        }
+        Ok(())
+    }
+
+    fn entry_tags(&self, rss_entry: &RssEntry) -> Vec<String> {
+        match self.state() {
+            Ok(state) => state.entry_tags(&rss_entry.hash(), &rss_entry.rss_url),
+            Err(_) => vec![],
+        }
+    }
+
+    fn prune_entries(&self, hashes: &[String]) -> Result<(), StorageError> {
+        let mut state = self.state()?;
+        for hash in hashes {
+            state.entries.remove(hash);
+            state.order.retain(|stored| stored != hash);
+            state.entry_tags.retain(|(tagged, _)| tagged != hash);
+            state.authors.remove(hash);
+            state.categories.retain(|(filed, _)| filed != hash);
+            state.contents.remove(hash);
+            state.pruned.insert(hash.clone());
+        }
+        Ok(())
+    }
+
+    fn is_pruned(&self, hash: &str) -> bool {
+        self.state()
+            .map(|state| state.pruned.contains(hash))
+            .unwrap_or(false)
+    }
+
+    fn tag_counts(&self) -> Vec<TagCount> {
+        let state = match self.state() {
+            Ok(state) => state,
+            Err(_) => return vec![],
+        };
+        let tags: BTreeSet<&String> = state
+            .entry_tags
+            .iter()
+            .chain(state.feed_tags.iter())
+            .map(|(_, tag)| tag)
+            .collect();
+        let mut counts: Vec<TagCount> = tags
+            .into_iter()
+            .map(|tag| {
+                let tagged = |(_, pair_tag): &&(String, String)| pair_tag == tag;
+                let inherited = state
+                    .entries
+                    .iter()
+                    .filter(|(hash, rss_entry)| {
+                        state
+                            .feed_tags
+                            .contains(&(rss_entry.rss_url.clone(), tag.clone()))
+                            && !state.entry_tags.contains(&(hash.to_string(), tag.clone()))
+                    })
+                    .count();
+                TagCount {
+                    tag: tag.clone(),
+                    entries: state.entry_tags.iter().filter(tagged).count() as u64,
+                    feeds: state.feed_tags.iter().filter(tagged).count() as u64,
+                    inherited: inherited as u64,
+                }
+            })
+            .collect();
+        // the tags come sorted, a stable sort keeps them so among equal counts
+        counts.sort_by_key(|count| std::cmp::Reverse(count.entries + count.inherited));
+        counts
+    }
+}
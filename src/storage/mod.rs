@@ -0,0 +1,124 @@
+pub mod errors;
+pub mod memory;
+pub mod sql;
+
+pub use memory::MemoryStorage;
+pub use sql::SqliteStorage;
+
+use crate::{
+    core::structs::{GetManyOptions, SaveOutcome},
+    db::{blog::RssEntry, entry_content::EntryContent, rss_entity::RssEntity, tag::TagCount},
+    rss::dto::{ChannelInfo, Item},
+};
+use errors::StorageError;
+
+/// The `Storage` trait is where the subscribed feeds and their entries are kept. `SqliteStorage`
+/// keeps them in the database the command line uses, `MemoryStorage` in memory, for embedding the
+/// crate without a database file or for tests. Both behave the same: an entry is identified by the
+/// hash of its title, `save_entry` only updates the link of a known entry, and tags are normalized
+/// with `normalize_tag`, an entry inheriting the tags of its feed.
+///
+/// The entries and feeds returned are detached, their `connection` is `None`.
+///
+/// `RssEntity::sync`, the terminal interface and the feed, entry and tag commands of the command
+/// line, and digests, read and write feeds and entries through this trait only. `CrudAble` is how
+/// `SqliteStorage` maps them to their tables, not a second way in. What only the database keeps,
+/// like credentials, settings, enclosures, podcast and media metadata, rules, hooks, downloads,
+/// retention policies or backups, is out of its scope and read with the connection; sync without
+/// a connection goes without them.
+pub trait Storage {
+    /// The function `save_feed` subscribes to a feed, returning `SaveOutcome::Inserted`.
+    fn save_feed(&self, rss_entity: &RssEntity) -> Result<SaveOutcome, StorageError>;
+
+    /// The function `get_feeds` returns the subscribed feeds in the order they were saved.
+    fn get_feeds<'s>(&self) -> Vec<RssEntity<'s>>;
+
+    /// The function `set_channel` stores the channel metadata last parsed from a feed.
+    fn set_channel(&self, rss_url: &str, channel: &ChannelInfo) -> Result<(), StorageError>;
+
+    /// The function `set_display_title` overrides the title a feed is listed under, `None` goes
+    /// back to the channel title.
+    fn set_display_title(
+        &self,
+        rss_url: &str,
+        display_title: Option<&str>,
+    ) -> Result<(), StorageError>;
+
+    /// The function `set_full_text` turns fetching the full article text on sync on or off.
+    fn set_full_text(&self, rss_url: &str, full_text: bool) -> Result<(), StorageError>;
+
+    /// The function `set_auto_download` turns downloading the enclosures on sync on or off.
+    fn set_auto_download(&self, rss_url: &str, auto_download: bool) -> Result<(), StorageError>;
+
+    /// The function `set_encoding` records the character encoding a feed was last decoded from.
+    fn set_encoding(&self, rss_url: &str, encoding: &str) -> Result<(), StorageError>;
+
+    /// The function `add_feed_tag` tags a feed, an invalid tag is ignored.
+    fn add_feed_tag(&self, rss_url: &str, tag: &str) -> Result<(), StorageError>;
+
+    /// The function `remove_feed_tag` removes a tag from a feed.
+    fn remove_feed_tag(&self, rss_url: &str, tag: &str) -> Result<(), StorageError>;
+
+    /// The function `feed_tags` returns the tags of a feed, sorted.
+    fn feed_tags(&self, rss_url: &str) -> Vec<String>;
+
+    /// The function `save_entry` stores an entry, see `CrudAble::save` of `RssEntry`. A new entry is
+    /// unread, not starred, and dated when it is stored if its publish date cannot be read.
+    fn save_entry(&self, rss_entry: &RssEntry) -> Result<SaveOutcome, StorageError>;
+
+    /// The function `save_details` stores the author and the categories of the item a stored entry
+    /// was built from, replacing those of a previous version of it, see `RssEntry::save_details`.
+    fn save_details(&self, rss_entry: &RssEntry, item: &Item) -> Result<(), StorageError>;
+
+    /// The function `entry_details` returns the author of an entry, if any, and its categories
+    /// sorted.
+    fn entry_details(&self, rss_entry: &RssEntry) -> (Option<String>, Vec<String>);
+
+    /// The function `save_content` stores the full article text of an entry, which the query of
+    /// `get_entries` matches besides the title.
+    fn save_content(&self, content: &EntryContent) -> Result<SaveOutcome, StorageError>;
+
+    /// The function `get_content` returns the full article stored for the entry with the given
+    /// hash, if any.
+    fn get_content<'s>(&self, hash: &str) -> Option<EntryContent<'s>>;
+
+    /// The function `get_entries` returns a page of the entries matching the filters of `options`,
+    /// in the order of `options.sort`, see `CrudAble::get_many` of `RssEntry`.
+    fn get_entries<'s>(&self, options: &GetManyOptions) -> Vec<RssEntry<'s>>;
+
+    /// The function `count_entries` returns how many entries match the filters of `options`,
+    /// ignoring its page and limit.
+    fn count_entries(&self, options: &GetManyOptions) -> u64;
+
+    /// The function `find_entry` returns the entry whose hash starts with `key`, or the newest entry
+    /// whose title contains `key`.
+    fn find_entry<'s>(&self, key: &str) -> Option<RssEntry<'s>>;
+
+    /// The function `set_read` marks a stored entry as read or unread.
+    fn set_read(&self, rss_entry: &RssEntry, is_read: bool) -> Result<(), StorageError>;
+
+    /// The function `set_starred` stars or unstars a stored entry.
+    fn set_starred(&self, rss_entry: &RssEntry, is_starred: bool) -> Result<(), StorageError>;
+
+    /// The function `add_entry_tag` tags an entry, an invalid tag is ignored.
+    fn add_entry_tag(&self, rss_entry: &RssEntry, tag: &str) -> Result<(), StorageError>;
+
+    /// The function `remove_entry_tag` removes a tag of the entry itself, an inherited tag stays.
+    fn remove_entry_tag(&self, rss_entry: &RssEntry, tag: &str) -> Result<(), StorageError>;
+
+    /// The function `entry_tags` returns the tags of an entry and of its feed, sorted and without
+    /// duplicates.
+    fn entry_tags(&self, rss_entry: &RssEntry) -> Vec<String>;
+
+    /// The function `prune_entries` deletes the entries with the given hashes with their tags,
+    /// details and full articles, and remembers them, see `is_pruned`.
+    fn prune_entries(&self, hashes: &[String]) -> Result<(), StorageError>;
+
+    /// The function `is_pruned` tells whether the entry with the given hash was deleted by
+    /// `prune_entries`, so sync does not store it again.
+    fn is_pruned(&self, hash: &str) -> bool;
+
+    /// The function `tag_counts` counts every tag used on an entry or a feed, most used first,
+    /// see `TagCount::list`.
+    fn tag_counts(&self) -> Vec<TagCount>;
+}
@@ -0,0 +1,183 @@
+use sqlite::Connection;
+use thread_safe::ThreadSafe;
+
+use super::{errors::StorageError, Storage};
+use crate::{
+    core::{
+        structs::{GetManyOptions, SaveOutcome},
+        traits::CrudAble,
+    },
+    db::{blog::RssEntry, entry_content::EntryContent, rss_entity::RssEntity, tag::TagCount},
+    prune,
+    rss::dto::{ChannelInfo, Item},
+};
+
+/// The `SqliteStorage` struct keeps the feeds and entries in a SQLite database opened with
+/// `crate::db::open_database`, the storage the command line uses.
+///
+/// Properties:
+///
+/// * `connection`: The connection to the database.
+pub struct SqliteStorage<'c> {
+    pub connection: ThreadSafe<&'c Connection>,
+}
+
+impl<'c> SqliteStorage<'c> {
+    /// The function `new` returns a storage using `connection`.
+    pub fn new(connection: &ThreadSafe<&'c Connection>) -> SqliteStorage<'c> {
+        SqliteStorage {
+            connection: connection.clone(),
+        }
+    }
+}
+
+impl<'c> Storage for SqliteStorage<'c> {
+    fn save_feed(&self, rss_entity: &RssEntity) -> Result<SaveOutcome, StorageError> {
+        Ok(rss_entity
+            .with_connection(Some(self.connection.clone()))
+            .save()?)
+    }
+
+    fn get_feeds<'s>(&self) -> Vec<RssEntity<'s>> {
+        RssEntity::get_many(&self.connection, GetManyOptions::new())
+    }
+
+    fn set_channel(&self, rss_url: &str, channel: &ChannelInfo) -> Result<(), StorageError> {
+        Ok(RssEntity::set_channel(&self.connection, rss_url, channel)?)
+    }
+
+    fn set_display_title(
+        &self,
+        rss_url: &str,
+        display_title: Option<&str>,
+    ) -> Result<(), StorageError> {
+        Ok(RssEntity::set_display_title(
+            &self.connection,
+            rss_url,
+            display_title,
+        )?)
+    }
+
+    fn set_full_text(&self, rss_url: &str, full_text: bool) -> Result<(), StorageError> {
+        Ok(RssEntity::set_full_text(
+            &self.connection,
+            rss_url,
+            full_text,
+        )?)
+    }
+
+    fn set_auto_download(&self, rss_url: &str, auto_download: bool) -> Result<(), StorageError> {
+        Ok(RssEntity::set_auto_download(
+            &self.connection,
+            rss_url,
+            auto_download,
+        )?)
+    }
+
+    fn set_encoding(&self, rss_url: &str, encoding: &str) -> Result<(), StorageError> {
+        Ok(RssEntity::set_encoding(
+            &self.connection,
+            rss_url,
+            encoding,
+        )?)
+    }
+
+    fn add_feed_tag(&self, rss_url: &str, tag: &str) -> Result<(), StorageError> {
+        Ok(RssEntity::add_tag(&self.connection, rss_url, tag)?)
+    }
+
+    fn remove_feed_tag(&self, rss_url: &str, tag: &str) -> Result<(), StorageError> {
+        Ok(RssEntity::remove_tag(&self.connection, rss_url, tag)?)
+    }
+
+    fn feed_tags(&self, rss_url: &str) -> Vec<String> {
+        RssEntity::tags(&self.connection, rss_url)
+    }
+
+    fn save_entry(&self, rss_entry: &RssEntry) -> Result<SaveOutcome, StorageError> {
+        Ok(rss_entry
+            .with_connection(Some(self.connection.clone()))
+            .save()?)
+    }
+
+    fn save_details(&self, rss_entry: &RssEntry, item: &Item) -> Result<(), StorageError> {
+        Ok(rss_entry
+            .with_connection(Some(self.connection.clone()))
+            .save_details(item)?)
+    }
+
+    fn entry_details(&self, rss_entry: &RssEntry) -> (Option<String>, Vec<String>) {
+        rss_entry.details(&self.connection)
+    }
+
+    fn save_content(&self, content: &EntryContent) -> Result<SaveOutcome, StorageError> {
+        let content = EntryContent {
+            hash: content.hash.clone(),
+            html: content.html.clone(),
+            text: content.text.clone(),
+            word_count: content.word_count,
+            reading_minutes: content.reading_minutes,
+            connection: Some(self.connection.clone()),
+        };
+        Ok(content.save()?)
+    }
+
+    fn get_content<'s>(&self, hash: &str) -> Option<EntryContent<'s>> {
+        EntryContent::get(&self.connection, hash).map(|content| EntryContent {
+            connection: None,
+            ..content
+        })
+    }
+
+    fn get_entries<'s>(&self, options: &GetManyOptions) -> Vec<RssEntry<'s>> {
+        RssEntry::get_many(&self.connection, options.clone())
+    }
+
+    fn count_entries(&self, options: &GetManyOptions) -> u64 {
+        RssEntry::count(&self.connection, options)
+    }
+
+    fn find_entry<'s>(&self, key: &str) -> Option<RssEntry<'s>> {
+        RssEntry::find(&self.connection, key).map(|rss_entry| rss_entry.with_connection(None))
+    }
+
+    fn set_read(&self, rss_entry: &RssEntry, is_read: bool) -> Result<(), StorageError> {
+        Ok(rss_entry
+            .with_connection(Some(self.connection.clone()))
+            .set_read(is_read)?)
+    }
+
+    fn set_starred(&self, rss_entry: &RssEntry, is_starred: bool) -> Result<(), StorageError> {
+        Ok(rss_entry
+            .with_connection(Some(self.connection.clone()))
+            .set_starred(is_starred)?)
+    }
+
+    fn add_entry_tag(&self, rss_entry: &RssEntry, tag: &str) -> Result<(), StorageError> {
+        Ok(rss_entry
+            .with_connection(Some(self.connection.clone()))
+            .add_tag(tag)?)
+    }
+
+    fn remove_entry_tag(&self, rss_entry: &RssEntry, tag: &str) -> Result<(), StorageError> {
+        Ok(rss_entry
+            .with_connection(Some(self.connection.clone()))
+            .remove_tag(tag)?)
+    }
+
+    fn entry_tags(&self, rss_entry: &RssEntry) -> Vec<String> {
+        rss_entry.tags(&self.connection)
+    }
+
+    fn prune_entries(&self, hashes: &[String]) -> Result<(), StorageError> {
+        Ok(prune::delete_entries(&self.connection, hashes)?)
+    }
+
+    fn is_pruned(&self, hash: &str) -> bool {
+        RssEntry::is_pruned(&self.connection, hash)
+    }
+
+    fn tag_counts(&self) -> Vec<TagCount> {
+        TagCount::list(&self.connection)
+    }
+}
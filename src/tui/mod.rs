@@ -0,0 +1,775 @@
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use sqlite::Connection;
+use std::{io, time::Duration};
+use thread_safe::ThreadSafe;
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver},
+    oneshot,
+};
+
+use crate::{
+    core::{
+        report::{SyncProgress, SyncReport},
+        structs::{GetManyOptions, SyncOptions},
+    },
+    db::{self, blog::RssEntry, rss_entity::RssEntity},
+    render::{self, RenderOptions},
+    secrets::redact_url,
+    storage::{SqliteStorage, Storage},
+};
+
+/// Number of entries loaded from the database at a time.
+const PAGE_SIZE: u64 = 50;
+
+/// How close to the end of the loaded entries the selection gets before the next page is loaded.
+const PREFETCH_MARGIN: usize = 5;
+
+/// The receiver of the report of a background sync, or of the reason it could not run.
+type SyncReceiver = oneshot::Receiver<Result<SyncReport, String>>;
+
+/// The `SidebarItem` enum lists what the sidebar can filter the entry list by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SidebarItem {
+    All,
+    Profile(String),
+    Feed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Sidebar,
+    Entries,
+    Content,
+}
+
+/// The `Action` enum lists what a key asks of the loop driving the interface, once `App` handled
+/// what only concerns its own state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    None,
+    Quit,
+    Sync,
+}
+
+/// The `App` struct holds the state of the terminal interface between two frames.
+struct App<'c> {
+    storage: &'c dyn Storage,
+    sidebar: Vec<(SidebarItem, String)>,
+    sidebar_state: ListState,
+    entries: Vec<RssEntry<'c>>,
+    entries_state: ListState,
+    exhausted: bool,
+    query: String,
+    searching: bool,
+    focus: Focus,
+    content: String,
+    content_key: (String, u16),
+    scroll: u16,
+    status: String,
+}
+
+impl<'c> App<'c> {
+    fn new(storage: &'c dyn Storage) -> App<'c> {
+        let mut app = App {
+            storage,
+            sidebar: vec![],
+            sidebar_state: ListState::default(),
+            entries: vec![],
+            entries_state: ListState::default(),
+            exhausted: false,
+            query: String::new(),
+            searching: false,
+            focus: Focus::Entries,
+            content: String::new(),
+            content_key: (String::new(), 0),
+            scroll: 0,
+            status: String::from("q quit  tab focus  enter open  r read  s star  / search  S sync"),
+        };
+        app.load_sidebar();
+        app.reload_entries();
+        app
+    }
+
+    /// The function `load_sidebar` lists every profile followed by its feeds, keeping the current
+    /// selection when it still exists.
+    fn load_sidebar(&mut self) {
+        let selected = self.selected_sidebar_item();
+        let mut rss_entities = self.storage.get_feeds();
+        rss_entities.sort_by(|a, b| (&a.profile, &a.rss_url).cmp(&(&b.profile, &b.rss_url)));
+
+        self.sidebar = vec![(SidebarItem::All, String::from("All entries"))];
+        let mut profile = None;
+        for rss_entity in rss_entities {
+            if profile.as_ref() != Some(&rss_entity.profile) {
+                profile = Some(rss_entity.profile.clone());
+                self.sidebar.push((
+                    SidebarItem::Profile(rss_entity.profile.clone()),
+                    format!("▸ {}", render::strip_controls(&rss_entity.profile)),
+                ));
+            }
+            self.sidebar.push((
+                SidebarItem::Feed(rss_entity.rss_url.clone()),
+                format!("  {}", render::strip_controls(&rss_entity.name())),
+            ));
+        }
+        let index = self
+            .sidebar
+            .iter()
+            .position(|(item, _)| *item == selected)
+            .unwrap_or(0);
+        self.sidebar_state.select(Some(index));
+    }
+
+    fn selected_sidebar_item(&self) -> SidebarItem {
+        self.sidebar_state
+            .selected()
+            .and_then(|index| self.sidebar.get(index))
+            .map(|(item, _)| item.clone())
+            .unwrap_or(SidebarItem::All)
+    }
+
+    fn entry_options(&self) -> GetManyOptions {
+        let mut options = GetManyOptions::new();
+        options.query = self.query.clone();
+        options.set_limit(PAGE_SIZE);
+        options.set_skip(self.entries.len() as u64);
+        match self.selected_sidebar_item() {
+            SidebarItem::All => &options,
+            SidebarItem::Profile(profile) => options.set_profile(&profile),
+            SidebarItem::Feed(rss_url) => options.set_rss_url(&rss_url),
+        };
+        options
+    }
+
+    /// The function `reload_entries` drops the loaded entries and loads the first page again for
+    /// the current sidebar selection and search query.
+    fn reload_entries(&mut self) {
+        self.entries.clear();
+        self.exhausted = false;
+        self.load_more();
+        self.entries_state.select(if self.entries.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+        self.scroll = 0;
+    }
+
+    /// The function `load_more` appends the next page of entries, unless the last page was short.
+    fn load_more(&mut self) {
+        if self.exhausted {
+            return;
+        }
+        let page = self.storage.get_entries(&self.entry_options());
+        self.exhausted = (page.len() as u64) < PAGE_SIZE;
+        self.entries.extend(page);
+    }
+
+    fn selected_entry(&self) -> Option<&RssEntry<'c>> {
+        self.entries_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+    }
+
+    fn move_sidebar(&mut self, delta: isize) {
+        let index = step(self.sidebar_state.selected(), delta, self.sidebar.len());
+        if index != self.sidebar_state.selected() {
+            self.sidebar_state.select(index);
+            self.reload_entries();
+        }
+    }
+
+    fn move_entries(&mut self, delta: isize) {
+        let index = step(self.entries_state.selected(), delta, self.entries.len());
+        if let Some(index) = index {
+            if index + PREFETCH_MARGIN >= self.entries.len() {
+                self.load_more();
+            }
+        }
+        if index != self.entries_state.selected() {
+            self.entries_state.select(index);
+            self.scroll = 0;
+        }
+    }
+
+    fn toggle_read(&mut self) {
+        if let Some(index) = self.entries_state.selected() {
+            let rss_entry = &mut self.entries[index];
+            match self.storage.set_read(rss_entry, !rss_entry.is_read) {
+                Ok(()) => rss_entry.is_read = !rss_entry.is_read,
+                Err(e) => self.status = format!("Updating the entry failed: {}", e),
+            }
+        }
+    }
+
+    fn toggle_star(&mut self) {
+        if let Some(index) = self.entries_state.selected() {
+            let rss_entry = &mut self.entries[index];
+            match self.storage.set_starred(rss_entry, !rss_entry.is_starred) {
+                Ok(()) => rss_entry.is_starred = !rss_entry.is_starred,
+                Err(e) => self.status = format!("Updating the entry failed: {}", e),
+            }
+        }
+    }
+
+    /// The function `open` shows the selected entry in the content pane and marks it as read.
+    fn open(&mut self) {
+        if let Some(index) = self.entries_state.selected() {
+            self.focus = Focus::Content;
+            self.scroll = 0;
+            let rss_entry = &mut self.entries[index];
+            if !rss_entry.is_read && self.storage.set_read(rss_entry, true).is_ok() {
+                rss_entry.is_read = true;
+            }
+        }
+    }
+
+    /// The function `refresh_content` renders the selected entry for the width of the content
+    /// pane, only when the entry or the width changed since the last frame.
+    fn refresh_content(&mut self, width: u16) {
+        let rss_entry = match self.selected_entry() {
+            Some(rss_entry) => rss_entry,
+            None => {
+                self.content.clear();
+                self.content_key = (String::new(), width);
+                return;
+            }
+        };
+        let hash = rss_entry.hash();
+        if self.content_key == (hash.clone(), width) {
+            return;
+        }
+
+        let content = self.storage.get_content(&hash);
+        let mut text = format!(
+            "{}\n{}\n",
+            render::strip_controls(&rss_entry.link),
+            render::strip_controls(&rss_entry.publish_date)
+        );
+        if let Some(content) = &content {
+            text.push_str(&format!(
+                "{} words, {} min read\n",
+                content.word_count, content.reading_minutes
+            ));
+        }
+        text.push('\n');
+        let html = match &content {
+            Some(content) => &content.html,
+            None => &rss_entry.description,
+        };
+        let render_options = RenderOptions {
+            width: width as usize,
+            color: false,
+        };
+        text.push_str(&render::render_html(html, render_options));
+        self.content = text;
+        self.content_key = (hash, width);
+    }
+
+    /// The function `handle_search_key` edits the search query, reloading the entry list after
+    /// every keystroke.
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.query.clear();
+                self.reload_entries();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.reload_entries();
+            }
+            KeyCode::Char(character) => {
+                self.query.push(character);
+                self.reload_entries();
+            }
+            _ => {}
+        }
+    }
+
+    /// The function `handle_key` applies a key press to the state of the interface.
+    ///
+    /// Returns:
+    ///
+    /// the `Action` left to the loop driving the interface.
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if self.searching {
+            self.handle_search_key(key);
+            return Action::None;
+        }
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Sidebar => Focus::Entries,
+                    Focus::Entries => Focus::Content,
+                    Focus::Content => Focus::Sidebar,
+                }
+            }
+            KeyCode::BackTab | KeyCode::Esc => {
+                self.focus = match self.focus {
+                    Focus::Content => Focus::Entries,
+                    _ => Focus::Sidebar,
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => match self.focus {
+                Focus::Sidebar => self.move_sidebar(1),
+                Focus::Entries => self.move_entries(1),
+                Focus::Content => self.scroll = self.scroll.saturating_add(1),
+            },
+            KeyCode::Char('k') | KeyCode::Up => match self.focus {
+                Focus::Sidebar => self.move_sidebar(-1),
+                Focus::Entries => self.move_entries(-1),
+                Focus::Content => self.scroll = self.scroll.saturating_sub(1),
+            },
+            KeyCode::PageDown | KeyCode::Char(' ') => match self.focus {
+                Focus::Entries => self.move_entries(10),
+                _ => self.scroll = self.scroll.saturating_add(10),
+            },
+            KeyCode::PageUp => match self.focus {
+                Focus::Entries => self.move_entries(-10),
+                _ => self.scroll = self.scroll.saturating_sub(10),
+            },
+            KeyCode::Enter => match self.focus {
+                Focus::Sidebar => self.focus = Focus::Entries,
+                _ => self.open(),
+            },
+            KeyCode::Char('r') => self.toggle_read(),
+            KeyCode::Char('s') => self.toggle_star(),
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.focus = Focus::Entries;
+            }
+            KeyCode::Char('S') => return Action::Sync,
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Percentage(35),
+                Constraint::Percentage(45),
+            ])
+            .split(rows[0]);
+
+        let sidebar: Vec<ListItem> = self
+            .sidebar
+            .iter()
+            .map(|(_, label)| ListItem::new(label.as_str()))
+            .collect();
+        let sidebar = List::new(sidebar)
+            .block(pane("Feeds", self.focus == Focus::Sidebar))
+            .highlight_style(highlight());
+        frame.render_stateful_widget(sidebar, columns[0], &mut self.sidebar_state);
+
+        let entries: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|rss_entry| {
+                let marker = format!(
+                    "{}{} ",
+                    if rss_entry.is_read { " " } else { "●" },
+                    if rss_entry.is_starred { "★" } else { " " }
+                );
+                let style = if rss_entry.is_read {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default().add_modifier(Modifier::BOLD)
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(render::strip_controls(&rss_entry.title), style),
+                ]))
+            })
+            .collect();
+        let title = if self.query.is_empty() {
+            String::from("Entries")
+        } else {
+            format!("Entries matching \"{}\"", self.query)
+        };
+        let entries = List::new(entries)
+            .block(pane(&title, self.focus == Focus::Entries))
+            .highlight_style(highlight());
+        frame.render_stateful_widget(entries, columns[1], &mut self.entries_state);
+
+        self.refresh_content(columns[2].width.saturating_sub(2));
+        let title = self
+            .selected_entry()
+            .map(|rss_entry| render::strip_controls(&rss_entry.title))
+            .unwrap_or_default();
+        let content = Paragraph::new(self.content.as_str())
+            .block(pane(&title, self.focus == Focus::Content))
+            .scroll((self.scroll, 0));
+        frame.render_widget(content, columns[2]);
+
+        let status = if self.searching {
+            format!("/{}", self.query)
+        } else {
+            self.status.clone()
+        };
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::REVERSED)),
+            rows[1],
+        );
+    }
+}
+
+/// The function `run` opens the interactive terminal interface and returns when the user quits.
+///
+/// Arguments:
+///
+/// * `connection`: The `connection` to the database the entries and feeds are read from, the
+///   background sync opens its own.
+///
+/// Returns:
+///
+/// an `io::Error` if the terminal cannot be set up or drawn to.
+pub async fn run<'c>(connection: &'c ThreadSafe<&'c Connection>) -> io::Result<()> {
+    enable_raw_mode()?;
+    let _restore = RestoreTerminal;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let storage = SqliteStorage::new(connection);
+    let mut app = App::new(&storage);
+    let mut sync: Option<(SyncReceiver, UnboundedReceiver<SyncProgress>)> = None;
+
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        // the sync runs on its own thread, the interface only collects its progress between frames
+        let mut finished = None;
+        if let Some((report, progress)) = sync.as_mut() {
+            while let Ok(update) = progress.try_recv() {
+                if update.done < update.total {
+                    app.status = format!(
+                        "Syncing {}/{} {}",
+                        update.done + 1,
+                        update.total,
+                        render::strip_controls(&redact_url(&update.rss_url))
+                    );
+                }
+            }
+            match report.try_recv() {
+                Ok(report) => finished = Some(report),
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    finished = Some(Err(String::from("the sync stopped unexpectedly")))
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+            }
+        }
+        match finished {
+            Some(Ok(report)) => {
+                sync = None;
+                app.status = format!(
+                    "Sync done: {} new entries, {} errors",
+                    report.total_new(),
+                    report.total_errors()
+                );
+                app.load_sidebar();
+                app.reload_entries();
+            }
+            Some(Err(e)) => {
+                sync = None;
+                app.status = format!("Sync failed: {}", e);
+            }
+            None => {}
+        }
+
+        let timeout = if sync.is_some() {
+            Duration::from_millis(50)
+        } else {
+            Duration::from_millis(250)
+        };
+        if !event::poll(timeout)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match app.handle_key(key) {
+            Action::Quit => break,
+            Action::Sync if sync.is_none() => {
+                let (sender, progress) = mpsc::unbounded_channel();
+                let mut options = SyncOptions::new();
+                options.set_progress(sender);
+                app.status = String::from("Syncing...");
+                sync = Some((spawn_sync(options), progress));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The function `spawn_sync` syncs every feed on a blocking thread of its own, with its own
+/// connection to the database, so fetching feeds never holds up the interface.
+///
+/// Arguments:
+///
+/// * `options`: The `options` of the sync, with the sender its progress is reported to.
+///
+/// Returns:
+///
+/// the receiver of the `SyncReport`, or of the error when the database cannot be opened.
+fn spawn_sync(options: SyncOptions) -> SyncReceiver {
+    let (sender, receiver) = oneshot::channel();
+    tokio::task::spawn_blocking(move || {
+        let report = db::open_database(db::DATABASE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|connection| {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                let connection = ThreadSafe::new(&connection);
+                let storage = SqliteStorage::new(&connection);
+                Ok(runtime.block_on(RssEntity::sync(&storage, Some(&connection), options)))
+            });
+        let _ = sender.send(report);
+    });
+    receiver
+}
+
+/// The `RestoreTerminal` struct leaves raw mode and the alternate screen when dropped, so the
+/// terminal is usable again even when the interface panics.
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+fn pane(title: &str, focused: bool) -> Block<'_> {
+    let style = if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+fn highlight() -> Style {
+    Style::default().add_modifier(Modifier::REVERSED)
+}
+
+/// The function `step` moves a list selection by `delta`, staying inside the list.
+fn step(selected: Option<usize>, delta: isize, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let index = selected.unwrap_or(0) as isize + delta;
+    Some(index.clamp(0, len as isize - 1) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::open_database, rss::dto::Item};
+
+    fn hash(title: &str) -> String {
+        RssEntry::from(Item {
+            title: title.to_string(),
+            ..Item::default()
+        })
+        .hash()
+    }
+
+    /// The function `database` stores `count` entries of a feed of the `work` profile, entry 0
+    /// being the newest, and one entry of a feed of the `home` profile.
+    fn database(count: usize) -> Connection {
+        let connection = open_database(":memory:").unwrap();
+        connection
+            .execute(
+                "INSERT INTO rss_table (rss_url, profile) VALUES ('https://a.test/rss', 'work');
+                INSERT INTO rss_table (rss_url, profile) VALUES ('https://b.test/rss', 'home');",
+            )
+            .unwrap();
+        let mut statement = connection
+            .prepare(
+                "INSERT INTO rss_entries (hash, title, link, publish_date, rss_url)
+                VALUES (?, 'Home entry', 'https://b.test/1', '2000-01-01 00:00:00',
+                'https://b.test/rss')",
+            )
+            .unwrap();
+        statement.bind((1, hash("Home entry").as_str())).unwrap();
+        statement.next().unwrap();
+        drop(statement);
+        for index in 0..count {
+            let mut statement = connection
+                .prepare(
+                    "INSERT INTO rss_entries (hash, title, link, publish_date, rss_url)
+                    VALUES (?, ?, 'https://a.test/', datetime('2024-01-01', ?), 'https://a.test/rss')",
+                )
+                .unwrap();
+            let title = format!("Entry {}", index);
+            statement.bind((1, hash(&title).as_str())).unwrap();
+            statement.bind((2, title.as_str())).unwrap();
+            statement
+                .bind((3, format!("-{} minutes", index).as_str()))
+                .unwrap();
+            statement.next().unwrap();
+        }
+        connection
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn pages_are_loaded_until_a_short_one() {
+        let connection = database(PAGE_SIZE as usize + 10);
+        let connection = ThreadSafe::new(&connection);
+        let storage = SqliteStorage::new(&connection);
+        let mut app = App::new(&storage);
+        assert_eq!(app.entries.len(), PAGE_SIZE as usize);
+        assert!(!app.exhausted);
+        assert_eq!(app.entries_state.selected(), Some(0));
+
+        // nearing the end of the first page loads the rest, the selection staying put
+        app.move_entries(PAGE_SIZE as isize - PREFETCH_MARGIN as isize);
+        assert_eq!(app.entries.len(), PAGE_SIZE as usize + 11);
+        assert!(app.exhausted);
+        app.move_entries(1000);
+        assert_eq!(app.entries_state.selected(), Some(PAGE_SIZE as usize + 10));
+        assert_eq!(app.selected_entry().unwrap().title, "Home entry");
+
+        app.load_more();
+        assert_eq!(app.entries.len(), PAGE_SIZE as usize + 11);
+    }
+
+    #[test]
+    fn the_sidebar_filters_the_entries() {
+        let connection = database(3);
+        let connection = ThreadSafe::new(&connection);
+        let storage = SqliteStorage::new(&connection);
+        let mut app = App::new(&storage);
+        let labels: Vec<&str> = app
+            .sidebar
+            .iter()
+            .map(|(_, label)| label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "All entries",
+                "▸ home",
+                "  https://b.test/rss",
+                "▸ work",
+                "  https://a.test/rss"
+            ]
+        );
+        assert_eq!(app.entries.len(), 4);
+
+        press(&mut app, KeyCode::BackTab);
+        assert_eq!(app.focus, Focus::Sidebar);
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(
+            app.selected_sidebar_item(),
+            SidebarItem::Profile("home".into())
+        );
+        assert_eq!(app.entries.len(), 1);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.entries.len(), 3);
+        assert!(app.exhausted);
+        assert_eq!(app.entries_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn keys_move_the_focus_and_ask_the_loop_to_quit_or_sync() {
+        let connection = database(2);
+        let connection = ThreadSafe::new(&connection);
+        let storage = SqliteStorage::new(&connection);
+        let mut app = App::new(&storage);
+        assert_eq!(app.focus, Focus::Entries);
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.focus, Focus::Content);
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.scroll, 1);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.focus, Focus::Entries);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.focus, Focus::Sidebar);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.focus, Focus::Entries);
+
+        // while searching, every key edits the query
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Action::None);
+        assert_eq!(app.query, "q");
+        assert!(app.entries.is_empty());
+        press(&mut app, KeyCode::Esc);
+        assert!(!app.searching);
+        assert_eq!(app.entries.len(), 3);
+
+        assert_eq!(press(&mut app, KeyCode::Char('S')), Action::Sync);
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Action::Quit);
+        assert_eq!(
+            app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Action::Quit
+        );
+    }
+
+    #[test]
+    fn read_and_star_toggles_are_stored() {
+        let connection = database(2);
+        let connection = ThreadSafe::new(&connection);
+        let storage = SqliteStorage::new(&connection);
+        let mut app = App::new(&storage);
+        press(&mut app, KeyCode::Char('r'));
+        press(&mut app, KeyCode::Char('s'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.focus, Focus::Content);
+
+        let flags = |app: &App| -> Vec<(bool, bool)> {
+            let entries = app.storage.get_entries(&GetManyOptions::new());
+            entries
+                .iter()
+                .map(|rss_entry| (rss_entry.is_read, rss_entry.is_starred))
+                .collect()
+        };
+        assert_eq!(flags(&app), [(true, true), (true, false), (false, false)]);
+
+        // opening an entry again never marks it unread
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('r'));
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(flags(&app), [(false, false), (true, false), (false, false)]);
+        assert!(!app.entries[0].is_read && !app.entries[0].is_starred);
+    }
+}
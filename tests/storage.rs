@@ -0,0 +1,559 @@
+//! The same suite runs against every `Storage`, so the in-memory storage keeps behaving like the
+//! SQLite one the command line uses.
+
+use rss_rust::{
+    core::structs::{EntrySort, GetManyOptions, SaveOutcome, SyncOptions},
+    db::{blog::RssEntry, entry_content::EntryContent, open_database, rss_entity::RssEntity},
+    rss::dto::{ChannelInfo, Item},
+    storage::{MemoryStorage, SqliteStorage, Storage},
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use thread_safe::ThreadSafe;
+
+fn feed<'c>(rss_url: &str, profile: &str) -> RssEntity<'c> {
+    RssEntity {
+        id: 0,
+        rss_url: rss_url.to_string(),
+        profile: profile.to_string(),
+        full_text: false,
+        encoding: String::new(),
+        auto_download: false,
+        channel: ChannelInfo::default(),
+        display_title: None,
+        connection: None,
+    }
+}
+
+fn entry<'c>(title: &str, link: &str, rss_url: &str) -> RssEntry<'c> {
+    RssEntry {
+        title: title.to_string(),
+        link: link.to_string(),
+        publish_date: String::from("2001-02-03 04:05:06"),
+        rss_url: rss_url.to_string(),
+        description: format!("About {}", title),
+        is_read: true,
+        is_starred: true,
+        connection: None,
+    }
+}
+
+fn titles(entries: &[RssEntry]) -> Vec<String> {
+    entries.iter().map(|entry| entry.title.clone()).collect()
+}
+
+fn all() -> GetManyOptions {
+    let mut options = GetManyOptions::new();
+    options.set_limit(100);
+    options.set_sort(EntrySort::Title);
+    options
+}
+
+/// Subscribes to two feeds of different profiles with three entries each.
+fn populate<S: Storage>(storage: &S) {
+    storage
+        .save_feed(&feed("https://a.test/rss", "work"))
+        .unwrap();
+    storage
+        .save_feed(&feed("https://b.test/rss", "home"))
+        .unwrap();
+    for title in ["Rust 1.75 released", "Async closures", "cargo tips"] {
+        let link = format!("https://a.test/{}", title.len());
+        storage
+            .save_entry(&entry(title, &link, "https://a.test/rss"))
+            .unwrap();
+    }
+    for title in ["Bread recipe", "Garden notes", "Rusty tools"] {
+        let link = format!("https://b.test/{}", title.len());
+        storage
+            .save_entry(&entry(title, &link, "https://b.test/rss"))
+            .unwrap();
+    }
+}
+
+fn feeds_keep_their_settings<S: Storage>(storage: &S) {
+    populate(storage);
+    let channel = ChannelInfo {
+        title: String::from("A blog"),
+        link: String::from("https://a.test"),
+        ..ChannelInfo::default()
+    };
+    storage.set_channel("https://a.test/rss", &channel).unwrap();
+    storage
+        .set_display_title("https://b.test/rss", Some("B"))
+        .unwrap();
+    storage.set_full_text("https://a.test/rss", true).unwrap();
+    storage
+        .set_auto_download("https://b.test/rss", true)
+        .unwrap();
+    storage
+        .set_encoding("https://a.test/rss", "windows-1252")
+        .unwrap();
+
+    let feeds = storage.get_feeds();
+    assert_eq!(feeds.len(), 2);
+    assert!(feeds[0].id < feeds[1].id);
+    assert_eq!(feeds[0].rss_url, "https://a.test/rss");
+    assert_eq!(feeds[0].profile, "work");
+    assert_eq!(feeds[0].channel, channel);
+    assert_eq!(feeds[0].name(), "A blog");
+    assert!(feeds[0].full_text && !feeds[0].auto_download);
+    assert_eq!(feeds[0].encoding, "windows-1252");
+    assert_eq!(feeds[1].name(), "B");
+    assert!(!feeds[1].full_text && feeds[1].auto_download);
+
+    storage
+        .set_display_title("https://b.test/rss", None)
+        .unwrap();
+    assert_eq!(storage.get_feeds()[1].display_title, None);
+}
+
+fn save_entry_reports_its_outcome<S: Storage>(storage: &S) {
+    let first = entry("Hello", "https://a.test/1", "https://a.test/rss");
+    assert_eq!(storage.save_entry(&first).unwrap(), SaveOutcome::Inserted);
+    assert_eq!(storage.save_entry(&first).unwrap(), SaveOutcome::Unchanged);
+    let moved = entry("Hello", "https://a.test/2", "https://a.test/rss");
+    assert_eq!(storage.save_entry(&moved).unwrap(), SaveOutcome::Updated);
+
+    let entries = storage.get_entries(&all());
+    assert_eq!(entries.len(), 1);
+    let stored = &entries[0];
+    assert_eq!(stored.link, "https://a.test/2");
+    assert_eq!(stored.description, "About Hello");
+    assert_eq!(stored.rss_url, "https://a.test/rss");
+    assert!(!stored.is_read && !stored.is_starred);
+    assert_eq!(stored.publish_date, "2001-02-03 04:05:06");
+    assert!(stored.connection.is_none());
+
+    let mut dated = entry("Dated", "https://a.test/3", "https://a.test/rss");
+    dated.publish_date = String::from("Sat, 03 Feb 2001 04:05:06 +0200");
+    let mut undated = entry("Undated", "https://a.test/4", "https://a.test/rss");
+    undated.publish_date = String::new();
+    storage.save_entry(&dated).unwrap();
+    storage.save_entry(&undated).unwrap();
+    let dates: Vec<String> = storage
+        .get_entries(&all())
+        .into_iter()
+        .map(|entry| entry.publish_date)
+        .collect();
+    assert!(dates.contains(&String::from("2001-02-03 02:05:06")));
+    // an entry without a date is dated by the sync that stored it
+    assert!(dates
+        .iter()
+        .any(|date| date.as_str() > "2020" && date.len() == "2001-02-03 04:05:06".len()));
+}
+
+fn get_entries_applies_the_filters<S: Storage>(storage: &S) {
+    populate(storage);
+    let query = |update: &dyn Fn(&mut GetManyOptions)| {
+        let mut options = all();
+        update(&mut options);
+        (
+            titles(&storage.get_entries(&options)),
+            storage.count_entries(&options),
+        )
+    };
+
+    let (found, count) = query(&|options| options.query = String::from("rust"));
+    assert_eq!(found, ["Rust 1.75 released", "Rusty tools"]);
+    assert_eq!(count, 2);
+    let (found, _) = query(&|options| options.query = String::from("c_rgo"));
+    assert_eq!(found, ["cargo tips"]);
+    let (found, _) = query(&|options| options.query = String::from("recipe%"));
+    assert_eq!(found, ["Bread recipe"]);
+
+    let (found, _) = query(&|options| {
+        options.set_rss_url("https://b.test/rss");
+    });
+    assert_eq!(found, ["Bread recipe", "Garden notes", "Rusty tools"]);
+    let (found, _) = query(&|options| {
+        options.set_profile("work");
+    });
+    assert_eq!(
+        found,
+        ["Async closures", "cargo tips", "Rust 1.75 released"]
+    );
+
+    let read = storage.find_entry("Garden").unwrap();
+    storage.set_read(&read, true).unwrap();
+    let starred = storage.find_entry("Async").unwrap();
+    storage.set_starred(&starred, true).unwrap();
+    let (_, count) = query(&|options| {
+        options.set_unread_only(true);
+    });
+    assert_eq!(count, 5);
+    let (found, _) = query(&|options| {
+        options.set_starred_only(true);
+    });
+    assert_eq!(found, ["Async closures"]);
+    storage.set_starred(&starred, false).unwrap();
+    assert_eq!(
+        query(&|options| {
+            options.set_starred_only(true);
+        }),
+        (vec![], 0)
+    );
+
+    let (_, count) = query(&|options| {
+        options.set_date_range("2000-01-01 00:00:00", "");
+    });
+    assert_eq!(count, 6);
+    let (_, count) = query(&|options| {
+        options.set_date_range("", "2000-01-01 00:00:00");
+    });
+    assert_eq!(count, 0);
+}
+
+fn get_entries_sorts_and_pages<S: Storage>(storage: &S) {
+    populate(storage);
+    let mut options = all();
+    options.set_limit(2);
+    options.set_page(2);
+    assert_eq!(
+        titles(&storage.get_entries(&options)),
+        ["cargo tips", "Garden notes"]
+    );
+    assert_eq!(storage.count_entries(&options), 6);
+    options.set_skip(5);
+    assert_eq!(titles(&storage.get_entries(&options)), ["Rusty tools"]);
+
+    let mut options = all();
+    options.set_sort(EntrySort::Oldest);
+    assert_eq!(storage.get_entries(&options).len(), 6);
+    let dates: Vec<String> = storage
+        .get_entries(&options)
+        .iter()
+        .map(|entry| entry.publish_date.clone())
+        .collect();
+    let mut sorted = dates.clone();
+    sorted.sort();
+    assert_eq!(dates, sorted);
+}
+
+fn get_entries_sorts_by_feed_and_relevance<S: Storage>(storage: &S) {
+    populate(storage);
+    storage
+        .set_display_title("https://b.test/rss", Some("Aardvark"))
+        .unwrap();
+    let mut options = all();
+    options.set_sort(EntrySort::Feed);
+    let feeds: Vec<String> = storage
+        .get_entries(&options)
+        .iter()
+        .map(|entry| entry.rss_url.clone())
+        .collect();
+    assert_eq!(feeds[..3], ["https://b.test/rss"; 3]);
+    assert_eq!(feeds[3..], ["https://a.test/rss"; 3]);
+
+    let mut options = all();
+    options.set_sort(EntrySort::Relevance);
+    options.query = String::from("r");
+    let mut found = titles(&storage.get_entries(&options));
+    assert_eq!(found.len(), 6);
+    let mut rest = found.split_off(2);
+    found.sort();
+    rest.sort();
+    assert_eq!(found, ["Rust 1.75 released", "Rusty tools"]);
+    assert_eq!(
+        rest,
+        [
+            "Async closures",
+            "Bread recipe",
+            "Garden notes",
+            "cargo tips"
+        ]
+    );
+}
+
+fn details_filter_entries<S: Storage>(storage: &S) {
+    populate(storage);
+    let mut options = all();
+    options.set_author("someone");
+    assert_eq!(storage.count_entries(&options), 0);
+
+    let rust = storage.find_entry("Rust 1.75").unwrap();
+    let cargo = storage.find_entry("cargo").unwrap();
+    let item = |author: &str, categories: &[&str]| Item {
+        author: author.to_string(),
+        category: categories
+            .iter()
+            .map(|category| category.to_string())
+            .collect(),
+        ..Item::default()
+    };
+    storage
+        .save_details(&rust, &item(" Jane Doe ", &[" Rust ", "Releases", " "]))
+        .unwrap();
+    storage.save_details(&cargo, &item("", &["rust"])).unwrap();
+    assert_eq!(
+        storage.entry_details(&rust),
+        (
+            Some(String::from("Jane Doe")),
+            vec![String::from("Releases"), String::from("Rust")]
+        )
+    );
+    assert_eq!(
+        storage.entry_details(&cargo),
+        (None, vec![String::from("rust")])
+    );
+
+    let mut options = all();
+    options.set_author("JANE");
+    assert_eq!(
+        titles(&storage.get_entries(&options)),
+        ["Rust 1.75 released"]
+    );
+    let mut options = all();
+    options.set_categories(vec![String::from(" RUST ")]);
+    assert_eq!(
+        titles(&storage.get_entries(&options)),
+        ["cargo tips", "Rust 1.75 released"]
+    );
+    options.set_categories(vec![String::from("rust"), String::from("releases")]);
+    assert_eq!(
+        titles(&storage.get_entries(&options)),
+        ["Rust 1.75 released"]
+    );
+    assert_eq!(storage.count_entries(&options), 1);
+
+    // a new version of the item replaces the details of the previous one
+    storage.save_details(&rust, &item("", &["news"])).unwrap();
+    assert_eq!(
+        storage.entry_details(&rust),
+        (None, vec![String::from("news")])
+    );
+    assert_eq!(storage.count_entries(&options), 0);
+}
+
+fn full_text_is_searched_and_ranked_last<S: Storage>(storage: &S) {
+    populate(storage);
+    storage
+        .save_entry(&entry(
+            "Learning rust",
+            "https://a.test/learn",
+            "https://a.test/rss",
+        ))
+        .unwrap();
+    let garden = storage.find_entry("Garden").unwrap();
+    let content = |text: &str| EntryContent {
+        hash: garden.hash(),
+        html: format!("<p>{}</p>", text),
+        text: text.to_string(),
+        word_count: 5,
+        reading_minutes: 1,
+        connection: None,
+    };
+    assert_eq!(
+        storage.save_content(&content("Weeds and roses")).unwrap(),
+        SaveOutcome::Inserted
+    );
+    assert_eq!(
+        storage
+            .save_content(&content("Rust on the garden tools"))
+            .unwrap(),
+        SaveOutcome::Updated
+    );
+    let stored = storage.get_content(&garden.hash()).unwrap();
+    assert_eq!(
+        (
+            stored.html.as_str(),
+            stored.text.as_str(),
+            stored.word_count
+        ),
+        (
+            "<p>Rust on the garden tools</p>",
+            "Rust on the garden tools",
+            5
+        )
+    );
+    assert!(stored.connection.is_none());
+    assert!(storage.get_content("unknown").is_none());
+
+    let mut options = all();
+    options.query = String::from("rust");
+    assert_eq!(storage.count_entries(&options), 4);
+    options.set_sort(EntrySort::Relevance);
+    let mut found = titles(&storage.get_entries(&options));
+    let rest = found.split_off(2);
+    found.sort();
+    assert_eq!(found, ["Rust 1.75 released", "Rusty tools"]);
+    assert_eq!(rest, ["Learning rust", "Garden notes"]);
+
+    options.query = String::from("weeds");
+    assert_eq!(storage.count_entries(&options), 0);
+}
+
+fn tags_are_normalized_and_inherited<S: Storage>(storage: &S) {
+    populate(storage);
+    let rust = storage.find_entry("Rust 1.75").unwrap();
+    storage.add_entry_tag(&rust, " To-Read ").unwrap();
+    storage.add_entry_tag(&rust, "to-read").unwrap();
+    storage.add_entry_tag(&rust, "a,b").unwrap();
+    storage.add_entry_tag(&rust, "  ").unwrap();
+    storage.add_feed_tag("https://a.test/rss", "Dev").unwrap();
+    storage
+        .add_feed_tag("https://a.test/rss", "to-read")
+        .unwrap();
+
+    assert_eq!(storage.feed_tags("https://a.test/rss"), ["dev", "to-read"]);
+    assert_eq!(storage.entry_tags(&rust), ["dev", "to-read"]);
+    let cargo = storage.find_entry("cargo").unwrap();
+    assert_eq!(storage.entry_tags(&cargo), ["dev", "to-read"]);
+
+    let mut options = all();
+    options.set_tags(vec![String::from("dev"), String::from("to-read")]);
+    assert_eq!(storage.count_entries(&options), 3);
+    // the filter is normalized like the stored tags, an invalid tag is ignored
+    let mut mixed_case = all();
+    mixed_case.set_tags(vec![
+        String::from(" Dev"),
+        String::from("TO-Read "),
+        String::from("a,b"),
+    ]);
+    assert_eq!(storage.count_entries(&mixed_case), 3);
+    storage
+        .remove_feed_tag("https://a.test/rss", "TO-READ")
+        .unwrap();
+    assert_eq!(
+        titles(&storage.get_entries(&options)),
+        ["Rust 1.75 released"]
+    );
+
+    storage.remove_entry_tag(&rust, "to-read").unwrap();
+    storage.remove_entry_tag(&rust, "dev").unwrap();
+    assert_eq!(storage.entry_tags(&rust), ["dev"]);
+    assert_eq!(storage.count_entries(&options), 0);
+    assert!(storage.feed_tags("https://b.test/rss").is_empty());
+}
+
+fn tag_counts_include_inherited_entries<S: Storage>(storage: &S) {
+    populate(storage);
+    assert!(storage.tag_counts().is_empty());
+    let rust = storage.find_entry("Rust 1.75").unwrap();
+    let garden = storage.find_entry("Garden").unwrap();
+    storage.add_entry_tag(&rust, "dev").unwrap();
+    storage.add_entry_tag(&rust, "later").unwrap();
+    storage.add_entry_tag(&garden, "later").unwrap();
+    storage.add_feed_tag("https://a.test/rss", "dev").unwrap();
+    storage.add_feed_tag("https://b.test/rss", "home").unwrap();
+
+    let counts: Vec<(String, u64, u64, u64)> = storage
+        .tag_counts()
+        .into_iter()
+        .map(|count| (count.tag, count.entries, count.feeds, count.inherited))
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            (String::from("dev"), 1, 1, 2),
+            (String::from("home"), 0, 1, 3),
+            (String::from("later"), 2, 0, 0),
+        ]
+    );
+}
+
+fn find_entry_prefers_the_hash<S: Storage>(storage: &S) {
+    populate(storage);
+    let garden = entry("Garden notes", "", "");
+    let hash = garden.hash();
+    let found = storage.find_entry(&hash[..8]).unwrap();
+    assert_eq!(found.title, "Garden notes");
+    assert!(found.connection.is_none());
+    assert_eq!(storage.find_entry("TIPS").unwrap().title, "cargo tips");
+    assert!(storage.find_entry("nothing like it").is_none());
+}
+
+fn pruned_entries_are_deleted_and_remembered<S: Storage>(storage: &S) {
+    populate(storage);
+    let rust = storage.find_entry("Rust 1.75").unwrap();
+    storage.add_entry_tag(&rust, "dev").unwrap();
+    assert!(!storage.is_pruned(&rust.hash()));
+
+    storage.prune_entries(&[rust.hash()]).unwrap();
+    assert!(storage.is_pruned(&rust.hash()));
+    assert!(storage.find_entry(&rust.hash()).is_none());
+    assert!(storage.tag_counts().is_empty());
+    assert_eq!(storage.count_entries(&all()), 5);
+}
+
+/// Feed files written by `sync_stores_a_local_feed`, numbered so the suites do not share one.
+static FEED_FILES: AtomicUsize = AtomicUsize::new(0);
+
+fn sync_stores_a_local_feed<S: Storage>(storage: &S) {
+    let path = std::env::temp_dir().join(format!(
+        "rss_rust_storage_{}_{}.xml",
+        std::process::id(),
+        FEED_FILES.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::write(
+        &path,
+        "<rss><channel><title>Local</title>
+        <item><title>Kept</title><link>https://l.test/kept</link></item>
+        <item><title>Pruned</title><link>https://l.test/pruned</link></item>
+        </channel></rss>",
+    )
+    .unwrap();
+    let rss_url = format!("file://{}", path.display());
+    storage.save_feed(&feed(&rss_url, "local")).unwrap();
+    let pruned = entry("Pruned", "https://l.test/pruned", &rss_url);
+    storage.prune_entries(&[pruned.hash()]).unwrap();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let report = runtime.block_on(RssEntity::sync(storage, None, SyncOptions::new()));
+    std::fs::remove_file(&path).unwrap();
+
+    let feed_report = &report.feeds[0];
+    assert!(feed_report.errors.is_empty(), "{:?}", feed_report.errors);
+    assert_eq!(
+        (feed_report.fetched, feed_report.new, feed_report.skipped),
+        (2, 1, 1)
+    );
+    assert_eq!(storage.get_feeds()[0].channel.title, "Local");
+    let mut options = all();
+    options.set_rss_url(&rss_url);
+    let stored = storage.get_entries(&options);
+    assert_eq!(titles(&stored), ["Kept"]);
+    assert!(!stored[0].is_read);
+}
+
+macro_rules! storage_suite {
+    ($($test:ident),* $(,)?) => {
+        mod sqlite_storage {
+            use super::*;
+            $(
+                #[test]
+                fn $test() {
+                    let connection = open_database(":memory:").expect("DATABASE NOT OPENED");
+                    super::$test(&SqliteStorage::new(&ThreadSafe::new(&connection)));
+                }
+            )*
+        }
+
+        mod memory_storage {
+            use super::*;
+            $(
+                #[test]
+                fn $test() {
+                    super::$test(&MemoryStorage::new());
+                }
+            )*
+        }
+    };
+}
+
+storage_suite!(
+    feeds_keep_their_settings,
+    save_entry_reports_its_outcome,
+    get_entries_applies_the_filters,
+    get_entries_sorts_and_pages,
+    get_entries_sorts_by_feed_and_relevance,
+    details_filter_entries,
+    full_text_is_searched_and_ranked_last,
+    tags_are_normalized_and_inherited,
+    tag_counts_include_inherited_entries,
+    find_entry_prefers_the_hash,
+    pruned_entries_are_deleted_and_remembered,
+    sync_stores_a_local_feed,
+);